@@ -1,7 +1,47 @@
 //! Benchmarks for ID set compression.
 
-use cnk::{IdSetCompressor, RocCompressor};
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use cnk::simd::{
+    decode_varint_batch, decode_varint_batch_scalar, encode_varint_batch,
+    encode_varint_batch_scalar,
+};
+use cnk::testutils::{sample_ids, Lcg};
+use cnk::varint::encode_varint;
+use cnk::{
+    BlockDeltaCompressor, CompressedIndex, EliasFanoCompressor, EliasFanoWithSelect,
+    FibonacciCompressor, IdSetCompressor, MultisetCompressor, NibbleCompressor,
+    PForDeltaCompressor, RandomAccessCompressedSet, RocCompressor, RocMultisetCompressor,
+    SegmentedCompressor, Simple16Compressor, SplitEliasFanoCompressor, TwoLevelEliasFanoCompressor,
+};
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator that counts allocations, for `bench_arena`.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+// SAFETY: defers every call to `System`
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
 
 fn bench_compress(c: &mut Criterion) {
     let mut group = c.benchmark_group("compress");
@@ -9,13 +49,24 @@ fn bench_compress(c: &mut Criterion) {
     let compressor = RocCompressor::new();
 
     for num_ids in [100, 1000, 10000] {
-        let ids: Vec<u32> = (0..num_ids).map(|i| i * 100).collect();
-        let universe_size = (num_ids * 100 + 10000) as u32;
+        let universe_size = num_ids * 100 + 10000;
+        let ids = sample_ids(universe_size, num_ids as usize, 0xDEADBEEF).unwrap();
 
-        group.throughput(Throughput::Elements(num_ids as u64));
+        group.throughput(Throughput::Bytes(4 * num_ids as u64));
         group.bench_with_input(BenchmarkId::new("roc", num_ids), &num_ids, |bench, _| {
             bench.iter(|| compressor.compress_set(black_box(&ids), black_box(universe_size)))
         });
+
+        // Same work, reported as compressed bytes written per second
+        let compressed_len = compressor.compress_set(&ids, universe_size).unwrap().len();
+        group.throughput(Throughput::Bytes(compressed_len as u64));
+        group.bench_with_input(
+            BenchmarkId::new("roc_output", num_ids),
+            &num_ids,
+            |bench, _| {
+                bench.iter(|| compressor.compress_set(black_box(&ids), black_box(universe_size)))
+            },
+        );
     }
 
     group.finish();
@@ -27,16 +78,28 @@ fn bench_decompress(c: &mut Criterion) {
     let compressor = RocCompressor::new();
 
     for num_ids in [100, 1000, 10000] {
-        let ids: Vec<u32> = (0..num_ids).map(|i| i * 100).collect();
-        let universe_size = (num_ids * 100 + 10000) as u32;
+        let universe_size = num_ids * 100 + 10000;
+        let ids = sample_ids(universe_size, num_ids as usize, 0xDEADBEEF).unwrap();
         let compressed = compressor.compress_set(&ids, universe_size).unwrap();
 
-        group.throughput(Throughput::Elements(num_ids as u64));
+        group.throughput(Throughput::Bytes(4 * num_ids as u64));
         group.bench_with_input(BenchmarkId::new("roc", num_ids), &num_ids, |bench, _| {
             bench.iter(|| {
                 compressor.decompress_set(black_box(&compressed), black_box(universe_size))
             })
         });
+
+        // Same work, reported as compressed bytes read per second
+        group.throughput(Throughput::Bytes(compressed.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("roc_input", num_ids),
+            &num_ids,
+            |bench, _| {
+                bench.iter(|| {
+                    compressor.decompress_set(black_box(&compressed), black_box(universe_size))
+                })
+            },
+        );
     }
 
     group.finish();
@@ -48,10 +111,10 @@ fn bench_round_trip(c: &mut Criterion) {
     let compressor = RocCompressor::new();
 
     for num_ids in [100, 1000] {
-        let ids: Vec<u32> = (0..num_ids).map(|i| i * 100).collect();
-        let universe_size = (num_ids * 100 + 10000) as u32;
+        let universe_size = num_ids * 100 + 10000;
+        let ids = sample_ids(universe_size, num_ids as usize, 0xDEADBEEF).unwrap();
 
-        group.throughput(Throughput::Elements(num_ids as u64));
+        group.throughput(Throughput::Bytes(4 * num_ids as u64));
         group.bench_with_input(BenchmarkId::new("roc", num_ids), &num_ids, |bench, _| {
             bench.iter(|| {
                 let compressed = compressor
@@ -67,5 +130,748 @@ fn bench_round_trip(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_compress, bench_decompress, bench_round_trip);
+fn bench_multiset(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multiset");
+
+    let set_compressor = RocCompressor::new();
+    let multiset_compressor = RocMultisetCompressor::new();
+
+    for num_ids in [100, 1000, 10000] {
+        let universe_size = num_ids * 100 + 10000;
+        let ids = sample_ids(universe_size, num_ids as usize, 0xDEADBEEF).unwrap();
+        let pairs: Vec<(u32, u32)> = ids.iter().map(|&id| (id, id % 7 + 1)).collect();
+
+        group.throughput(Throughput::Bytes(4 * num_ids as u64));
+        group.bench_with_input(BenchmarkId::new("set", num_ids), &num_ids, |bench, _| {
+            bench.iter(|| set_compressor.compress_set(black_box(&ids), black_box(universe_size)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("multiset", num_ids),
+            &num_ids,
+            |bench, _| {
+                bench.iter(|| {
+                    multiset_compressor
+                        .compress_multiset(black_box(&pairs), black_box(universe_size))
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_next_geq(c: &mut Criterion) {
+    let mut group = c.benchmark_group("next_geq_1m");
+
+    let num_ids = 1_000_000u32;
+    let ids: Vec<u32> = (0..num_ids).map(|i| i * 3).collect();
+    let universe_size = num_ids * 3;
+
+    let roc = RocCompressor::new();
+    let roc_compressed = roc.compress_set(&ids, universe_size).unwrap();
+    let block = BlockDeltaCompressor::new(128);
+    let block_compressed = block.compress_set(&ids, universe_size).unwrap();
+
+    // Targets spread across the list so monolithic decoding pays for the prefix
+    let targets: Vec<u32> = (1..=16).map(|i| i * (universe_size / 17)).collect();
+
+    group.bench_function("roc", |bench| {
+        bench.iter(|| {
+            for &x in &targets {
+                black_box(roc.next_geq(&roc_compressed, universe_size, x).unwrap());
+            }
+        })
+    });
+    group.bench_function("block_delta", |bench| {
+        bench.iter(|| {
+            for &x in &targets {
+                black_box(block.next_geq(&block_compressed, universe_size, x).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_decompress_range(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decompress_range_100k");
+
+    let num_ids = 100_000u32;
+    let ids: Vec<u32> = (0..num_ids).map(|i| i * 3).collect();
+    let universe_size = num_ids * 3;
+    let roc = RocCompressor::new();
+    let compressed = roc.compress_set(&ids, universe_size).unwrap();
+
+    group.bench_function("full_then_filter", |bench| {
+        bench.iter(|| {
+            let all = roc.decompress_set(&compressed, universe_size).unwrap();
+            black_box(all.into_iter().filter(|&x| x < 3000).count())
+        })
+    });
+    // Each query covers 1% of the IDs; decoding still walks the prefix, so
+    // the saving shrinks as the range moves toward the end of the list
+    for percentile in [0u32, 50, 98] {
+        let lo = percentile * (universe_size / 100);
+        let hi = lo + universe_size / 100;
+        group.bench_with_input(
+            BenchmarkId::new("range", percentile),
+            &(lo, hi),
+            |bench, &(lo, hi)| {
+                bench.iter(|| {
+                    black_box(
+                        roc.decompress_range(&compressed, universe_size, lo, hi)
+                            .unwrap(),
+                    )
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_random_access(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random_access_1m");
+
+    let num_ids = 1_000_000u32;
+    let ids: Vec<u32> = (0..num_ids).map(|i| i * 3).collect();
+    let universe_size = num_ids * 3;
+    let roc = RocCompressor::new();
+    let compressed = roc.compress_set(&ids, universe_size).unwrap();
+    let set = RandomAccessCompressedSet::build(&compressed, universe_size, 64, &roc).unwrap();
+    let positions: Vec<usize> = (1..=16).map(|i| i * (num_ids as usize / 17)).collect();
+
+    group.bench_function("full_decompress", |bench| {
+        bench.iter(|| {
+            let all = roc.decompress_set(&compressed, universe_size).unwrap();
+            for &k in &positions {
+                black_box(all[k]);
+            }
+        })
+    });
+    group.bench_function("get", |bench| {
+        bench.iter(|| {
+            for &k in &positions {
+                black_box(set.get(black_box(k)).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_sort_then_compress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sort_then_compress");
+    let roc = RocCompressor::new();
+
+    // Run with `--features simd-sort` to compare the radix sort
+    for num_ids in [10_000u32, 1_000_000] {
+        let mut lcg = Lcg::new(0xDEADBEEF);
+        let mut ids: Vec<u32> = (0..num_ids).map(|_| lcg.next_u32()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        // Deterministic shuffle
+        for i in (1..ids.len()).rev() {
+            ids.swap(i, lcg.next_u32() as usize % (i + 1));
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("sort_unstable", num_ids),
+            &ids,
+            |bench, ids| {
+                bench.iter_batched_ref(
+                    || ids.clone(),
+                    |ids| {
+                        ids.sort_unstable();
+                        black_box(roc.compress_set(ids, u32::MAX).unwrap())
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("compress_set_with_sort", num_ids),
+            &ids,
+            |bench, ids| {
+                bench.iter_batched_ref(
+                    || ids.clone(),
+                    |ids| black_box(roc.compress_set_with_sort(ids, u32::MAX).unwrap()),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_elias_fano_select(c: &mut Criterion) {
+    let mut group = c.benchmark_group("elias_fano_select_1m");
+
+    let num_ids = 1_000_000u32;
+    let ids: Vec<u32> = (0..num_ids).map(|i| i * 3).collect();
+    let universe_size = num_ids * 3;
+    let compressed = EliasFanoCompressor::new()
+        .compress_set(&ids, universe_size)
+        .unwrap();
+    let index = EliasFanoWithSelect::new(compressed, universe_size).unwrap();
+
+    // Same cost near the start and the end of the list
+    for fraction in [1u32, 8, 16] {
+        let x = fraction * (universe_size / 17);
+        group.bench_with_input(BenchmarkId::new("next_geq", fraction), &x, |bench, &x| {
+            bench.iter(|| black_box(index.next_geq(black_box(x)).unwrap()))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_two_level_elias_fano(c: &mut Criterion) {
+    let mut group = c.benchmark_group("two_level_ef_10k");
+
+    // 10 IDs in each of 1000 blocks of 2^32, as hashed IDs clustered by prefix
+    let ids: Vec<u64> = sample_ids(u32::MAX, 1000, 1)
+        .unwrap()
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, block)| {
+            sample_ids(u32::MAX, 10, i as u64 + 2)
+                .unwrap()
+                .into_iter()
+                .map(move |low| ((block as u64) << 32) | low as u64)
+        })
+        .collect();
+    let codecs: [(&str, Box<dyn IdSetCompressor<u64>>); 2] = [
+        ("two_level", Box::new(TwoLevelEliasFanoCompressor::new())),
+        ("split_32", Box::new(SplitEliasFanoCompressor::new(32))),
+    ];
+    let x = ids[ids.len() / 2] + 1;
+
+    group.throughput(Throughput::Bytes(8 * ids.len() as u64));
+    for (name, codec) in &codecs {
+        let compressed = codec.compress_set(&ids, u64::MAX).unwrap();
+        group.bench_function(BenchmarkId::new("compress", name), |bench| {
+            bench.iter(|| codec.compress_set(black_box(&ids), u64::MAX).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("decompress", name), |bench| {
+            bench.iter(|| {
+                codec
+                    .decompress_set(black_box(&compressed), u64::MAX)
+                    .unwrap()
+            })
+        });
+    }
+
+    let two_level = TwoLevelEliasFanoCompressor::new();
+    let compressed = two_level.compress_set(&ids, u64::MAX).unwrap();
+    group.bench_function(BenchmarkId::new("next_geq", "two_level"), |bench| {
+        bench.iter(|| {
+            two_level
+                .next_geq_u64(black_box(&compressed), u64::MAX, black_box(x))
+                .unwrap()
+        })
+    });
+    let split = SplitEliasFanoCompressor::new(32);
+    let compressed = split.compress_set(&ids, u64::MAX).unwrap();
+    group.bench_function(BenchmarkId::new("next_geq", "split_32"), |bench| {
+        bench.iter(|| {
+            split
+                .next_geq_u64(black_box(&compressed), u64::MAX, black_box(x))
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+/// Deterministic gap sequences: uniform in `1..=2 * mean`, or power-law with
+/// mostly small gaps and occasional huge ones.
+fn gap_ids(num_ids: u32, power_law: bool) -> Vec<u32> {
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let mut id = 0u32;
+    (0..num_ids)
+        .map(|_| {
+            let gap = if power_law {
+                // P(gap >= 2^k) = 2^-k
+                1 << (rng.next_u32().trailing_zeros().min(20))
+            } else {
+                1 + rng.next_u32() % 200
+            };
+            id += gap;
+            id
+        })
+        .collect()
+}
+
+fn bench_pfor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pfor_vs_roc");
+
+    let roc = RocCompressor::new();
+    let pfor = PForDeltaCompressor::default();
+    let num_ids = 100_000u32;
+
+    for (name, power_law) in [("uniform", false), ("power_law", true)] {
+        let ids = gap_ids(num_ids, power_law);
+        let universe_size = ids.last().unwrap() + 1;
+        let roc_compressed = roc.compress_set(&ids, universe_size).unwrap();
+        let pfor_compressed = pfor.compress_set(&ids, universe_size).unwrap();
+
+        group.throughput(Throughput::Bytes(4 * num_ids as u64));
+        group.bench_function(BenchmarkId::new("roc_decompress", name), |bench| {
+            bench.iter(|| roc.decompress_set(black_box(&roc_compressed), universe_size))
+        });
+        group.bench_function(BenchmarkId::new("pfor_decompress", name), |bench| {
+            bench.iter(|| pfor.decompress_set(black_box(&pfor_compressed), universe_size))
+        });
+        group.bench_function(BenchmarkId::new("pfor_compress", name), |bench| {
+            bench.iter(|| pfor.compress_set(black_box(&ids), universe_size))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_fibonacci(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fibonacci_vs_varint");
+
+    let roc = RocCompressor::new();
+    let fibonacci = FibonacciCompressor::new();
+    let num_ids = 100_000u32;
+    let ids = gap_ids(num_ids, false);
+    let universe_size = ids.last().unwrap() + 1;
+    let roc_compressed = roc.compress_set(&ids, universe_size).unwrap();
+    let fib_compressed = fibonacci.compress_set(&ids, universe_size).unwrap();
+
+    group.throughput(Throughput::Bytes(4 * num_ids as u64));
+    group.bench_function("varint_compress", |bench| {
+        bench.iter(|| roc.compress_set(black_box(&ids), universe_size))
+    });
+    group.bench_function("fibonacci_compress", |bench| {
+        bench.iter(|| fibonacci.compress_set(black_box(&ids), universe_size))
+    });
+    group.bench_function("varint_decompress", |bench| {
+        bench.iter(|| roc.decompress_set(black_box(&roc_compressed), universe_size))
+    });
+    group.bench_function("fibonacci_decompress", |bench| {
+        bench.iter(|| fibonacci.decompress_set(black_box(&fib_compressed), universe_size))
+    });
+
+    group.finish();
+}
+
+fn bench_nibble(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nibble_vs_varint");
+
+    // Clustered list: 95% of gaps in 1..=14, the rest escaped
+    let roc = RocCompressor::new();
+    let nibble = NibbleCompressor::new();
+    let num_ids = 100_000u32;
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let mut id = 0u32;
+    let ids: Vec<u32> = (0..num_ids)
+        .map(|_| {
+            let r = rng.next_u32();
+            id += if r % 100 < 5 {
+                100 + r % 1000
+            } else {
+                1 + r % 14
+            };
+            id
+        })
+        .collect();
+    let universe_size = ids.last().unwrap() + 1;
+    let roc_compressed = roc.compress_set(&ids, universe_size).unwrap();
+    let nibble_compressed = nibble.compress_set(&ids, universe_size).unwrap();
+
+    group.throughput(Throughput::Bytes(4 * num_ids as u64));
+    group.bench_function("varint_decompress", |bench| {
+        bench.iter(|| roc.decompress_set(black_box(&roc_compressed), universe_size))
+    });
+    group.bench_function("nibble_decompress", |bench| {
+        bench.iter(|| nibble.decompress_set(black_box(&nibble_compressed), universe_size))
+    });
+
+    group.finish();
+}
+
+fn bench_simple16(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simple16_vs_varint");
+
+    let roc = RocCompressor::new();
+    let simple16 = Simple16Compressor::new();
+    let num_ids = 100_000u32;
+    let ids = gap_ids(num_ids, false);
+    let universe_size = ids.last().unwrap() + 1;
+    let roc_compressed = roc.compress_set(&ids, universe_size).unwrap();
+    let s16_compressed = simple16.compress_set(&ids, universe_size).unwrap();
+
+    // Bytes of raw u32 IDs per second; divide by 4e9 for integers per nanosecond
+    group.throughput(Throughput::Bytes(4 * num_ids as u64));
+    group.bench_function("varint_compress", |bench| {
+        bench.iter(|| roc.compress_set(black_box(&ids), universe_size))
+    });
+    group.bench_function("simple16_compress", |bench| {
+        bench.iter(|| simple16.compress_set(black_box(&ids), universe_size))
+    });
+    group.bench_function("varint_decompress", |bench| {
+        bench.iter(|| roc.decompress_set(black_box(&roc_compressed), universe_size))
+    });
+    group.bench_function("simple16_decompress", |bench| {
+        bench.iter(|| simple16.decompress_set(black_box(&s16_compressed), universe_size))
+    });
+
+    group.finish();
+}
+
+fn bench_segmented(c: &mut Criterion) {
+    let mut group = c.benchmark_group("segmented_bimodal");
+
+    // Dense first half (consecutive IDs), sparse second half
+    let num_ids = 100_000u32;
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let mut ids: Vec<u32> = (0..num_ids / 2).collect();
+    let mut id = num_ids / 2;
+    for _ in 0..num_ids / 2 {
+        id += 1 + rng.next_u32() % 1000;
+        ids.push(id);
+    }
+    let universe_size = id + 1;
+
+    let roc = RocCompressor::new();
+    let segmented = SegmentedCompressor::default();
+    let roc_compressed = roc.compress_set(&ids, universe_size).unwrap();
+    let seg_compressed = segmented.compress_set(&ids, universe_size).unwrap();
+    assert!(seg_compressed.len() < roc_compressed.len());
+
+    // Compressed sizes are part of the benchmark IDs so reports show both
+    group.throughput(Throughput::Bytes(4 * num_ids as u64));
+    group.bench_with_input(
+        BenchmarkId::new("roc_compress", roc_compressed.len()),
+        &ids,
+        |bench, ids| bench.iter(|| roc.compress_set(black_box(ids), universe_size)),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("segmented_compress", seg_compressed.len()),
+        &ids,
+        |bench, ids| bench.iter(|| segmented.compress_set(black_box(ids), universe_size)),
+    );
+    group.bench_function("roc_decompress", |bench| {
+        bench.iter(|| roc.decompress_set(black_box(&roc_compressed), universe_size))
+    });
+    group.bench_function("segmented_decompress", |bench| {
+        bench.iter(|| segmented.decompress_set(black_box(&seg_compressed), universe_size))
+    });
+
+    group.finish();
+}
+
+/// Codec throughput next to a plain memory copy of the same raw IDs.
+///
+/// `memcpy` is the ceiling set by memory bandwidth at each buffer size. A
+/// codec close to it is memory-bound; one far below it, with throughput
+/// flat across sizes, is compute- or branch-bound.
+fn bench_memory_bandwidth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("memory_bandwidth");
+
+    let roc = RocCompressor::new();
+    for num_ids in [1_000u32, 100_000, 1_000_000] {
+        let ids = gap_ids(num_ids, false);
+        let universe_size = ids.last().unwrap() + 1;
+        let compressed = roc.compress_set(&ids, universe_size).unwrap();
+        let mut dst = vec![0u32; ids.len()];
+
+        group.throughput(Throughput::Bytes(4 * num_ids as u64));
+        group.bench_with_input(BenchmarkId::new("memcpy", num_ids), &ids, |bench, ids| {
+            bench.iter(|| {
+                // SAFETY: `dst` and `ids` are distinct allocations of equal length
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        black_box(ids.as_ptr()),
+                        dst.as_mut_ptr(),
+                        ids.len(),
+                    )
+                };
+                black_box(&mut dst);
+            })
+        });
+        group.bench_with_input(
+            BenchmarkId::new("roc_compress", num_ids),
+            &ids,
+            |bench, ids| bench.iter(|| roc.compress_set(black_box(ids), universe_size)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("roc_decompress", num_ids),
+            &compressed,
+            |bench, compressed| {
+                bench.iter(|| roc.decompress_set(black_box(compressed), universe_size))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_arena(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arena_1000_lists");
+
+    // Write 1000 posting lists back to back into one 16 MB arena
+    let roc = RocCompressor::new();
+    let lists: Vec<Vec<u32>> = (0..1000u32)
+        .map(|i| (0..1000).map(|j| j * (1 + i % 50)).collect())
+        .collect();
+    let universe_size = 50_000;
+    let mut arena = vec![0u8; 16 << 20];
+
+    let fill = |arena: &mut [u8]| {
+        let mut offset = 0;
+        for ids in &lists {
+            let written = roc
+                .compress_set_reuse(black_box(ids), universe_size, &mut arena[offset..])
+                .unwrap();
+            offset += written.len();
+        }
+        offset
+    };
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    fill(&mut arena);
+    assert_eq!(
+        ALLOCATIONS.load(Ordering::Relaxed),
+        before,
+        "compress_set_reuse allocated"
+    );
+
+    group.throughput(Throughput::Bytes(4 * 1000 * 1000));
+    group.bench_function("compress_set_reuse", |bench| {
+        bench.iter(|| fill(black_box(&mut arena)))
+    });
+    group.bench_function("compress_set", |bench| {
+        bench.iter(|| {
+            for ids in &lists {
+                black_box(roc.compress_set(black_box(ids), universe_size).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+/// Prefix sums of gaps drawn by `gap`, starting at 0.
+fn ids_from_gaps(num_ids: usize, mut gap: impl FnMut() -> u32) -> Vec<u32> {
+    let mut id = 0u32;
+    (0..num_ids)
+        .map(|i| {
+            if i > 0 {
+                id += gap();
+            }
+            id
+        })
+        .collect()
+}
+
+/// Compress, decompress and round-trip throughput for one input, reported in
+/// bytes of raw `u32` IDs per second.
+fn bench_varint_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("varint_batch_10k");
+
+    let mut lcg = Lcg::new(0xDEADBEEF);
+    // Dense deltas fit one byte; sparse ones take two or three
+    for (name, max_gap) in [("small", 128u64), ("mixed", 1 << 20)] {
+        let mut buf = Vec::new();
+        for _ in 0..10_000 {
+            encode_varint(1 + lcg.next_u32() as u64 % max_gap, &mut buf);
+        }
+        group.throughput(Throughput::Bytes(buf.len() as u64));
+        let mut out = Vec::with_capacity(buf.len());
+        group.bench_function(BenchmarkId::new("scalar", name), |bench| {
+            bench.iter(|| {
+                out.clear();
+                black_box(decode_varint_batch_scalar(black_box(&buf), &mut out).unwrap())
+            })
+        });
+        group.bench_function(BenchmarkId::new("sse2", name), |bench| {
+            bench.iter(|| {
+                out.clear();
+                black_box(decode_varint_batch(black_box(&buf), &mut out).unwrap())
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_varint_encode_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("varint_encode_100k");
+
+    let mut lcg = Lcg::new(0xDEADBEEF);
+    for (name, max_value) in [("small", 128u64), ("mixed", 1 << 20)] {
+        let values: Vec<u64> = (0..100_000)
+            .map(|_| lcg.next_u32() as u64 % max_value)
+            .collect();
+        group.throughput(Throughput::Bytes(8 * values.len() as u64));
+        let mut out = Vec::with_capacity(values.len() * 3);
+        group.bench_function(BenchmarkId::new("scalar", name), |bench| {
+            bench.iter(|| {
+                out.clear();
+                encode_varint_batch_scalar(black_box(&values), &mut out);
+                black_box(out.len())
+            })
+        });
+        group.bench_function(BenchmarkId::new("avx2", name), |bench| {
+            bench.iter(|| {
+                out.clear();
+                encode_varint_batch(black_box(&values), &mut out);
+                black_box(out.len())
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_distribution(c: &mut Criterion, name: &str, ids: &[u32]) {
+    let mut group = c.benchmark_group(name);
+
+    let compressor = RocCompressor::new();
+    let universe_size = ids.last().map_or(1, |&last| last + 1);
+    let compressed = compressor.compress_set(ids, universe_size).unwrap();
+
+    group.throughput(Throughput::Bytes(4 * ids.len() as u64));
+    group.bench_function("compress", |bench| {
+        bench.iter(|| compressor.compress_set(black_box(ids), universe_size))
+    });
+    group.bench_function("decompress", |bench| {
+        bench.iter(|| compressor.decompress_set(black_box(&compressed), universe_size))
+    });
+    group.bench_function("round_trip", |bench| {
+        bench.iter(|| {
+            let compressed = compressor
+                .compress_set(black_box(ids), universe_size)
+                .unwrap();
+            compressor.decompress_set(&compressed, universe_size)
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_power_law(c: &mut Criterion) {
+    // Zipf(s = 1.0) gaps over 1..=10_000, sampled by inverting the CDF
+    let cdf: Vec<f64> = (1..=10_000u32)
+        .scan(0.0, |acc, k| {
+            *acc += 1.0 / k as f64;
+            Some(*acc)
+        })
+        .collect();
+    let total = cdf[cdf.len() - 1];
+
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let ids = ids_from_gaps(100_000, || {
+        let u = rng.next_f64() * total;
+        (cdf.partition_point(|&p| p < u) + 1) as u32
+    });
+    bench_distribution(c, "power_law", &ids);
+}
+
+fn bench_geometric(c: &mut Criterion) {
+    // Geometric(p = 0.1) gaps on 1, 2, ..., mean 10
+    let p = 0.1f64;
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let ids = ids_from_gaps(100_000, || {
+        1 + (rng.next_f64().ln() / (1.0 - p).ln()).floor() as u32
+    });
+    bench_distribution(c, "geometric", &ids);
+}
+
+fn bench_uniform_sparse(c: &mut Criterion) {
+    // 1000 uniformly random IDs from a universe of 10^9
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let mut ids = std::collections::BTreeSet::new();
+    while ids.len() < 1000 {
+        ids.insert(rng.next_u32() % 1_000_000_000);
+    }
+    let ids: Vec<u32> = ids.into_iter().collect();
+    bench_distribution(c, "uniform_sparse", &ids);
+}
+
+/// 10 000 Zipf-distributed lookups against a 40 MB index, before and after
+/// moving the hot lists to the front of its buffer.
+///
+/// Rank `r` maps to a scattered term, so without repacking the hot lists are
+/// spread across the whole buffer.
+fn bench_index_repack(c: &mut Criterion) {
+    const NUM_TERMS: u32 = 20_000;
+    const NUM_QUERIES: usize = 10_000;
+
+    let universe = 1 << 24;
+    let term_of_rank = |rank: u32| (rank as u64 * 7919 % NUM_TERMS as u64) as u32;
+    let mut index = CompressedIndex::new(RocCompressor::new(), universe);
+    for term in 0..NUM_TERMS {
+        let ids: Vec<u32> = (0..1000).map(|i| i * 1000 + term % 1000).collect();
+        index.insert(term, &ids).unwrap();
+    }
+
+    // Zipf(s = 1.0) over ranks, sampled by inverting the CDF
+    let cdf: Vec<f64> = (1..=NUM_TERMS)
+        .scan(0.0, |acc, k| {
+            *acc += 1.0 / k as f64;
+            Some(*acc)
+        })
+        .collect();
+    let total = cdf[cdf.len() - 1];
+    let mut rng = Lcg::new(0xDEADBEEF);
+    let queries: Vec<u32> = (0..NUM_QUERIES)
+        .map(|_| {
+            let u = rng.next_f64() * total;
+            term_of_rank(cdf.partition_point(|&p| p < u) as u32)
+        })
+        .collect();
+    let frequencies: Vec<(u32, u64)> = (0..NUM_TERMS)
+        .map(|rank| (term_of_rank(rank), (1_000_000.0 / (rank + 1) as f64) as u64))
+        .collect();
+
+    let mut group = c.benchmark_group("index_repack");
+    // Every list holds 1000 IDs
+    group.throughput(Throughput::Bytes(4 * 1000 * NUM_QUERIES as u64));
+    let mut run = |name: &str, index: &CompressedIndex<RocCompressor>| {
+        group.bench_function(name, |bench| {
+            bench.iter(|| {
+                for &term in &queries {
+                    black_box(index.get(black_box(term)).unwrap());
+                }
+            })
+        });
+    };
+    run("before", &index);
+    index.repack_by_frequency(&frequencies);
+    run("after", &index);
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_compress,
+    bench_decompress,
+    bench_round_trip,
+    bench_multiset,
+    bench_next_geq,
+    bench_decompress_range,
+    bench_elias_fano_select,
+    bench_two_level_elias_fano,
+    bench_random_access,
+    bench_sort_then_compress,
+    bench_varint_batch,
+    bench_varint_encode_batch,
+    bench_pfor,
+    bench_fibonacci,
+    bench_nibble,
+    bench_simple16,
+    bench_segmented,
+    bench_memory_bandwidth,
+    bench_arena,
+    bench_index_repack,
+    bench_power_law,
+    bench_geometric,
+    bench_uniform_sparse
+);
 criterion_main!(benches);
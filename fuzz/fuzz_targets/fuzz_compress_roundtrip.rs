@@ -0,0 +1,28 @@
+//! Compress arbitrary sorted sets and check that decompression restores them.
+
+#![no_main]
+
+use cnk::{IdSetCompressor, RocCompressor};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: Vec<u8>| {
+    let mut ids: Vec<u32> = data
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .filter(|&id| id < u32::MAX)
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let universe = ids.last().map_or(1, |&max| max + 1);
+
+    let compressor = RocCompressor::new();
+    let compressed = compressor
+        .compress_set(&ids, universe)
+        .expect("sorted unique in-universe IDs must compress");
+    let decompressed = compressor
+        .decompress_set(&compressed, universe)
+        .expect("compressed output must decompress");
+
+    assert_eq!(ids, decompressed);
+});
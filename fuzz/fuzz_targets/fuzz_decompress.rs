@@ -0,0 +1,93 @@
+//! Feed arbitrary bytes to every decoder; each must return `Ok` or `Err`, never panic.
+//!
+//! The first byte picks the decoder and the next eight the universe. Inputs
+//! are not truncated: huge claimed lengths and long payloads are exactly
+//! what the decoders must survive.
+
+#![no_main]
+
+use cnk::{
+    AdaptiveCompressor, ArithmeticCompressor, BlockDeltaCompressor, Codec,
+    ContextualRocCompressor, EliasDeltaCompressor, EliasGammaCompressor,
+    ExceptionBasedDeltaCompressor, FibonacciCompressor, HuffmanCompressor, IdCompressionMethod,
+    IdSetCompressor, NibbleCompressor, PForDeltaCompressor, SegmentedCompressor,
+    Simple16Compressor, SplitEliasFanoCompressor, TwoLevelEliasFanoCompressor,
+    WindowedCompressor, XorDeltaCompressor, ZigzagDeltaCompressor,
+};
+use libfuzzer_sys::fuzz_target;
+
+/// Decoders of `u32` sets: every method with a [`Codec`], then the rest.
+fn decoders() -> Vec<Box<dyn IdSetCompressor>> {
+    let methods = [
+        IdCompressionMethod::Roc,
+        IdCompressionMethod::EliasFano,
+        IdCompressionMethod::Bitset,
+        IdCompressionMethod::Interpolative,
+        IdCompressionMethod::RoaringBitmap,
+        IdCompressionMethod::Uncompressed,
+        IdCompressionMethod::WaveletTree,
+    ];
+    let mut decoders: Vec<Box<dyn IdSetCompressor>> = methods
+        .into_iter()
+        .filter_map(|method| Codec::try_from(method).ok())
+        .map(|codec| Box::new(codec) as Box<dyn IdSetCompressor>)
+        .collect();
+    decoders.push(Box::new(HuffmanCompressor::new()));
+    decoders.push(Box::new(ArithmeticCompressor::new()));
+    decoders.push(Box::new(ContextualRocCompressor::new()));
+    decoders.push(Box::new(NibbleCompressor::new()));
+    decoders.push(Box::new(XorDeltaCompressor::new()));
+    decoders.push(Box::new(SegmentedCompressor::default()));
+    decoders.push(Box::new(EliasGammaCompressor::new()));
+    decoders.push(Box::new(EliasDeltaCompressor::new()));
+    decoders.push(Box::new(ExceptionBasedDeltaCompressor::default()));
+    decoders.push(Box::new(BlockDeltaCompressor::default()));
+    decoders.push(Box::new(ZigzagDeltaCompressor::new()));
+    decoders.push(Box::new(FibonacciCompressor::new()));
+    decoders.push(Box::new(Simple16Compressor::new()));
+    decoders.push(Box::new(PForDeltaCompressor::new(None)));
+    decoders.push(Box::new(AdaptiveCompressor::new(1)));
+    decoders
+}
+
+/// Decoders of `u64` sets.
+fn decoders_u64() -> Vec<Box<dyn IdSetCompressor<u64>>> {
+    vec![
+        Box::new(SplitEliasFanoCompressor::default()),
+        Box::new(TwoLevelEliasFanoCompressor::new()),
+        Box::new(WindowedCompressor::new(24)),
+    ]
+}
+
+/// Output must lie in the universe, and be sorted unless the codec keeps
+/// the input order.
+fn check<T: Ord + Copy>(ids: &[T], universe: T, sorted: bool) {
+    if sorted {
+        assert!(ids.windows(2).all(|w| w[0] < w[1]), "output must be sorted");
+    }
+    assert!(ids.iter().all(|&id| id < universe), "output must be in universe");
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 9 {
+        return;
+    }
+
+    let selector = data[0] as usize;
+    let universe = u64::from_le_bytes(data[1..9].try_into().unwrap()).max(1);
+    let compressed = &data[9..];
+
+    let decoders = decoders();
+    if selector < decoders.len() {
+        // Low four bytes pick the universe in 1..=u32::MAX
+        let universe = (universe as u32).max(1);
+        let decoder = &decoders[selector];
+        if let Ok(ids) = decoder.decompress_set(compressed, universe) {
+            check(&ids, universe, decoder.requires_sorted_input());
+        }
+    } else if let Some(decoder) = decoders_u64().get(selector - decoders.len()) {
+        if let Ok(ids) = decoder.decompress_set(compressed, universe) {
+            check(&ids, universe, decoder.requires_sorted_input());
+        }
+    }
+});
@@ -0,0 +1,19 @@
+//! Decode arbitrary bytes as a varint and check re-encoding is consistent.
+
+#![no_main]
+
+use cnk::varint::{decode_varint, encode_varint};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((value, consumed)) = decode_varint(data) {
+        assert!(consumed >= 1 && consumed <= data.len());
+
+        // Re-encoding yields the canonical (shortest) form, which must decode
+        // to the same value
+        let mut buf = Vec::new();
+        encode_varint(value, &mut buf);
+        assert!(buf.len() <= consumed);
+        assert_eq!(decode_varint(&buf).unwrap(), (value, buf.len()));
+    }
+});
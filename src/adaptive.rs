@@ -0,0 +1,337 @@
+//! A compressor that picks its codec from the first sets it sees.
+//!
+//! When an index is built as a stream, the first posting lists are a fair
+//! sample of the rest. [`AdaptiveCompressor`] compresses each of the first
+//! `calibration_sets` sets with every codec, keeping the smallest output,
+//! then settles on the codec with the fewest bytes over all of them.
+//!
+//! Output uses the tagged format of
+//! [`CompressorRegistry`](crate::CompressorRegistry): the
+//! [`IdCompressionMethod`] discriminant of the codec, then its bytes. Any
+//! `AdaptiveCompressor`, or the default registry, decodes it whatever the
+//! calibration state.
+
+use std::sync::Mutex;
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+use crate::{
+    BitmapSetCompressor, Codec, EliasFanoCompressor, IdCompressionMethod, InterpolativeCompressor,
+};
+
+/// Progress of the calibration phase.
+#[derive(Debug, Default)]
+struct Calibration {
+    /// Sets compressed so far, up to `calibration_sets`.
+    seen: usize,
+    /// Total bytes per codec over the sets seen, or `None` once a codec
+    /// has rejected one of them.
+    totals: Vec<Option<usize>>,
+    /// Index into `codecs` of the chosen codec.
+    winner: Option<usize>,
+}
+
+/// Chooses the smallest codec over its first `calibration_sets` sets.
+///
+/// The candidates are the codecs of the default
+/// [`CompressorRegistry`](crate::CompressorRegistry). Until calibration
+/// ends, each set is compressed with all of them. Codecs that reject a set
+/// are dropped from the running, and if all are dropped the winner is
+/// [`RocCompressor`]. Ties go to the lower tag.
+///
+/// # Thread safety
+///
+/// The calibration state sits behind a `Mutex`, so concurrent calls through
+/// a shared reference are safe. Calls during calibration hold the lock while
+/// they compress; after it, only long enough to read the winner. Not
+/// `Clone`; use [`save_state`](Self::save_state) to copy the state.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{AdaptiveCompressor, IdCompressionMethod, IdSetCompressor};
+///
+/// let adaptive = AdaptiveCompressor::new(2);
+/// // Dense sets: a bitset wins
+/// let sets: Vec<Vec<u32>> = (0..3).map(|i| (i..100).step_by(2).collect()).collect();
+/// let compressed: Vec<Vec<u8>> = sets
+///     .iter()
+///     .map(|ids| adaptive.compress_set(ids, 100).unwrap())
+///     .collect();
+/// assert_eq!(adaptive.winner(), Some(IdCompressionMethod::Bitset));
+///
+/// // A fresh compressor decodes all of them
+/// let other = AdaptiveCompressor::new(2);
+/// for (ids, bytes) in sets.iter().zip(&compressed) {
+///     assert_eq!(&other.decompress_set(bytes, 100).unwrap(), ids);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveCompressor {
+    calibration_sets: usize,
+    codecs: Vec<Codec>,
+    state: Mutex<Calibration>,
+}
+
+impl AdaptiveCompressor {
+    /// Create a compressor that calibrates on its first `calibration_sets`
+    /// sets. With zero, it uses [`RocCompressor`] from the start.
+    pub fn new(calibration_sets: usize) -> Self {
+        let codecs = vec![
+            Codec::Roc(RocCompressor::new()),
+            Codec::EliasFano(EliasFanoCompressor::new()),
+            Codec::Bitset(BitmapSetCompressor::new()),
+            Codec::Interpolative(InterpolativeCompressor::new()),
+        ];
+        let state = Calibration {
+            totals: vec![Some(0); codecs.len()],
+            winner: (calibration_sets == 0).then_some(0),
+            ..Calibration::default()
+        };
+        Self {
+            calibration_sets,
+            codecs,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// The chosen codec, or `None` while calibrating.
+    pub fn winner(&self) -> Option<IdCompressionMethod> {
+        self.lock()
+            .winner
+            .map(|i| IdCompressionMethod::from(self.codecs[i].clone()))
+    }
+
+    /// Sets still to be seen before calibration ends.
+    pub fn calibration_remaining(&self) -> usize {
+        let state = self.lock();
+        if state.winner.is_some() {
+            0
+        } else {
+            self.calibration_sets - state.seen
+        }
+    }
+
+    /// Encode the calibration state, for [`load_state`](Self::load_state).
+    ///
+    /// The layout is varints: `calibration_sets`, `seen`, `winner + 1` (0
+    /// for none), then per codec its running total `+ 1` (0 once dropped).
+    pub fn save_state(&self) -> Vec<u8> {
+        let state = self.lock();
+        let mut out = Vec::new();
+        encode_varint(self.calibration_sets as u64, &mut out);
+        encode_varint(state.seen as u64, &mut out);
+        encode_varint(state.winner.map_or(0, |i| i as u64 + 1), &mut out);
+        for total in &state.totals {
+            encode_varint(total.map_or(0, |t| t as u64 + 1), &mut out);
+        }
+        out
+    }
+
+    /// Restore a state written by [`save_state`](Self::save_state),
+    /// replacing this compressor's calibration length and progress.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `bytes` is not a valid
+    /// state; the compressor is then left unchanged.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), CompressionError> {
+        let invalid = || CompressionError::InvalidInput("Invalid adaptive state".to_string());
+        let mut offset = 0;
+        let mut next = || -> Result<u64, CompressionError> {
+            let (value, consumed) =
+                decode_varint(bytes.get(offset..).unwrap_or(&[])).map_err(|_| invalid())?;
+            offset += consumed;
+            Ok(value)
+        };
+
+        let calibration_sets = next()? as usize;
+        let seen = next()? as usize;
+        let winner = match next()? {
+            0 => None,
+            i if i as usize <= self.codecs.len() => Some(i as usize - 1),
+            _ => return Err(invalid()),
+        };
+        let totals = (0..self.codecs.len())
+            .map(|_| Ok(next()?.checked_sub(1).map(|t| t as usize)))
+            .collect::<Result<Vec<_>, CompressionError>>()?;
+        if offset != bytes.len() || seen > calibration_sets {
+            return Err(invalid());
+        }
+
+        self.calibration_sets = calibration_sets;
+        *self.state.get_mut().unwrap_or_else(|e| e.into_inner()) = Calibration {
+            seen,
+            totals,
+            winner,
+        };
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Calibration> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn tag(codec: &Codec) -> u8 {
+        IdCompressionMethod::from(codec.clone()) as u8
+    }
+
+    /// Compress one calibration set with every codec, returning the
+    /// smallest output with its tag.
+    fn calibrate(
+        &self,
+        state: &mut Calibration,
+        ids: &[u32],
+        universe_size: u32,
+    ) -> Result<(u8, Vec<u8>), CompressionError> {
+        let results: Vec<_> = self
+            .codecs
+            .iter()
+            .map(|codec| codec.compress_set(ids, universe_size))
+            .collect();
+        // A set every codec rejects is invalid input, not a calibration sample
+        if results.iter().all(Result::is_err) {
+            return Err(results.into_iter().find_map(Result::err).unwrap());
+        }
+
+        let mut best: Option<(u8, Vec<u8>)> = None;
+        for ((codec, total), result) in self.codecs.iter().zip(&mut state.totals).zip(results) {
+            let Ok(compressed) = result else {
+                *total = None;
+                continue;
+            };
+            if let Some(t) = total {
+                *t += compressed.len();
+            }
+            match &best {
+                Some((_, b)) if b.len() <= compressed.len() => {}
+                _ => best = Some((Self::tag(codec), compressed)),
+            }
+        }
+
+        state.seen += 1;
+        if state.seen == self.calibration_sets {
+            let winner = state
+                .totals
+                .iter()
+                .enumerate()
+                .filter_map(|(i, t)| t.map(|t| (t, i)))
+                .min()
+                .map_or(0, |(_, i)| i);
+            state.winner = Some(winner);
+        }
+        Ok(best.unwrap())
+    }
+}
+
+impl IdSetCompressor for AdaptiveCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let mut state = self.lock();
+        let (tag, compressed) = match state.winner {
+            Some(i) => {
+                drop(state);
+                let codec = &self.codecs[i];
+                (Self::tag(codec), codec.compress_set(ids, universe_size)?)
+            }
+            None => self.calibrate(&mut state, ids, universe_size)?,
+        };
+        let mut tagged = Vec::with_capacity(1 + compressed.len());
+        tagged.push(tag);
+        tagged.extend_from_slice(&compressed);
+        Ok(tagged)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let (&tag, payload) = compressed.split_first().ok_or_else(|| {
+            CompressionError::DecompressionFailed("Missing compressor tag".to_string())
+        })?;
+        let codec = self
+            .codecs
+            .iter()
+            .find(|c| Self::tag(c) == tag)
+            .ok_or_else(|| {
+                CompressionError::DecompressionFailed(format!("Unknown compressor tag {}", tag))
+            })?;
+        codec.decompress_set(payload, universe_size)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        let i = self.lock().winner.unwrap_or(0);
+        1 + self.codecs[i].estimate_size(num_ids, universe_size)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / num_ids as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressorRegistry;
+
+    #[test]
+    fn test_sparse_sets_pick_a_gap_codec() {
+        let adaptive = AdaptiveCompressor::new(3);
+        let sets: Vec<Vec<u32>> = (0..5u32)
+            .map(|s| (0..50).map(|i| i * 20_011 + s).collect())
+            .collect();
+        for (n, ids) in sets.iter().enumerate() {
+            assert_eq!(adaptive.calibration_remaining(), 3usize.saturating_sub(n));
+            let compressed = adaptive.compress_set(ids, 1_000_000).unwrap();
+            assert_eq!(
+                &adaptive.decompress_set(&compressed, 1_000_000).unwrap(),
+                ids
+            );
+            assert_eq!(
+                CompressorRegistry::default()
+                    .decompress_tagged(&compressed, 1_000_000)
+                    .unwrap(),
+                *ids
+            );
+        }
+        let winner = adaptive.winner().unwrap();
+        assert_ne!(winner, IdCompressionMethod::Bitset);
+    }
+
+    #[test]
+    fn test_rejected_sets_and_zero_calibration() {
+        let adaptive = AdaptiveCompressor::new(1);
+        assert!(adaptive.compress_set(&[3, 1], 10).is_err());
+        assert_eq!(adaptive.calibration_remaining(), 1);
+
+        let immediate = AdaptiveCompressor::new(0);
+        assert_eq!(immediate.winner(), Some(IdCompressionMethod::Roc));
+        let compressed = immediate.compress_set(&[1, 2], 10).unwrap();
+        assert_eq!(compressed[0], IdCompressionMethod::Roc as u8);
+        assert!(immediate.decompress_set(&[], 10).is_err());
+        assert!(immediate.decompress_set(&[200], 10).is_err());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let adaptive = AdaptiveCompressor::new(4);
+        adaptive.compress_set(&[1, 2, 3], 100).unwrap();
+        let mut restored = AdaptiveCompressor::new(1);
+        restored.load_state(&adaptive.save_state()).unwrap();
+        assert_eq!(restored.calibration_remaining(), 3);
+        assert_eq!(restored.save_state(), adaptive.save_state());
+
+        let before = restored.save_state();
+        assert!(restored.load_state(&[1, 2]).is_err());
+        assert!(restored.load_state(&[1, 0, 9, 1, 1, 1, 1]).is_err());
+        let mut trailing = before.clone();
+        trailing.push(0);
+        assert!(restored.load_state(&trailing).is_err());
+        assert_eq!(restored.save_state(), before);
+    }
+}
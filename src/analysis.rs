@@ -0,0 +1,312 @@
+//! Size distribution of compressed sets, and gap structure of raw ones.
+//!
+//! A handful of huge posting lists usually dominate index size and query
+//! latency, which a mean alone hides. [`SizeHistogram`] buckets compressed
+//! sizes by powers of two and reports percentiles, so an index build can log
+//! p50/p99/max next to the total.
+//!
+//! Before anything is compressed, [`analyze_gaps`] summarizes the gaps
+//! between consecutive IDs, which decide how well each codec does.
+
+use std::collections::HashMap;
+
+use crate::roc::RocCompressor;
+
+/// Upper bounds (inclusive) of the histogram buckets, in bytes.
+///
+/// A final overflow bucket holds everything above the last bound.
+const BUCKET_BOUNDS: [usize; 13] = [1, 2, 4, 8, 16, 32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// Histogram of compressed set sizes in power-of-two buckets.
+///
+/// Buckets are `[0, 1], (1, 2], (2, 4], ..., (2048, 4096]` and `> 4096`
+/// bytes. Mean and standard deviation are exact; percentiles interpolate
+/// linearly within a bucket and are clamped to the observed range.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RocCompressor};
+///
+/// let sets: Vec<Vec<u32>> = (1..100).map(|n| (0..n).collect()).collect();
+/// let histogram = RocCompressor::new().analyze_batch(&sets, 1000);
+///
+/// assert_eq!(histogram.count(), 99);
+/// assert_eq!(histogram.percentile(1.0), histogram.max());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SizeHistogram {
+    buckets: [u64; BUCKET_BOUNDS.len() + 1],
+    count: u64,
+    sum: f64,
+    sum_sq: f64,
+    min: usize,
+    max: usize,
+}
+
+impl SizeHistogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the size of one compressed set.
+    pub fn observe(&mut self, compressed: &[u8]) {
+        self.observe_size(compressed.len());
+    }
+
+    /// Record a compressed size in bytes.
+    pub fn observe_size(&mut self, size: usize) {
+        let bucket = BUCKET_BOUNDS.partition_point(|&bound| bound < size);
+        self.buckets[bucket] += 1;
+        self.min = if self.count == 0 {
+            size
+        } else {
+            self.min.min(size)
+        };
+        self.max = self.max.max(size);
+        self.count += 1;
+        self.sum += size as f64;
+        self.sum_sq += (size as f64) * (size as f64);
+    }
+
+    /// Number of sizes observed.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Largest size observed, or 0 if empty.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Number of sizes in each bucket, smallest bucket first.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Approximate size at quantile `p` (in `[0, 1]`), or 0 if empty.
+    ///
+    /// `percentile(1.0)` is exactly [`max`](Self::max).
+    pub fn percentile(&self, p: f64) -> usize {
+        if self.count == 0 {
+            return 0;
+        }
+
+        let rank = p.clamp(0.0, 1.0) * self.count as f64;
+        let mut below = 0u64;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            if n == 0 || ((below + n) as f64) < rank {
+                below += n;
+                continue;
+            }
+            let lower = if i == 0 { 0 } else { BUCKET_BOUNDS[i - 1] };
+            let upper = BUCKET_BOUNDS.get(i).copied().unwrap_or(self.max);
+            let fraction = (rank - below as f64) / n as f64;
+            let estimate = lower as f64 + fraction * (upper - lower) as f64;
+            return (estimate.round() as usize).clamp(self.min, self.max);
+        }
+        self.max
+    }
+
+    /// Mean size in bytes, or 0.0 if empty.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum / self.count as f64
+    }
+
+    /// Population standard deviation of sizes, or 0.0 if empty.
+    pub fn std_dev(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self.sum_sq / self.count as f64 - mean * mean)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+/// Summary of the gaps between consecutive IDs of a set.
+///
+/// All fields are zero when there are no gaps.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GapStatistics {
+    /// Smallest gap.
+    pub min_gap: u32,
+    /// Largest gap.
+    pub max_gap: u32,
+    /// Mean gap.
+    pub mean_gap: f64,
+    /// Population standard deviation of the gaps.
+    pub std_dev: f64,
+    /// Shannon entropy of the empirical gap distribution, in bits per gap.
+    ///
+    /// A lower bound for any code that stores each gap on its own.
+    pub entropy_bits: f64,
+    /// Fraction of gaps equal to 1, i.e. of IDs that extend a run.
+    pub fraction_unit_gaps: f64,
+    /// Fraction of gaps that are a power of two, 1 included.
+    pub fraction_power_of_two_gaps: f64,
+}
+
+impl GapStatistics {
+    /// Summarize a sequence of gaps.
+    pub(crate) fn from_gaps(gaps: impl IntoIterator<Item = u32>) -> Self {
+        let mut counts: HashMap<u32, u64> = HashMap::new();
+        let mut stats = Self {
+            min_gap: u32::MAX,
+            ..Self::default()
+        };
+        let (mut n, mut sum, mut sum_sq, mut unit, mut power_of_two) = (0u64, 0.0, 0.0, 0, 0);
+        for gap in gaps {
+            *counts.entry(gap).or_insert(0) += 1;
+            stats.min_gap = stats.min_gap.min(gap);
+            stats.max_gap = stats.max_gap.max(gap);
+            n += 1;
+            sum += gap as f64;
+            sum_sq += (gap as f64) * (gap as f64);
+            unit += (gap == 1) as u64;
+            power_of_two += gap.is_power_of_two() as u64;
+        }
+        if n == 0 {
+            return Self::default();
+        }
+
+        let n_f = n as f64;
+        stats.mean_gap = sum / n_f;
+        stats.std_dev = (sum_sq / n_f - stats.mean_gap * stats.mean_gap)
+            .max(0.0)
+            .sqrt();
+        stats.entropy_bits = counts
+            .values()
+            .map(|&count| {
+                let p = count as f64 / n_f;
+                -p * p.log2()
+            })
+            .sum::<f64>()
+            .max(0.0);
+        stats.fraction_unit_gaps = unit as f64 / n_f;
+        stats.fraction_power_of_two_gaps = power_of_two as f64 / n_f;
+        stats
+    }
+}
+
+/// Summarize the gaps between consecutive IDs of `ids`.
+///
+/// If `ids` has fewer than two IDs, or is not sorted and unique, there are
+/// no gaps and every field is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::analyze_gaps;
+///
+/// let stats = analyze_gaps(&[10, 11, 12, 14, 18]);
+/// assert_eq!((stats.min_gap, stats.max_gap), (1, 4));
+/// assert_eq!(stats.fraction_unit_gaps, 0.5);
+/// assert_eq!(stats.fraction_power_of_two_gaps, 1.0);
+/// assert_eq!(stats.entropy_bits, 1.5);
+/// ```
+pub fn analyze_gaps(ids: &[u32]) -> GapStatistics {
+    if RocCompressor::validate_ids(ids).is_err() {
+        return GapStatistics::default();
+    }
+    GapStatistics::from_gaps(ids.windows(2).map(|w| w[1] - w[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::{sample_ids, Lcg};
+    use crate::{IdSetCompressor, RocCompressor};
+
+    #[test]
+    fn test_bucket_boundaries() {
+        let mut histogram = SizeHistogram::new();
+        for size in [0, 1, 2, 3, 4096, 4097] {
+            histogram.observe_size(size);
+        }
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[0], 2);
+        assert_eq!(buckets[1], 1);
+        assert_eq!(buckets[2], 1);
+        assert_eq!(buckets[12], 1);
+        assert_eq!(buckets[13], 1);
+    }
+
+    #[test]
+    fn test_mean_and_std_dev() {
+        let mut histogram = SizeHistogram::new();
+        for size in [2, 4, 4, 4, 5, 5, 7, 9] {
+            histogram.observe_size(size);
+        }
+        assert_eq!(histogram.mean(), 5.0);
+        assert!((histogram.std_dev() - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_percentiles_of_random_batch() {
+        let mut rng = Lcg::new(12345);
+        let sets: Vec<Vec<u32>> = (0..1000)
+            .map(|_| {
+                let len = rng.below(2000) as usize;
+                sample_ids(1_000_000, len, rng.next_u64()).unwrap()
+            })
+            .collect();
+
+        let roc = RocCompressor::new();
+        let histogram = roc.analyze_batch(&sets, 1_000_000);
+        let max = sets
+            .iter()
+            .map(|ids| roc.compress_set(ids, 1_000_000).unwrap().len())
+            .max()
+            .unwrap();
+
+        assert_eq!(histogram.count(), 1000);
+        assert_eq!(histogram.percentile(1.0), max);
+        assert!(histogram.percentile(0.1) <= histogram.percentile(0.5));
+        assert!(histogram.percentile(0.5) <= histogram.percentile(0.99));
+    }
+
+    #[test]
+    fn test_gaps_of_consecutive_ids() {
+        let ids: Vec<u32> = (100..1100).collect();
+        let stats = analyze_gaps(&ids);
+        assert_eq!(stats.min_gap, 1);
+        assert_eq!(stats.max_gap, 1);
+        assert_eq!(stats.mean_gap, 1.0);
+        assert_eq!(stats.std_dev, 0.0);
+        assert!(stats.entropy_bits.abs() < 1e-12);
+        assert_eq!(stats.fraction_unit_gaps, 1.0);
+    }
+
+    #[test]
+    fn test_gaps_of_random_ids() {
+        let ids = sample_ids(1_000_000, 5000, 7).unwrap();
+
+        let stats = analyze_gaps(&ids);
+        assert!(stats.entropy_bits > 5.0, "{:?}", stats);
+        assert!(stats.min_gap < stats.max_gap);
+        // Gaps of a uniform sample are roughly geometric, so std dev ~ mean
+        assert!((stats.std_dev / stats.mean_gap - 1.0).abs() < 0.2);
+        assert!(stats.fraction_unit_gaps < 0.01);
+    }
+
+    #[test]
+    fn test_no_gaps() {
+        assert_eq!(analyze_gaps(&[]), GapStatistics::default());
+        assert_eq!(analyze_gaps(&[5]), GapStatistics::default());
+        assert_eq!(analyze_gaps(&[5, 3]), GapStatistics::default());
+    }
+
+    #[test]
+    fn test_empty() {
+        let histogram = SizeHistogram::new();
+        assert_eq!(histogram.percentile(0.5), 0);
+        assert_eq!(histogram.mean(), 0.0);
+        assert_eq!(histogram.std_dev(), 0.0);
+    }
+}
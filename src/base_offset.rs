@@ -0,0 +1,173 @@
+//! Compression of IDs drawn from a sub-range of the universe.
+//!
+//! HNSW layers, shards and tenants often own a contiguous ID range
+//! `[base, universe)`. [`BaseOffsetCompressor`] shifts IDs down by `base`
+//! before handing them to the inner compressor, which then sees a universe
+//! of `universe - base` instead of the global one.
+//!
+//! # Format
+//!
+//! ```text
+//! [base: u32 LE] [inner compressor output]
+//! ```
+//!
+//! The empty set is encoded as zero bytes, as for every other compressor.
+//!
+//! Universe-sized codecs (bitmaps, Elias-Fano style low bits) shrink with the
+//! smaller universe. Pure gap codecs such as [`RocCompressor`](crate::RocCompressor)
+//! only save on the first ID, which rarely pays for the 4-byte header.
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Wraps a compressor so IDs are stored relative to a fixed base.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{BaseOffsetCompressor, IdSetCompressor, RocCompressor};
+///
+/// // Layer occupying IDs 1_000_000..1_001_000
+/// let compressor = BaseOffsetCompressor::new(RocCompressor::new(), 1_000_000);
+/// let ids = vec![1_000_003u32, 1_000_010, 1_000_999];
+///
+/// let compressed = compressor.compress_set(&ids, 1_001_000).unwrap();
+/// assert_eq!(compressor.decompress_set(&compressed, 1_001_000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BaseOffsetCompressor<C> {
+    inner: C,
+    base: u32,
+}
+
+impl<C: IdSetCompressor> BaseOffsetCompressor<C> {
+    /// Wrap `inner`, storing IDs relative to `base`.
+    pub fn new(inner: C, base: u32) -> Self {
+        Self { inner, base }
+    }
+
+    /// Smallest ID this compressor accepts.
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// The wrapped compressor.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Universe seen by the inner compressor.
+    fn inner_universe(base: u32, universe_size: u32) -> Result<u32, CompressionError> {
+        universe_size.checked_sub(base).ok_or_else(|| {
+            CompressionError::InvalidInput(format!(
+                "Base {} exceeds universe size {}",
+                base, universe_size
+            ))
+        })
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor for BaseOffsetCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let inner_universe = Self::inner_universe(self.base, universe_size)?;
+
+        let shifted = ids
+            .iter()
+            .map(|&id| {
+                id.checked_sub(self.base).ok_or_else(|| {
+                    CompressionError::InvalidInput(format!("ID {} is below base {}", id, self.base))
+                })
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        let mut encoded = self.base.to_le_bytes().to_vec();
+        encoded.extend(self.inner.compress_set(&shifted, inner_universe)?);
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Use the stored base, so sets written with another base still decode
+        let base = compressed.get(..4).ok_or_else(|| {
+            CompressionError::DecompressionFailed("Unexpected end of compressed data".into())
+        })?;
+        let base = u32::from_le_bytes(base.try_into().unwrap());
+        let inner_universe = Self::inner_universe(base, universe_size)
+            .map_err(|e| CompressionError::DecompressionFailed(e.to_string()))?;
+
+        let mut ids = self
+            .inner
+            .decompress_set(&compressed[4..], inner_universe)?;
+        for id in &mut ids {
+            *id += base;
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+        let inner_universe = universe_size.saturating_sub(self.base);
+        4 + self.inner.estimate_size(num_ids, inner_universe)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        let inner_universe = universe_size.saturating_sub(self.base);
+        self.inner.theoretical_bits_per_id(num_ids, inner_universe)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.inner.requires_sorted_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_round_trip() {
+        let compressor = BaseOffsetCompressor::new(RocCompressor::new(), 500);
+        let ids = vec![500u32, 501, 750, 999];
+
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        assert_eq!(&compressed[..4], &500u32.to_le_bytes());
+        assert_eq!(compressor.decompress_set(&compressed, 1000).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_rejects_ids_below_base() {
+        let compressor = BaseOffsetCompressor::new(RocCompressor::new(), 500);
+        assert!(compressor.compress_set(&[499, 600], 1000).is_err());
+        assert!(compressor.compress_set(&[600], 400).is_err());
+    }
+
+    #[test]
+    fn test_decodes_with_stored_base() {
+        let writer = BaseOffsetCompressor::new(RocCompressor::new(), 100);
+        let reader = BaseOffsetCompressor::new(RocCompressor::new(), 0);
+
+        let compressed = writer.compress_set(&[100, 150], 200).unwrap();
+        assert_eq!(reader.decompress_set(&compressed, 200).unwrap(), [100, 150]);
+        assert!(reader.decompress_set(&compressed, 50).is_err());
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressor = BaseOffsetCompressor::new(RocCompressor::new(), 7);
+        assert!(compressor.compress_set(&[], 10).unwrap().is_empty());
+        assert!(compressor.decompress_set(&[], 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+//! Many compressed sets in one allocation.
+//!
+//! An IVF index holds one posting list per centroid, often tens of thousands
+//! of small sets. [`CompressedSetVec`] stores their compressed bytes back to
+//! back with an end offset per set, instead of one `Vec<u8>` each.
+
+use std::iter::FusedIterator;
+use std::ops::Index;
+
+use crate::budget::CompressionBudget;
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// A sequence of compressed sets sharing one buffer.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedSetVec, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let sets = vec![vec![1, 2, 3], vec![], vec![10, 500]];
+/// let batch = CompressedSetVec::compress_batch(&sets, 1000, &roc, None).unwrap();
+///
+/// assert_eq!(batch.len(), 3);
+/// assert!(batch[1].is_empty());
+/// for (i, bytes) in batch.iter() {
+///     assert_eq!(bytes, &batch[i]);
+/// }
+/// assert_eq!(batch.decompress_batch(1000, &roc).unwrap(), sets);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompressedSetVec {
+    bytes: Vec<u8>,
+    /// End offset in `bytes` of each set; set `i` starts where `i - 1` ends.
+    ends: Vec<usize>,
+}
+
+impl CompressedSetVec {
+    /// Create an empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress every set in `sets` with `c`, within `budget` if given.
+    ///
+    /// The input limit is checked before anything is compressed. The others
+    /// are checked after each set, before it is added to the batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `c.compress_set`, or
+    /// `CompressionError::BudgetExceeded` for the first limit crossed: on
+    /// output, `attempted` is the batch size including the set that did not
+    /// fit.
+    pub fn compress_batch(
+        sets: &[Vec<u32>],
+        universe: u32,
+        c: &dyn IdSetCompressor,
+        budget: Option<&CompressionBudget>,
+    ) -> Result<Self, CompressionError> {
+        let unlimited = CompressionBudget::default();
+        let budget = budget.unwrap_or(&unlimited);
+        let input_bytes = sets.iter().map(|ids| std::mem::size_of_val(&ids[..])).sum();
+        CompressionBudget::check(budget.max_input_bytes, input_bytes)?;
+
+        let mut batch = Self::new();
+        for ids in sets {
+            let compressed = c.compress_set(ids, universe)?;
+            CompressionBudget::check(budget.max_temp_bytes, compressed.len())?;
+            CompressionBudget::check(
+                budget.max_output_bytes,
+                batch.bytes.len() + compressed.len(),
+            )?;
+            batch.push(&compressed);
+        }
+        Ok(batch)
+    }
+
+    /// Append one compressed set.
+    pub fn push(&mut self, compressed: &[u8]) {
+        self.bytes.extend_from_slice(compressed);
+        self.ends.push(self.bytes.len());
+    }
+
+    /// Number of sets.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Whether the batch holds no sets.
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Compressed bytes of set `i`, or `None` if out of bounds.
+    pub fn get(&self, i: usize) -> Option<&[u8]> {
+        let end = *self.ends.get(i)?;
+        let start = if i == 0 { 0 } else { self.ends[i - 1] };
+        Some(&self.bytes[start..end])
+    }
+
+    /// Iterate over `(index, compressed_bytes)` pairs.
+    pub fn iter(&self) -> CompressedSetVecIter<'_> {
+        CompressedSetVecIter {
+            batch: self,
+            front: 0,
+            back: self.len(),
+        }
+    }
+
+    /// Decompress every set with `c`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `c.decompress_set`.
+    pub fn decompress_batch(
+        &self,
+        universe: u32,
+        c: &dyn IdSetCompressor,
+    ) -> Result<Vec<Vec<u32>>, CompressionError> {
+        self.iter()
+            .map(|(_, bytes)| c.decompress_set(bytes, universe))
+            .collect()
+    }
+
+    /// Decompress the sets one at a time, as the iterator is advanced.
+    ///
+    /// Only the set being yielded is held decompressed, so memory stays at
+    /// the compressed batch plus one set.
+    pub fn into_iter_decoded<'c>(
+        self,
+        universe: u32,
+        c: &'c dyn IdSetCompressor,
+    ) -> impl Iterator<Item = Result<Vec<u32>, CompressionError>> + 'c {
+        (0..self.len()).map(move |i| c.decompress_set(&self[i], universe))
+    }
+}
+
+impl Index<usize> for CompressedSetVec {
+    type Output = [u8];
+
+    /// Compressed bytes of set `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i >= self.len()`.
+    fn index(&self, i: usize) -> &[u8] {
+        match self.get(i) {
+            Some(bytes) => bytes,
+            None => panic!("index {} out of bounds for batch of {} sets", i, self.len()),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a CompressedSetVec {
+    type Item = (usize, &'a [u8]);
+    type IntoIter = CompressedSetVecIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the sets of a [`CompressedSetVec`], from [`CompressedSetVec::iter`].
+#[derive(Clone, Debug)]
+pub struct CompressedSetVecIter<'a> {
+    batch: &'a CompressedSetVec,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Iterator for CompressedSetVecIter<'a> {
+    type Item = (usize, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let i = self.front;
+        self.front += 1;
+        Some((i, &self.batch[i]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for CompressedSetVecIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some((self.back, &self.batch[self.back]))
+    }
+}
+
+impl ExactSizeIterator for CompressedSetVecIter<'_> {}
+
+impl FusedIterator for CompressedSetVecIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_get_and_index() {
+        let mut batch = CompressedSetVec::new();
+        batch.push(&[1, 2]);
+        batch.push(&[]);
+        batch.push(&[3]);
+
+        assert_eq!(batch.get(0), Some(&[1u8, 2][..]));
+        assert_eq!(&batch[1], &[] as &[u8]);
+        assert_eq!(&batch[2], &[3]);
+        assert_eq!(batch.get(3), None);
+        assert_eq!(
+            batch.iter().rev().map(|(i, _)| i).collect::<Vec<_>>(),
+            [2, 1, 0]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_index_out_of_bounds_panics() {
+        let _ = &CompressedSetVec::new()[0];
+    }
+
+    #[test]
+    fn test_decoded_iterator_reports_bad_set() {
+        let roc = RocCompressor::new();
+        let mut batch = CompressedSetVec::new();
+        batch.push(&roc.compress_set(&[1u32, 2], 10).unwrap());
+        batch.push(&[0xFF]);
+
+        let mut decoded = batch.into_iter_decoded(10, &roc);
+        assert_eq!(decoded.next().unwrap().unwrap(), [1, 2]);
+        assert!(decoded.next().unwrap().is_err());
+        assert!(decoded.next().is_none());
+    }
+}
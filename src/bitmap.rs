@@ -0,0 +1,173 @@
+//! Plain bitsets for very dense sets.
+//!
+//! Once a set holds more than about half its universe, no gap code beats
+//! one bit per possible ID. [`BitmapSetCompressor`] writes exactly that,
+//! with no header: the universe fixes the length, and trailing zero bytes
+//! are trimmed since they carry no members.
+//!
+//! # Format
+//!
+//! ```text
+//! [bits: up to ceil(universe / 8) bytes]   bit (id % 8) of byte (id / 8), MSB-first
+//! ```
+//!
+//! The empty set is encoded as zero bytes.
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+
+/// Set bit `id % 8` (MSB-first) of byte `id / 8` for each ID, in `len` bytes.
+///
+/// Every ID must be below `len * 8`.
+pub(crate) fn pack_bits(ids: &[u32], len: usize) -> Vec<u8> {
+    let mut bits = vec![0u8; len];
+    for &id in ids {
+        bits[id as usize / 8] |= 0x80 >> (id % 8);
+    }
+    bits
+}
+
+/// Positions of the set bits in `bits`, in ascending order.
+pub(crate) fn unpack_bits(bits: &[u8]) -> Vec<u32> {
+    let mut ids = Vec::with_capacity(bits.iter().map(|b| b.count_ones() as usize).sum());
+    for (i, &byte) in bits.iter().enumerate() {
+        let mut rest = byte;
+        while rest != 0 {
+            let bit = rest.leading_zeros();
+            ids.push(i as u32 * 8 + bit);
+            rest &= !(0x80 >> bit);
+        }
+    }
+    ids
+}
+
+/// Raw bitset compressor for sets covering most of the universe.
+///
+/// # Performance
+///
+/// - Best for: density above 50%, see [`density`](crate::math::density)
+/// - Size is fixed by the largest ID, so sparse sets waste almost all of it
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{BitmapSetCompressor, IdSetCompressor};
+///
+/// let bitmap = BitmapSetCompressor::new();
+/// let ids: Vec<u32> = (0..1000).filter(|i| i % 3 != 0).collect();
+/// let compressed = bitmap.compress_set(&ids, 1000).unwrap();
+/// assert_eq!(compressed.len(), 125);
+/// assert_eq!(bitmap.decompress_set(&compressed, 1000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BitmapSetCompressor;
+
+impl BitmapSetCompressor {
+    /// Create a new bitset compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for BitmapSetCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let last = match ids.last() {
+            Some(&last) => last,
+            None => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        // The byte holding the largest ID is the last non-zero one
+        Ok(pack_bits(ids, last as usize / 8 + 1))
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let max_bytes = (universe_size as usize).div_ceil(8);
+        if compressed.len() > max_bytes {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Bitset of {} bytes exceeds universe size {}",
+                compressed.len(),
+                universe_size
+            )));
+        }
+
+        let ids = unpack_bits(compressed);
+        if let Some(&last) = ids.last() {
+            if last >= universe_size {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    last, universe_size
+                )));
+            }
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+        (universe_size as usize).div_ceil(8)
+    }
+
+    fn estimate_compressed_size_bytes(&self, ids: &[u32], _universe_size: u32) -> usize {
+        ids.last().map_or(0, |&last| last as usize / 8 + 1)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/bitset")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_universe_takes_one_bit_per_id() {
+        let bitmap = BitmapSetCompressor::new();
+        for universe in [1u32, 7, 8, 9, 1000, 1001] {
+            let ids: Vec<u32> = (0..universe).collect();
+            let compressed = bitmap.compress_set(&ids, universe).unwrap();
+            assert_eq!(compressed.len(), (universe as usize).div_ceil(8));
+            assert_eq!(bitmap.decompress_set(&compressed, universe).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn test_trailing_zero_bytes_are_trimmed() {
+        let bitmap = BitmapSetCompressor::new();
+        let compressed = bitmap.compress_set(&[0u32, 9], 1000).unwrap();
+        assert_eq!(compressed, [0b1000_0000, 0b0100_0000]);
+        assert_eq!(bitmap.estimate_compressed_size_bytes(&[0, 9], 1000), 2);
+        assert!(bitmap.compress_set(&[], 1000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_ids_outside_universe() {
+        let bitmap = BitmapSetCompressor::new();
+        assert!(bitmap.compress_set(&[10u32], 10).is_err());
+        // Bit 10 fits in the second byte but not in the universe
+        assert!(bitmap.decompress_set(&[0, 0b0010_0000], 10).is_err());
+        assert!(bitmap.decompress_set(&[0, 0, 0], 10).is_err());
+    }
+}
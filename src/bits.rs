@@ -0,0 +1,135 @@
+//! Bit-level I/O for codecs that are not byte-aligned.
+//!
+//! Bits are written MSB-first within each byte. The final byte is padded
+//! with zero bits; decoders must know how many symbols to read rather than
+//! relying on end-of-stream.
+
+use crate::error::CompressionError;
+
+/// Appends bits to a byte buffer, MSB-first.
+#[derive(Debug, Default)]
+pub(crate) struct BitWriter {
+    buf: Vec<u8>,
+    /// Number of bits used in the last byte (0 means the last byte is full).
+    used: u32,
+}
+
+impl BitWriter {
+    /// Create an empty writer.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a single bit.
+    #[inline]
+    pub(crate) fn write_bit(&mut self, bit: bool) {
+        if self.used == 0 {
+            self.buf.push(0);
+        }
+        if bit {
+            *self.buf.last_mut().unwrap() |= 0x80 >> self.used;
+        }
+        self.used = (self.used + 1) % 8;
+    }
+
+    /// Write the low `nbits` bits of `value`, most significant first.
+    #[inline]
+    pub(crate) fn write_bits(&mut self, value: u64, nbits: u32) {
+        debug_assert!(nbits <= 64);
+        for i in (0..nbits).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    /// Finish writing and return the zero-padded bytes.
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Reads bits from a byte slice, MSB-first.
+#[derive(Debug)]
+pub(crate) struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Create a reader over `buf`, starting at the first bit.
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Create a reader over `buf`, starting at bit `pos`.
+    pub(crate) fn at(buf: &'a [u8], pos: usize) -> Self {
+        Self { buf, pos }
+    }
+
+    /// Read a single bit.
+    #[inline]
+    pub(crate) fn read_bit(&mut self) -> Result<bool, CompressionError> {
+        let byte = self.buf.get(self.pos / 8).ok_or_else(|| {
+            CompressionError::DecompressionFailed("Unexpected end of bit stream".to_string())
+        })?;
+        let bit = (byte >> (7 - (self.pos % 8))) & 1 == 1;
+        self.pos += 1;
+        Ok(bit)
+    }
+
+    /// Read `nbits` bits as an unsigned integer, most significant first.
+    #[inline]
+    pub(crate) fn read_bits(&mut self, nbits: u32) -> Result<u64, CompressionError> {
+        debug_assert!(nbits <= 64);
+        let mut value = 0u64;
+        for _ in 0..nbits {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+        Ok(value)
+    }
+
+    /// Number of bits consumed so far (including the starting offset).
+    pub(crate) fn bit_pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of whole bytes that have not been touched by any read.
+    pub(crate) fn unread_bytes(&self) -> usize {
+        self.buf.len() - self.pos.div_ceil(8).min(self.buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_round_trip() {
+        let mut writer = BitWriter::new();
+        writer.write_bit(true);
+        writer.write_bits(0b101, 3);
+        writer.write_bits(u64::MAX, 64);
+        writer.write_bits(0, 7);
+
+        let bytes = writer.finish();
+        assert_eq!(bytes.len(), 10);
+
+        let mut reader = BitReader::new(&bytes);
+        assert!(reader.read_bit().unwrap());
+        assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+        assert_eq!(reader.read_bits(64).unwrap(), u64::MAX);
+        assert_eq!(reader.read_bits(7).unwrap(), 0);
+        assert_eq!(reader.unread_bytes(), 0);
+
+        let mut reader = BitReader::at(&bytes, 4);
+        assert_eq!(reader.read_bits(8).unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        let mut reader = BitReader::new(&[0xFF]);
+        for _ in 0..8 {
+            assert!(reader.read_bit().unwrap());
+        }
+        assert!(reader.read_bit().is_err());
+    }
+}
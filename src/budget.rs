@@ -0,0 +1,145 @@
+//! Memory limits for batch compression.
+//!
+//! Compressing thousands of posting lists at once can outgrow a container's
+//! memory limit. A [`CompressionBudget`] caps what
+//! [`CompressedSetVec::compress_batch`](crate::CompressedSetVec::compress_batch)
+//! may read and build, failing with
+//! [`CompressionError::BudgetExceeded`] instead of growing past it.
+//! [`estimate_batch_size`] sizes a budget before compressing anything.
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Byte limits for one batch operation.
+///
+/// The default is unlimited on every axis.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedSetVec, CompressionBudget, CompressionError, RocCompressor};
+///
+/// let sets = vec![vec![1, 2, 3], vec![10, 500]];
+/// let budget = CompressionBudget {
+///     max_output_bytes: 6,
+///     ..CompressionBudget::default()
+/// };
+/// let result = CompressedSetVec::compress_batch(&sets, 1000, &RocCompressor::new(), Some(&budget));
+/// assert_eq!(
+///     result,
+///     Err(CompressionError::BudgetExceeded { limit: 6, attempted: 10 })
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressionBudget {
+    /// Total size of the input IDs, at their in-memory width.
+    pub max_input_bytes: usize,
+    /// Total compressed size of the batch.
+    pub max_output_bytes: usize,
+    /// Compressed size of any one set, which is held in a buffer of its
+    /// own before being copied into the batch.
+    pub max_temp_bytes: usize,
+}
+
+impl Default for CompressionBudget {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: usize::MAX,
+            max_output_bytes: usize::MAX,
+            max_temp_bytes: usize::MAX,
+        }
+    }
+}
+
+impl CompressionBudget {
+    /// Fail if `attempted` bytes would exceed `limit`.
+    pub(crate) fn check(limit: usize, attempted: usize) -> Result<(), CompressionError> {
+        if attempted > limit {
+            return Err(CompressionError::BudgetExceeded { limit, attempted });
+        }
+        Ok(())
+    }
+}
+
+/// Estimate the total compressed size of `sets` without compressing them.
+///
+/// Sums [`IdSetCompressor::estimate_compressed_size_bytes`] over the sets,
+/// so it is exact where that is, as for
+/// [`RocCompressor`](crate::RocCompressor).
+pub fn estimate_batch_size(sets: &[&[u32]], universe: u32, c: &dyn IdSetCompressor) -> usize {
+    sets.iter()
+        .map(|ids| c.estimate_compressed_size_bytes(ids, universe))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressedSetVec, RocCompressor};
+
+    #[test]
+    fn test_error_on_first_set_over_budget() {
+        let roc = RocCompressor::new();
+        let sets: Vec<Vec<u32>> = (0..10u32)
+            .map(|i| (0..20).map(|j| j * 50 + i).collect())
+            .collect();
+        let sizes: Vec<usize> = sets
+            .iter()
+            .map(|ids| roc.compress_set(ids, 1000).unwrap().len())
+            .collect();
+        let slices: Vec<&[u32]> = sets.iter().map(Vec::as_slice).collect();
+        let total: usize = sizes.iter().sum();
+        assert_eq!(estimate_batch_size(&slices, 1000, &roc), total);
+
+        // Room for the first six sets and part of the seventh
+        let limit = sizes[..6].iter().sum::<usize>() + 1;
+        let budget = CompressionBudget {
+            max_output_bytes: limit,
+            ..CompressionBudget::default()
+        };
+        assert_eq!(
+            CompressedSetVec::compress_batch(&sets, 1000, &roc, Some(&budget)),
+            Err(CompressionError::BudgetExceeded {
+                limit,
+                attempted: sizes[..7].iter().sum(),
+            })
+        );
+
+        let budget = CompressionBudget {
+            max_output_bytes: total,
+            ..CompressionBudget::default()
+        };
+        let batch = CompressedSetVec::compress_batch(&sets, 1000, &roc, Some(&budget)).unwrap();
+        assert_eq!(batch.decompress_batch(1000, &roc).unwrap(), sets);
+    }
+
+    #[test]
+    fn test_input_and_temp_limits() {
+        let roc = RocCompressor::new();
+        let sets = vec![vec![1u32, 2, 3], vec![5, 900]];
+        let budget = CompressionBudget {
+            max_input_bytes: 19,
+            ..CompressionBudget::default()
+        };
+        assert_eq!(
+            CompressedSetVec::compress_batch(&sets, 1000, &roc, Some(&budget)),
+            Err(CompressionError::BudgetExceeded {
+                limit: 19,
+                attempted: 20
+            })
+        );
+
+        // Each set takes 5 bytes on its own
+        let budget = CompressionBudget {
+            max_temp_bytes: 4,
+            ..CompressionBudget::default()
+        };
+        assert_eq!(
+            CompressedSetVec::compress_batch(&sets, 1000, &roc, Some(&budget)),
+            Err(CompressionError::BudgetExceeded {
+                limit: 4,
+                attempted: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Incremental construction of compressed sets.
+//!
+//! [`CompressedSetBuilder`] accepts IDs one at a time in ascending order and
+//! varint-encodes each delta as soon as it arrives, so memory usage is
+//! proportional to the compressed output rather than the number of IDs.
+//! The output is byte-identical to [`RocCompressor::compress_set`] at
+//! [`CompressionLevel::Default`](crate::CompressionLevel::Default).
+//!
+//! [`compress_set_append`] extends a set that is already compressed, for
+//! posting lists that grow one document at a time.
+//!
+//! [`RocCompressor::compress_set`]: crate::IdSetCompressor::compress_set
+
+use crate::error::CompressionError;
+use crate::roc::{CompressionLevel, RocCompressor};
+use crate::varint::{decode_varint, encode_varint};
+
+/// Streaming builder producing the [`RocCompressor`](crate::RocCompressor) format.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedSetBuilder, IdSetCompressor, RocCompressor};
+///
+/// let mut builder = CompressedSetBuilder::new(1000);
+/// for id in [1u32, 5, 10, 20, 50] {
+///     builder.push(id).unwrap();
+/// }
+/// let compressed = builder.finish().unwrap();
+///
+/// let ids: Vec<u32> = RocCompressor::new().decompress_set(&compressed, 1000).unwrap();
+/// assert_eq!(ids, vec![1, 5, 10, 20, 50]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompressedSetBuilder {
+    universe_size: u32,
+    /// Number of IDs pushed so far.
+    count: u64,
+    /// Last ID pushed, used for delta computation and ordering checks.
+    last: Option<u32>,
+    /// Encoded first ID and deltas (the level and count header is written on finish).
+    body: Vec<u8>,
+}
+
+impl CompressedSetBuilder {
+    /// Create an empty builder for IDs in `[0, universe_size)`.
+    pub fn new(universe_size: u32) -> Self {
+        Self {
+            universe_size,
+            count: 0,
+            last: None,
+            body: Vec::new(),
+        }
+    }
+
+    /// Append an ID to the set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `id` is not strictly greater
+    /// than the previous ID or is outside the universe. The builder is left
+    /// unchanged on error.
+    pub fn push(&mut self, id: u32) -> Result<(), CompressionError> {
+        if id >= self.universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                id, self.universe_size
+            )));
+        }
+
+        let delta = match self.last {
+            Some(last) if id <= last => {
+                return Err(CompressionError::InvalidInput(format!(
+                    "IDs must be sorted and unique, found {} <= {}",
+                    id, last
+                )));
+            }
+            Some(last) => id - last,
+            None => id,
+        };
+
+        encode_varint(delta as u64, &mut self.body);
+        self.last = Some(id);
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Number of IDs pushed so far.
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Whether no IDs have been pushed.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Finish building and return the compressed bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the output cannot be produced.
+    pub fn finish(self) -> Result<Vec<u8>, CompressionError> {
+        if self.count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut encoded = Vec::with_capacity(self.body.len() + 11);
+        RocCompressor::write_header(CompressionLevel::Default, self.count, &mut encoded);
+        encoded.extend_from_slice(&self.body);
+        Ok(encoded)
+    }
+}
+
+/// Append `new_id` to a set in the [`RocCompressor`] format, in place.
+///
+/// Only one delta is written and the length header is rewritten, so the
+/// result is byte-identical to compressing the extended set from scratch at
+/// the set's own level. Finding the last ID still walks the deltas, since
+/// the format stores no pointer to it, but nothing is decoded into memory.
+/// An empty buffer is treated as the empty set.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `new_id` is not greater than
+/// every ID in the set or is outside the universe, and
+/// `CompressionError::DecompressionFailed` if `compressed` is malformed.
+/// `compressed` is left unchanged on error.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{compress_set_append, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let mut compressed = roc.compress_set(&[1u32, 5, 10], 1000).unwrap();
+/// compress_set_append(&mut compressed, 1000, 42).unwrap();
+///
+/// assert_eq!(compressed, roc.compress_set(&[1u32, 5, 10, 42], 1000).unwrap());
+/// assert!(compress_set_append(&mut compressed, 1000, 42).is_err());
+/// ```
+pub fn compress_set_append(
+    compressed: &mut Vec<u8>,
+    universe: u32,
+    new_id: u32,
+) -> Result<(), CompressionError> {
+    if new_id >= universe {
+        return Err(CompressionError::InvalidInput(format!(
+            "ID {} exceeds universe size {}",
+            new_id, universe
+        )));
+    }
+    if compressed.is_empty() {
+        RocCompressor::write_header(CompressionLevel::Default, 1, compressed);
+        encode_varint(new_id as u64, compressed);
+        return Ok(());
+    }
+
+    let (count, header_len) = RocCompressor::read_header(compressed)?;
+    let mut offset = header_len;
+    let mut last: Option<u64> = None;
+    for _ in 0..count {
+        let (value, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+        last = match last {
+            None => Some(value),
+            Some(prev) => Some(prev.checked_add(value).ok_or_else(|| {
+                CompressionError::DecompressionFailed("Delta overflows ID range".to_string())
+            })?),
+        };
+    }
+    if offset < compressed.len() {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Extra data after decompression: {} bytes",
+            compressed.len() - offset
+        )));
+    }
+
+    let delta = match last {
+        Some(last) if new_id as u64 <= last => {
+            return Err(CompressionError::InvalidInput(format!(
+                "IDs must be sorted and unique, found {} <= {}",
+                new_id, last
+            )));
+        }
+        Some(last) => new_id as u64 - last,
+        None => new_id as u64,
+    };
+
+    let count = count + 1;
+    if compressed[0] == CompressionLevel::Fastest as u8 {
+        let count = u32::try_from(count).map_err(|_| {
+            CompressionError::InvalidInput(format!("Fastest level stores at most {} IDs", u32::MAX))
+        })?;
+        compressed[1..5].copy_from_slice(&count.to_le_bytes());
+    } else {
+        // The count may need one more varint byte, shifting the body
+        let mut header = Vec::with_capacity(10);
+        encode_varint(count, &mut header);
+        compressed.splice(1..header_len, header);
+    }
+    encode_varint(delta, compressed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdSetCompressor, RocCompressor};
+
+    #[test]
+    fn test_matches_compress_set() {
+        let ids = vec![0u32, 1, 7, 300, 301, 65_000];
+        let mut builder = CompressedSetBuilder::new(100_000);
+        for &id in &ids {
+            builder.push(id).unwrap();
+        }
+        assert_eq!(builder.len(), ids.len());
+
+        let expected = RocCompressor::new().compress_set(&ids, 100_000).unwrap();
+        assert_eq!(builder.finish().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_empty_builder() {
+        let builder = CompressedSetBuilder::new(1000);
+        assert!(builder.is_empty());
+        assert!(builder.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_out_of_order() {
+        let mut builder = CompressedSetBuilder::new(1000);
+        builder.push(5).unwrap();
+        assert!(builder.push(5).is_err());
+        assert!(builder.push(4).is_err());
+        assert!(builder.push(1000).is_err());
+
+        // Failed pushes leave the builder usable
+        builder.push(6).unwrap();
+        assert_eq!(builder.len(), 2);
+    }
+
+    #[test]
+    fn test_append_grows_count_varint() {
+        let roc = RocCompressor::new();
+        let mut ids: Vec<u32> = (0..127).collect();
+        let mut compressed = roc.compress_set(&ids, 1000u32).unwrap();
+        assert_eq!(compressed[1], 127);
+
+        // 128 IDs need a two-byte count
+        compress_set_append(&mut compressed, 1000, 500).unwrap();
+        ids.push(500);
+        assert_eq!(compressed, roc.compress_set(&ids, 1000u32).unwrap());
+    }
+
+    #[test]
+    fn test_append_keeps_level_and_rejects_bad_ids() {
+        let fastest = RocCompressor::with_level(CompressionLevel::Fastest);
+        let mut compressed = fastest.compress_set(&[3u32], 10).unwrap();
+        compress_set_append(&mut compressed, 10, 7).unwrap();
+        assert_eq!(compressed, fastest.compress_set(&[3u32, 7], 10).unwrap());
+
+        let before = compressed.clone();
+        assert!(compress_set_append(&mut compressed, 10, 7).is_err());
+        assert!(compress_set_append(&mut compressed, 10, 10).is_err());
+        assert_eq!(compressed, before);
+
+        let mut empty = Vec::new();
+        compress_set_append(&mut empty, 10, 0).unwrap();
+        assert_eq!(
+            empty,
+            RocCompressor::new().compress_set(&[0u32], 10).unwrap()
+        );
+
+        let mut padded = before;
+        padded.push(0);
+        assert!(compress_set_append(&mut padded, 10, 9).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Chunked compression of sets too large to buffer.
+//!
+//! A posting list of tens of millions of IDs is rarely available as one
+//! slice. [`CheckpointedCompressor`] takes it in sorted pieces and cuts it
+//! into chunks of a fixed number of IDs, each compressed on its own in the
+//! [`RocCompressor`](crate::RocCompressor) format. Each chunk stores its
+//! first ID in full, so decoding can start at any chunk boundary, and
+//! [`decompress_checkpointed`] only ever holds one chunk decoded.
+//!
+//! # Format
+//!
+//! ```text
+//! [num_chunks: varint] [(chunk_len: varint, chunk: chunk_len bytes) * num_chunks]
+//! ```
+//!
+//! The empty set is encoded as zero bytes.
+
+use crate::builder::CompressedSetBuilder;
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Streaming compressor writing a set as independently decodable chunks.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{decompress_checkpointed, CheckpointedCompressor};
+///
+/// let mut compressor = CheckpointedCompressor::new(1_000_000, 1000);
+/// for start in (0..10_000u32).step_by(2500) {
+///     let piece: Vec<u32> = (start..start + 2500).map(|i| i * 7).collect();
+///     compressor.feed(&piece).unwrap();
+/// }
+/// let compressed = compressor.finish();
+///
+/// let ids: Result<Vec<u32>, _> = decompress_checkpointed(&compressed, 1_000_000).collect();
+/// assert_eq!(ids.unwrap(), (0..10_000).map(|i| i * 7).collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Debug)]
+pub struct CheckpointedCompressor {
+    universe: u32,
+    chunk_size: usize,
+    /// The chunk being filled.
+    current: CompressedSetBuilder,
+    /// Last ID fed, carried across chunk boundaries.
+    last: Option<u32>,
+    /// Framed chunks flushed so far.
+    chunks: Vec<u8>,
+    num_chunks: u64,
+}
+
+impl CheckpointedCompressor {
+    /// Create a compressor for IDs in `[0, universe)`, cutting a chunk every
+    /// `chunk_size` IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn new(universe: u32, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        Self {
+            universe,
+            chunk_size,
+            current: CompressedSetBuilder::new(universe),
+            last: None,
+            chunks: Vec::new(),
+            num_chunks: 0,
+        }
+    }
+
+    /// Append sorted IDs, all greater than any fed before.
+    ///
+    /// Every full chunk is compressed as soon as it fills up.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if an ID is out of order or
+    /// outside the universe. IDs before it have been taken; it and the rest
+    /// of `ids` have not.
+    pub fn feed(&mut self, ids: &[u32]) -> Result<(), CompressionError> {
+        for &id in ids {
+            if let Some(last) = self.last {
+                if id <= last {
+                    return Err(CompressionError::InvalidInput(format!(
+                        "IDs must be sorted and unique, found {} <= {}",
+                        id, last
+                    )));
+                }
+            }
+            self.current.push(id)?;
+            self.last = Some(id);
+            if self.current.len() == self.chunk_size {
+                self.checkpoint();
+            }
+        }
+        Ok(())
+    }
+
+    /// End the current chunk early, even if it is not full.
+    ///
+    /// Returns the framed chunk as appended to the output, e.g. to persist
+    /// progress as it is made, or nothing if no IDs were fed since the last
+    /// chunk.
+    pub fn checkpoint(&mut self) -> Vec<u8> {
+        if self.current.is_empty() {
+            return Vec::new();
+        }
+        let builder =
+            std::mem::replace(&mut self.current, CompressedSetBuilder::new(self.universe));
+        let chunk = builder
+            .finish()
+            .expect("finishing a non-empty builder cannot fail");
+
+        let mut frame = Vec::with_capacity(chunk.len() + 5);
+        encode_varint(chunk.len() as u64, &mut frame);
+        frame.extend_from_slice(&chunk);
+        self.chunks.extend_from_slice(&frame);
+        self.num_chunks += 1;
+        frame
+    }
+
+    /// Number of chunks flushed so far.
+    pub fn num_chunks(&self) -> u64 {
+        self.num_chunks
+    }
+
+    /// Flush the last chunk and return the whole compressed set.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.checkpoint();
+        if self.num_chunks == 0 {
+            return Vec::new();
+        }
+
+        let mut out = Vec::with_capacity(self.chunks.len() + 10);
+        encode_varint(self.num_chunks, &mut out);
+        out.extend_from_slice(&self.chunks);
+        out
+    }
+}
+
+/// Lazily decode a set written by [`CheckpointedCompressor`].
+///
+/// Chunks are decoded one at a time as the iterator reaches them. The first
+/// error ends the iteration.
+pub fn decompress_checkpointed(
+    compressed: &[u8],
+    universe: u32,
+) -> impl Iterator<Item = Result<u32, CompressionError>> + '_ {
+    ChunkIter {
+        compressed,
+        pos: 0,
+        remaining: None,
+        universe,
+        chunk: Vec::new().into_iter(),
+        last: None,
+        done: compressed.is_empty(),
+    }
+}
+
+/// Iterator behind [`decompress_checkpointed`].
+struct ChunkIter<'a> {
+    compressed: &'a [u8],
+    pos: usize,
+    /// Chunks left to decode; `None` until the header is read.
+    remaining: Option<u64>,
+    universe: u32,
+    /// IDs of the current chunk not yet yielded.
+    chunk: std::vec::IntoIter<u32>,
+    last: Option<u32>,
+    done: bool,
+}
+
+impl ChunkIter<'_> {
+    /// Read the chunk count, or the next chunk into `self.chunk`.
+    ///
+    /// Returns `Ok(false)` once every chunk has been read.
+    fn advance(&mut self) -> Result<bool, CompressionError> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => {
+                let (num_chunks, consumed) = decode_varint(self.compressed)?;
+                self.pos = consumed;
+                num_chunks
+            }
+        };
+
+        if remaining == 0 {
+            if self.pos < self.compressed.len() {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Extra data after decompression: {} bytes",
+                    self.compressed.len() - self.pos
+                )));
+            }
+            self.remaining = Some(0);
+            return Ok(false);
+        }
+
+        let (len, consumed) = decode_varint(&self.compressed[self.pos..])?;
+        let start = self.pos + consumed;
+        let end = start
+            .checked_add(len as usize)
+            .filter(|&end| end <= self.compressed.len())
+            .ok_or_else(|| {
+                CompressionError::DecompressionFailed(
+                    "Unexpected end of compressed data".to_string(),
+                )
+            })?;
+        let ids: Vec<u32> =
+            RocCompressor::new().decompress_set(&self.compressed[start..end], self.universe)?;
+
+        match (ids.first(), self.last) {
+            (None, _) => {
+                return Err(CompressionError::DecompressionFailed(
+                    "Empty chunk".to_string(),
+                ))
+            }
+            (Some(&first), Some(last)) if first <= last => {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "IDs must be sorted and unique, found {} <= {}",
+                    first, last
+                )))
+            }
+            _ => {}
+        }
+
+        self.last = ids.last().copied();
+        self.chunk = ids.into_iter();
+        self.pos = end;
+        self.remaining = Some(remaining - 1);
+        Ok(true)
+    }
+}
+
+impl Iterator for ChunkIter<'_> {
+    type Item = Result<u32, CompressionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(id) = self.chunk.next() {
+                return Some(Ok(id));
+            }
+            if self.done {
+                return None;
+            }
+            match self.advance() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(compressed: &[u8], universe: u32) -> Result<Vec<u32>, CompressionError> {
+        decompress_checkpointed(compressed, universe).collect()
+    }
+
+    #[test]
+    fn test_chunks_cut_at_chunk_size() {
+        let mut compressor = CheckpointedCompressor::new(100, 3);
+        compressor.feed(&[1, 2]).unwrap();
+        assert_eq!(compressor.num_chunks(), 0);
+        compressor.feed(&[3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(compressor.num_chunks(), 2);
+
+        let frame = compressor.checkpoint();
+        assert_eq!(compressor.num_chunks(), 3);
+        // len, then level, count and one ID
+        assert_eq!(frame, [3, 5, 1, 7]);
+        assert!(compressor.checkpoint().is_empty());
+
+        let compressed = compressor.finish();
+        assert_eq!(compressed[0], 3);
+        assert_eq!(decode(&compressed, 100).unwrap(), [1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_rejects_out_of_order_across_chunks() {
+        let mut compressor = CheckpointedCompressor::new(100, 2);
+        compressor.feed(&[5, 6]).unwrap();
+        assert!(compressor.feed(&[6]).is_err());
+        assert!(compressor.feed(&[100]).is_err());
+        compressor.feed(&[7]).unwrap();
+        assert_eq!(decode(&compressor.finish(), 100).unwrap(), [5, 6, 7]);
+    }
+
+    #[test]
+    fn test_empty_and_corrupt_input() {
+        let compressed = CheckpointedCompressor::new(100, 2).finish();
+        assert!(compressed.is_empty());
+        assert!(decode(&compressed, 100).unwrap().is_empty());
+
+        let mut compressor = CheckpointedCompressor::new(100, 2);
+        compressor.feed(&[1, 2, 3]).unwrap();
+        let compressed = compressor.finish();
+
+        let mut padded = compressed.clone();
+        padded.push(0);
+        assert!(decode(&padded, 100).is_err());
+        assert!(decode(&compressed[..compressed.len() - 1], 100).is_err());
+
+        // The first chunk twice: the second copy repeats its IDs
+        let chunk = &compressed[1..1 + 1 + compressed[1] as usize];
+        let repeated = [&[2], chunk, chunk].concat();
+        assert!(decode(&repeated, 100).is_err());
+    }
+}
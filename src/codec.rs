@@ -0,0 +1,223 @@
+//! Enum dispatch over the concrete compressors.
+//!
+//! `Box<dyn IdSetCompressor>` erases the concrete type, so inherent methods
+//! such as [`RocCompressor::next_geq`] are out of reach and every call goes
+//! through a vtable. [`Codec`] holds one of the compressors by value instead:
+//! it implements [`IdSetCompressor`] by matching on the variant, and the
+//! variant can still be matched to reach the concrete type.
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+use crate::{
+    BitmapSetCompressor, EliasFanoCompressor, IdCompressionMethod, InterpolativeCompressor,
+    RocCompressor,
+};
+
+/// One of the crate's set compressors, chosen at runtime.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{Codec, IdCompressionMethod, IdSetCompressor};
+///
+/// let codec: Codec = IdCompressionMethod::EliasFano.try_into().unwrap();
+/// let compressed = codec.compress_set(&[2, 3, 5, 7], 10).unwrap();
+/// assert_eq!(codec.decompress_set(&compressed, 10).unwrap(), [2, 3, 5, 7]);
+/// assert_eq!(IdCompressionMethod::from(codec), IdCompressionMethod::EliasFano);
+/// ```
+#[derive(Clone, Debug)]
+pub enum Codec {
+    /// Delta + varint, see [`RocCompressor`].
+    Roc(RocCompressor),
+    /// Plain Elias-Fano, see [`EliasFanoCompressor`].
+    EliasFano(EliasFanoCompressor),
+    /// Roaring bitmap, see [`RoaringBitmapCompressor`](crate::RoaringBitmapCompressor).
+    #[cfg(feature = "roaring")]
+    Bitmap(crate::RoaringBitmapCompressor),
+    /// Binary interpolative coding, see [`InterpolativeCompressor`].
+    Interpolative(InterpolativeCompressor),
+    /// Plain bitset, see [`BitmapSetCompressor`].
+    Bitset(BitmapSetCompressor),
+}
+
+/// Evaluate `$call` with `$c` bound to whichever compressor `$codec` holds.
+macro_rules! dispatch {
+    ($codec:expr, $c:ident => $call:expr) => {
+        match $codec {
+            Codec::Roc($c) => $call,
+            Codec::EliasFano($c) => $call,
+            #[cfg(feature = "roaring")]
+            Codec::Bitmap($c) => $call,
+            Codec::Interpolative($c) => $call,
+            Codec::Bitset($c) => $call,
+        }
+    };
+}
+
+impl IdSetCompressor for Codec {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        dispatch!(self, c => c.compress_set(ids, universe_size))
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        dispatch!(self, c => c.decompress_set(compressed, universe_size))
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        dispatch!(self, c => c.estimate_size(num_ids, universe_size))
+    }
+
+    fn estimate_compressed_size_bytes(&self, ids: &[u32], universe_size: u32) -> usize {
+        dispatch!(self, c => c.estimate_compressed_size_bytes(ids, universe_size))
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        dispatch!(self, c => c.theoretical_bits_per_id(num_ids, universe_size))
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        dispatch!(self, c => IdSetCompressor::<u32>::requires_sorted_input(c))
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        dispatch!(self, c => IdSetCompressor::<u32>::format_id(c))
+    }
+}
+
+impl TryFrom<IdCompressionMethod> for Codec {
+    type Error = CompressionError;
+
+    /// The codec for `method` in its default configuration.
+    ///
+    /// Fails for [`IdCompressionMethod::Uncompressed`] and
+    /// [`IdCompressionMethod::WaveletTree`], which have no compressor, and
+    /// for [`IdCompressionMethod::RoaringBitmap`] without the `roaring`
+    /// feature.
+    // Every variant is named so that a new method is a compile error here
+    // rather than silently falling through to "no codec"
+    fn try_from(method: IdCompressionMethod) -> Result<Self, Self::Error> {
+        match method {
+            IdCompressionMethod::Roc => Ok(Codec::Roc(RocCompressor::new())),
+            IdCompressionMethod::EliasFano => Ok(Codec::EliasFano(EliasFanoCompressor::new())),
+            #[cfg(feature = "roaring")]
+            IdCompressionMethod::RoaringBitmap => {
+                Ok(Codec::Bitmap(crate::RoaringBitmapCompressor::new()))
+            }
+            IdCompressionMethod::Interpolative => {
+                Ok(Codec::Interpolative(InterpolativeCompressor::new()))
+            }
+            IdCompressionMethod::Bitset => Ok(Codec::Bitset(BitmapSetCompressor::new())),
+            #[cfg(not(feature = "roaring"))]
+            IdCompressionMethod::RoaringBitmap => Err(CompressionError::InvalidInput(
+                "RoaringBitmap requires the `roaring` feature".to_string(),
+            )),
+            IdCompressionMethod::Uncompressed | IdCompressionMethod::WaveletTree => Err(
+                CompressionError::InvalidInput(format!("{:?} has no codec", method)),
+            ),
+        }
+    }
+}
+
+impl From<Codec> for IdCompressionMethod {
+    fn from(codec: Codec) -> Self {
+        match codec {
+            Codec::Roc(_) => IdCompressionMethod::Roc,
+            Codec::EliasFano(_) => IdCompressionMethod::EliasFano,
+            #[cfg(feature = "roaring")]
+            Codec::Bitmap(_) => IdCompressionMethod::RoaringBitmap,
+            Codec::Interpolative(_) => IdCompressionMethod::Interpolative,
+            Codec::Bitset(_) => IdCompressionMethod::Bitset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_round_trip() {
+        for method in [
+            IdCompressionMethod::Roc,
+            IdCompressionMethod::EliasFano,
+            IdCompressionMethod::Interpolative,
+            IdCompressionMethod::Bitset,
+            #[cfg(feature = "roaring")]
+            IdCompressionMethod::RoaringBitmap,
+        ] {
+            let codec = Codec::try_from(method.clone()).unwrap();
+            assert_eq!(IdCompressionMethod::from(codec), method);
+        }
+        assert!(Codec::try_from(IdCompressionMethod::Uncompressed).is_err());
+        assert!(Codec::try_from(IdCompressionMethod::WaveletTree).is_err());
+    }
+
+    /// Every method, in discriminant order.
+    fn all_methods() -> Vec<IdCompressionMethod> {
+        // Fails to compile when a variant is added, until it is listed below
+        const _: () = match IdCompressionMethod::Roc {
+            IdCompressionMethod::Roc
+            | IdCompressionMethod::EliasFano
+            | IdCompressionMethod::Bitset
+            | IdCompressionMethod::Interpolative
+            | IdCompressionMethod::Uncompressed
+            | IdCompressionMethod::WaveletTree
+            | IdCompressionMethod::RoaringBitmap => (),
+        };
+        vec![
+            IdCompressionMethod::Roc,
+            IdCompressionMethod::EliasFano,
+            IdCompressionMethod::Bitset,
+            IdCompressionMethod::Interpolative,
+            IdCompressionMethod::Uncompressed,
+            IdCompressionMethod::WaveletTree,
+            IdCompressionMethod::RoaringBitmap,
+        ]
+    }
+
+    #[test]
+    fn test_codec_for_every_method() {
+        for (tag, method) in all_methods().into_iter().enumerate() {
+            assert_eq!(method.clone() as u8, tag as u8);
+            let has_codec = !matches!(
+                method,
+                IdCompressionMethod::Uncompressed | IdCompressionMethod::WaveletTree
+            ) && (cfg!(feature = "roaring")
+                || method != IdCompressionMethod::RoaringBitmap);
+            match Codec::try_from(method.clone()) {
+                Ok(codec) => {
+                    assert!(has_codec, "{:?}", method);
+                    assert_eq!(IdCompressionMethod::from(codec), method);
+                }
+                Err(err) => {
+                    assert!(!has_codec, "{:?}: {}", method, err);
+                    assert!(matches!(err, CompressionError::InvalidInput(_)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatch_matches_inner() {
+        let ids = vec![1u32, 4, 9, 16, 25];
+        let codec = Codec::Interpolative(InterpolativeCompressor::new());
+        assert_eq!(
+            codec.compress_set(&ids, 100).unwrap(),
+            InterpolativeCompressor::new()
+                .compress_set(&ids, 100)
+                .unwrap()
+        );
+        assert_eq!(codec.format_id(), Some("cnk/interpolative"));
+
+        // Inherent methods stay reachable through the variant
+        let codec = Codec::Roc(RocCompressor::new());
+        let compressed = codec.compress_set(&ids, 100).unwrap();
+        if let Codec::Roc(roc) = &codec {
+            assert_eq!(roc.next_geq(&compressed, 100, 10).unwrap(), Some(16));
+        }
+    }
+}
@@ -0,0 +1,618 @@
+//! Self-describing container for a compressed set.
+//!
+//! The bare output of an [`IdSetCompressor`] can only be decoded by a caller
+//! who remembers which compressor and universe produced it. [`CompressedSet`]
+//! prefixes the payload with both, so a set read back from disk or the
+//! network can be decoded on its own.
+//!
+//! # Format
+//!
+//! ```text
+//! [magic: b"CNKS"] [method: u8] [hash: u8] [universe: u32 LE]
+//! [payload: compressor output]
+//! [fingerprint: u64 LE]   (only if hash != 0)
+//! ```
+//!
+//! `method` identifies the compressor's format (see [`IdSetCompressor::format_id`]).
+//! `hash` is 0 for a plain [`CompressedSet`] and names the fingerprint
+//! algorithm for a [`CompressedSetWithHash`], whose fingerprint covers every
+//! preceding byte.
+
+use std::fmt;
+
+use crate::error::CompressionError;
+use crate::fingerprint::{fingerprint_with, DEFAULT_ALGORITHM};
+use crate::traits::IdSetCompressor;
+use crate::{
+    BlockDeltaCompressor, EliasFanoCompressor, FibonacciCompressor, HuffmanCompressor,
+    InterpolativeCompressor, PForDeltaCompressor, RocCompressor, SegmentedCompressor,
+    Simple16Compressor, XorDeltaCompressor, ZigzagDeltaCompressor,
+};
+
+/// Magic bytes at the start of every container.
+const MAGIC: &[u8; 4] = b"CNKS";
+
+/// Size of magic, method, hash and universe.
+pub(crate) const HEADER_LEN: usize = 10;
+
+/// Size of the fingerprint trailer.
+const FINGERPRINT_LEN: usize = 8;
+
+/// Method tags, keyed by [`IdSetCompressor::format_id`].
+const METHODS: &[(u8, &str)] = &[
+    (1, "cnk/delta-varint"),
+    (2, "cnk/block-delta"),
+    (3, "cnk/huffman"),
+    (4, "cnk/pfor-delta"),
+    (5, "cnk/fibonacci"),
+    (6, "cnk/xor-delta"),
+    (7, "cnk/simple16"),
+    (8, "roaring/portable"),
+    (9, "cnk/segmented"),
+    (10, "cnk/zigzag-delta"),
+    (11, "cnk/elias-fano"),
+    (12, "cnk/interpolative"),
+];
+
+/// Method tag for a compressor's format, if it has one.
+fn method_tag(c: &dyn IdSetCompressor) -> Option<u8> {
+    let format = c.format_id()?;
+    METHODS
+        .iter()
+        .find(|&&(_, id)| id == format)
+        .map(|&(tag, _)| tag)
+}
+
+/// Compressor able to decode payloads with method `tag`.
+///
+/// Every supported format describes its own parameters, so a default
+/// instance decodes what any configuration wrote.
+fn decoder(tag: u8) -> Option<Box<dyn IdSetCompressor>> {
+    Some(match tag {
+        1 => Box::new(RocCompressor::new()),
+        2 => Box::new(BlockDeltaCompressor::default()),
+        3 => Box::new(HuffmanCompressor::new()),
+        4 => Box::new(PForDeltaCompressor::default()),
+        5 => Box::new(FibonacciCompressor::new()),
+        6 => Box::new(XorDeltaCompressor::new()),
+        7 => Box::new(Simple16Compressor::new()),
+        #[cfg(feature = "roaring")]
+        8 => Box::new(crate::RoaringBitmapCompressor::new()),
+        9 => Box::new(SegmentedCompressor::default()),
+        10 => Box::new(ZigzagDeltaCompressor::new()),
+        11 => Box::new(EliasFanoCompressor::new()),
+        12 => Box::new(InterpolativeCompressor::new()),
+        _ => return None,
+    })
+}
+
+/// Parsed container header.
+struct Header {
+    method: u8,
+    hash: u8,
+    universe: u32,
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Header, CompressionError> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err(CompressionError::DecompressionFailed(
+            "Not a compressed set".to_string(),
+        ));
+    }
+    let header = Header {
+        method: bytes[4],
+        hash: bytes[5],
+        universe: u32::from_le_bytes(bytes[6..10].try_into().unwrap()),
+    };
+    if decoder(header.method).is_none() {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Unknown compression method tag {}",
+            header.method
+        )));
+    }
+    Ok(header)
+}
+
+/// A compressed set together with its method and universe.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedSet, RocCompressor};
+///
+/// let set = CompressedSet::new(&[3, 14, 15, 92], 100, &RocCompressor::new()).unwrap();
+/// let bytes = set.into_bytes();
+///
+/// // Later, without knowing the compressor or universe
+/// let set = CompressedSet::from_bytes(bytes).unwrap();
+/// assert_eq!(set.universe(), 100);
+/// assert_eq!(set.decompress().unwrap(), vec![3, 14, 15, 92]);
+/// ```
+///
+/// `Display` prints the header, the decoded element count and the start of
+/// the payload in hex; `Debug` adds an `xxd`-style dump of every byte.
+///
+/// Equality and `Hash` compare the bytes, header included, so a set can key
+/// a deduplication `HashMap`. Compression is deterministic, so the same IDs
+/// compressed with the same compressor and configuration give equal sets;
+/// another configuration of the same method generally does not.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CompressedSet {
+    bytes: Vec<u8>,
+}
+
+impl CompressedSet {
+    /// Compress `ids` with `c` and wrap the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `c` has no format known to
+    /// the container, or any error from `c.compress_set`.
+    pub fn new(
+        ids: &[u32],
+        universe: u32,
+        c: &dyn IdSetCompressor,
+    ) -> Result<Self, CompressionError> {
+        let method = method_tag(c).ok_or_else(|| {
+            CompressionError::InvalidInput(format!(
+                "Compressor format {:?} cannot be stored in a compressed set",
+                c.format_id()
+            ))
+        })?;
+
+        let payload = c.compress_set(ids, universe)?;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(method);
+        bytes.push(0);
+        bytes.extend_from_slice(&universe.to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(Self { bytes })
+    }
+
+    /// Compress `ids` with [`RocCompressor`], inferring the universe.
+    ///
+    /// The universe is `max_id + 1`, or 1 for the empty set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `ids` is not sorted and
+    /// unique, or contains `u32::MAX` (the universe would not fit in a `u32`).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::CompressedSet;
+    ///
+    /// let set = CompressedSet::from_ids(&[2, 3, 5, 7]).unwrap();
+    /// assert_eq!(set.universe(), 8);
+    ///
+    /// let ids: Vec<u32> = set.try_into().unwrap();
+    /// assert_eq!(ids, [2, 3, 5, 7]);
+    /// ```
+    pub fn from_ids(ids: &[u32]) -> Result<Self, CompressionError> {
+        let universe = match ids.iter().max() {
+            Some(&max) => max.checked_add(1).ok_or_else(|| {
+                CompressionError::InvalidInput(format!(
+                    "ID {} leaves no room for an inferred universe",
+                    max
+                ))
+            })?,
+            None => 1,
+        };
+        Self::new(ids, universe, &RocCompressor::new())
+    }
+
+    /// Wrap bytes produced by [`into_bytes`](Self::into_bytes).
+    ///
+    /// Only the header is checked; the payload is decoded lazily.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` if the header is
+    /// malformed or carries a fingerprint (use [`CompressedSetWithHash`]).
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, CompressionError> {
+        if parse_header(&bytes)?.hash != 0 {
+            return Err(CompressionError::DecompressionFailed(
+                "Compressed set carries a fingerprint".to_string(),
+            ));
+        }
+        Ok(Self { bytes })
+    }
+
+    /// Whether `bytes` is a well-formed container whose payload decodes.
+    ///
+    /// If a fingerprint is present it must also match.
+    pub fn is_valid_compressed(bytes: &[u8]) -> bool {
+        let Ok(header) = parse_header(bytes) else {
+            return false;
+        };
+        let payload_end = if header.hash == 0 {
+            bytes.len()
+        } else {
+            match CompressedSetWithHash::check(bytes, header.hash) {
+                Ok(()) => bytes.len() - FINGERPRINT_LEN,
+                Err(_) => return false,
+            }
+        };
+        decoder(header.method).is_some_and(|c| {
+            c.decompress_set(&bytes[HEADER_LEN..payload_end], header.universe)
+                .is_ok()
+        })
+    }
+
+    /// Universe the IDs were compressed against.
+    pub fn universe(&self) -> u32 {
+        u32::from_le_bytes(self.bytes[6..10].try_into().unwrap())
+    }
+
+    /// Tag of the compression method.
+    pub fn method_tag(&self) -> u8 {
+        self.bytes[4]
+    }
+
+    /// Compressor output, without the container header.
+    pub fn payload(&self) -> &[u8] {
+        &self.bytes[HEADER_LEN..]
+    }
+
+    /// Decompress the IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the payload is malformed.
+    pub fn decompress(&self) -> Result<Vec<u32>, CompressionError> {
+        decoder(self.method_tag())
+            .expect("method tag checked on construction")
+            .decompress_set(self.payload(), self.universe())
+    }
+
+    /// Append a fingerprint of the container.
+    pub fn with_fingerprint(self) -> CompressedSetWithHash {
+        let mut bytes = self.bytes;
+        bytes[5] = DEFAULT_ALGORITHM;
+        let hash = fingerprint_with(DEFAULT_ALGORITHM, &bytes).expect("default algorithm");
+        bytes.extend_from_slice(&hash.to_le_bytes());
+        CompressedSetWithHash { bytes }
+    }
+
+    /// The full container, header included.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consume the set and return the container bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl TryFrom<&[u32]> for CompressedSet {
+    type Error = CompressionError;
+
+    /// Same as [`CompressedSet::from_ids`].
+    fn try_from(ids: &[u32]) -> Result<Self, Self::Error> {
+        Self::from_ids(ids)
+    }
+}
+
+impl TryFrom<CompressedSet> for Vec<u32> {
+    type Error = CompressionError;
+
+    /// Same as [`CompressedSet::decompress`].
+    fn try_from(set: CompressedSet) -> Result<Self, Self::Error> {
+        set.decompress()
+    }
+}
+
+/// Payload bytes shown by `Display`.
+const DISPLAY_PAYLOAD_BYTES: usize = 32;
+
+impl fmt::Display for CompressedSet {
+    /// Summarize the container on one line.
+    ///
+    /// The element count comes from decoding the payload. Malformed bytes
+    /// are printed raw instead of failing.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Ok(header) = parse_header(&self.bytes) else {
+            write!(f, "invalid compressed set ({} bytes):", self.bytes.len())?;
+            return write_hex(f, &self.bytes);
+        };
+
+        let payload = &self.bytes[HEADER_LEN..];
+        let format = METHODS
+            .iter()
+            .find(|&&(tag, _)| tag == header.method)
+            .map_or("?", |&(_, id)| id);
+        write!(
+            f,
+            "{} method={} ({}) universe={} count=",
+            String::from_utf8_lossy(MAGIC),
+            header.method,
+            format,
+            header.universe
+        )?;
+        match decoder(header.method).map(|c| c.decompress_set(payload, header.universe)) {
+            Some(Ok(ids)) => write!(f, "{}", ids.len())?,
+            _ => f.write_str("?")?,
+        }
+
+        write!(f, " payload={} bytes:", payload.len())?;
+        write_hex(f, &payload[..payload.len().min(DISPLAY_PAYLOAD_BYTES)])?;
+        if payload.len() > DISPLAY_PAYLOAD_BYTES {
+            f.write_str(" ...")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CompressedSet {
+    /// The `Display` summary followed by every byte, eight per line, with
+    /// offsets and an ASCII column.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)?;
+        for (i, line) in self.bytes.chunks(8).enumerate() {
+            write!(f, "\n{:08x} ", i * 8)?;
+            write_hex(f, line)?;
+            write!(f, "{:width$} |", "", width = 3 * (8 - line.len()))?;
+            for &b in line {
+                let c = if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                };
+                write!(f, "{}", c)?;
+            }
+            f.write_str("|")?;
+        }
+        Ok(())
+    }
+}
+
+/// Write each byte as a space-prefixed pair of hex digits.
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|b| write!(f, " {:02x}", b))
+}
+
+/// A [`CompressedSet`] followed by a fingerprint of its bytes.
+///
+/// Created by [`CompressedSet::with_fingerprint`]. The fingerprint is not
+/// checked until [`verify_fingerprint`](Self::verify_fingerprint) or
+/// [`into_verified`](Self::into_verified) is called.
+#[derive(Clone, Debug)]
+pub struct CompressedSetWithHash {
+    bytes: Vec<u8>,
+}
+
+impl CompressedSetWithHash {
+    /// Wrap bytes produced by [`into_bytes`](Self::into_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` if the header is
+    /// malformed or no fingerprint is present.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, CompressionError> {
+        if parse_header(&bytes)?.hash == 0 || bytes.len() < HEADER_LEN + FINGERPRINT_LEN {
+            return Err(CompressionError::DecompressionFailed(
+                "Compressed set carries no fingerprint".to_string(),
+            ));
+        }
+        Ok(Self { bytes })
+    }
+
+    /// Check the stored fingerprint against the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` on a mismatch, or if
+    /// the fingerprint algorithm is not available in this build.
+    pub fn verify_fingerprint(&self) -> Result<(), CompressionError> {
+        Self::check(&self.bytes, self.bytes[5])
+    }
+
+    /// Verify the fingerprint and strip it.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`verify_fingerprint`](Self::verify_fingerprint).
+    pub fn into_verified(self) -> Result<CompressedSet, CompressionError> {
+        self.verify_fingerprint()?;
+        let mut bytes = self.bytes;
+        bytes.truncate(bytes.len() - FINGERPRINT_LEN);
+        bytes[5] = 0;
+        Ok(CompressedSet { bytes })
+    }
+
+    /// The full container, header and fingerprint included.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consume the set and return the container bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn check(bytes: &[u8], algorithm: u8) -> Result<(), CompressionError> {
+        let split = bytes
+            .len()
+            .checked_sub(FINGERPRINT_LEN)
+            .filter(|&split| split >= HEADER_LEN)
+            .ok_or_else(|| {
+                CompressionError::DecompressionFailed("Unexpected end of compressed data".into())
+            })?;
+        let (data, stored) = bytes.split_at(split);
+        let stored = u64::from_le_bytes(stored.try_into().unwrap());
+
+        let computed = fingerprint_with(algorithm, data).ok_or_else(|| {
+            CompressionError::DecompressionFailed(format!(
+                "Fingerprint algorithm {} is not available in this build",
+                algorithm
+            ))
+        })?;
+        if computed != stored {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Fingerprint mismatch: stored {:016x}, computed {:016x}",
+                stored, computed
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_every_method() {
+        let ids = vec![1u32, 2, 3, 50, 51, 900];
+        for (tag, _) in METHODS {
+            let Some(c) = decoder(*tag) else { continue };
+            let set = CompressedSet::new(&ids, 1000, c.as_ref()).unwrap();
+            assert_eq!(set.method_tag(), *tag);
+
+            let set = CompressedSet::from_bytes(set.into_bytes()).unwrap();
+            assert_eq!(set.decompress().unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_round_trip() {
+        let set = CompressedSet::new(&[4, 8, 15], 16, &RocCompressor::new()).unwrap();
+        let plain = set.as_bytes().to_vec();
+
+        let hashed = set.with_fingerprint();
+        assert_eq!(hashed.as_bytes().len(), plain.len() + FINGERPRINT_LEN);
+        assert!(hashed.verify_fingerprint().is_ok());
+        assert!(CompressedSet::is_valid_compressed(hashed.as_bytes()));
+
+        let restored = hashed.into_verified().unwrap();
+        assert_eq!(restored.as_bytes(), plain);
+    }
+
+    #[test]
+    fn test_corruption_detected() {
+        let set = CompressedSet::new(&[4, 8, 15], 16, &RocCompressor::new()).unwrap();
+        let mut bytes = set.with_fingerprint().into_bytes();
+        let last_payload = bytes.len() - FINGERPRINT_LEN - 1;
+        bytes[last_payload] ^= 0x01;
+
+        assert!(!CompressedSet::is_valid_compressed(&bytes));
+        let hashed = CompressedSetWithHash::from_bytes(bytes).unwrap();
+        assert!(hashed.verify_fingerprint().is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_headers() {
+        assert!(CompressedSet::from_bytes(b"CNKS".to_vec()).is_err());
+        assert!(CompressedSet::from_bytes(b"XXXX\x01\x00\x10\x00\x00\x00".to_vec()).is_err());
+        assert!(CompressedSet::from_bytes(b"CNKS\xff\x00\x10\x00\x00\x00".to_vec()).is_err());
+        assert!(
+            CompressedSetWithHash::from_bytes(b"CNKS\x01\x00\x10\x00\x00\x00".to_vec()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_from_ids_infers_universe() {
+        let set = CompressedSet::try_from(&[0u32, 9, 41][..]).unwrap();
+        assert_eq!(set.universe(), 42);
+        assert_eq!(Vec::<u32>::try_from(set).unwrap(), [0, 9, 41]);
+
+        let empty = CompressedSet::from_ids(&[]).unwrap();
+        assert_eq!(empty.universe(), 1);
+        assert!(empty.decompress().unwrap().is_empty());
+
+        assert!(CompressedSet::from_ids(&[u32::MAX]).is_err());
+        assert!(CompressedSet::from_ids(&[5, 3]).is_err());
+    }
+
+    #[test]
+    fn test_display_shows_header_and_count() {
+        let set = CompressedSet::new(&[3, 14, 15, 92], 100, &RocCompressor::new()).unwrap();
+
+        let display = format!("{}", set);
+        assert!(display.starts_with("CNKS method=1 (cnk/delta-varint)"));
+        assert!(display.contains("universe=100"));
+        assert!(display.contains("count=4"));
+
+        let debug = format!("{:?}", set);
+        assert!(debug.starts_with(&display));
+        assert!(debug.contains("\n00000000  43 4e 4b 53 01 00 64 00 |CNKS..d.|"));
+    }
+
+    #[test]
+    fn test_display_never_panics_on_bad_bytes() {
+        let ids: Vec<u32> = (0..100).map(|i| i * 3).collect();
+        let bytes = CompressedSet::new(&ids, 1000, &RocCompressor::new())
+            .unwrap()
+            .into_bytes();
+
+        for len in 0..bytes.len() {
+            let set = CompressedSet {
+                bytes: bytes[..len].to_vec(),
+            };
+            let display = format!("{}", set);
+            let _ = format!("{:?}", set);
+            if len < HEADER_LEN {
+                assert!(display.starts_with("invalid compressed set"));
+            } else {
+                assert!(display.contains("count=?") || len == HEADER_LEN);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unregistered_compressor() {
+        struct Opaque;
+        impl IdSetCompressor for Opaque {
+            fn compress_set(&self, _: &[u32], _: u32) -> Result<Vec<u8>, CompressionError> {
+                Ok(Vec::new())
+            }
+            fn decompress_set(&self, _: &[u8], _: u32) -> Result<Vec<u32>, CompressionError> {
+                Ok(Vec::new())
+            }
+            fn estimate_size(&self, _: usize, _: u32) -> usize {
+                0
+            }
+            fn theoretical_bits_per_id(&self, _: usize, _: u32) -> f64 {
+                0.0
+            }
+        }
+        assert!(CompressedSet::new(&[1], 2, &Opaque).is_err());
+    }
+
+    #[test]
+    fn test_hash_map_keys_are_content_hashes() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashMap;
+        use std::hash::{Hash, Hasher};
+
+        let roc = RocCompressor::new();
+        let sets: Vec<Vec<u32>> = (0..1000u32)
+            .map(|i| (0..1 + i % 50).map(|j| i + j * (1 + i % 13)).collect())
+            .collect();
+        let mut dedup = HashMap::new();
+        for (i, ids) in sets.iter().enumerate() {
+            dedup.insert(CompressedSet::new(ids, 100_000, &roc).unwrap(), i);
+        }
+        assert_eq!(dedup.len(), 1000);
+        for (i, ids) in sets.iter().enumerate() {
+            let again = CompressedSet::new(ids, 100_000, &roc).unwrap();
+            assert_eq!(dedup.get(&again), Some(&i));
+        }
+
+        // Same set and method, another header layout
+        let hash = |set: &CompressedSet| {
+            let mut hasher = DefaultHasher::new();
+            set.hash(&mut hasher);
+            hasher.finish()
+        };
+        let fastest = RocCompressor::with_level(crate::CompressionLevel::Fastest);
+        let a = CompressedSet::new(&sets[7], 100_000, &roc).unwrap();
+        let b = CompressedSet::new(&sets[7], 100_000, &fastest).unwrap();
+        assert_eq!(a.decompress().unwrap(), b.decompress().unwrap());
+        assert_ne!(a, b);
+        assert_ne!(hash(&a), hash(&b));
+    }
+}
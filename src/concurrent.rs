@@ -0,0 +1,160 @@
+//! Posting lists updated while they are being read.
+//!
+//! [`CompressedIndex`](crate::CompressedIndex) takes `&mut self` to write,
+//! so a live service must stop queries, or swap whole indexes, to apply an
+//! update. [`ConcurrentCompressedIndex`] keeps each list in a sharded
+//! concurrent map instead: writers lock only the shard of the term they
+//! update, and readers of other shards never wait.
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+
+use crate::error::CompressionError;
+use crate::merge::sorted_merge_compress;
+use crate::traits::IdSetCompressor;
+
+/// Posting lists keyed by term ID, each compressed with `C`, shareable
+/// across threads by reference.
+///
+/// Every list of one index must be read and written with the same universe.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{ConcurrentCompressedIndex, RocCompressor};
+///
+/// let index = ConcurrentCompressedIndex::new(RocCompressor::new());
+/// std::thread::scope(|s| {
+///     s.spawn(|| index.insert_merge(7, &[3, 40], 1000).unwrap());
+///     s.spawn(|| index.insert_merge(7, &[40, 512], 1000).unwrap());
+/// });
+/// assert_eq!(index.get(7, 1000).unwrap(), Some(vec![3, 40, 512]));
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentCompressedIndex<C: IdSetCompressor + Send + Sync> {
+    compressor: C,
+    lists: DashMap<u32, Vec<u8>>,
+}
+
+impl<C: IdSetCompressor + Send + Sync> ConcurrentCompressedIndex<C> {
+    /// Create an empty index.
+    pub fn new(compressor: C) -> Self {
+        Self {
+            compressor,
+            lists: DashMap::new(),
+        }
+    }
+
+    /// Merge `new_ids` into the posting list of `term`, creating it if
+    /// needed.
+    ///
+    /// The list is the union of the old and new IDs. The term's shard stays
+    /// locked from reading the old list to storing the new one, so
+    /// concurrent merges into one term all take effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `new_ids` is not sorted,
+    /// or any error from decoding the old list or compressing the union;
+    /// the list is then left unchanged.
+    pub fn insert_merge(
+        &self,
+        term: u32,
+        new_ids: &[u32],
+        universe: u32,
+    ) -> Result<(), CompressionError> {
+        match self.lists.entry(term) {
+            Entry::Occupied(mut entry) => {
+                let old = self.compressor.decompress_set(entry.get(), universe)?;
+                let merged = sorted_merge_compress(&self.compressor, &[&old, new_ids], universe)?;
+                entry.insert(merged);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(sorted_merge_compress(
+                    &self.compressor,
+                    &[new_ids],
+                    universe,
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decompress the posting list of `term`, or `None` if it has none.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the stored list cannot be decoded.
+    pub fn get(&self, term: u32, universe: u32) -> Result<Option<Vec<u32>>, CompressionError> {
+        self.lists
+            .get(&term)
+            .map(|list| self.compressor.decompress_set(&list, universe))
+            .transpose()
+    }
+
+    /// Drop the posting list of `term`, if any.
+    pub fn remove(&self, term: u32) {
+        self.lists.remove(&term);
+    }
+
+    /// Number of terms.
+    pub fn len(&self) -> usize {
+        self.lists.len()
+    }
+
+    /// Whether the index has no terms.
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    /// The compressor used for every list.
+    pub fn compressor(&self) -> &C {
+        &self.compressor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_concurrent_merges_lose_nothing() {
+        let index = ConcurrentCompressedIndex::new(RocCompressor::new());
+        // Thread t adds ID t to every one of 1000 terms, and its own term
+        std::thread::scope(|s| {
+            for t in 0..8u32 {
+                let index = &index;
+                s.spawn(move || {
+                    for term in 0..1000 {
+                        index.insert_merge(term, &[t, 100 + term], 2000).unwrap();
+                    }
+                    index.insert_merge(1000 + t, &[t], 2000).unwrap();
+                });
+            }
+        });
+
+        assert_eq!(index.len(), 1008);
+        for term in 0..1000 {
+            let mut expected: Vec<u32> = (0..8).collect();
+            expected.push(100 + term);
+            assert_eq!(index.get(term, 2000).unwrap(), Some(expected));
+        }
+        assert_eq!(index.get(1003, 2000).unwrap(), Some(vec![3]));
+    }
+
+    #[test]
+    fn test_failed_merge_keeps_list() {
+        let index = ConcurrentCompressedIndex::new(RocCompressor::new());
+        index.insert_merge(1, &[5, 6], 10).unwrap();
+        assert!(index.insert_merge(1, &[9, 2], 10).is_err());
+        assert!(index.insert_merge(1, &[10], 10).is_err());
+        assert!(index.insert_merge(2, &[10], 10).is_err());
+        assert_eq!(index.get(1, 10).unwrap(), Some(vec![5, 6]));
+        assert_eq!(index.get(2, 10).unwrap(), None);
+
+        index.remove(1);
+        assert!(index.is_empty());
+        assert_eq!(index.get(1, 10).unwrap(), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! Indexes configured from TOML files.
+//!
+//! Search services usually keep their settings in a configuration file
+//! rather than in code, so the index takes its universe and codec from one.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::CompressionError;
+use crate::{Codec, CompressedIndex, IdCompressionMethod, RocCompressor};
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    index: IndexConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IndexConfig {
+    universe_size: u32,
+    compression_method: Option<String>,
+    ans_precision: Option<u32>,
+    block_size: Option<u32>,
+}
+
+/// Parse the method name used in configuration files.
+fn parse_method(name: &str) -> Option<IdCompressionMethod> {
+    Some(match name {
+        "roc" => IdCompressionMethod::Roc,
+        "elias_fano" => IdCompressionMethod::EliasFano,
+        "bitset" => IdCompressionMethod::Bitset,
+        "interpolative" => IdCompressionMethod::Interpolative,
+        "uncompressed" => IdCompressionMethod::Uncompressed,
+        "wavelet_tree" => IdCompressionMethod::WaveletTree,
+        "roaring_bitmap" => IdCompressionMethod::RoaringBitmap,
+        _ => return None,
+    })
+}
+
+/// Build the codec and universe described by a configuration file's text.
+fn parse_config(text: &str) -> Result<(Codec, u32), CompressionError> {
+    let config: ConfigFile = toml::from_str(text).map_err(|e| {
+        CompressionError::InvalidInput(format!("Invalid index config: {}", e.message()))
+    })?;
+    let config = config.index;
+    let invalid = |key: &str, reason: String| {
+        CompressionError::InvalidInput(format!("Invalid index config key `{}`: {}", key, reason))
+    };
+
+    let name = config.compression_method.as_deref().unwrap_or("roc");
+    let method = parse_method(name)
+        .ok_or_else(|| invalid("compression_method", format!("unknown method \"{}\"", name)))?;
+    if config.block_size.is_some() {
+        return Err(invalid(
+            "block_size",
+            "no configurable method is block-based".to_string(),
+        ));
+    }
+    let codec = match (method, config.ans_precision) {
+        (IdCompressionMethod::Roc, Some(precision)) => {
+            if !precision.is_power_of_two() {
+                return Err(invalid(
+                    "ans_precision",
+                    format!("{} is not a power of two", precision),
+                ));
+            }
+            Codec::Roc(RocCompressor::with_precision(precision))
+        }
+        (method, Some(_)) => {
+            return Err(invalid(
+                "ans_precision",
+                format!("{:?} does not use ANS", method),
+            ))
+        }
+        (method, None) => {
+            Codec::try_from(method).map_err(|e| invalid("compression_method", e.to_string()))?
+        }
+    };
+    Ok((codec, config.universe_size))
+}
+
+impl CompressedIndex<Codec> {
+    /// Create an empty index from the `[index]` table of a TOML file.
+    ///
+    /// ```toml
+    /// [index]
+    /// universe_size = 1000000
+    /// compression_method = "roc"   # optional, "roc" by default
+    /// ans_precision = 4096         # optional, roc only
+    /// ```
+    ///
+    /// `compression_method` is an [`IdCompressionMethod`] in snake case,
+    /// such as `"elias_fano"` or `"interpolative"`. Other tables in the file
+    /// are ignored, so the index can share a file with the rest of a
+    /// service's settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the file cannot be read, and
+    /// `CompressionError::InvalidInput` naming the offending key if the
+    /// table is missing `universe_size`, has an unknown key, or names a
+    /// method without a codec.
+    pub fn from_config_file(path: &Path) -> Result<Self, CompressionError> {
+        let text = std::fs::read_to_string(path)?;
+        let (codec, universe) = parse_config(&text)?;
+        Ok(Self::new(codec, universe))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdSetCompressor;
+
+    /// Error message of a config that must fail to parse.
+    fn error(text: &str) -> String {
+        match parse_config(text) {
+            Err(CompressionError::InvalidInput(msg)) => msg,
+            other => panic!("expected InvalidInput, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("cnk-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[server]\nport = 8080\n\n\
+             [index]\nuniverse_size = 100000\ncompression_method = \"elias_fano\"\n",
+        )
+        .unwrap();
+        let mut index = CompressedIndex::from_config_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(index.compressor(), Codec::EliasFano(_)));
+        assert_eq!(index.universe(), 100_000);
+
+        let lists: Vec<Vec<u32>> = (0..10u32)
+            .map(|term| (0..100).map(|i| i * 997 + term).collect())
+            .collect();
+        for (term, ids) in lists.iter().enumerate() {
+            index.insert(term as u32, ids).unwrap();
+        }
+        for (term, ids) in lists.iter().enumerate() {
+            assert_eq!(index.get(term as u32).unwrap().as_ref(), Some(ids));
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let (codec, universe) = parse_config("[index]\nuniverse_size = 64\n").unwrap();
+        assert!(matches!(codec, Codec::Roc(_)));
+        assert_eq!(universe, 64);
+        assert_eq!(
+            codec.compress_set(&[1u32, 5], 64).unwrap(),
+            RocCompressor::new().compress_set(&[1u32, 5], 64).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ans_precision() {
+        let (codec, _) =
+            parse_config("[index]\nuniverse_size = 64\nans_precision = 1024\n").unwrap();
+        match codec {
+            Codec::Roc(roc) => assert_eq!(roc.ans_precision(), 1024),
+            _ => panic!("expected a Roc codec"),
+        }
+
+        let msg = error("[index]\nuniverse_size = 64\nans_precision = 1000\n");
+        assert!(msg.contains("ans_precision") && msg.contains("power of two"));
+        assert!(error(
+            "[index]\nuniverse_size = 64\ncompression_method = \"bitset\"\nans_precision = 1024\n"
+        )
+        .contains("ans_precision"));
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        assert!(error("[index]\ncompression_method = \"roc\"\n").contains("universe_size"));
+        let msg = error("[index]\nuniverse_size = 10\ncompression_method = \"lz4\"\n");
+        assert!(msg.contains("compression_method") && msg.contains("lz4"));
+        assert!(
+            error("[index]\nuniverse_size = 10\ncompression_method = \"uncompressed\"\n")
+                .contains("compression_method")
+        );
+        assert!(error("[index]\nuniverse_size = 10\nblock_size = 128\n").contains("block_size"));
+        assert!(error("[index]\nuniverse_size = 10\nuniverse = 10\n").contains("universe"));
+        assert!(error("[server]\nport = 1\n").contains("index"));
+
+        let missing = std::env::temp_dir().join("cnk-config-missing.toml");
+        assert!(matches!(
+            CompressedIndex::from_config_file(&missing),
+            Err(CompressionError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,396 @@
+//! Gap coding with a trained probability model per context.
+//!
+//! The gap distribution of a posting list often depends on something the
+//! reader already knows: in IVF, the centroid decides how many vectors fall
+//! in its cluster and hence how far apart their IDs are. A per-set code
+//! table, as in [`HuffmanCompressor`](crate::HuffmanCompressor), pays for
+//! that knowledge again in every set. [`ContextualRocCompressor`] instead
+//! keeps one trained table per context key on both sides, so sets carry
+//! only the coded gaps.
+//!
+//! Gaps are split into a *class*, the bit length `k` of `gap - 1` (0 to 32),
+//! and `k - 1` raw mantissa bits below the implicit leading one. Classes are
+//! Huffman-coded with a code built from the context's class frequencies
+//! (plus one, so every class stays codable), or from a uniform prior when
+//! the context was never trained.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [first_id: varint] [model: u8]
+//! [(class code, mantissa bits) * (len - 1), MSB-first, zero-padded]
+//! ```
+//!
+//! `model` is 0 for the uniform prior and 1 for a trained table. The table
+//! itself is not stored: decoding a trained set needs the same context,
+//! trained on the same data.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::CompressionError;
+use crate::huffman::{DecodeTable, HuffmanCompressor};
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Number of gap classes: bit lengths 0 through 32.
+const NUM_CLASSES: usize = 33;
+
+/// Model tag for the uniform prior.
+const UNIFORM: u8 = 0;
+/// Model tag for a trained table.
+const TRAINED: u8 = 1;
+
+/// Gap class counts and the Huffman code derived from them.
+#[derive(Clone, Debug)]
+struct FreqTable {
+    counts: [u64; NUM_CLASSES],
+    /// Code length of each class.
+    lengths: Vec<(u32, u8)>,
+}
+
+impl FreqTable {
+    /// Table with every class equally likely.
+    fn uniform() -> Self {
+        Self::from_counts([0; NUM_CLASSES])
+    }
+
+    fn from_counts(counts: [u64; NUM_CLASSES]) -> Self {
+        let freqs: Vec<(u32, u64)> = (0..NUM_CLASSES as u32)
+            .map(|class| (class, counts[class as usize] + 1))
+            .collect();
+        let lengths = HuffmanCompressor::code_lengths(&freqs)
+            .into_iter()
+            .enumerate()
+            .map(|(class, len)| (class as u32, len))
+            .collect();
+        Self { counts, lengths }
+    }
+
+    /// Add the gap classes of `ids` and rebuild the code.
+    fn observe(&mut self, ids: &[u32]) {
+        let mut counts = self.counts;
+        for w in ids.windows(2) {
+            counts[class(w[1] - w[0] - 1) as usize] += 1;
+        }
+        *self = Self::from_counts(counts);
+    }
+}
+
+/// Bit length of `value`.
+#[inline]
+fn class(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
+
+/// Gap compressor with a trained Huffman model per context key.
+///
+/// `compress_set` and `decompress_set` use the uniform prior;
+/// [`compress_with_context`](IdSetCompressor::compress_with_context) uses
+/// the context's table if one was [`train`](Self::train)ed.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{ContextualRocCompressor, IdSetCompressor};
+///
+/// let mut compressor = ContextualRocCompressor::new();
+/// let training: Vec<u32> = (0..1000).map(|i| i * 3).collect();
+/// compressor.train(7, &training, 10_000);
+///
+/// let ids: Vec<u32> = (0..500).map(|i| 100 + i * 3).collect();
+/// let trained = compressor.compress_with_context(&ids, 10_000, 7).unwrap();
+/// let uniform = compressor.compress_set(&ids, 10_000).unwrap();
+/// assert!(trained.len() < uniform.len());
+/// assert_eq!(compressor.decompress_with_context(&trained, 10_000, 7).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContextualRocCompressor {
+    tables: HashMap<u64, FreqTable>,
+    uniform: FreqTable,
+}
+
+impl ContextualRocCompressor {
+    /// Create a compressor with no trained contexts.
+    pub fn new() -> Self {
+        Self {
+            tables: HashMap::new(),
+            uniform: FreqTable::uniform(),
+        }
+    }
+
+    /// Add the gaps of `ids` to the model for `context`.
+    ///
+    /// Training is cumulative. Sets compressed with `context` before a
+    /// later `train` call can no longer be decoded, since the code changes.
+    /// Invalid input (unsorted IDs or IDs outside `universe`) is ignored.
+    pub fn train(&mut self, context: u64, ids: &[u32], universe: u32) {
+        if RocCompressor::validate_ids(ids).is_err() || ids.last().is_some_and(|&id| id >= universe)
+        {
+            return;
+        }
+        self.tables
+            .entry(context)
+            .or_insert_with(FreqTable::uniform)
+            .observe(ids);
+    }
+
+    /// Whether `context` has a trained model.
+    pub fn is_trained(&self, context: u64) -> bool {
+        self.tables.contains_key(&context)
+    }
+
+    fn encode(
+        ids: &[u32],
+        universe_size: u32,
+        model: u8,
+        table: &FreqTable,
+    ) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        encode_varint(first as u64, &mut encoded);
+        encoded.push(model);
+
+        let codes: BTreeMap<u32, (u8, u64)> = HuffmanCompressor::canonical_codes(&table.lengths)
+            .into_iter()
+            .map(|(class, len, code)| (class, (len, code)))
+            .collect();
+        let mut writer = BitWriter::new();
+        for w in ids.windows(2) {
+            let value = w[1] - w[0] - 1;
+            let k = class(value);
+            let (len, code) = codes[&k];
+            writer.write_bits(code, len as u32);
+            if k > 1 {
+                writer.write_bits(value as u64, k - 1);
+            }
+        }
+        encoded.extend(writer.finish());
+        Ok(encoded)
+    }
+
+    fn decode(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+        context: Option<u64>,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (len, mut offset) = decode_varint(compressed)?;
+        if len == 0 || len > universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for universe size {}",
+                len, universe_size
+            )));
+        }
+        let (first, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+        let model = *compressed.get(offset).ok_or_else(|| {
+            CompressionError::DecompressionFailed("Unexpected end of compressed data".into())
+        })?;
+        offset += 1;
+
+        let table = match (model, context) {
+            (UNIFORM, _) => &self.uniform,
+            (TRAINED, Some(context)) => self.tables.get(&context).ok_or_else(|| {
+                CompressionError::DecompressionFailed(format!(
+                    "No trained model for context {}",
+                    context
+                ))
+            })?,
+            (TRAINED, None) => {
+                return Err(CompressionError::DecompressionFailed(
+                    "Set was compressed with a context model".to_string(),
+                ))
+            }
+            _ => {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Unknown model tag {}",
+                    model
+                )))
+            }
+        };
+
+        let decode_table = DecodeTable::new(&table.lengths);
+        let mut reader = BitReader::new(&compressed[offset..]);
+        // Every gap code takes at least one bit
+        let mut ids = Vec::with_capacity((len as usize).min(1 + reader.unread_bytes() * 8));
+        let mut prev = first;
+        for i in 0..len {
+            if i > 0 {
+                let k = decode_table.decode(&mut reader)?;
+                let value = match k {
+                    0 => 0,
+                    1 => 1,
+                    _ => (1u64 << (k - 1)) | reader.read_bits(k - 1)?,
+                };
+                prev += value + 1;
+            }
+            if prev >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev as u32);
+        }
+
+        if reader.unread_bytes() > 0 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                reader.unread_bytes()
+            )));
+        }
+        Ok(ids)
+    }
+}
+
+impl Default for ContextualRocCompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdSetCompressor for ContextualRocCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        Self::encode(ids, universe_size, UNIFORM, &self.uniform)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        self.decode(compressed, universe_size, None)
+    }
+
+    fn compress_with_context(
+        &self,
+        ids: &[u32],
+        universe_size: u32,
+        context: u64,
+    ) -> Result<Vec<u8>, CompressionError> {
+        match self.tables.get(&context) {
+            Some(table) => Self::encode(ids, universe_size, TRAINED, table),
+            None => self.compress_set(ids, universe_size),
+        }
+    }
+
+    fn decompress_with_context(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+        context: u64,
+    ) -> Result<Vec<u32>, CompressionError> {
+        self.decode(compressed, universe_size, Some(context))
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        RocCompressor::new().estimate_size(num_ids, universe_size)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_round_trip() {
+        let compressor = ContextualRocCompressor::new();
+        let ids = vec![0u32, 1, 2, 10, 1000, 65_536, u32::MAX - 1];
+
+        let compressed = compressor.compress_set(&ids, u32::MAX).unwrap();
+        assert_eq!(
+            compressor.decompress_set(&compressed, u32::MAX).unwrap(),
+            ids
+        );
+        assert_eq!(
+            compressor
+                .decompress_with_context(&compressed, u32::MAX, 42)
+                .unwrap(),
+            ids
+        );
+    }
+
+    #[test]
+    fn test_trained_context_is_smaller_and_required() {
+        let mut compressor = ContextualRocCompressor::new();
+        let ids: Vec<u32> = (0..1000).map(|i| i * 2).collect();
+        compressor.train(1, &ids, 2000);
+        assert!(compressor.is_trained(1));
+
+        let trained = compressor.compress_with_context(&ids, 2000, 1).unwrap();
+        let uniform = compressor.compress_set(&ids, 2000).unwrap();
+        assert!(trained.len() < uniform.len());
+
+        assert_eq!(
+            compressor
+                .decompress_with_context(&trained, 2000, 1)
+                .unwrap(),
+            ids
+        );
+        assert!(compressor.decompress_set(&trained, 2000).is_err());
+        assert!(compressor
+            .decompress_with_context(&trained, 2000, 2)
+            .is_err());
+    }
+
+    #[test]
+    fn test_untrained_context_uses_prior() {
+        let compressor = ContextualRocCompressor::new();
+        let ids = vec![5u32, 6, 100];
+        assert_eq!(
+            compressor.compress_with_context(&ids, 200, 9).unwrap(),
+            compressor.compress_set(&ids, 200).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_train_ignores_invalid_input() {
+        let mut compressor = ContextualRocCompressor::new();
+        compressor.train(1, &[5, 3], 10);
+        compressor.train(2, &[5, 30], 10);
+        assert!(!compressor.is_trained(1));
+        assert!(!compressor.is_trained(2));
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressor = ContextualRocCompressor::new();
+        assert!(compressor.compress_set(&[], 10).unwrap().is_empty());
+        assert!(compressor.decompress_set(&[], 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_huge_claimed_length_is_an_error() {
+        let compressor = ContextualRocCompressor::new();
+        let mut garbage = Vec::new();
+        encode_varint(u32::MAX as u64 - 1, &mut garbage);
+        garbage.extend([0, UNIFORM, 0, 0, 0, 0]);
+        assert!(compressor.decompress_set(&garbage, u32::MAX).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Conversions between raw bitsets and compressed sets.
+//!
+//! Some producers, such as GPU k-means assigning vectors to clusters, emit
+//! set membership as one bit per ID rather than a list. The bitsets here use
+//! the layout of [`BitmapSetCompressor`](crate::BitmapSetCompressor): bit
+//! `id % 8` of byte `id / 8`, most significant bit first.
+
+use crate::bitmap::{pack_bits, unpack_bits};
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Compress the IDs whose bits are set in `bitmap` with `c`.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `bitmap` has fewer than
+/// `universe` bits, and any error from `c.compress_set`, including a set bit
+/// at or beyond `universe`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{from_sorted_bitmap, to_sorted_bitmap, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// // IDs 1, 2 and 8
+/// let bitmap = [0b0110_0000, 0b1000_0000];
+/// let compressed = from_sorted_bitmap(&bitmap, 16, &roc).unwrap();
+/// assert_eq!(roc.decompress_set(&compressed, 16u32).unwrap(), [1, 2, 8]);
+/// assert_eq!(to_sorted_bitmap(&compressed, 16, &roc).unwrap(), bitmap);
+/// ```
+pub fn from_sorted_bitmap(
+    bitmap: &[u8],
+    universe: u32,
+    c: &dyn IdSetCompressor,
+) -> Result<Vec<u8>, CompressionError> {
+    if universe as u64 > bitmap.len() as u64 * 8 {
+        return Err(CompressionError::InvalidInput(format!(
+            "Bitmap of {} bytes cannot cover universe size {}",
+            bitmap.len(),
+            universe
+        )));
+    }
+    c.compress_set(&unpack_bits(bitmap), universe)
+}
+
+/// Decompress a set with `c` into a bitset of `ceil(universe / 8)` bytes.
+///
+/// # Errors
+///
+/// Returns any error from `c.decompress_set`, or
+/// `CompressionError::DecompressionFailed` if it yields an ID outside the
+/// universe.
+pub fn to_sorted_bitmap(
+    compressed: &[u8],
+    universe: u32,
+    c: &dyn IdSetCompressor,
+) -> Result<Vec<u8>, CompressionError> {
+    let ids = c.decompress_set(compressed, universe)?;
+    if let Some(&last) = ids.iter().max() {
+        if last >= universe {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                last, universe
+            )));
+        }
+    }
+    Ok(pack_bits(&ids, (universe as usize).div_ceil(8)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_bitmap_must_cover_universe() {
+        let roc = RocCompressor::new();
+        assert!(from_sorted_bitmap(&[0xff], 9, &roc).is_err());
+        // Bits past the universe are IDs outside it
+        assert!(from_sorted_bitmap(&[0b0000_0001], 7, &roc).is_err());
+        assert!(from_sorted_bitmap(&[0b0000_0010], 7, &roc).is_ok());
+        assert_eq!(from_sorted_bitmap(&[0, 0], 16, &roc).unwrap(), []);
+    }
+
+    #[test]
+    fn test_to_bitmap_is_padded_to_universe() {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&[0u32], 20).unwrap();
+        assert_eq!(
+            to_sorted_bitmap(&compressed, 20, &roc).unwrap(),
+            [0x80, 0, 0]
+        );
+        assert_eq!(to_sorted_bitmap(&[], 0, &roc).unwrap(), []);
+    }
+}
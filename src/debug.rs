@@ -0,0 +1,92 @@
+//! Human-readable dumps of compressed sets (debug builds only).
+//!
+//! A failing round-trip test usually prints two ID lists that differ, which
+//! says nothing about the bytes in between. [`dump_compressed_set_hex`]
+//! prints the bytes next to what a codec decodes from them. It is compiled
+//! only with `debug_assertions`, so it never reaches release builds.
+
+use std::fmt::Write;
+
+use crate::traits::IdSetCompressor;
+
+/// Bytes per line of the hex dump.
+const BYTES_PER_LINE: usize = 16;
+
+/// Format `compressed` as a hex dump followed by its decoding with `c`.
+///
+/// ```text
+/// compressed [5 bytes] universe=1000:
+/// 00000000: 00 03 01 04 05
+/// decoded IDs: [1, 5, 10]
+/// ```
+///
+/// The last line is `decode error: ...` instead if `c` rejects the bytes.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use cnk::{dump_compressed_set_hex, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let compressed = roc.compress_set(&[1u32, 5, 10], 1000).unwrap();
+/// let dump = dump_compressed_set_hex(&compressed, 1000, &roc);
+/// assert!(dump.ends_with("decoded IDs: [1, 5, 10]"));
+/// ```
+pub fn dump_compressed_set_hex(
+    compressed: &[u8],
+    universe: u32,
+    c: &dyn IdSetCompressor,
+) -> String {
+    let mut out = format!(
+        "compressed [{} bytes] universe={}:\n",
+        compressed.len(),
+        universe
+    );
+    for (line, chunk) in compressed.chunks(BYTES_PER_LINE).enumerate() {
+        write!(out, "{:08x}:", line * BYTES_PER_LINE).unwrap();
+        for byte in chunk {
+            write!(out, " {:02x}", byte).unwrap();
+        }
+        out.push('\n');
+    }
+    match c.decompress_set(compressed, universe) {
+        Ok(ids) => write!(out, "decoded IDs: {:?}", ids).unwrap(),
+        Err(e) => write!(out, "decode error: {}", e).unwrap(),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_dump_layout() {
+        let roc = RocCompressor::new();
+        let ids: Vec<u32> = (0..20).map(|i| i * 3).collect();
+        let compressed = roc.compress_set(&ids, 100).unwrap();
+        let dump = dump_compressed_set_hex(&compressed, 100, &roc);
+
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("compressed [{} bytes] universe=100:", compressed.len())
+        );
+        assert!(lines[1].starts_with("00000000: "));
+        assert_eq!(lines[1].split(' ').count(), 1 + BYTES_PER_LINE);
+        assert!(lines[2].starts_with("00000010: "));
+        assert_eq!(lines.last().unwrap(), &format!("decoded IDs: {:?}", ids));
+    }
+
+    #[test]
+    fn test_dump_decode_error() {
+        let roc = RocCompressor::new();
+        let dump = dump_compressed_set_hex(&[0, 5, 1], 10, &roc);
+        assert!(dump.starts_with("compressed [3 bytes] universe=10:\n00000000: 00 05 01\n"));
+        assert!(dump.lines().last().unwrap().starts_with("decode error: "));
+
+        let empty = dump_compressed_set_hex(&[], 10, &roc);
+        assert_eq!(empty, "compressed [0 bytes] universe=10:\ndecoded IDs: []");
+    }
+}
@@ -0,0 +1,293 @@
+//! Deltas between compressed sets, for incremental index updates.
+//!
+//! When a posting list changes by a handful of IDs, storing the added and
+//! removed IDs as two small compressed sets is far cheaper than rewriting the
+//! whole list. The diff is applied later, e.g. during segment merging.
+//!
+//! [`explain_diff`] describes the same difference in words, for debugging
+//! sets that should be identical but are not.
+
+use std::fmt::Write;
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Most IDs listed per side by [`explain_diff`].
+const MAX_LISTED: usize = 20;
+
+/// The difference between two sets, each side stored as a compressed set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiffSet {
+    /// IDs present in the new set but not the old one (compressed).
+    pub added: Vec<u8>,
+    /// IDs present in the old set but not the new one (compressed).
+    pub removed: Vec<u8>,
+}
+
+impl DiffSet {
+    /// Whether the diff changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Split two sorted sets into `(only in a, only in b)`.
+fn symmetric_difference(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let (mut only_a, mut only_b) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => {
+                only_a.push(a[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                only_b.push(b[j]);
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    only_a.extend_from_slice(&a[i..]);
+    only_b.extend_from_slice(&b[j..]);
+    (only_a, only_b)
+}
+
+/// Compute the diff that turns compressed set `old` into `new`.
+///
+/// Both inputs and both sides of the result use the same compressor `c`.
+///
+/// # Errors
+///
+/// Returns `CompressionError` if either input cannot be decompressed.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{apply_diff, diff, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let old = roc.compress_set(&[1u32, 2, 3, 10], 100).unwrap();
+/// let new = roc.compress_set(&[1u32, 3, 10, 42], 100).unwrap();
+///
+/// let delta = diff(&old, &new, 100, &roc).unwrap();
+/// let patched = apply_diff(&old, &delta, 100, &roc).unwrap();
+/// assert_eq!(roc.decompress_set(&patched, 100).unwrap(), [1u32, 3, 10, 42]);
+/// ```
+pub fn diff(
+    old: &[u8],
+    new: &[u8],
+    universe: u32,
+    c: &dyn IdSetCompressor,
+) -> Result<DiffSet, CompressionError> {
+    let old_ids = c.decompress_set(old, universe)?;
+    let new_ids = c.decompress_set(new, universe)?;
+    let (removed, added) = symmetric_difference(&old_ids, &new_ids);
+
+    Ok(DiffSet {
+        added: c.compress_set(&added, universe)?,
+        removed: c.compress_set(&removed, universe)?,
+    })
+}
+
+/// Apply `diff` to the compressed set `base`, returning the compressed result.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if the diff does not match `base`
+/// (an added ID is already present or a removed ID is missing), or any
+/// decompression error from the inputs.
+pub fn apply_diff(
+    base: &[u8],
+    diff: &DiffSet,
+    universe: u32,
+    c: &dyn IdSetCompressor,
+) -> Result<Vec<u8>, CompressionError> {
+    let base_ids = c.decompress_set(base, universe)?;
+    let added = c.decompress_set(&diff.added, universe)?;
+    let removed = c.decompress_set(&diff.removed, universe)?;
+
+    let (kept, missing) = symmetric_difference(&base_ids, &removed);
+    if let Some(&id) = missing.first() {
+        return Err(CompressionError::InvalidInput(format!(
+            "Diff removes ID {} which is not in the base set",
+            id
+        )));
+    }
+    if let Some(&id) = added.iter().find(|id| kept.binary_search(id).is_ok()) {
+        return Err(CompressionError::InvalidInput(format!(
+            "Diff adds ID {} which is already in the base set",
+            id
+        )));
+    }
+
+    let mut ids = kept;
+    ids.extend_from_slice(&added);
+    ids.sort_unstable();
+    c.compress_set(&ids, universe)
+}
+
+/// Describe how compressed sets `a` and `b` differ, in human-readable form.
+///
+/// Lists the IDs only in `a`, the IDs only in `b` (at most 20 each) and the
+/// first position where the decoded sets disagree. A side that fails to
+/// decompress is reported with its error instead.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{explain_diff, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let a = roc.compress_set(&[1u32, 2, 42, 100], 1000).unwrap();
+/// let b = roc.compress_set(&[1u32, 2, 7, 100], 1000).unwrap();
+///
+/// assert_eq!(
+///     explain_diff(&a, &b, 1000, &roc),
+///     "A has 1 extra ID: [42]. B has 1 extra ID: [7]. \
+///      First divergence at position 2: A=[42], B=[7]."
+/// );
+/// ```
+pub fn explain_diff(a: &[u8], b: &[u8], universe: u32, c: &dyn IdSetCompressor) -> String {
+    let (a_ids, b_ids) = match (c.decompress_set(a, universe), c.decompress_set(b, universe)) {
+        (Ok(a_ids), Ok(b_ids)) => (a_ids, b_ids),
+        (a_result, b_result) => {
+            let errors: Vec<String> = [("A", a_result.err()), ("B", b_result.err())]
+                .into_iter()
+                .filter_map(|(side, e)| e.map(|e| format!("Cannot decompress {}: {}.", side, e)))
+                .collect();
+            return errors.join(" ");
+        }
+    };
+
+    let divergence = a_ids
+        .iter()
+        .zip(&b_ids)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a_ids.len() != b_ids.len()).then(|| a_ids.len().min(b_ids.len())));
+    let pos = match divergence {
+        Some(pos) => pos,
+        None => return format!("Sets are identical ({} IDs).", a_ids.len()),
+    };
+
+    let (only_a, only_b) = symmetric_difference(&a_ids, &b_ids);
+    let at = |ids: &[u32]| match ids.get(pos) {
+        Some(id) => format!("[{}]", id),
+        None => "[]".to_string(),
+    };
+    format!(
+        "{} {} First divergence at position {}: A={}, B={}.",
+        describe_extra("A", &only_a),
+        describe_extra("B", &only_b),
+        pos,
+        at(&a_ids),
+        at(&b_ids)
+    )
+}
+
+/// One sentence listing the IDs only on `side`, capped at [`MAX_LISTED`].
+fn describe_extra(side: &str, ids: &[u32]) -> String {
+    if ids.is_empty() {
+        return format!("{} has no extra IDs.", side);
+    }
+    let noun = if ids.len() == 1 { "ID" } else { "IDs" };
+    let mut out = format!("{} has {} extra {}: [", side, ids.len(), noun);
+    for (i, id) in ids.iter().take(MAX_LISTED).enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write!(out, "{}", id).unwrap();
+    }
+    if ids.len() > MAX_LISTED {
+        write!(out, ", ... {} more", ids.len() - MAX_LISTED).unwrap();
+    }
+    out.push_str("].");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_diff_round_trip() {
+        let roc = RocCompressor::new();
+        let old = roc.compress_set(&[1, 5, 9], 100u32).unwrap();
+        let new = roc.compress_set(&[1, 2, 9, 50], 100u32).unwrap();
+
+        let delta = diff(&old, &new, 100, &roc).unwrap();
+        assert_eq!(roc.decompress_set(&delta.added, 100u32).unwrap(), [2, 50]);
+        assert_eq!(roc.decompress_set(&delta.removed, 100u32).unwrap(), [5]);
+
+        let patched = apply_diff(&old, &delta, 100, &roc).unwrap();
+        assert_eq!(patched, new);
+    }
+
+    #[test]
+    fn test_identical_sets_give_empty_diff() {
+        let roc = RocCompressor::new();
+        let set = roc.compress_set(&[3, 4, 5], 10u32).unwrap();
+        assert!(diff(&set, &set, 10, &roc).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_diff_is_error() {
+        let roc = RocCompressor::new();
+        let base = roc.compress_set(&[1, 2], 10u32).unwrap();
+        let bad_remove = DiffSet {
+            added: Vec::new(),
+            removed: roc.compress_set(&[7], 10u32).unwrap(),
+        };
+        assert!(apply_diff(&base, &bad_remove, 10, &roc).is_err());
+
+        let bad_add = DiffSet {
+            added: roc.compress_set(&[2], 10u32).unwrap(),
+            removed: Vec::new(),
+        };
+        assert!(apply_diff(&base, &bad_add, 10, &roc).is_err());
+    }
+
+    #[test]
+    fn test_explain_diff_lists_extra_ids() {
+        let roc = RocCompressor::new();
+        let a = roc
+            .compress_set(&[1, 2, 3, 4, 5, 42, 100, 2048], 4096u32)
+            .unwrap();
+        let b = roc.compress_set(&[1, 2, 3, 4, 5, 7, 999], 4096u32).unwrap();
+
+        let explanation = explain_diff(&a, &b, 4096, &roc);
+        assert_eq!(
+            explanation,
+            "A has 3 extra IDs: [42, 100, 2048]. B has 2 extra IDs: [7, 999]. \
+             First divergence at position 5: A=[42], B=[7]."
+        );
+        assert_eq!(
+            explain_diff(&a, &a, 4096, &roc),
+            "Sets are identical (8 IDs)."
+        );
+
+        // A strict prefix diverges where it ends
+        let prefix = roc.compress_set(&[1, 2, 3], 4096u32).unwrap();
+        assert!(explain_diff(&prefix, &a, 4096, &roc)
+            .ends_with("First divergence at position 3: A=[], B=[4]."));
+    }
+
+    #[test]
+    fn test_explain_diff_caps_and_reports_errors() {
+        let roc = RocCompressor::new();
+        let many: Vec<u32> = (0..50).collect();
+        let a = roc.compress_set(&many, 100u32).unwrap();
+        let explanation = explain_diff(&a, &[], 100, &roc);
+        assert!(explanation.starts_with("A has 50 extra IDs: [0, 1,"));
+        assert!(explanation.contains("19, ... 30 more]."));
+        assert!(!explanation.contains("20,"));
+
+        let explanation = explain_diff(&a, &[0xFF], 100, &roc);
+        assert!(explanation.starts_with("Cannot decompress B: "));
+        assert!(!explanation.contains("Cannot decompress A"));
+    }
+}
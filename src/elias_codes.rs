@@ -0,0 +1,355 @@
+//! Elias gamma and delta coding of delta values.
+//!
+//! Both codes are bit-aligned and self-delimiting (Elias, 1975). Gamma
+//! writes a positive integer `n` as `floor(log2(n))` zero bits followed by
+//! `n` in binary, `2 floor(log2(n)) + 1` bits in all. Delta replaces the
+//! unary length prefix with the gamma code of the length `floor(log2(n)) + 1`
+//! and drops the leading `1` of `n`, taking
+//! `floor(log2(n)) + 2 floor(log2(floor(log2(n)) + 1)) + 1` bits: no shorter
+//! than gamma below 16, and shorter from 32 on.
+//!
+//! Against byte-aligned LEB128 varints, both win on the small gaps of dense
+//! sets. Delta also wins just above each byte boundary (e.g. `128..512`),
+//! where a varint pays for a whole extra byte, but LEB128 stays shorter
+//! just below the next boundary.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint]
+//! [code(first_id + 1)] [code(gap) * (len - 1)]   (bit stream, MSB-first, zero-padded)
+//! ```
+//!
+//! # References
+//!
+//! - Elias, P. (1975). "Universal codeword sets and representations of the
+//!   integers"
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// `floor(log2(value))` for `value >= 1`.
+fn floor_log2(value: u64) -> u32 {
+    63 - value.leading_zeros()
+}
+
+/// Bits in the gamma code of `value`.
+fn gamma_len(value: u64) -> u32 {
+    2 * floor_log2(value) + 1
+}
+
+/// Bits in the delta code of `value`.
+fn delta_len(value: u64) -> u32 {
+    let n = floor_log2(value);
+    n + gamma_len(n as u64 + 1)
+}
+
+/// Append the gamma code of `value` (which must be >= 1).
+fn encode_gamma(value: u64, writer: &mut BitWriter) {
+    debug_assert!(value >= 1);
+    let n = floor_log2(value);
+    writer.write_bits(0, n);
+    writer.write_bits(value, n + 1);
+}
+
+/// Read one gamma codeword.
+fn decode_gamma(reader: &mut BitReader<'_>) -> Result<u64, CompressionError> {
+    let mut n = 0;
+    while !reader.read_bit()? {
+        n += 1;
+        if n > 63 {
+            return Err(CompressionError::DecompressionFailed(
+                "Elias gamma code overflows u64".to_string(),
+            ));
+        }
+    }
+    Ok(1 << n | reader.read_bits(n)?)
+}
+
+/// Append the delta code of `value` (which must be >= 1).
+fn encode_delta(value: u64, writer: &mut BitWriter) {
+    debug_assert!(value >= 1);
+    let n = floor_log2(value);
+    encode_gamma(n as u64 + 1, writer);
+    writer.write_bits(value, n);
+}
+
+/// Read one delta codeword.
+fn decode_delta(reader: &mut BitReader<'_>) -> Result<u64, CompressionError> {
+    let len = decode_gamma(reader)?;
+    if len > 64 {
+        return Err(CompressionError::DecompressionFailed(
+            "Elias delta code overflows u64".to_string(),
+        ));
+    }
+    let n = len as u32 - 1;
+    Ok(1 << n | reader.read_bits(n)?)
+}
+
+/// Write the length and codes of `ids`, the body of both `compress_set`s.
+fn compress_with(
+    ids: &[u32],
+    universe_size: u32,
+    encode: fn(u64, &mut BitWriter),
+) -> Result<Vec<u8>, CompressionError> {
+    RocCompressor::validate_ids(ids)?;
+
+    let (first, last) = match (ids.first(), ids.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return Ok(Vec::new()),
+    };
+    if last >= universe_size {
+        return Err(CompressionError::InvalidInput(format!(
+            "ID {} exceeds universe size {}",
+            last, universe_size
+        )));
+    }
+
+    let mut encoded = Vec::new();
+    encode_varint(ids.len() as u64, &mut encoded);
+
+    let mut writer = BitWriter::new();
+    // Elias codes start at 1, so shift the first ID up by one
+    encode(first as u64 + 1, &mut writer);
+    for w in ids.windows(2) {
+        encode((w[1] - w[0]) as u64, &mut writer);
+    }
+    encoded.extend(writer.finish());
+    Ok(encoded)
+}
+
+/// Read a set written by [`compress_with`], the body of both `decompress_set`s.
+fn decompress_with(
+    compressed: &[u8],
+    universe_size: u32,
+    decode: fn(&mut BitReader<'_>) -> Result<u64, CompressionError>,
+) -> Result<Vec<u32>, CompressionError> {
+    if compressed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (num_ids, offset) = decode_varint(compressed)?;
+    let payload = &compressed[offset..];
+    // Every codeword takes at least one bit
+    if num_ids == 0 || num_ids > (payload.len() as u64 * 8) {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Invalid set length {} for {} bytes of data",
+            num_ids,
+            payload.len()
+        )));
+    }
+
+    let mut reader = BitReader::new(payload);
+    let mut ids = Vec::with_capacity(num_ids as usize);
+    let mut prev = decode(&mut reader)? - 1;
+    for i in 0..num_ids {
+        if i > 0 {
+            prev = prev.saturating_add(decode(&mut reader)?);
+        }
+        if prev >= universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                prev, universe_size
+            )));
+        }
+        ids.push(prev as u32);
+    }
+    if reader.unread_bytes() > 0 {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Extra data after decompression: {} bytes",
+            reader.unread_bytes()
+        )));
+    }
+    Ok(ids)
+}
+
+/// Size estimate from the code length of the average gap.
+fn estimate_with(num_ids: usize, universe_size: u32, code_len: fn(u64) -> u32) -> usize {
+    if num_ids == 0 {
+        return 0;
+    }
+    let avg_gap = (universe_size as u64 / num_ids as u64).max(1);
+    (num_ids as u64 * code_len(avg_gap) as u64).div_ceil(8) as usize + 5
+}
+
+/// Elias gamma compressor for sets.
+///
+/// Best when most gaps are tiny: a gap of 1 takes a single bit.
+#[derive(Clone, Debug, Default)]
+pub struct EliasGammaCompressor;
+
+impl EliasGammaCompressor {
+    /// Create a new Elias gamma compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for EliasGammaCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        compress_with(ids, universe_size, encode_gamma)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        decompress_with(compressed, universe_size, decode_gamma)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        estimate_with(num_ids, universe_size, gamma_len)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/elias-gamma")
+    }
+}
+
+/// Elias delta compressor for sets.
+///
+/// Shorter than gamma for gaps of 32 and more, so better suited to sparse
+/// sets.
+#[derive(Clone, Debug, Default)]
+pub struct EliasDeltaCompressor;
+
+impl EliasDeltaCompressor {
+    /// Create a new Elias delta compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for EliasDeltaCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        compress_with(ids, universe_size, encode_delta)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        decompress_with(compressed, universe_size, decode_delta)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        estimate_with(num_ids, universe_size, delta_len)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/elias-delta")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::varint::varint_len;
+
+    fn code_bits(value: u64, encode: fn(u64, &mut BitWriter), len: u32) -> String {
+        let mut writer = BitWriter::new();
+        encode(value, &mut writer);
+        let bytes = writer.finish();
+        let mut reader = BitReader::new(&bytes);
+        (0..len)
+            .map(|_| if reader.read_bit().unwrap() { '1' } else { '0' })
+            .collect()
+    }
+
+    #[test]
+    fn test_known_codewords() {
+        let gamma = |v| code_bits(v, encode_gamma, gamma_len(v));
+        let delta = |v| code_bits(v, encode_delta, delta_len(v));
+        assert_eq!(gamma(1), "1");
+        assert_eq!(gamma(2), "010");
+        assert_eq!(gamma(5), "00101");
+        assert_eq!(gamma(17), "000010001");
+        assert_eq!(delta(1), "1");
+        assert_eq!(delta(2), "0100");
+        assert_eq!(delta(5), "01101");
+        assert_eq!(delta(17), "001010001");
+    }
+
+    #[test]
+    fn test_codeword_round_trip() {
+        let values: Vec<u64> = (1..2000)
+            .chain([u32::MAX as u64, u32::MAX as u64 + 1, u64::MAX])
+            .collect();
+        for (encode, decode) in [
+            (
+                encode_gamma as fn(u64, &mut BitWriter),
+                decode_gamma as fn(&mut BitReader<'_>) -> _,
+            ),
+            (encode_delta, decode_delta),
+        ] {
+            let mut writer = BitWriter::new();
+            for &v in &values {
+                encode(v, &mut writer);
+            }
+            let bytes = writer.finish();
+
+            let mut reader = BitReader::new(&bytes);
+            for &v in &values {
+                assert_eq!(decode(&mut reader).unwrap(), v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_lengths_compared() {
+        for v in 1..=u16::MAX as u64 {
+            let (gamma, delta) = (gamma_len(v), delta_len(v));
+            match v {
+                1..=15 => assert!(delta >= gamma, "{}", v),
+                16..=31 => assert_eq!(delta, gamma, "{}", v),
+                _ => assert!(delta < gamma, "{}", v),
+            }
+        }
+
+        // Delta beats a LEB128 varint just past its first byte boundary...
+        for v in 128..512u64 {
+            assert!(delta_len(v) < 8 * varint_len(v) as u32, "{}", v);
+        }
+        // ...but not all the way to the next one
+        assert!(delta_len((1 << 14) - 1) > 8 * varint_len((1 << 14) - 1) as u32);
+    }
+
+    #[test]
+    fn test_round_trip_and_size() {
+        let ids = vec![0u32, 1, 2, 10, 100, 1000, 65_536, 4_000_000];
+        let dense: Vec<u32> = (0..800).collect();
+        for c in [
+            &EliasGammaCompressor::new() as &dyn IdSetCompressor,
+            &EliasDeltaCompressor::new(),
+        ] {
+            let compressed = c.compress_set(&ids, 5_000_000).unwrap();
+            assert_eq!(c.decompress_set(&compressed, 5_000_000).unwrap(), ids);
+            assert!(c
+                .decompress_set(&compressed[..compressed.len() - 1], 5_000_000)
+                .is_err());
+
+            // len (2) + 800 one-bit codewords
+            let compressed = c.compress_set(&dense, 1000).unwrap();
+            assert_eq!(compressed.len(), 2 + 100);
+        }
+    }
+}
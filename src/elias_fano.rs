@@ -0,0 +1,487 @@
+//! Elias-Fano encoding of monotone sequences.
+//!
+//! A strictly increasing sequence of `n` values from `[0, u)` is split into
+//! `l = floor(log2(u / n))` low bits per value, stored verbatim, and the
+//! remaining high bits, stored in unary as a bitvector of `n + (u >> l) + 1`
+//! bits where value `i` sets bit `(v_i >> l) + i`. The total is at most
+//! `n * (2 + log2(u / n))` bits, within 2 bits per element of the
+//! information-theoretic minimum.
+//!
+//! This module holds the codec core shared by the Elias-Fano based
+//! compressors, which works on `u64` so it can serve 64-bit universes,
+//! [`EliasFanoCompressor`], plain Elias-Fano over a `u32` universe, and
+//! [`EliasFanoWithSelect`], a sampled select structure over its output for
+//! `next_geq` without decoding the prefix.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [low bits: l * len] [high bits], zero-padded
+//! ```
+//!
+//! # References
+//!
+//! - Elias, P. (1974). "Efficient storage and retrieval by content and address"
+//! - Fano, R. (1971). "On the number of bits required to implement an associative memory"
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint, varint_len};
+
+/// Number of low bits per value for `n` values in `[0, universe)`.
+#[inline]
+pub(crate) fn low_bits(n: u64, universe: u64) -> u32 {
+    if n == 0 || universe <= n {
+        0
+    } else {
+        (universe / n).ilog2()
+    }
+}
+
+/// Encode strictly increasing `values` from `[0, universe)` as Elias-Fano.
+///
+/// Writes the low-bits array followed by the high-bits bitvector.
+pub(crate) fn encode(values: &[u64], universe: u64, writer: &mut BitWriter) {
+    let l = low_bits(values.len() as u64, universe);
+    let low_mask = if l == 0 { 0 } else { u64::MAX >> (64 - l) };
+
+    for &v in values {
+        writer.write_bits(v & low_mask, l);
+    }
+
+    // Unary-coded gaps between consecutive high parts
+    let mut prev_high = 0u64;
+    for &v in values {
+        let high = v >> l;
+        for _ in prev_high..high {
+            writer.write_bit(false);
+        }
+        writer.write_bit(true);
+        prev_high = high;
+    }
+}
+
+/// Decode `n` Elias-Fano values from `[0, universe)` starting at bit `start`.
+///
+/// Returns the values and the bit position just past the encoding.
+pub(crate) fn decode(
+    buf: &[u8],
+    start: usize,
+    n: usize,
+    universe: u64,
+) -> Result<(Vec<u64>, usize), CompressionError> {
+    let l = low_bits(n as u64, universe);
+    let high_limit = universe >> l;
+
+    // Reject counts the buffer cannot possibly hold before allocating
+    if n.saturating_mul(l as usize + 1) > (buf.len() * 8).saturating_sub(start) {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Elias-Fano data too short for {} values",
+            n
+        )));
+    }
+
+    let mut lows = BitReader::at(buf, start);
+    let mut highs = BitReader::at(buf, start + n * l as usize);
+    let mut values = Vec::with_capacity(n);
+    let mut high = 0u64;
+    for _ in 0..n {
+        while !highs.read_bit()? {
+            high += 1;
+            if high > high_limit {
+                return Err(CompressionError::DecompressionFailed(
+                    "Elias-Fano high bits exceed universe".to_string(),
+                ));
+            }
+        }
+        let v = (high << l) | lows.read_bits(l)?;
+        if v >= universe || values.last().is_some_and(|&prev| v <= prev) {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid Elias-Fano value {}",
+                v
+            )));
+        }
+        values.push(v);
+    }
+
+    Ok((values, highs.bit_pos()))
+}
+
+/// Position and value of the first of the `n` Elias-Fano values from
+/// `[0, universe)` at bit `start` that is `>= x`.
+///
+/// Skips the high buckets below that of `x` without reading low bits, then
+/// decodes values from there.
+pub(crate) fn next_geq(
+    buf: &[u8],
+    start: usize,
+    n: usize,
+    universe: u64,
+    x: u64,
+) -> Result<Option<(usize, u64)>, CompressionError> {
+    let l = low_bits(n as u64, universe);
+    let high_limit = universe >> l;
+    let target_high = x >> l;
+
+    let mut highs = BitReader::at(buf, start + n * l as usize);
+    let mut high = 0u64;
+    let mut i = 0;
+    while i < n {
+        if !highs.read_bit()? {
+            high += 1;
+            if high > high_limit {
+                return Err(CompressionError::DecompressionFailed(
+                    "Elias-Fano high bits exceed universe".to_string(),
+                ));
+            }
+            continue;
+        }
+        if high >= target_high {
+            let v = (high << l) | BitReader::at(buf, start + i * l as usize).read_bits(l)?;
+            if v >= x {
+                return Ok(Some((i, v)));
+            }
+        }
+        i += 1;
+    }
+    Ok(None)
+}
+
+/// Size in bits of the Elias-Fano encoding of `n` values from `[0, universe)`.
+pub(crate) fn encoded_bits(n: u64, universe: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    let l = low_bits(n, universe);
+    n * l as u64 + n + ((universe - 1) >> l)
+}
+
+/// Elias-Fano compressor for sets over a `u32` universe.
+///
+/// Size depends only on the set length and universe, never on how the IDs
+/// are spread, which makes it a predictable baseline. For 64-bit or
+/// clustered IDs use [`SplitEliasFanoCompressor`](crate::SplitEliasFanoCompressor).
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{EliasFanoCompressor, IdSetCompressor};
+///
+/// let compressor = EliasFanoCompressor::new();
+/// let ids: Vec<u32> = (0..1000).map(|i| i * 1000 + i % 7).collect();
+/// let compressed = compressor.compress_set(&ids, 1_000_000).unwrap();
+/// // Within 2 bits per ID of log2(1_000_000 / 1000) ~ 10 bits, plus the header
+/// assert!(compressed.len() <= 2 + 1000 * 12 / 8);
+/// assert_eq!(compressor.decompress_set(&compressed, 1_000_000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EliasFanoCompressor;
+
+impl EliasFanoCompressor {
+    /// Create a new Elias-Fano compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for EliasFanoCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let last = match ids.last() {
+            Some(&last) => last,
+            None => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        let values: Vec<u64> = ids.iter().map(|&id| id as u64).collect();
+        let mut writer = BitWriter::new();
+        encode(&values, universe_size as u64, &mut writer);
+        encoded.extend(writer.finish());
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (len, offset) = decode_varint(compressed)?;
+        if len == 0 || len > universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for universe size {}",
+                len, universe_size
+            )));
+        }
+        let payload = &compressed[offset..];
+        let (values, end) = decode(payload, 0, len as usize, universe_size as u64)?;
+        let used = end.div_ceil(8);
+        if used < payload.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                payload.len() - used
+            )));
+        }
+        Ok(values.into_iter().map(|v| v as u32).collect())
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+        let n = num_ids as u64;
+        let bits = encoded_bits(n.min(universe_size as u64), universe_size.max(1) as u64);
+        bits.div_ceil(8) as usize + varint_len(n)
+    }
+
+    fn estimate_compressed_size_bytes(&self, ids: &[u32], universe_size: u32) -> usize {
+        let last = match ids.last() {
+            Some(&last) => last as u64,
+            None => return 0,
+        };
+        // Exact for valid input: the high bits end at the last ID's bucket
+        let n = ids.len() as u64;
+        let l = low_bits(n, universe_size as u64);
+        (n * l as u64 + n + (last >> l)).div_ceil(8) as usize + varint_len(n)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/elias-fano")
+    }
+}
+
+/// Number of IDs between consecutive [`EliasFanoWithSelect`] samples.
+const SELECT_SAMPLE: usize = 64;
+
+/// An [`EliasFanoCompressor`] set with a sampled select structure.
+///
+/// Element `i` of an Elias-Fano set is the `i`-th set bit of the high-bits
+/// vector, at position `(id >> l) + i`. The index keeps every 64th ID, from
+/// which the position of every 64th set bit follows, so `next_geq` is a
+/// binary search over `n / 64` samples and a scan of at most 64 elements,
+/// whatever the position of the answer. The low bits have a fixed width and
+/// are read in place.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{EliasFanoCompressor, EliasFanoWithSelect, IdSetCompressor};
+///
+/// let ids: Vec<u32> = (0..1000).map(|i| i * 5).collect();
+/// let compressed = EliasFanoCompressor::new().compress_set(&ids, 5000).unwrap();
+///
+/// let index = EliasFanoWithSelect::new(compressed, 5000).unwrap();
+/// assert_eq!(index.next_geq(2501).unwrap(), Some(2505));
+/// assert_eq!(index.next_geq(4996).unwrap(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct EliasFanoWithSelect {
+    /// Set in the [`EliasFanoCompressor`] format.
+    compressed: Vec<u8>,
+    len: usize,
+    /// Low bits per ID.
+    l: u32,
+    /// Byte offset of the low bits, past the length varint.
+    payload_start: usize,
+    /// Every 64th ID; sample `k` is set bit `64 * k` of the high bits.
+    samples: Vec<u32>,
+}
+
+impl EliasFanoWithSelect {
+    /// Build the select samples over `compressed`, an [`EliasFanoCompressor`]
+    /// set over `[0, universe_size)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if `compressed` is not a valid set.
+    pub fn new(compressed: Vec<u8>, universe_size: u32) -> Result<Self, CompressionError> {
+        let ids = EliasFanoCompressor::new().decompress_set(&compressed, universe_size)?;
+        let payload_start = if compressed.is_empty() {
+            0
+        } else {
+            decode_varint(&compressed)?.1
+        };
+        Ok(Self {
+            len: ids.len(),
+            l: low_bits(ids.len() as u64, universe_size as u64),
+            payload_start,
+            samples: ids.iter().copied().step_by(SELECT_SAMPLE).collect(),
+            compressed,
+        })
+    }
+
+    /// Find the first ID `>= x`.
+    ///
+    /// Binary-searches the samples, then decodes at most 64 IDs from the
+    /// high bits after the sample.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the decoded bits are malformed.
+    pub fn next_geq(&self, x: u32) -> Result<Option<u32>, CompressionError> {
+        // Last sample <= x; the answer lies in its block or is the next sample
+        let s = self.samples.partition_point(|&id| id <= x);
+        if s == 0 {
+            return Ok(self.samples.first().copied());
+        }
+
+        let first = (s - 1) * SELECT_SAMPLE;
+        let id = self.samples[s - 1];
+        if id >= x {
+            return Ok(Some(id));
+        }
+
+        let payload = &self.compressed[self.payload_start..];
+        let l = self.l as usize;
+        let mut high = (id >> self.l) as usize;
+        let mut lows = BitReader::at(payload, (first + 1) * l);
+        // Just past the set bit of element `first`
+        let mut highs = BitReader::at(payload, self.len * l + high + first + 1);
+        for _ in first + 1..(first + SELECT_SAMPLE).min(self.len) {
+            while !highs.read_bit()? {
+                high += 1;
+            }
+            let id = ((high as u64) << self.l) | lows.read_bits(self.l)?;
+            if id >= x as u64 {
+                return Ok(Some(id as u32));
+            }
+        }
+        Ok(self.samples.get(s).copied())
+    }
+
+    /// Number of IDs in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The set in the [`EliasFanoCompressor`] format.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.compressed
+    }
+
+    /// Drop the samples and return the compressed bytes.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.compressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let values = vec![0u64, 3, 4, 7, 100, 101, 999];
+        let mut writer = BitWriter::new();
+        encode(&values, 1000, &mut writer);
+        let bytes = writer.finish();
+
+        let (decoded, end) = decode(&bytes, 0, values.len(), 1000).unwrap();
+        assert_eq!(decoded, values);
+        assert!(end as u64 <= encoded_bits(values.len() as u64, 1000));
+    }
+
+    #[test]
+    fn test_full_64_bit_range() {
+        let values = vec![1u64, 1 << 40, u64::MAX - 2];
+        let mut writer = BitWriter::new();
+        encode(&values, u64::MAX, &mut writer);
+        let bytes = writer.finish();
+
+        let (decoded, _) = decode(&bytes, 0, values.len(), u64::MAX).unwrap();
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn test_rejects_truncated() {
+        let values: Vec<u64> = (0..50).map(|i| i * 17).collect();
+        let mut writer = BitWriter::new();
+        encode(&values, 1000, &mut writer);
+        let bytes = writer.finish();
+
+        assert!(decode(&bytes[..bytes.len() / 2], 0, values.len(), 1000).is_err());
+    }
+
+    #[test]
+    fn test_compressor_size_matches_estimate() {
+        let compressor = EliasFanoCompressor::new();
+        for ids in [
+            vec![0u32],
+            vec![5, 6, 7],
+            (0..500).map(|i| i * 13).collect(),
+        ] {
+            let compressed = compressor.compress_set(&ids, 10_000).unwrap();
+            assert_eq!(
+                compressed.len(),
+                compressor.estimate_compressed_size_bytes(&ids, 10_000)
+            );
+            assert_eq!(compressor.decompress_set(&compressed, 10_000).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn test_compressor_rejects_trailing_data() {
+        let compressor = EliasFanoCompressor::new();
+        let mut compressed = compressor.compress_set(&[1, 2, 900], 1000).unwrap();
+        compressed.push(0);
+        assert!(compressor.decompress_set(&compressed, 1000).is_err());
+    }
+
+    #[test]
+    fn test_select_next_geq_matches_scan() {
+        let mut ids: Vec<u32> = (0..700).map(|i| i * i % 10_007).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let compressed = EliasFanoCompressor::new()
+            .compress_set(&ids, 10_007)
+            .unwrap();
+
+        let index = EliasFanoWithSelect::new(compressed, 10_007).unwrap();
+        assert_eq!(index.len(), ids.len());
+        for x in 0..10_100 {
+            let expected = ids.iter().copied().find(|&id| id >= x);
+            assert_eq!(index.next_geq(x).unwrap(), expected, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_select_empty_and_dense() {
+        let index = EliasFanoWithSelect::new(Vec::new(), 10).unwrap();
+        assert!(index.is_empty());
+        assert_eq!(index.next_geq(0).unwrap(), None);
+
+        // No low bits when every ID is present
+        let ids: Vec<u32> = (0..200).collect();
+        let compressed = EliasFanoCompressor::new().compress_set(&ids, 200).unwrap();
+        let index = EliasFanoWithSelect::new(compressed.clone(), 200).unwrap();
+        assert_eq!(index.next_geq(130).unwrap(), Some(130));
+        assert_eq!(index.into_inner(), compressed);
+
+        assert!(EliasFanoWithSelect::new(vec![3, 0], 10).is_err());
+    }
+}
@@ -19,6 +19,35 @@ pub enum CompressionError {
 
     /// I/O error.
     Io(String),
+
+    /// The set was cut short to fit a byte budget, see
+    /// [`MaxSizeCompressor`](crate::MaxSizeCompressor).
+    Truncated {
+        /// IDs kept in the compressed bytes.
+        stored_count: usize,
+        /// IDs in the set before truncation.
+        original_count: usize,
+    },
+
+    /// The output buffer cannot hold the compressed set, see
+    /// [`IdSetCompressor::compress_set_reuse`](crate::IdSetCompressor::compress_set_reuse).
+    ///
+    /// From [`StackCompressor::decompress_fixed`](crate::StackCompressor::decompress_fixed),
+    /// both counts are IDs rather than bytes.
+    BufferTooSmall {
+        /// Bytes the compressed set takes.
+        needed: usize,
+        /// Bytes the buffer holds.
+        available: usize,
+    },
+
+    /// A batch operation would go over a [`CompressionBudget`](crate::CompressionBudget).
+    BudgetExceeded {
+        /// Bytes the budget allows.
+        limit: usize,
+        /// Bytes the operation would have used.
+        attempted: usize,
+    },
 }
 
 impl fmt::Display for CompressionError {
@@ -39,6 +68,30 @@ impl fmt::Display for CompressionError {
             CompressionError::Io(msg) => {
                 write!(f, "I/O error: {}", msg)
             }
+            CompressionError::Truncated {
+                stored_count,
+                original_count,
+            } => {
+                write!(
+                    f,
+                    "Truncated set: {} of {} IDs stored",
+                    stored_count, original_count
+                )
+            }
+            CompressionError::BufferTooSmall { needed, available } => {
+                write!(
+                    f,
+                    "Buffer too small: {} bytes needed, {} available",
+                    needed, available
+                )
+            }
+            CompressionError::BudgetExceeded { limit, attempted } => {
+                write!(
+                    f,
+                    "Budget exceeded: {} bytes attempted, limit {}",
+                    attempted, limit
+                )
+            }
         }
     }
 }
@@ -50,3 +103,21 @@ impl From<std::io::Error> for CompressionError {
         Self::Io(e.to_string())
     }
 }
+
+impl From<CompressionError> for std::io::Error {
+    fn from(e: CompressionError) -> Self {
+        use std::io::ErrorKind;
+
+        let kind = match &e {
+            CompressionError::InvalidInput(_)
+            | CompressionError::DecompressionFailed(_)
+            | CompressionError::AnsError(_) => ErrorKind::InvalidData,
+            CompressionError::CompressionFailed(_)
+            | CompressionError::Io(_)
+            | CompressionError::BufferTooSmall { .. }
+            | CompressionError::BudgetExceeded { .. } => ErrorKind::Other,
+            CompressionError::Truncated { .. } => ErrorKind::UnexpectedEof,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
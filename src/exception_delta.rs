@@ -0,0 +1,283 @@
+//! Delta encoding with large gaps moved out of line.
+//!
+//! Some posting lists have a bimodal gap distribution: almost every gap is
+//! tiny, but a few jump hundreds of thousands of IDs at section boundaries.
+//! [`ExceptionBasedDeltaCompressor`] keeps the small gaps as inline varints
+//! and replaces each gap of at least `threshold` with a one-byte sentinel,
+//! storing its position and value in an exception list after the inline
+//! stream. A reader that only needs the dense runs can skip the exceptions,
+//! and a reader that only needs the section boundaries can find them without
+//! scanning the inline stream.
+//!
+//! # Format
+//!
+//! ```text
+//! [count: varint] [first_id: varint]
+//! [inline: (gap, or 0 for an exception) as varint * (count - 1)]
+//! [exception_count: varint]
+//! [exception_positions: varint * exception_count]   gap index, strictly increasing
+//! [exception_values: varint * exception_count]      gap
+//! ```
+//!
+//! Gaps between sorted unique IDs are at least 1, so the sentinel `0` never
+//! collides with an inline gap.
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint, varint_len};
+
+/// Inline marker for a gap stored in the exception list.
+const SENTINEL: u64 = 0;
+
+/// Default threshold: gaps above `u16::MAX`.
+const DEFAULT_THRESHOLD: u32 = 1 << 16;
+
+/// Delta compressor storing gaps of at least `threshold` as exceptions.
+///
+/// # Performance
+///
+/// - Best for: runs of small gaps separated by rare, very large jumps
+/// - Each exception costs one sentinel byte plus its position and value
+///   varints, so thresholds well below the large gaps only add overhead
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{ExceptionBasedDeltaCompressor, IdSetCompressor};
+///
+/// let compressor = ExceptionBasedDeltaCompressor::new(1000);
+/// let ids = [1u32, 2, 4, 500_000, 500_001, 500_003];
+/// let compressed = compressor.compress_set(&ids, 1_000_000).unwrap();
+/// assert_eq!(compressor.decompress_set(&compressed, 1_000_000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExceptionBasedDeltaCompressor {
+    threshold: u32,
+}
+
+impl ExceptionBasedDeltaCompressor {
+    /// Create a compressor moving gaps `>= threshold` into the exception list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is 0.
+    pub fn new(threshold: u32) -> Self {
+        assert!(threshold > 0, "threshold must be at least 1");
+        Self { threshold }
+    }
+
+    /// The smallest gap stored as an exception.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+}
+
+impl Default for ExceptionBasedDeltaCompressor {
+    /// Gaps of 2^16 and above are exceptions.
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD)
+    }
+}
+
+/// Read a varint at `*offset`, advancing past it.
+fn read_varint(compressed: &[u8], offset: &mut usize) -> Result<u64, CompressionError> {
+    let (value, consumed) = decode_varint(&compressed[*offset..])?;
+    *offset += consumed;
+    Ok(value)
+}
+
+impl IdSetCompressor for ExceptionBasedDeltaCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        encode_varint(first as u64, &mut encoded);
+
+        let mut positions = Vec::new();
+        let mut values = Vec::new();
+        for (i, w) in ids.windows(2).enumerate() {
+            let gap = w[1] - w[0];
+            if gap >= self.threshold {
+                encode_varint(SENTINEL, &mut encoded);
+                positions.push(i as u64);
+                values.push(gap as u64);
+            } else {
+                encode_varint(gap as u64, &mut encoded);
+            }
+        }
+
+        encode_varint(positions.len() as u64, &mut encoded);
+        for v in positions.into_iter().chain(values) {
+            encode_varint(v, &mut encoded);
+        }
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut offset = 0;
+        let num_ids = read_varint(compressed, &mut offset)?;
+        // Every inline gap takes at least one byte
+        if num_ids == 0 || num_ids - 1 > compressed.len() as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for {} bytes of data",
+                num_ids,
+                compressed.len()
+            )));
+        }
+        let first_id = read_varint(compressed, &mut offset)?;
+
+        let num_gaps = num_ids as usize - 1;
+        let mut gaps = Vec::with_capacity(num_gaps);
+        let mut sentinels = Vec::new();
+        for i in 0..num_gaps {
+            let gap = read_varint(compressed, &mut offset)?;
+            if gap == SENTINEL {
+                sentinels.push(i as u64);
+            }
+            gaps.push(gap);
+        }
+
+        let num_exceptions = read_varint(compressed, &mut offset)?;
+        if num_exceptions != sentinels.len() as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Exception list holds {} entries for {} sentinels",
+                num_exceptions,
+                sentinels.len()
+            )));
+        }
+        for &expected in &sentinels {
+            let position = read_varint(compressed, &mut offset)?;
+            if position != expected {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Exception position {} does not match sentinel at {}",
+                    position, expected
+                )));
+            }
+        }
+        for &position in &sentinels {
+            gaps[position as usize] = read_varint(compressed, &mut offset)?;
+        }
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+
+        let mut ids = Vec::with_capacity(num_ids as usize);
+        let mut prev = first_id;
+        for (i, gap) in std::iter::once(0).chain(gaps).enumerate() {
+            if i > 0 {
+                if gap == SENTINEL {
+                    return Err(CompressionError::DecompressionFailed(
+                        "Exception value of zero".to_string(),
+                    ));
+                }
+                prev = prev.saturating_add(gap);
+            }
+            if prev >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev as u32);
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Evenly spread IDs: every gap is the average one, inline or not
+        let avg_gap = (universe_size as u64 / num_ids as u64).max(1);
+        let inline = if avg_gap >= self.threshold as u64 {
+            1 + varint_len(num_ids as u64) + varint_len(avg_gap)
+        } else {
+            varint_len(avg_gap)
+        };
+        (num_ids - 1) * inline + 11
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/exception-delta")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_gaps_become_exceptions() {
+        let compressor = ExceptionBasedDeltaCompressor::new(100);
+        let ids = [0u32, 1, 3, 1003, 1004];
+        let compressed = compressor.compress_set(&ids, 2000).unwrap();
+        // count, first, inline [1, 2, sentinel, 1], one exception at gap 2 of 1000
+        assert_eq!(compressed, [5, 0, 1, 2, 0, 1, 1, 2, 0xe8, 0x07]);
+        assert_eq!(compressor.decompress_set(&compressed, 2000).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_threshold_boundary() {
+        let compressor = ExceptionBasedDeltaCompressor::new(10);
+        // A gap of 9 stays inline, a gap of exactly 10 does not
+        let inline = compressor.compress_set(&[0u32, 9], 100).unwrap();
+        assert_eq!(inline, [2, 0, 9, 0]);
+        let exception = compressor.compress_set(&[0u32, 10], 100).unwrap();
+        assert_eq!(exception, [2, 0, 0, 1, 0, 10]);
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_exceptions() {
+        let compressor = ExceptionBasedDeltaCompressor::new(100);
+        // Sentinel without an exception entry
+        assert!(compressor.decompress_set(&[2, 0, 0, 0], 2000).is_err());
+        // Exception position pointing at an inline gap
+        assert!(compressor
+            .decompress_set(&[3, 0, 1, 0, 1, 0, 200], 2000)
+            .is_err());
+        // Exception value of zero
+        assert!(compressor
+            .decompress_set(&[2, 0, 0, 1, 0, 0], 2000)
+            .is_err());
+        // Trailing byte
+        assert!(compressor.decompress_set(&[2, 0, 9, 0, 0], 2000).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "threshold")]
+    fn test_zero_threshold_panics() {
+        let _ = ExceptionBasedDeltaCompressor::new(0);
+    }
+}
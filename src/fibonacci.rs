@@ -0,0 +1,268 @@
+//! Fibonacci coding of delta values.
+//!
+//! Every positive integer has a unique Zeckendorf representation as a sum of
+//! non-consecutive Fibonacci numbers. Writing that representation least
+//! significant term first and appending an extra `1` yields a code in which
+//! `11` only ever appears as the terminator, so codewords are self-delimiting
+//! without any length prefix (Apostolico & Fraenkel, 1987). The code for `k`
+//! takes about `log_phi(k * sqrt(5)) + 1` bits, i.e. roughly 1.44 `log2(k)`.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint]
+//! [fib(first_id + 1)] [fib(gap) * (len - 1)]   (bit stream, MSB-first, zero-padded)
+//! ```
+//!
+//! # References
+//!
+//! - Apostolico, A. & Fraenkel, A. S. (1987). "Robust transmission of
+//!   unbounded strings using Fibonacci representations"
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Fibonacci numbers `F(2), F(3), ...` = `1, 2, 3, 5, ...` that fit in a `u64`.
+const FIB: [u64; 92] = {
+    let mut fib = [0u64; 92];
+    fib[0] = 1;
+    fib[1] = 2;
+    let mut i = 2;
+    while i < fib.len() {
+        fib[i] = fib[i - 1] + fib[i - 2];
+        i += 1;
+    }
+    fib
+};
+
+/// Append the Fibonacci code of `value` (which must be >= 1).
+fn encode_fib(value: u64, writer: &mut BitWriter) {
+    debug_assert!(value >= 1);
+    // Greedy Zeckendorf decomposition from the largest term down
+    let top = FIB.partition_point(|&f| f <= value) - 1;
+    let mut terms = 0u128;
+    let mut rest = value;
+    for i in (0..=top).rev() {
+        if FIB[i] <= rest {
+            terms |= 1 << i;
+            rest -= FIB[i];
+        }
+    }
+    for i in 0..=top {
+        writer.write_bit(terms >> i & 1 == 1);
+    }
+    writer.write_bit(true);
+}
+
+/// Read one Fibonacci codeword, scanning for the `11` terminator.
+fn decode_fib(reader: &mut BitReader<'_>) -> Result<u64, CompressionError> {
+    let mut value = 0u64;
+    let mut prev = false;
+    for &fib in FIB.iter() {
+        let bit = reader.read_bit()?;
+        if bit && prev {
+            return Ok(value);
+        }
+        if bit {
+            value = value.checked_add(fib).ok_or_else(|| {
+                CompressionError::DecompressionFailed("Fibonacci code overflows u64".to_string())
+            })?;
+        }
+        prev = bit;
+    }
+    // The terminator may follow the last representable term
+    if prev && reader.read_bit()? {
+        return Ok(value);
+    }
+    Err(CompressionError::DecompressionFailed(
+        "Fibonacci code too long".to_string(),
+    ))
+}
+
+/// Fibonacci-code compressor for sets.
+///
+/// Mainly of theoretical interest: for small gaps it is close to Elias
+/// gamma, and its codewords can be resynchronised after bit errors by
+/// scanning for `11`.
+#[derive(Clone, Debug, Default)]
+pub struct FibonacciCompressor;
+
+impl FibonacciCompressor {
+    /// Create a new Fibonacci compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for FibonacciCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+
+        let mut writer = BitWriter::new();
+        // Fibonacci codes start at 1, so shift the first ID up by one
+        encode_fib(first as u64 + 1, &mut writer);
+        for w in ids.windows(2) {
+            encode_fib((w[1] - w[0]) as u64, &mut writer);
+        }
+        encoded.extend(writer.finish());
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, offset) = decode_varint(compressed)?;
+        let payload = &compressed[offset..];
+        // Every codeword takes at least two bits
+        if num_ids == 0 || num_ids > (payload.len() as u64 * 4) {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for {} bytes of data",
+                num_ids,
+                payload.len()
+            )));
+        }
+
+        let mut reader = BitReader::new(payload);
+        let mut ids = Vec::with_capacity(num_ids as usize);
+        let mut prev = decode_fib(&mut reader)? - 1;
+        for i in 0..num_ids {
+            if i > 0 {
+                prev = prev.saturating_add(decode_fib(&mut reader)?);
+            }
+            if prev >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev as u32);
+        }
+        if reader.unread_bytes() > 0 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                reader.unread_bytes()
+            )));
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Codeword length for the average gap: log_phi(gap * sqrt(5)) + 1
+        let phi = (1.0 + 5f64.sqrt()) / 2.0;
+        let avg_gap = (universe_size as f64 / num_ids as f64).max(1.0);
+        let bits_per_gap = (avg_gap * 5f64.sqrt()).ln() / phi.ln() + 1.0;
+        (num_ids as f64 * bits_per_gap / 8.0).ceil() as usize + 5
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/fibonacci")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_bits(value: u64) -> String {
+        let mut writer = BitWriter::new();
+        encode_fib(value, &mut writer);
+        let bytes = writer.finish();
+        let mut reader = BitReader::new(&bytes);
+        let mut bits = String::new();
+        while !bits.ends_with("11") {
+            bits.push(if reader.read_bit().unwrap() { '1' } else { '0' });
+        }
+        bits
+    }
+
+    #[test]
+    fn test_known_codewords() {
+        assert_eq!(code_bits(1), "11");
+        assert_eq!(code_bits(2), "011");
+        assert_eq!(code_bits(3), "0011");
+        assert_eq!(code_bits(4), "1011");
+        assert_eq!(code_bits(11), "001011");
+        assert_eq!(code_bits(12), "101011");
+    }
+
+    #[test]
+    fn test_codeword_round_trip() {
+        let values: Vec<u64> = (1..2000)
+            .chain([u32::MAX as u64, u32::MAX as u64 + 1, u64::MAX])
+            .collect();
+        let mut writer = BitWriter::new();
+        for &v in &values {
+            encode_fib(v, &mut writer);
+        }
+        let bytes = writer.finish();
+
+        let mut reader = BitReader::new(&bytes);
+        for &v in &values {
+            assert_eq!(decode_fib(&mut reader).unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let compressor = FibonacciCompressor::new();
+        let ids = vec![0u32, 1, 2, 10, 100, 1000, 65_536, 4_000_000];
+
+        let compressed = compressor.compress_set(&ids, 5_000_000).unwrap();
+        let decompressed = compressor.decompress_set(&compressed, 5_000_000).unwrap();
+
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_consecutive_ids_use_two_bits() {
+        let compressor = FibonacciCompressor::new();
+        let ids: Vec<u32> = (0..400).collect();
+
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        // len (2) + 400 codewords of "11"
+        assert_eq!(compressed.len(), 2 + 100);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let compressor = FibonacciCompressor::new();
+        let compressed = compressor.compress_set(&[5, 900, 70_000], 100_000).unwrap();
+        assert!(compressor
+            .decompress_set(&compressed[..compressed.len() - 1], 100_000)
+            .is_err());
+    }
+}
@@ -0,0 +1,89 @@
+//! Content hashes for detecting corrupted compressed sets.
+//!
+//! A flipped bit in a delta-encoded set usually still decodes, just to the
+//! wrong IDs. [`fingerprint`] gives a cheap 64-bit checksum that
+//! [`CompressedSetWithHash`](crate::CompressedSetWithHash) stores alongside
+//! the data.
+//!
+//! With the `xxhash` feature the hash is XXH3-64; otherwise it is FNV-1a, a
+//! pure-Rust fallback that detects every single-byte change. The algorithm
+//! used is recorded next to each stored fingerprint, so data written with
+//! one build can be verified by another as long as the algorithm is
+//! available.
+//!
+//! Neither hash is cryptographic: they catch accidental corruption, not
+//! tampering.
+
+/// Algorithm tag for 64-bit FNV-1a.
+pub(crate) const FNV1A: u8 = 1;
+
+/// Algorithm tag for XXH3-64.
+#[cfg(feature = "xxhash")]
+pub(crate) const XXH3: u8 = 2;
+
+/// Algorithm used by [`fingerprint`] in this build.
+#[cfg(feature = "xxhash")]
+pub(crate) const DEFAULT_ALGORITHM: u8 = XXH3;
+
+/// Algorithm used by [`fingerprint`] in this build.
+#[cfg(not(feature = "xxhash"))]
+pub(crate) const DEFAULT_ALGORITHM: u8 = FNV1A;
+
+/// 64-bit fingerprint of `compressed`.
+///
+/// Uses XXH3-64 with the `xxhash` feature and FNV-1a without it.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::fingerprint;
+///
+/// assert_eq!(fingerprint(b"abc"), fingerprint(b"abc"));
+/// assert_ne!(fingerprint(b"abc"), fingerprint(b"abd"));
+/// ```
+pub fn fingerprint(compressed: &[u8]) -> u64 {
+    fingerprint_with(DEFAULT_ALGORITHM, compressed).expect("default algorithm is available")
+}
+
+/// Fingerprint with a specific algorithm, or `None` if it is unknown or
+/// not compiled in.
+pub(crate) fn fingerprint_with(algorithm: u8, bytes: &[u8]) -> Option<u64> {
+    match algorithm {
+        FNV1A => Some(fnv1a(bytes)),
+        #[cfg(feature = "xxhash")]
+        XXH3 => Some(xxhash_rust::xxh3::xxh3_64(bytes)),
+        _ => None,
+    }
+}
+
+/// 64-bit FNV-1a.
+///
+/// XOR and multiplication by the odd prime are both bijections on the
+/// state, so two inputs of equal length differing in one byte always hash
+/// differently.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_unknown_algorithm() {
+        assert_eq!(fingerprint_with(0, b"abc"), None);
+        assert_eq!(fingerprint_with(FNV1A, b"abc"), Some(fnv1a(b"abc")));
+    }
+}
@@ -0,0 +1,173 @@
+//! Fixed-width packing of IDs.
+//!
+//! Every ID takes exactly [`bits_needed`]`(universe)` bits, so the `i`-th
+//! ID starts at bit `32 + i * width` and any ID can be decoded without
+//! touching the others. That costs more space than the gap codes, but suits
+//! SIMD and GPU kernels, where each lane decodes its own slot and branches
+//! on variable-length codes stall the warp.
+//!
+//! # Format
+//!
+//! ```text
+//! [count: u32 LE] [count * width bits, MSB-first, zero-padded to a byte]
+//! ```
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::CompressionError;
+
+/// Size of the count header.
+const HEADER_LEN: usize = 4;
+
+/// Bits needed to store any ID in `[0, universe)`: `ceil(log2(universe))`.
+///
+/// A universe of 1 needs no bits, since 0 is the only ID it holds.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::bits_needed;
+///
+/// assert_eq!(bits_needed(1), 0);
+/// assert_eq!(bits_needed(256), 8);
+/// assert_eq!(bits_needed(257), 9);
+/// assert_eq!(bits_needed(u32::MAX), 32);
+/// ```
+pub fn bits_needed(universe: u32) -> u8 {
+    (32 - universe.saturating_sub(1).leading_zeros()) as u8
+}
+
+/// Pack `ids` at [`bits_needed`]`(universe)` bits each.
+///
+/// IDs are stored in the order given; they need not be sorted.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if an ID is outside the universe
+/// or there are more than `u32::MAX` IDs.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{compress_fixed_width, decompress_fixed_width};
+///
+/// let ids = [3, 14, 15, 92];
+/// let compressed = compress_fixed_width(&ids, 100).unwrap();
+/// // 4-byte count, then 4 IDs of 7 bits
+/// assert_eq!(compressed.len(), 4 + 4);
+/// assert_eq!(decompress_fixed_width(&compressed, 4, 100).unwrap(), ids);
+/// ```
+pub fn compress_fixed_width(ids: &[u32], universe: u32) -> Result<Vec<u8>, CompressionError> {
+    let count = u32::try_from(ids.len()).map_err(|_| {
+        CompressionError::InvalidInput(format!("Too many IDs for a u32 count: {}", ids.len()))
+    })?;
+    let width = bits_needed(universe) as u32;
+
+    let mut writer = BitWriter::new();
+    for &id in ids {
+        if id >= universe {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                id, universe
+            )));
+        }
+        writer.write_bits(id as u64, width);
+    }
+    let packed = writer.finish();
+
+    let mut out = Vec::with_capacity(HEADER_LEN + packed.len());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&packed);
+    Ok(out)
+}
+
+/// Unpack `count` IDs written by [`compress_fixed_width`].
+///
+/// # Errors
+///
+/// Returns `CompressionError::DecompressionFailed` if the stored count is
+/// not `count`, the length does not match it exactly, or an ID is outside
+/// the universe.
+pub fn decompress_fixed_width(
+    compressed: &[u8],
+    count: usize,
+    universe: u32,
+) -> Result<Vec<u32>, CompressionError> {
+    if compressed.len() < HEADER_LEN {
+        return Err(CompressionError::DecompressionFailed(
+            "Unexpected end of compressed data".to_string(),
+        ));
+    }
+    let stored = u32::from_le_bytes(compressed[..HEADER_LEN].try_into().unwrap()) as usize;
+    if stored != count {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Expected {} IDs but the header stores {}",
+            count, stored
+        )));
+    }
+
+    let width = bits_needed(universe) as u32;
+    let packed = &compressed[HEADER_LEN..];
+    let expected = (count as u64 * width as u64).div_ceil(8);
+    if packed.len() as u64 != expected {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Expected {} packed bytes for {} IDs of {} bits, found {}",
+            expected,
+            count,
+            width,
+            packed.len()
+        )));
+    }
+
+    let mut reader = BitReader::new(packed);
+    let mut ids = Vec::with_capacity(count);
+    for _ in 0..count {
+        let id = reader.read_bits(width)? as u32;
+        if id >= universe {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                id, universe
+            )));
+        }
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_needed() {
+        assert_eq!(bits_needed(0), 0);
+        assert_eq!(bits_needed(2), 1);
+        assert_eq!(bits_needed(3), 2);
+        assert_eq!(bits_needed(1 << 31), 31);
+        assert_eq!(bits_needed((1 << 31) + 1), 32);
+    }
+
+    #[test]
+    fn test_packs_msb_first() {
+        // 3 bits each: 101 011 -> 1010_1100
+        let compressed = compress_fixed_width(&[5, 3], 8).unwrap();
+        assert_eq!(compressed, [2, 0, 0, 0, 0b1010_1100]);
+
+        // Order is kept, and a single-ID universe takes no payload
+        assert_eq!(decompress_fixed_width(&compressed, 2, 8).unwrap(), [5, 3]);
+        let zeros = compress_fixed_width(&[0, 0, 0], 1).unwrap();
+        assert_eq!(zeros, [3, 0, 0, 0]);
+        assert_eq!(decompress_fixed_width(&zeros, 3, 1).unwrap(), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert!(compress_fixed_width(&[8], 8).is_err());
+
+        let compressed = compress_fixed_width(&[1, 2, 3], 5).unwrap();
+        assert!(decompress_fixed_width(&compressed, 2, 5).is_err());
+        assert!(decompress_fixed_width(&compressed[..4], 3, 5).is_err());
+        assert!(decompress_fixed_width(&compressed[..2], 3, 5).is_err());
+        // 7 fits in 3 bits but not in the universe
+        assert!(decompress_fixed_width(&[1, 0, 0, 0, 0b1110_0000], 1, 5).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Seed inputs for fuzzing and auditing the delta-varint decoder.
+//!
+//! [`generate_fuzz_corpus`] writes one file per known-tricky input in the
+//! [`RocCompressor`](crate::RocCompressor) format, named by its scenario:
+//! truncated headers, deltas past the universe, trailing bytes, and varints
+//! at or past their 9-byte limit. Every file must decode to `Ok` or `Err`,
+//! never panic.
+//!
+//! The files are raw compressed bytes. The `fuzz_decompress` target reads
+//! a decoder selector byte and an eight-byte universe from the front of its
+//! input, so seeds for it need that prefix, with selector 0 for
+//! `RocCompressor`; `fuzz_varint` takes them as they are.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! let dir = std::path::Path::new("fuzz/corpus/audit");
+//! let written = cnk::fuzz_corpus::generate_fuzz_corpus(dir).unwrap();
+//! assert_eq!(written, 9);
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::varint::encode_varint;
+
+/// Header tag of the default delta-varint level.
+const TAG: u8 = 5;
+
+/// The scenarios, as `(file name, bytes)`.
+fn scenarios() -> Vec<(&'static str, Vec<u8>)> {
+    let varint = |values: &[u64]| {
+        let mut out = vec![TAG];
+        for &v in values {
+            encode_varint(v, &mut out);
+        }
+        out
+    };
+
+    vec![
+        ("empty", Vec::new()),
+        ("single_zero_byte", vec![0]),
+        (
+            "zero_count_with_payload",
+            [varint(&[0]), vec![1, 2, 3]].concat(),
+        ),
+        ("truncated_after_count", varint(&[1])),
+        ("delta_overflow", varint(&[2, 10, u32::MAX as u64 + 1])),
+        ("trailing_bytes", [varint(&[3, 1, 1, 1]), vec![0]].concat()),
+        ("max_varint", varint(&[0x7FFF_FFFF_FFFF_FFFF])),
+        (
+            "accumulated_id_overflow",
+            varint(&[2, u32::MAX as u64 - 1, 2]),
+        ),
+        ("all_continuation_bits", vec![0xFF; 1000]),
+    ]
+}
+
+/// Write the edge-case corpus to `out_dir`, creating it if needed, and
+/// return the number of files written.
+///
+/// Existing files with the same names are overwritten.
+///
+/// # Errors
+///
+/// Returns the underlying `io::Error` if the directory or a file cannot be
+/// written.
+pub fn generate_fuzz_corpus(out_dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(out_dir)?;
+    let scenarios = scenarios();
+    for (name, bytes) in &scenarios {
+        fs::write(out_dir.join(name), bytes)?;
+    }
+    Ok(scenarios.len())
+}
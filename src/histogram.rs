@@ -0,0 +1,133 @@
+//! Gap frequency tables shared across many sets.
+//!
+//! A per-set Huffman code, as written by
+//! [`HuffmanCompressor::compress_set`](crate::HuffmanCompressor), costs an
+//! analysis pass and a code table in every set. When sets come from one
+//! distribution, e.g. the posting lists of an IVF index, a single
+//! [`GapHistogram`] learned once from a sample can stand in for all of them;
+//! see [`HuffmanCompressor::compress_with_histogram`](crate::HuffmanCompressor::compress_with_histogram).
+
+use crate::huffman::HuffmanCompressor;
+use crate::roc::RocCompressor;
+
+/// Gaps below this get their own symbol; larger gaps share [`ESCAPE`].
+pub(crate) const NUM_SMALL_GAPS: usize = 256;
+
+/// Symbol for gaps of [`NUM_SMALL_GAPS`] or more.
+pub(crate) const ESCAPE: u32 = NUM_SMALL_GAPS as u32;
+
+/// Frequency table over gap values `[0, 256)` plus a catch-all bucket.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{GapHistogram, HuffmanCompressor};
+///
+/// let sets: Vec<Vec<u32>> = (0..50).map(|i| (0..200).map(|j| i + j * 3).collect()).collect();
+/// let histogram = GapHistogram::learn_from_sets(&sets);
+/// assert_eq!(histogram.count(3), 50 * 199);
+///
+/// let compressor = HuffmanCompressor::new();
+/// let compressed = compressor.compress_with_histogram(&sets[7], 1000, &histogram).unwrap();
+/// let decoded = compressor.decompress_with_histogram(&compressed, 1000, &histogram).unwrap();
+/// assert_eq!(decoded, sets[7]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GapHistogram {
+    counts: Vec<u64>,
+}
+
+impl GapHistogram {
+    /// Create an empty histogram.
+    ///
+    /// An empty histogram gives every gap bucket the same weight.
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; NUM_SMALL_GAPS + 1],
+        }
+    }
+
+    /// Count the gaps of every set in `sets`.
+    ///
+    /// Sets that are not sorted and unique are skipped.
+    pub fn learn_from_sets(sets: &[Vec<u32>]) -> Self {
+        let mut histogram = Self::new();
+        for ids in sets {
+            histogram.observe(ids);
+        }
+        histogram
+    }
+
+    /// Count the gaps of one set. Ignored if `ids` is not sorted and unique.
+    pub fn observe(&mut self, ids: &[u32]) {
+        if RocCompressor::validate_ids(ids).is_err() {
+            return;
+        }
+        for w in ids.windows(2) {
+            self.counts[Self::symbol(w[1] - w[0]) as usize] += 1;
+        }
+    }
+
+    /// Number of gaps equal to `gap` seen, or of all gaps `>= 256` if
+    /// `gap >= 256`.
+    pub fn count(&self, gap: u32) -> u64 {
+        self.counts[Self::symbol(gap) as usize]
+    }
+
+    /// Total number of gaps seen.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Bucket of a gap value.
+    pub(crate) fn symbol(gap: u32) -> u32 {
+        gap.min(ESCAPE)
+    }
+
+    /// Canonical Huffman code lengths as `(symbol, length)`.
+    ///
+    /// Counts are smoothed by one, so gaps never seen in training stay
+    /// codable.
+    pub(crate) fn code_table(&self) -> Vec<(u32, u8)> {
+        let freqs: Vec<(u32, u64)> = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(symbol, &count)| (symbol as u32, count + 1))
+            .collect();
+        freqs
+            .iter()
+            .zip(HuffmanCompressor::code_lengths(&freqs))
+            .map(|(&(symbol, _), len)| (symbol, len))
+            .collect()
+    }
+}
+
+impl Default for GapHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_and_escape_bucket() {
+        let histogram = GapHistogram::learn_from_sets(&[vec![0, 1, 2, 1000, 5000], vec![3, 2]]);
+        assert_eq!(histogram.count(1), 2);
+        assert_eq!(histogram.count(256), 2);
+        assert_eq!(histogram.count(4000), 2);
+        assert_eq!(histogram.total(), 4);
+    }
+
+    #[test]
+    fn test_frequent_gaps_get_shorter_codes() {
+        let sets = vec![(0..1000).map(|i| i * 2).collect::<Vec<u32>>()];
+        let table = GapHistogram::learn_from_sets(&sets).code_table();
+        let len = |symbol: u32| table.iter().find(|&&(s, _)| s == symbol).unwrap().1;
+        assert!(len(2) < len(3));
+        assert!(len(2) < len(ESCAPE));
+    }
+}
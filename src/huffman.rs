@@ -0,0 +1,552 @@
+//! Static Huffman coding of delta values.
+//!
+//! Dense posting lists (e.g. a term appearing in most documents) have gaps
+//! clustered near 1. Varint spends a full byte on every such gap, while a
+//! Huffman code built from the observed gap distribution spends close to the
+//! empirical entropy, often a single bit.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [first_id: varint]
+//! [num_symbols: varint] [(symbol_delta: varint, code_len: u8) * num_symbols]
+//! [huffman-coded gaps, MSB-first, zero-padded]
+//! ```
+//!
+//! The code table is canonical: only code lengths are stored, and codes are
+//! reassigned in `(length, symbol)` order on both sides. Symbols are stored in
+//! ascending order as deltas from the previous symbol.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::CompressionError;
+use crate::histogram::{GapHistogram, ESCAPE};
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Longest code length accepted when decoding a header, so every code fits
+/// a `u64` with room to shift.
+const MAX_CODE_LEN: u8 = 63;
+
+/// Bit width of the smallest escaped gap, 256.
+const ESCAPE_MIN_WIDTH: u32 = 9;
+
+/// Bits storing an escaped gap's width minus [`ESCAPE_MIN_WIDTH`].
+const ESCAPE_WIDTH_BITS: u32 = 5;
+
+/// Huffman compressor for sets, coding gaps with a per-set static code.
+///
+/// Beats [`RocCompressor`] when the gap distribution is highly skewed. For
+/// sparse sets with mostly distinct gaps the code table dominates the output
+/// and plain varint is smaller.
+#[derive(Clone, Debug, Default)]
+pub struct HuffmanCompressor;
+
+impl HuffmanCompressor {
+    /// Create a new Huffman compressor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Compress `ids` with the code of a shared `histogram`.
+    ///
+    /// Skips the analysis pass and the per-set code table of
+    /// [`compress_set`](IdSetCompressor::compress_set); the decoder must
+    /// pass the same histogram to
+    /// [`decompress_with_histogram`](Self::decompress_with_histogram).
+    /// Gaps of 256 or more are written as an escape code, a 5-bit width and
+    /// the bits below the leading one.
+    ///
+    /// Format: `[len: varint] [first_id: varint] [huffman-coded gaps]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the IDs are not sorted and
+    /// unique or exceed the universe.
+    pub fn compress_with_histogram(
+        &self,
+        ids: &[u32],
+        universe_size: u32,
+        histogram: &GapHistogram,
+    ) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        encode_varint(first as u64, &mut encoded);
+
+        let codes: Vec<(u8, u64)> = {
+            let mut codes = vec![(0, 0); ESCAPE as usize + 1];
+            for (symbol, len, code) in Self::canonical_codes(&histogram.code_table()) {
+                codes[symbol as usize] = (len, code);
+            }
+            codes
+        };
+        let mut writer = BitWriter::new();
+        for w in ids.windows(2) {
+            let gap = w[1] - w[0];
+            let (len, code) = codes[GapHistogram::symbol(gap) as usize];
+            writer.write_bits(code, len as u32);
+            if gap >= ESCAPE {
+                let width = 32 - gap.leading_zeros();
+                writer.write_bits((width - ESCAPE_MIN_WIDTH) as u64, ESCAPE_WIDTH_BITS);
+                writer.write_bits(gap as u64, width - 1);
+            }
+        }
+        encoded.extend(writer.finish());
+        Ok(encoded)
+    }
+
+    /// Decompress a set written by
+    /// [`compress_with_histogram`](Self::compress_with_histogram).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` if the data is
+    /// truncated, has trailing bytes or decodes outside the universe. A
+    /// different histogram than the one used to compress usually fails
+    /// this way, but may also decode to wrong IDs.
+    pub fn decompress_with_histogram(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+        histogram: &GapHistogram,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, mut offset) = decode_varint(compressed)?;
+        if num_ids == 0 || num_ids > universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for universe size {}",
+                num_ids, universe_size
+            )));
+        }
+        let (first_id, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+
+        let decoder = DecodeTable::new(&histogram.code_table());
+        let mut reader = BitReader::new(&compressed[offset..]);
+        let mut ids = Vec::with_capacity(num_ids as usize);
+        let mut prev = first_id;
+        for i in 0..num_ids {
+            if i > 0 {
+                let mut gap = decoder.decode(&mut reader)? as u64;
+                if gap == ESCAPE as u64 {
+                    let width = reader.read_bits(ESCAPE_WIDTH_BITS)? as u32 + ESCAPE_MIN_WIDTH;
+                    if width > 32 {
+                        return Err(CompressionError::DecompressionFailed(format!(
+                            "Escaped gap width {} exceeds 32 bits",
+                            width
+                        )));
+                    }
+                    gap = (1 << (width - 1)) | reader.read_bits(width - 1)?;
+                }
+                if gap == 0 {
+                    return Err(CompressionError::DecompressionFailed(
+                        "Zero delta produces duplicate ID".to_string(),
+                    ));
+                }
+                prev += gap;
+            }
+            if prev >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev as u32);
+        }
+        if reader.unread_bytes() > 0 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                reader.unread_bytes()
+            )));
+        }
+        Ok(ids)
+    }
+
+    /// Compute optimal code lengths for `(symbol, frequency)` pairs.
+    ///
+    /// Returns lengths in the same order as the input.
+    pub(crate) fn code_lengths(freqs: &[(u32, u64)]) -> Vec<u8> {
+        let n = freqs.len();
+        if n == 1 {
+            return vec![1];
+        }
+
+        // Nodes 0..n are leaves; internal nodes are appended as they are merged.
+        let mut parent = vec![usize::MAX; 2 * n - 1];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = freqs
+            .iter()
+            .enumerate()
+            .map(|(i, &(_, freq))| Reverse((freq, i)))
+            .collect();
+
+        let mut next = n;
+        while let (Some(Reverse((fa, a))), Some(Reverse((fb, b)))) = (heap.pop(), heap.pop()) {
+            parent[a] = next;
+            parent[b] = next;
+            heap.push(Reverse((fa + fb, next)));
+            next += 1;
+        }
+
+        (0..n)
+            .map(|leaf| {
+                let mut depth = 0u8;
+                let mut node = leaf;
+                while parent[node] != usize::MAX {
+                    node = parent[node];
+                    depth += 1;
+                }
+                depth
+            })
+            .collect()
+    }
+
+    /// Assign canonical codes to `(symbol, length)` pairs.
+    ///
+    /// Returns `(symbol, length, code)` sorted by `(length, symbol)`.
+    pub(crate) fn canonical_codes(table: &[(u32, u8)]) -> Vec<(u32, u8, u64)> {
+        let mut sorted: Vec<(u32, u8)> = table.to_vec();
+        sorted.sort_by_key(|&(symbol, len)| (len, symbol));
+
+        let mut codes = Vec::with_capacity(sorted.len());
+        let mut code = 0u64;
+        let mut prev_len = 0u8;
+        for (symbol, len) in sorted {
+            code <<= len - prev_len;
+            codes.push((symbol, len, code));
+            code += 1;
+            prev_len = len;
+        }
+        codes
+    }
+}
+
+/// Canonical Huffman decoding table.
+pub(crate) struct DecodeTable {
+    /// Number of codes of each length, indexed by length.
+    counts: Vec<u64>,
+    /// Symbols sorted by `(length, symbol)`.
+    symbols: Vec<u32>,
+}
+
+impl DecodeTable {
+    pub(crate) fn new(table: &[(u32, u8)]) -> Self {
+        let max_len = table.iter().map(|&(_, len)| len).max().unwrap_or(0) as usize;
+        let mut counts = vec![0u64; max_len + 1];
+        for &(_, len) in table {
+            counts[len as usize] += 1;
+        }
+        let symbols = HuffmanCompressor::canonical_codes(table)
+            .into_iter()
+            .map(|(symbol, _, _)| symbol)
+            .collect();
+        Self { counts, symbols }
+    }
+
+    pub(crate) fn decode(&self, reader: &mut BitReader<'_>) -> Result<u32, CompressionError> {
+        let mut code = 0u64;
+        let mut first = 0u64;
+        let mut index = 0usize;
+        for &count in self.counts.iter().skip(1) {
+            code |= reader.read_bit()? as u64;
+            if code < first + count {
+                return Ok(self.symbols[index + (code - first) as usize]);
+            }
+            index += count as usize;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(CompressionError::DecompressionFailed(
+            "Invalid Huffman code".to_string(),
+        ))
+    }
+}
+
+impl IdSetCompressor for HuffmanCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        encode_varint(first as u64, &mut encoded);
+        if ids.len() == 1 {
+            return Ok(encoded);
+        }
+
+        // First pass: gap frequencies
+        let mut freq_map: BTreeMap<u32, u64> = BTreeMap::new();
+        for w in ids.windows(2) {
+            *freq_map.entry(w[1] - w[0]).or_insert(0) += 1;
+        }
+        let freqs: Vec<(u32, u64)> = freq_map.into_iter().collect();
+        let lengths = Self::code_lengths(&freqs);
+
+        // Header: canonical code lengths in ascending symbol order
+        encode_varint(freqs.len() as u64, &mut encoded);
+        let mut prev_symbol = 0u32;
+        let mut table = Vec::with_capacity(freqs.len());
+        for (&(symbol, _), &len) in freqs.iter().zip(&lengths) {
+            encode_varint((symbol - prev_symbol) as u64, &mut encoded);
+            encoded.push(len);
+            prev_symbol = symbol;
+            table.push((symbol, len));
+        }
+
+        // Second pass: encode gaps
+        let code_map: BTreeMap<u32, (u8, u64)> = Self::canonical_codes(&table)
+            .into_iter()
+            .map(|(symbol, len, code)| (symbol, (len, code)))
+            .collect();
+        let mut writer = BitWriter::new();
+        for w in ids.windows(2) {
+            let (len, code) = code_map[&(w[1] - w[0])];
+            writer.write_bits(code, len as u32);
+        }
+        encoded.extend(writer.finish());
+
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, mut offset) = decode_varint(compressed)?;
+        if num_ids == 0 {
+            return Ok(Vec::new());
+        }
+        let (first_id, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+        if first_id >= universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                first_id, universe_size
+            )));
+        }
+
+        let mut ids = vec![first_id as u32];
+        if num_ids > 1 {
+            let (num_symbols, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            if num_symbols == 0 {
+                return Err(CompressionError::DecompressionFailed(
+                    "Empty Huffman table".to_string(),
+                ));
+            }
+
+            let mut table = Vec::new();
+            let mut symbol = 0u64;
+            for _ in 0..num_symbols {
+                let (delta, consumed) = decode_varint(&compressed[offset..])?;
+                offset += consumed;
+                symbol += delta;
+                let len = *compressed.get(offset).ok_or_else(|| {
+                    CompressionError::DecompressionFailed(
+                        "Unexpected end of Huffman table".to_string(),
+                    )
+                })?;
+                offset += 1;
+                if symbol == 0 || symbol > u32::MAX as u64 || len == 0 || len > MAX_CODE_LEN {
+                    return Err(CompressionError::DecompressionFailed(format!(
+                        "Invalid Huffman table entry: symbol {} length {}",
+                        symbol, len
+                    )));
+                }
+                table.push((symbol as u32, len));
+            }
+            // Kraft inequality: sum of 2^-len over all codes must not exceed 1
+            let kraft: u128 = table
+                .iter()
+                .map(|&(_, len)| 1u128 << (MAX_CODE_LEN - len))
+                .sum();
+            if kraft > 1u128 << MAX_CODE_LEN {
+                return Err(CompressionError::DecompressionFailed(
+                    "Huffman code lengths violate the Kraft inequality".to_string(),
+                ));
+            }
+
+            let decoder = DecodeTable::new(&table);
+            let mut reader = BitReader::new(&compressed[offset..]);
+            let mut prev = first_id;
+            for _ in 1..num_ids {
+                let next = prev + decoder.decode(&mut reader)? as u64;
+                if next >= universe_size as u64 {
+                    return Err(CompressionError::DecompressionFailed(format!(
+                        "ID {} exceeds universe size {}",
+                        next, universe_size
+                    )));
+                }
+                ids.push(next as u32);
+                prev = next;
+            }
+            if reader.unread_bytes() > 0 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Extra data after decompression: {} bytes",
+                    reader.unread_bytes()
+                )));
+            }
+        } else if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Entropy bound plus a small code table
+        let bits = RocCompressor::theoretical_bits(num_ids, universe_size);
+        (bits / 8.0).ceil() as usize + 16
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/huffman")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let compressor = HuffmanCompressor::new();
+        let ids = vec![1u32, 2, 3, 4, 6, 7, 8, 20, 21, 22, 500];
+
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        let decompressed = compressor.decompress_set(&compressed, 1000).unwrap();
+
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_single_and_empty() {
+        let compressor = HuffmanCompressor::new();
+        assert!(compressor.compress_set(&[], 1000).unwrap().is_empty());
+
+        let compressed = compressor.compress_set(&[42], 1000).unwrap();
+        assert_eq!(compressor.decompress_set(&compressed, 1000).unwrap(), [42]);
+    }
+
+    #[test]
+    fn test_single_gap_symbol_uses_one_bit() {
+        let compressor = HuffmanCompressor::new();
+        let ids: Vec<u32> = (0..801).collect();
+
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        // len (2) + first (1) + table (1 + 2) + 800 bits
+        assert_eq!(compressed.len(), 2 + 1 + 3 + 100);
+        assert_eq!(compressor.decompress_set(&compressed, 1000).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_code_lengths_are_prefix_free() {
+        let freqs = [(1u32, 50u64), (2, 20), (3, 15), (4, 10), (5, 5)];
+        let lengths = HuffmanCompressor::code_lengths(&freqs);
+        let kraft: f64 = lengths.iter().map(|&l| 0.5f64.powi(l as i32)).sum();
+        assert!((kraft - 1.0).abs() < 1e-12);
+        assert_eq!(lengths[0], 1);
+    }
+
+    #[test]
+    fn test_unsorted_ids() {
+        let compressor = HuffmanCompressor::new();
+        assert!(compressor.compress_set(&[5, 1, 10], 1000).is_err());
+    }
+
+    #[test]
+    fn test_shared_histogram_omits_table() {
+        let compressor = HuffmanCompressor::new();
+        let sets: Vec<Vec<u32>> = (0..20)
+            .map(|i| (0..100).map(|j| i + j * 5 + (j % 3)).collect())
+            .collect();
+        let histogram = GapHistogram::learn_from_sets(&sets);
+
+        let shared = compressor
+            .compress_with_histogram(&sets[3], 1000, &histogram)
+            .unwrap();
+        let per_set = compressor.compress_set(&sets[3], 1000).unwrap();
+        assert!(shared.len() < per_set.len());
+        assert_eq!(
+            compressor
+                .decompress_with_histogram(&shared, 1000, &histogram)
+                .unwrap(),
+            sets[3]
+        );
+    }
+
+    #[test]
+    fn test_histogram_escapes_large_gaps() {
+        let compressor = HuffmanCompressor::new();
+        let histogram = GapHistogram::new();
+        let ids = vec![0u32, 1, 255, 256, 512, 100_000, u32::MAX - 1];
+
+        let compressed = compressor
+            .compress_with_histogram(&ids, u32::MAX, &histogram)
+            .unwrap();
+        assert_eq!(
+            compressor
+                .decompress_with_histogram(&compressed, u32::MAX, &histogram)
+                .unwrap(),
+            ids
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_code_lengths() {
+        let compressor = HuffmanCompressor::new();
+        // One symbol with a 64-bit code
+        let too_long = [3, 0, 1, 1, 64, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(compressor.decompress_set(&too_long, 1000).is_err());
+        // Three 1-bit codes
+        let over_full = [3, 0, 3, 1, 1, 1, 1, 1, 1, 0];
+        assert!(compressor.decompress_set(&over_full, 1000).is_err());
+    }
+}
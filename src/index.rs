@@ -0,0 +1,361 @@
+//! Inverted index of compressed posting lists.
+//!
+//! [`CompressedIndex`] maps term IDs to sets compressed by one codec, in
+//! term order. LSM-style engines write such indexes as immutable segments
+//! and periodically combine them; [`merge_compressed_indices`] does so in a
+//! single merge-join over the two term lists.
+//!
+//! The lists live back to back in one buffer. Query traffic is usually
+//! Zipfian, so [`CompressedIndex::repack_by_frequency`] moves the hottest
+//! lists to the front of it, where a few megabytes of last-level cache can
+//! hold every list that most queries touch.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use crate::error::CompressionError;
+use crate::merge::sorted_merge_compress;
+use crate::traits::IdSetCompressor;
+
+/// Posting lists keyed by term ID, each compressed with `C`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedIndex, RocCompressor};
+///
+/// let mut index = CompressedIndex::new(RocCompressor::new(), 1000);
+/// index.insert(7, &[3, 40, 512]).unwrap();
+///
+/// assert_eq!(index.get(7).unwrap(), Some(vec![3, 40, 512]));
+/// assert_eq!(index.get(8).unwrap(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompressedIndex<C> {
+    compressor: C,
+    universe: u32,
+    /// Compressed lists back to back. Replaced lists stay behind as dead
+    /// bytes until the next repack.
+    buffer: Vec<u8>,
+    /// Byte range of each term's list in `buffer`.
+    offsets: BTreeMap<u32, Range<usize>>,
+}
+
+impl<C: IdSetCompressor> CompressedIndex<C> {
+    /// Create an empty index of lists from `[0, universe)`.
+    pub fn new(compressor: C, universe: u32) -> Self {
+        Self {
+            compressor,
+            universe,
+            buffer: Vec::new(),
+            offsets: BTreeMap::new(),
+        }
+    }
+
+    /// Compress `ids` as the posting list of `term`, replacing any previous list.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the compressor; the index is left unchanged.
+    pub fn insert(&mut self, term: u32, ids: &[u32]) -> Result<(), CompressionError> {
+        let compressed = self.compressor.compress_set(ids, self.universe)?;
+        self.push(term, &compressed);
+        Ok(())
+    }
+
+    /// Append `bytes` as the list of `term`.
+    fn push(&mut self, term: u32, bytes: &[u8]) {
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+        self.offsets.insert(term, start..self.buffer.len());
+    }
+
+    /// Decompress the posting list of `term`, or `None` if it has none.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the stored list cannot be decoded.
+    pub fn get(&self, term: u32) -> Result<Option<Vec<u32>>, CompressionError> {
+        self.get_compressed(term)
+            .map(|bytes| self.compressor.decompress_set(bytes, self.universe))
+            .transpose()
+    }
+
+    /// The compressed posting list of `term`.
+    pub fn get_compressed(&self, term: u32) -> Option<&[u8]> {
+        self.offsets
+            .get(&term)
+            .map(|range| &self.buffer[range.clone()])
+    }
+
+    /// Byte offset of the list of `term` in the backing buffer.
+    pub fn offset_of(&self, term: u32) -> Option<usize> {
+        self.offsets.get(&term).map(|range| range.start)
+    }
+
+    /// Term IDs with a posting list, in ascending order.
+    pub fn terms(&self) -> impl Iterator<Item = u32> + '_ {
+        self.offsets.keys().copied()
+    }
+
+    /// Number of terms.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the index has no terms.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Universe of the posting lists.
+    pub fn universe(&self) -> u32 {
+        self.universe
+    }
+
+    /// The compressor used for every list.
+    pub fn compressor(&self) -> &C {
+        &self.compressor
+    }
+
+    /// Rewrite the backing buffer with the most queried lists first.
+    ///
+    /// Terms are laid out by descending frequency in `term_frequencies`,
+    /// ties broken by term ID; terms without a frequency follow in term
+    /// order. Frequencies of terms not in the index are ignored. Dead bytes
+    /// left by replaced lists are dropped. With Zipfian queries, the lists
+    /// behind most lookups then share the first few megabytes of the buffer
+    /// and stay in cache.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::{CompressedIndex, RocCompressor};
+    ///
+    /// let mut index = CompressedIndex::new(RocCompressor::new(), 1000);
+    /// for term in 0..100 {
+    ///     index.insert(term, &[term, term + 500]).unwrap();
+    /// }
+    /// index.repack_by_frequency(&[(42, 9000), (7, 100)]);
+    ///
+    /// assert_eq!(index.offset_of(42), Some(0));
+    /// assert!(index.offset_of(7) < index.offset_of(0));
+    /// assert_eq!(index.get(42).unwrap(), Some(vec![42, 542]));
+    /// ```
+    pub fn repack_by_frequency(&mut self, term_frequencies: &[(u32, u64)]) {
+        let mut hot: Vec<(u32, u64)> = term_frequencies
+            .iter()
+            .copied()
+            .filter(|(term, _)| self.offsets.contains_key(term))
+            .collect();
+        hot.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.dedup_by_key(|&mut (term, _)| term);
+
+        let mut order: Vec<u32> = hot.iter().map(|&(term, _)| term).collect();
+        let placed: BTreeSet<u32> = order.iter().copied().collect();
+        order.extend(self.offsets.keys().copied().filter(|t| !placed.contains(t)));
+
+        let live: usize = self.offsets.values().map(|range| range.len()).sum();
+        let mut buffer = Vec::with_capacity(live);
+        for term in order {
+            let range = self.offsets[&term].clone();
+            let start = buffer.len();
+            buffer.extend_from_slice(&self.buffer[range]);
+            self.offsets.insert(term, start..buffer.len());
+        }
+        self.buffer = buffer;
+    }
+}
+
+/// Move a list written by `from` over `from_universe` to `to` over `to_universe`.
+///
+/// The bytes are reused when the format and universe are unchanged.
+fn carry<C: IdSetCompressor>(
+    bytes: &[u8],
+    from: &C,
+    from_universe: u32,
+    to: &C,
+    to_universe: u32,
+) -> Result<Vec<u8>, CompressionError> {
+    if from_universe == to_universe
+        && from.format_id().is_some()
+        && from.format_id() == to.format_id()
+    {
+        return Ok(bytes.to_vec());
+    }
+    let ids = from.decompress_set(bytes, from_universe)?;
+    to.compress_set(&ids, to_universe)
+}
+
+/// Merge two index segments into one over `universe`.
+///
+/// Walks both term lists in ascending order: a term in both indexes gets the
+/// union of its two lists, and a term in one is carried over, without
+/// re-encoding when `universe` matches its segment. The result uses `a`'s
+/// compressor.
+///
+/// # Errors
+///
+/// Returns `CompressionError` if a list cannot be decoded, or
+/// `CompressionError::InvalidInput` if an ID does not fit in `universe`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{merge_compressed_indices, CompressedIndex, RocCompressor};
+///
+/// let mut a = CompressedIndex::new(RocCompressor::new(), 100);
+/// a.insert(1, &[2, 4]).unwrap();
+/// a.insert(5, &[9]).unwrap();
+/// let mut b = CompressedIndex::new(RocCompressor::new(), 100);
+/// b.insert(1, &[3, 4]).unwrap();
+/// b.insert(3, &[50]).unwrap();
+///
+/// let merged = merge_compressed_indices(a, b, 100).unwrap();
+/// assert_eq!(merged.terms().collect::<Vec<_>>(), [1, 3, 5]);
+/// assert_eq!(merged.get(1).unwrap(), Some(vec![2, 3, 4]));
+/// ```
+pub fn merge_compressed_indices<C: IdSetCompressor>(
+    a: CompressedIndex<C>,
+    b: CompressedIndex<C>,
+    universe: u32,
+) -> Result<CompressedIndex<C>, CompressionError> {
+    let mut buffer = Vec::new();
+    let mut offsets = BTreeMap::new();
+    let mut a_terms = a.offsets.iter().peekable();
+    let mut b_terms = b.offsets.iter().peekable();
+
+    loop {
+        let (term, bytes) = match (a_terms.peek(), b_terms.peek()) {
+            (None, None) => break,
+            (Some(&(ta, _)), Some(&(tb, _))) if ta == tb => {
+                let (&term, a_range) = a_terms.next().unwrap();
+                let (_, b_range) = b_terms.next().unwrap();
+                let a_ids = a
+                    .compressor
+                    .decompress_set(&a.buffer[a_range.clone()], a.universe)?;
+                let b_ids = b
+                    .compressor
+                    .decompress_set(&b.buffer[b_range.clone()], b.universe)?;
+                let union = sorted_merge_compress(&a.compressor, &[&a_ids, &b_ids], universe)?;
+                (term, union)
+            }
+            (Some(&(ta, _)), Some(&(tb, _))) if tb < ta => {
+                let (&term, range) = b_terms.next().unwrap();
+                let bytes = &b.buffer[range.clone()];
+                let bytes = carry(bytes, &b.compressor, b.universe, &a.compressor, universe)?;
+                (term, bytes)
+            }
+            (Some(_), _) => {
+                let (&term, range) = a_terms.next().unwrap();
+                let bytes = &a.buffer[range.clone()];
+                let bytes = carry(bytes, &a.compressor, a.universe, &a.compressor, universe)?;
+                (term, bytes)
+            }
+            (None, Some(_)) => {
+                let (&term, range) = b_terms.next().unwrap();
+                let bytes = &b.buffer[range.clone()];
+                let bytes = carry(bytes, &b.compressor, b.universe, &a.compressor, universe)?;
+                (term, bytes)
+            }
+        };
+        let start = buffer.len();
+        buffer.extend_from_slice(&bytes);
+        offsets.insert(term, start..buffer.len());
+    }
+
+    Ok(CompressedIndex {
+        compressor: a.compressor,
+        universe,
+        buffer,
+        offsets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EliasFanoCompressor, RocCompressor};
+
+    #[test]
+    fn test_merge_unions_shared_terms() {
+        let mut a = CompressedIndex::new(RocCompressor::new(), 100);
+        a.insert(2, &[1, 5, 9]).unwrap();
+        a.insert(4, &[]).unwrap();
+        let mut b = CompressedIndex::new(RocCompressor::new(), 100);
+        b.insert(2, &[5, 6]).unwrap();
+        b.insert(3, &[0]).unwrap();
+        b.insert(9, &[99]).unwrap();
+
+        let merged = merge_compressed_indices(a, b, 100).unwrap();
+        assert_eq!(merged.terms().collect::<Vec<_>>(), [2, 3, 4, 9]);
+        assert_eq!(merged.get(2).unwrap(), Some(vec![1, 5, 6, 9]));
+        assert_eq!(merged.get(4).unwrap(), Some(vec![]));
+        assert_eq!(merged.get(9).unwrap(), Some(vec![99]));
+    }
+
+    #[test]
+    fn test_merge_into_wider_universe() {
+        let mut a = CompressedIndex::new(EliasFanoCompressor::new(), 10);
+        a.insert(0, &[3, 9]).unwrap();
+        let mut b = CompressedIndex::new(EliasFanoCompressor::new(), 1000);
+        b.insert(1, &[999]).unwrap();
+
+        let merged = merge_compressed_indices(a, b, 1000).unwrap();
+        assert_eq!(merged.universe(), 1000);
+        assert_eq!(merged.get(0).unwrap(), Some(vec![3, 9]));
+        assert_eq!(merged.get(1).unwrap(), Some(vec![999]));
+
+        // IDs of a wider segment must fit the merged universe
+        let mut a = CompressedIndex::new(RocCompressor::new(), 10);
+        a.insert(0, &[3]).unwrap();
+        let mut b = CompressedIndex::new(RocCompressor::new(), 1000);
+        b.insert(0, &[999]).unwrap();
+        assert!(merge_compressed_indices(a, b, 10).is_err());
+    }
+
+    #[test]
+    fn test_hot_lists_fit_in_first_4mb() {
+        const LLC_BYTES: usize = 4 << 20;
+
+        // About 16 MB of lists, more than a last-level cache
+        let universe = 1 << 24;
+        let ids: Vec<u32> = (0..1 << 12).map(|i| i * 1000).collect();
+        let mut index = CompressedIndex::new(RocCompressor::new(), universe);
+        for term in 0..2000 {
+            index.insert(term, &ids).unwrap();
+        }
+        let list_len = index.get_compressed(0).unwrap().len();
+
+        // Zipfian: term t is queried about 1/(t+1) as often, hottest at the back
+        let frequencies: Vec<(u32, u64)> = (0..2000)
+            .map(|term| (1999 - term, 1_000_000 / (term as u64 + 1)))
+            .collect();
+        let hot: Vec<u32> = (0..LLC_BYTES / list_len).map(|t| 1999 - t as u32).collect();
+        assert!(hot
+            .iter()
+            .all(|&t| index.offset_of(t).unwrap() >= LLC_BYTES));
+
+        index.repack_by_frequency(&frequencies);
+        for &term in &hot {
+            assert!(index.offset_of(term).unwrap() + list_len <= LLC_BYTES);
+        }
+        assert_eq!(index.get(1999).unwrap().unwrap(), ids);
+    }
+
+    #[test]
+    fn test_repack_drops_replaced_lists() {
+        let mut index = CompressedIndex::new(RocCompressor::new(), 100);
+        index.insert(1, &[1, 2, 3]).unwrap();
+        index.insert(2, &[50]).unwrap();
+        index.insert(1, &[4]).unwrap();
+        assert_eq!(index.offset_of(1), Some(8));
+
+        // Unknown terms and duplicates are ignored
+        index.repack_by_frequency(&[(2, 5), (9, 100), (2, 1)]);
+        assert_eq!(index.offset_of(2), Some(0));
+        assert_eq!(index.offset_of(1), Some(3));
+        assert_eq!(index.get(1).unwrap(), Some(vec![4]));
+        assert_eq!(index.get(2).unwrap(), Some(vec![50]));
+    }
+}
@@ -0,0 +1,258 @@
+//! Byte-level layout of [`RocCompressor`](crate::RocCompressor) sets.
+//!
+//! When a codec change produces the wrong IDs, the first question is which
+//! bytes decoded to what. [`CompressedSetInspector::inspect`] walks a
+//! compressed set and labels every byte with the value it encodes, without
+//! failing: whatever cannot be decoded is labelled
+//! [`SpanMeaning::Truncated`].
+
+use crate::roc::CompressionLevel;
+use crate::varint::decode_varint;
+
+/// What a run of bytes in a compressed set encodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpanMeaning {
+    /// The [`CompressionLevel`] tag.
+    LevelTag(u8),
+    /// The number of IDs in the set.
+    CountHeader(usize),
+    /// The first ID, stored as is.
+    FirstId(u32),
+    /// The gap from the previous ID to the ID at `position`.
+    Delta {
+        /// Index of the ID this gap leads to.
+        position: usize,
+        /// The gap.
+        value: u32,
+    },
+    /// Bytes after the last delta.
+    Padding,
+    /// Bytes that could not be decoded: a cut-off value, an unknown level
+    /// tag, or everything after an ID outside the universe.
+    Truncated,
+}
+
+/// A labelled run of bytes, `start_byte..end_byte`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByteSpan {
+    /// First byte of the span.
+    pub start_byte: usize,
+    /// One past the last byte of the span.
+    pub end_byte: usize,
+    /// What the bytes encode.
+    pub meaning: SpanMeaning,
+}
+
+/// Append a span of `len` bytes at `*offset` and move past it.
+fn push_span(spans: &mut Vec<ByteSpan>, offset: &mut usize, len: usize, meaning: SpanMeaning) {
+    spans.push(ByteSpan {
+        start_byte: *offset,
+        end_byte: *offset + len,
+        meaning,
+    });
+    *offset += len;
+}
+
+/// Debugging view of the `u32` [`RocCompressor`](crate::RocCompressor) format.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedSetInspector, IdSetCompressor, RocCompressor, SpanMeaning};
+///
+/// let compressed = RocCompressor::new().compress_set(&[5u32, 300], 1000).unwrap();
+/// let spans = CompressedSetInspector::inspect(&compressed, 1000);
+///
+/// let meanings: Vec<_> = spans.iter().map(|s| s.meaning.clone()).collect();
+/// assert_eq!(
+///     meanings,
+///     [
+///         SpanMeaning::LevelTag(5),
+///         SpanMeaning::CountHeader(2),
+///         SpanMeaning::FirstId(5),
+///         SpanMeaning::Delta { position: 1, value: 295 },
+///     ]
+/// );
+/// // The gap of 295 takes two varint bytes
+/// assert_eq!((spans[3].start_byte, spans[3].end_byte), (3, 5));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CompressedSetInspector;
+
+impl CompressedSetInspector {
+    /// Label every byte of `compressed`, in order.
+    ///
+    /// The spans are contiguous and cover the whole buffer. Never panics,
+    /// whatever the input.
+    pub fn inspect(compressed: &[u8], universe: u32) -> Vec<ByteSpan> {
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        let tag = match compressed.first() {
+            Some(&tag) => tag,
+            None => return spans,
+        };
+        push_span(&mut spans, &mut offset, 1, SpanMeaning::LevelTag(tag));
+
+        let count = if tag == CompressionLevel::Fastest as u8 {
+            match compressed.get(1..5) {
+                Some(len) => {
+                    let count = u32::from_le_bytes([len[0], len[1], len[2], len[3]]);
+                    push_span(
+                        &mut spans,
+                        &mut offset,
+                        4,
+                        SpanMeaning::CountHeader(count as usize),
+                    );
+                    Some(count as u64)
+                }
+                None => None,
+            }
+        } else if tag == CompressionLevel::Default as u8 {
+            match decode_varint(&compressed[1..]) {
+                Ok((count, consumed)) => {
+                    push_span(
+                        &mut spans,
+                        &mut offset,
+                        consumed,
+                        SpanMeaning::CountHeader(count as usize),
+                    );
+                    Some(count)
+                }
+                Err(_) => None,
+            }
+        } else {
+            None
+        };
+
+        if let Some(count) = count {
+            let mut prev = 0u64;
+            let mut position = 0u64;
+            while position < count {
+                let (value, consumed) = match decode_varint(&compressed[offset..]) {
+                    Ok(decoded) => decoded,
+                    Err(_) => break,
+                };
+                let id = if position == 0 {
+                    value
+                } else {
+                    prev.saturating_add(value)
+                };
+                if id >= universe as u64 || value > u32::MAX as u64 {
+                    break;
+                }
+                let meaning = if position == 0 {
+                    SpanMeaning::FirstId(value as u32)
+                } else {
+                    SpanMeaning::Delta {
+                        position: position as usize,
+                        value: value as u32,
+                    }
+                };
+                push_span(&mut spans, &mut offset, consumed, meaning);
+                prev = id;
+                position += 1;
+            }
+
+            if position == count && offset < compressed.len() {
+                let rest = compressed.len() - offset;
+                push_span(&mut spans, &mut offset, rest, SpanMeaning::Padding);
+            }
+        }
+
+        if offset < compressed.len() {
+            let rest = compressed.len() - offset;
+            push_span(&mut spans, &mut offset, rest, SpanMeaning::Truncated);
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdSetCompressor, RocCompressor};
+
+    /// Spans must tile `0..len` with no gap or overlap.
+    fn assert_covers(spans: &[ByteSpan], len: usize) {
+        let mut end = 0;
+        for span in spans {
+            assert_eq!(span.start_byte, end, "{:?}", spans);
+            assert!(span.end_byte > span.start_byte, "{:?}", spans);
+            end = span.end_byte;
+        }
+        assert_eq!(end, len, "{:?}", spans);
+    }
+
+    #[test]
+    fn test_spans_cover_buffer_and_accumulate_to_ids() {
+        let ids = [0u32, 1, 127, 128, 20_000, 3_000_000];
+        for level in [CompressionLevel::Default, CompressionLevel::Fastest] {
+            let compressed = RocCompressor::with_level(level)
+                .compress_set(&ids, 4_000_000)
+                .unwrap();
+            let spans = CompressedSetInspector::inspect(&compressed, 4_000_000);
+            assert_covers(&spans, compressed.len());
+
+            let mut decoded = Vec::new();
+            for span in &spans {
+                match span.meaning {
+                    SpanMeaning::CountHeader(n) => assert_eq!(n, ids.len()),
+                    SpanMeaning::FirstId(id) => decoded.push(id),
+                    SpanMeaning::Delta { position, value } => {
+                        assert_eq!(position, decoded.len());
+                        decoded.push(decoded[position - 1] + value);
+                    }
+                    SpanMeaning::LevelTag(_) => {}
+                    _ => panic!("unexpected span {:?}", span),
+                }
+            }
+            assert_eq!(decoded, ids);
+        }
+    }
+
+    #[test]
+    fn test_malformed_input_is_labelled() {
+        // Unknown level tag
+        let spans = CompressedSetInspector::inspect(&[7, 1, 2], 100);
+        assert_covers(&spans, 3);
+        assert_eq!(spans[1].meaning, SpanMeaning::Truncated);
+
+        // Count of 3 but one ID, which is cut off mid-varint
+        let spans = CompressedSetInspector::inspect(&[5, 3, 0x80], 100);
+        assert_covers(&spans, 3);
+        assert_eq!(spans[2].meaning, SpanMeaning::Truncated);
+
+        // Trailing byte after the last delta
+        let spans = CompressedSetInspector::inspect(&[5, 1, 4, 9], 100);
+        assert_covers(&spans, 4);
+        assert_eq!(spans[3].meaning, SpanMeaning::Padding);
+
+        // Second ID outside the universe
+        let spans = CompressedSetInspector::inspect(&[5, 2, 4, 99], 100);
+        assert_covers(&spans, 4);
+        assert_eq!(spans[3].meaning, SpanMeaning::Truncated);
+
+        assert!(CompressedSetInspector::inspect(&[], 100).is_empty());
+    }
+
+    #[test]
+    fn test_never_panics() {
+        let mut state = 0x2545_f491_u32;
+        for len in 0..64 {
+            let bytes: Vec<u8> = (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect();
+            for tag in [1u8, 5] {
+                let mut input = bytes.clone();
+                input.insert(0, tag);
+                let spans = CompressedSetInspector::inspect(&input, 1000);
+                assert_covers(&spans, input.len());
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Streaming compression through `std::io::Write` and `std::io::Read`.
+//!
+//! [`CompressorWriter`] takes raw little-endian `u32` IDs as bytes, e.g. from
+//! [`std::io::copy`], and writes them out as a sequence of independently
+//! compressed batches. [`DecompressorReader`] reads such a stream back and
+//! yields the raw IDs again.
+//!
+//! IDs must be sorted and unique within each batch, and below the universe
+//! size given to both ends.
+//!
+//! # Format
+//!
+//! ```text
+//! [(frame_len: varint, compressed batch: frame_len bytes) * num_batches]
+//! ```
+
+use std::io::{self, Read, Write};
+
+use crate::traits::IdSetCompressor;
+use crate::varint::encode_varint;
+
+/// Batch size used by [`CompressorWriter::new`].
+pub const DEFAULT_BATCH_SIZE: usize = 4096;
+
+/// `io::Write` adapter that compresses raw `u32` IDs in batches.
+///
+/// Bytes written are read as little-endian `u32` IDs. Every `batch_size`
+/// IDs are compressed with [`compress_into`](IdSetCompressor::compress_into)
+/// and written to the inner writer as one frame. Call
+/// [`finish`](Self::finish) to write the last, partial batch; dropping the
+/// writer discards it.
+///
+/// `flush` also ends the current batch early, so a stream flushed often
+/// compresses worse.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::{self, Read};
+/// use cnk::{CompressorWriter, DecompressorReader, RocCompressor};
+///
+/// let raw: Vec<u8> = (0..1000u32).flat_map(|id| (id * 3).to_le_bytes()).collect();
+///
+/// let mut writer = CompressorWriter::new(RocCompressor::new(), Vec::new(), 3000);
+/// io::copy(&mut raw.as_slice(), &mut writer).unwrap();
+/// let compressed = writer.finish().unwrap();
+/// assert!(compressed.len() < raw.len());
+///
+/// let mut reader = DecompressorReader::new(RocCompressor::new(), compressed.as_slice(), 3000);
+/// let mut decoded = Vec::new();
+/// reader.read_to_end(&mut decoded).unwrap();
+/// assert_eq!(decoded, raw);
+/// ```
+pub struct CompressorWriter<C, W: Write> {
+    compressor: C,
+    writer: W,
+    universe_size: u32,
+    batch_size: usize,
+    ids: Vec<u32>,
+    partial: [u8; 4],
+    partial_len: usize,
+    frame: Vec<u8>,
+}
+
+impl<C: IdSetCompressor, W: Write> CompressorWriter<C, W> {
+    /// Compress into `writer` in batches of [`DEFAULT_BATCH_SIZE`] IDs.
+    pub fn new(compressor: C, writer: W, universe_size: u32) -> Self {
+        Self::with_batch_size(compressor, writer, universe_size, DEFAULT_BATCH_SIZE)
+    }
+
+    /// Compress into `writer` in batches of `batch_size` IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is 0.
+    pub fn with_batch_size(
+        compressor: C,
+        writer: W,
+        universe_size: u32,
+        batch_size: usize,
+    ) -> Self {
+        assert!(batch_size > 0, "batch size must be positive");
+        Self {
+            compressor,
+            writer,
+            universe_size,
+            batch_size,
+            ids: Vec::with_capacity(batch_size),
+            partial: [0; 4],
+            partial_len: 0,
+            frame: Vec::new(),
+        }
+    }
+
+    /// The inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Write the last batch and return the inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidData` if the bytes written do not form a
+    /// whole number of IDs or the last batch fails to compress, and any
+    /// error of the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.partial_len != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} trailing bytes do not form a u32 ID", self.partial_len),
+            ));
+        }
+        self.write_batch()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_batch(&mut self) -> io::Result<()> {
+        if self.ids.is_empty() {
+            return Ok(());
+        }
+        self.frame.clear();
+        let len = self
+            .compressor
+            .compress_into(&self.ids, self.universe_size, &mut self.frame)?;
+        let mut header = Vec::new();
+        encode_varint(len as u64, &mut header);
+        self.writer.write_all(&header)?;
+        self.writer.write_all(&self.frame)?;
+        self.ids.clear();
+        Ok(())
+    }
+}
+
+impl<C: IdSetCompressor, W: Write> Write for CompressorWriter<C, W> {
+    /// Buffer `buf` and write out every batch it completes.
+    ///
+    /// On error some of `buf` may already have been consumed, and the
+    /// stream should be abandoned.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.partial[self.partial_len] = byte;
+            self.partial_len += 1;
+            if self.partial_len == 4 {
+                self.ids.push(u32::from_le_bytes(self.partial));
+                self.partial_len = 0;
+                if self.ids.len() == self.batch_size {
+                    self.write_batch()?;
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_batch()?;
+        self.writer.flush()
+    }
+}
+
+/// `io::Read` adapter that decompresses a [`CompressorWriter`] stream.
+///
+/// Reads yield the IDs as little-endian `u32` bytes, one frame at a time.
+pub struct DecompressorReader<C, R: Read> {
+    compressor: C,
+    reader: R,
+    universe_size: u32,
+    frame: Vec<u8>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<C: IdSetCompressor, R: Read> DecompressorReader<C, R> {
+    /// Decompress the stream read from `reader`.
+    ///
+    /// `universe_size` must match the one given to the writer.
+    pub fn new(compressor: C, reader: R, universe_size: u32) -> Self {
+        Self {
+            compressor,
+            reader,
+            universe_size,
+            frame: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Return the inner reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Decode the next frame into `out`; `false` at a clean end of stream.
+    fn next_frame(&mut self) -> io::Result<bool> {
+        let len = match self.read_frame_len()? {
+            Some(len) => len,
+            None => return Ok(false),
+        };
+
+        self.frame.clear();
+        (&mut self.reader).take(len).read_to_end(&mut self.frame)?;
+        if (self.frame.len() as u64) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Unexpected end of compressed data",
+            ));
+        }
+
+        let ids = self
+            .compressor
+            .decompress_set(&self.frame, self.universe_size)?;
+        self.out.clear();
+        self.out.extend(ids.iter().flat_map(|id| id.to_le_bytes()));
+        self.pos = 0;
+        Ok(true)
+    }
+
+    /// Read a varint frame length; `None` if the stream ends before it.
+    fn read_frame_len(&mut self) -> io::Result<Option<u64>> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let mut byte = [0u8];
+            match self.reader.read(&mut byte) {
+                Ok(0) if shift == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Unexpected end of compressed data",
+                    ))
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            if shift >= 64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Varint frame length overflows u64",
+                ));
+            }
+            value |= ((byte[0] & 0x7F) as u64) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(Some(value));
+            }
+            shift += 7;
+        }
+    }
+}
+
+impl<C: IdSetCompressor, R: Read> Read for DecompressorReader<C, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.len() {
+            if !self.next_frame()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionError, RocCompressor};
+
+    fn raw(ids: &[u32]) -> Vec<u8> {
+        ids.iter().flat_map(|id| id.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_batches_are_separate_frames() {
+        let mut writer =
+            CompressorWriter::with_batch_size(RocCompressor::new(), Vec::new(), 100, 2);
+        writer.write_all(&raw(&[1, 5, 2, 9, 50])).unwrap();
+        let stream = writer.finish().unwrap();
+
+        let roc = RocCompressor::new();
+        let mut expected = Vec::new();
+        for batch in [&[1u32, 5][..], &[2, 9], &[50]] {
+            let frame = roc.compress_set(batch, 100).unwrap();
+            encode_varint(frame.len() as u64, &mut expected);
+            expected.extend(frame);
+        }
+        assert_eq!(stream, expected);
+    }
+
+    #[test]
+    fn test_finish_rejects_partial_id() {
+        let mut writer = CompressorWriter::new(RocCompressor::new(), Vec::new(), 100);
+        writer.write_all(&[1, 0, 0, 0, 7]).unwrap();
+        let err = writer.finish().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_unsorted_batch_is_invalid_data() {
+        let mut writer =
+            CompressorWriter::with_batch_size(RocCompressor::new(), Vec::new(), 100, 2);
+        let err = writer.write_all(&raw(&[5, 1])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("sorted and unique"));
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let mut writer = CompressorWriter::new(RocCompressor::new(), Vec::new(), 1000);
+        writer.write_all(&raw(&[1, 2, 3, 500])).unwrap();
+        let stream = writer.finish().unwrap();
+
+        let mut reader =
+            DecompressorReader::new(RocCompressor::new(), &stream[..stream.len() - 1], 1000);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_error_kind_mapping() {
+        let kind = |e: CompressionError| io::Error::from(e).kind();
+        assert_eq!(
+            kind(CompressionError::InvalidInput("x".into())),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind(CompressionError::DecompressionFailed("x".into())),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind(CompressionError::CompressionFailed("x".into())),
+            io::ErrorKind::Other
+        );
+        assert_eq!(
+            kind(CompressionError::Truncated {
+                stored_count: 1,
+                original_count: 2
+            }),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            kind(CompressionError::BufferTooSmall {
+                needed: 2,
+                available: 1
+            }),
+            io::ErrorKind::Other
+        );
+        assert_eq!(
+            kind(CompressionError::BudgetExceeded {
+                limit: 1,
+                attempted: 2
+            }),
+            io::ErrorKind::Other
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Lazy iteration over a [`RocCompressor`] set.
+//!
+//! [`DecompressIter`] decodes one delta per call to `next`, so callers that
+//! stop early (intersections, top-k) never pay for the tail of the set.
+
+use std::iter::FusedIterator;
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::varint::decode_varint;
+
+/// Iterator over the IDs of a set in the [`RocCompressor`] format.
+///
+/// The element count is read from the header when the iterator is created,
+/// so [`len`](ExactSizeIterator::len) is known up front. On malformed input
+/// the iterator yields the error once and then ends, possibly before `len`
+/// items have been produced. Bytes left after the last ID are an error in
+/// place of that ID, as they are for
+/// [`decompress_set`](crate::IdSetCompressor::decompress_set).
+///
+/// Created by [`RocCompressor::iter`].
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let compressed = roc.compress_set(&[2u32, 3, 5, 7], 10).unwrap();
+///
+/// let iter = roc.iter(&compressed, 10).unwrap();
+/// assert_eq!(iter.len(), 4);
+/// let ids: Result<Vec<u32>, _> = iter.rev().collect();
+/// assert_eq!(ids.unwrap(), vec![7, 5, 3, 2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DecompressIter<'a> {
+    compressed: &'a [u8],
+    universe_size: u32,
+    offset: usize,
+    /// Last decoded ID, `None` before the first.
+    prev: Option<u64>,
+    remaining: usize,
+    /// ID decoded by `peek` but not yet returned.
+    peeked: Option<u32>,
+    /// Rest of the set, decoded eagerly on the first `next_back`.
+    buffered: Option<std::vec::IntoIter<u32>>,
+}
+
+impl<'a> DecompressIter<'a> {
+    /// Read the header of `compressed` and position before the first ID.
+    pub(crate) fn new(compressed: &'a [u8], universe_size: u32) -> Result<Self, CompressionError> {
+        let (num_ids, offset) = if compressed.is_empty() {
+            (0, 0)
+        } else {
+            RocCompressor::read_header(compressed)?
+        };
+        let remaining = usize::try_from(num_ids).map_err(|_| {
+            CompressionError::DecompressionFailed(format!(
+                "Set length {} exceeds address space",
+                num_ids
+            ))
+        })?;
+
+        Ok(Self {
+            compressed,
+            universe_size,
+            offset,
+            prev: None,
+            remaining,
+            peeked: None,
+            buffered: None,
+        })
+    }
+
+    /// Decode the next delta from the stream.
+    fn decode_next(&mut self) -> Result<u32, CompressionError> {
+        let (value, consumed) = decode_varint(&self.compressed[self.offset..])?;
+        self.offset += consumed;
+
+        let id = match self.prev {
+            None => value,
+            Some(_) if value == 0 => {
+                return Err(CompressionError::DecompressionFailed(
+                    "Zero delta produces duplicate ID".to_string(),
+                ))
+            }
+            Some(prev) => prev + value,
+        };
+        if id >= self.universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                id, self.universe_size
+            )));
+        }
+        // The same check `decompress_set` makes once the last ID is read
+        if self.remaining == 1 && self.offset < self.compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                self.compressed.len() - self.offset
+            )));
+        }
+        self.prev = Some(id);
+        Ok(id as u32)
+    }
+
+    /// Return the next ID without consuming it.
+    ///
+    /// An error is returned (and the iterator ends) as it would be by `next`.
+    pub fn peek(&mut self) -> Option<Result<u32, CompressionError>> {
+        if let Some(id) = self.peeked {
+            return Some(Ok(id));
+        }
+        let next = self.next()?;
+        if let Ok(id) = next {
+            self.peeked = Some(id);
+        }
+        Some(next)
+    }
+
+    /// Skip IDs until the next one yielded is `>= x`, or the set is exhausted.
+    ///
+    /// Delta-encoded data has no skip pointers, so this decodes every
+    /// skipped ID; see [`SampledIndex`](crate::SampledIndex) for sublinear
+    /// skipping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if a skipped ID is malformed.
+    pub fn advance_to(&mut self, x: u32) -> Result<(), CompressionError> {
+        loop {
+            match self.peek() {
+                Some(Ok(id)) if id < x => self.peeked = None,
+                Some(Err(e)) => return Err(e),
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+impl Iterator for DecompressIter<'_> {
+    type Item = Result<u32, CompressionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(id) = self.peeked.take() {
+            return Some(Ok(id));
+        }
+        if let Some(buffered) = &mut self.buffered {
+            return buffered.next().map(Ok);
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let result = self.decode_next();
+        self.remaining = if result.is_ok() {
+            self.remaining - 1
+        } else {
+            0
+        };
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for DecompressIter<'_> {
+    fn len(&self) -> usize {
+        let pending = match &self.buffered {
+            Some(buffered) => buffered.len(),
+            None => self.remaining,
+        };
+        pending + self.peeked.is_some() as usize
+    }
+}
+
+/// Delta-encoded data can only be decoded front to back, so the first call
+/// to `next_back` decodes the rest of the set into a buffer.
+impl DoubleEndedIterator for DecompressIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.buffered.is_none() {
+            // The header count is unchecked, but every ID takes at least a byte
+            let unread = self.compressed.len() - self.offset;
+            let mut rest = Vec::with_capacity(self.remaining.min(unread) + 1);
+            rest.extend(self.peeked.take());
+            while self.remaining > 0 {
+                match self.decode_next() {
+                    Ok(id) => rest.push(id),
+                    Err(e) => {
+                        self.remaining = 0;
+                        return Some(Err(e));
+                    }
+                }
+                self.remaining -= 1;
+            }
+            self.buffered = Some(rest.into_iter());
+        }
+        // `peek` may have moved the front of the buffer into `peeked`
+        match self.buffered.as_mut()?.next_back() {
+            Some(id) => Some(Ok(id)),
+            None => self.peeked.take().map(Ok),
+        }
+    }
+}
+
+impl FusedIterator for DecompressIter<'_> {}
+
+/// Union of several compressed sets, yielded in ascending order.
+///
+/// Each step peeks at every input and advances those holding the smallest
+/// ID, so the cost is `O(k)` per output ID for `k` inputs; that is cheap for
+/// the handful of lists merged per query. IDs `>= universe` are reported as
+/// errors. The first error ends the union.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{k_way_union, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let a = roc.compress_set(&[1u32, 4, 9], 10).unwrap();
+/// let b = roc.compress_set(&[2u32, 4, 8], 10).unwrap();
+///
+/// let iters = vec![roc.iter(&a, 10).unwrap(), roc.iter(&b, 10).unwrap()];
+/// let union: Result<Vec<u32>, _> = k_way_union(iters, 10).collect();
+/// assert_eq!(union.unwrap(), vec![1, 2, 4, 8, 9]);
+/// ```
+pub fn k_way_union<'a>(
+    mut iters: Vec<DecompressIter<'a>>,
+    universe: u32,
+) -> impl Iterator<Item = Result<u32, CompressionError>> + 'a {
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+
+        let mut min = None;
+        for iter in &mut iters {
+            match iter.peek() {
+                Some(Ok(id)) => min = Some(min.map_or(id, |m: u32| m.min(id))),
+                Some(Err(e)) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+                None => {}
+            }
+        }
+
+        let Some(min) = min else {
+            done = true;
+            return None;
+        };
+        if min >= universe {
+            done = true;
+            return Some(Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                min, universe
+            ))));
+        }
+        for iter in &mut iters {
+            if iter.peeked == Some(min) {
+                iter.peeked = None;
+            }
+        }
+        Some(Ok(min))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{IdSetCompressor, RocCompressor};
+
+    #[test]
+    fn test_len_counts_down() {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&[1, 4, 9, 16], 20u32).unwrap();
+
+        let mut iter = roc.iter(&compressed, 20).unwrap();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 16);
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next().unwrap().unwrap(), 4);
+        assert_eq!(iter.next().unwrap().unwrap(), 9);
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn test_peek_and_advance_to() {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&[2, 5, 9, 12], 20u32).unwrap();
+
+        let mut iter = roc.iter(&compressed, 20).unwrap();
+        assert_eq!(iter.peek().unwrap().unwrap(), 2);
+        assert_eq!(iter.peek().unwrap().unwrap(), 2);
+        assert_eq!(iter.len(), 4);
+
+        iter.advance_to(6).unwrap();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 12);
+        assert_eq!(iter.next().unwrap().unwrap(), 9);
+
+        iter.advance_to(100).unwrap();
+        assert!(iter.peek().is_none());
+    }
+
+    #[test]
+    fn test_peek_after_next_back() {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&[1, 2], 10u32).unwrap();
+
+        let mut iter = roc.iter(&compressed, 10).unwrap();
+        assert_eq!(iter.next_back().unwrap().unwrap(), 2);
+        assert_eq!(iter.peek().unwrap().unwrap(), 1);
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next_back().unwrap().unwrap(), 1);
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn test_next_back_with_huge_claimed_length() {
+        // Default level tag, then a length of 2^60 and a single ID
+        let compressed = [5, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x10, 3];
+        let mut iter = RocCompressor::new().iter(&compressed, 10).unwrap();
+        assert!(iter.next_back().unwrap().is_err());
+        assert!(iter.next_back().is_none());
+    }
+
+    #[test]
+    fn test_trailing_bytes_are_an_error() {
+        let roc = RocCompressor::new();
+        let mut compressed = roc.compress_set(&[1, 4, 9], 10u32).unwrap();
+        compressed.push(0);
+        assert!(roc.decompress_set(&compressed, 10u32).is_err());
+
+        let mut iter = roc.iter(&compressed, 10).unwrap();
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert_eq!(iter.next().unwrap().unwrap(), 4);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        assert!(roc
+            .iter(&compressed, 10)
+            .unwrap()
+            .next_back()
+            .unwrap()
+            .is_err());
+        assert!(roc.partition_by(&compressed, 10, 5).is_err());
+        let iters = vec![roc.iter(&compressed, 10).unwrap()];
+        assert!(super::k_way_union(iters, 10).any(|r| r.is_err()));
+    }
+
+    #[test]
+    fn test_k_way_union() {
+        let roc = RocCompressor::new();
+        let sets = [vec![1u32, 5, 7], vec![], vec![0, 5, 9], vec![7]];
+        let compressed: Vec<Vec<u8>> = sets
+            .iter()
+            .map(|ids| roc.compress_set(ids, 10).unwrap())
+            .collect();
+
+        let iters = compressed
+            .iter()
+            .map(|c| roc.iter(c, 10).unwrap())
+            .collect();
+        let union: Vec<u32> = super::k_way_union(iters, 10)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(union, [0, 1, 5, 7, 9]);
+
+        let iters = vec![roc.iter(&compressed[2], 10).unwrap()];
+        assert!(super::k_way_union(iters, 9).any(|r| r.is_err()));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut iter = RocCompressor::new().iter(&[], 10).unwrap();
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_error_ends_iteration() {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&[1, 4, 9], 10u32).unwrap();
+
+        // Universe too small for the last ID
+        let mut iter = roc.iter(&compressed, 5).unwrap();
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert_eq!(iter.next().unwrap().unwrap(), 4);
+        assert!(iter.next().unwrap().is_err());
+        assert_eq!(iter.len(), 0);
+        assert!(iter.next().is_none());
+    }
+}
@@ -11,6 +11,64 @@
 //!
 //! - **Delta encoding**: Simple baseline, varint-encodes gaps between IDs
 //! - **ROC (Random Order Coding)**: Near-optimal for sets using bits-back with ANS
+//! - **Block delta**: Delta encoding in independently decodable blocks with a skip index
+//! - **PFOR-delta**: Bit-packed frames of gaps with patched exceptions, for fast decoding
+//! - **Exception delta**: Varint gaps with rare large gaps moved to an out-of-line list via [`ExceptionBasedDeltaCompressor`]
+//! - **Fibonacci**: Self-delimiting Zeckendorf codes over gaps
+//! - **Nibble**: Gaps of 1 to 14 in four bits each, for clustered IVF posting lists
+//! - **Elias gamma/delta**: Bit-aligned universal codes over gaps, one bit per gap of 1
+//! - **Simple-16**: Gaps packed into 32-bit words by a 4-bit selector, for word-aligned decoding
+//! - **Segmented**: Per-window choice of delta-varint, bitmap or fixed-width, for lists mixing dense and sparse runs
+//! - **Huffman**: Static per-set Huffman code over gaps, for skewed gap distributions, or one code shared by many sets via [`GapHistogram`]
+//! - **Contextual**: Huffman-coded gap classes with a model trained per context key (e.g. IVF centroid) via [`ContextualRocCompressor`]
+//! - **Arithmetic coding**: Adaptive binary arithmetic coding of membership bits, within a few bits of `log2(C(N,n))` (`arithmetic` feature)
+//! - **Bitset**: One bit per ID of the universe via [`BitmapSetCompressor`], for sets covering more than half of it; [`from_sorted_bitmap`] compresses such a bitset with any codec
+//! - **Roaring bitmap**: Container-based bitmaps for dense sets, convertible to and from [`CompressedSet`] (`roaring` feature)
+//! - **Elias-Fano**: Low bits verbatim plus unary high bits, a size fixed by `n` and the universe, with sampled select for `next_geq` via [`EliasFanoWithSelect`]
+//! - **Fixed width**: Every ID in `ceil(log2(N))` bits via [`compress_fixed_width`], for SIMD and GPU decoding
+//! - **Interpolative**: Recursive midpoint coding within shrinking ranges, for clustered sets
+//! - **Split Elias-Fano**: Two-level Elias-Fano over 64-bit universes, for clustered hashed IDs
+//! - **Two-level Elias-Fano**: Elias-Fano over the occupied `2^32`-ID blocks, then within each, via [`TwoLevelEliasFanoCompressor`], for hashed IDs spread over the whole 64-bit space
+//! - **Windowed**: Independent windows of `2^k` IDs over any inner codec via [`WindowedCompressor`], for 64-bit IDs
+//! - **XOR delta**: XOR of neighbouring IDs, for unsorted or Z-order IDs
+//! - **Zigzag delta**: Signed differences as zigzag varints, for sequences that move up and down
+//! - **Base offset**: IDs from a sub-range `[base, N)` via [`BaseOffsetCompressor`], wrapping any codec
+//! - **Remapping**: IDs replaced by their rank among a sorted list of valid IDs via [`RemappingCompressor`], wrapping any codec, for ID spaces with holes
+//! - **Multisets**: `(id, count)` pairs via [`MultisetCompressor`], e.g. term frequencies
+//! - **Byte budget**: The longest prefix that fits a fixed size via [`MaxSizeCompressor`], wrapping any codec
+//!
+//! [`Codec`] holds any of the main compressors by value, for runtime choice without boxing.
+//! [`StackCompressor`] compresses small sets into fixed-size arrays, with no heap allocation.
+//! [`CheckpointedCompressor`] takes a huge set in sorted pieces and writes it as independently decodable chunks.
+//! [`RandomAccessCompressedSet`] reads the `k`-th ID of a delta-varint set from a nearby checkpoint instead of decoding the prefix.
+//! [`CompressedSetVec`] packs many compressed sets into one buffer, optionally within a [`CompressionBudget`].
+//! [`AdaptiveCompressor`] tries every main codec on the first sets it compresses and keeps the smallest for the rest.
+//! [`CompressorRegistry`] prefixes compressed sets with a tag byte naming their codec, so stored data can be decoded without knowing it.
+//! [`CompressedSetMap`] attaches a value, such as a WAND upper bound, to each compressed set.
+//! [`PeekableCompressedSet`] is a posting list cursor for WAND-style top-k queries.
+//! [`Universe`] and [`IdCount`] keep universe sizes and set lengths apart from IDs.
+//! [`CompressedSetInspector`] labels each byte of a compressed set with the value it encodes, for debugging codecs.
+//! [`DensityOracle`] tells whether a set is large enough to be worth compressing at all.
+//! [`analyze_gaps`] summarizes the gaps of a set as [`GapStatistics`], the structure that decides which codec fits.
+//! The `simd` module decodes runs of varints 16 bytes at a time with SSE2.
+//! [`fuzz_corpus::generate_fuzz_corpus`] writes edge-case delta-varint inputs, such as truncated headers and overflowing deltas, as fuzzing seeds.
+//! In debug builds, `dump_compressed_set_hex` prints a compressed set as hex next to its decoded IDs, for test failure messages.
+//! With the `concurrent` feature, `ConcurrentCompressedIndex` merges new IDs into posting lists while other threads read them.
+//! With the `toml-config` feature, `CompressedIndex::from_config_file` builds an index from a TOML file.
+//! With the `brotli` or `zstd` feature, `PostCompressor` runs a general-purpose coder over any codec's output.
+//! With the `postcard` feature, [`CompressedSet`] is `Serialize + Deserialize`.
+//! With the `tracing` feature, [`RocCompressor`] emits a structured trace event per call.
+//! With the `test-utils` feature, the `testutils` module generates valid sets for tests.
+//!
+//! # Thread Safety
+//!
+//! Every compressor is `Send + Sync` and holds only configuration, so one
+//! instance can be shared across threads without locking. The exceptions
+//! are [`RecordingCompressor`], which logs calls behind a `Mutex`, and
+//! [`AdaptiveCompressor`], which keeps its calibration state behind one
+//! and locks it on every call.
+//! [`CompressionError`] is `Send + Sync` as well and can cross thread
+//! boundaries inside `anyhow`-style error chains.
 //!
 //! # Historical Context
 //!
@@ -18,7 +76,7 @@
 //! like Elias-Fano (1971) exploit monotonicity of sorted sequences. Modern
 //! methods like ROC (Severo et al., 2022) exploit the additional structure
 //! that *order doesn't matter*, achieving log(C(N,n)) bits instead of
-//! log(N^n) bits.
+//! log(N^n) bits. The [`math`] module computes both bounds.
 //!
 //! # Example
 //!
@@ -47,27 +105,233 @@
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+mod adaptive;
+mod analysis;
+mod base_offset;
+mod batch;
+mod bitmap;
+mod bits;
+mod block_delta;
+mod budget;
+mod builder;
+mod checkpointed;
+mod codec;
+mod compressed_set;
+mod contextual;
+mod convert;
+mod diff;
+mod elias_codes;
+mod elias_fano;
 mod error;
+mod exception_delta;
+mod fibonacci;
+mod fingerprint;
+mod fixed_width;
+pub mod fuzz_corpus;
+mod histogram;
+mod huffman;
+mod index;
+mod inspector;
+mod interpolative;
+mod io;
+mod iter;
+mod map;
+pub mod math;
+mod max_size;
+mod merge;
+mod multiset;
+mod nibble;
+mod oracle;
+mod pfor;
+mod posting;
+mod random_access;
+mod recording;
+mod registry;
+mod remap;
 mod roc;
+mod sampled;
+mod segmented;
+mod selector;
+mod self_codec;
+pub mod simd;
+mod simple16;
+mod stack;
+mod trace;
 mod traits;
+mod transcode;
+mod two_level_ef;
+mod types;
+pub mod varint;
+mod verifying;
+mod windowed;
+mod xor_delta;
+mod zigzag;
 
 #[cfg(feature = "ans")]
 mod ans;
 
+#[cfg(feature = "arithmetic")]
+mod arithmetic;
+
+#[cfg(feature = "concurrent")]
+mod concurrent;
+
+#[cfg(feature = "toml-config")]
+mod config;
+
+#[cfg(debug_assertions)]
+mod debug;
+
+#[cfg(feature = "postcard")]
+mod postcard;
+
+#[cfg(any(feature = "brotli", feature = "zstd"))]
+mod post_compress;
+
+#[cfg(feature = "roaring")]
+mod roaring;
+
+#[cfg(feature = "roaring")]
+mod roaring_interop;
+
+#[cfg(feature = "mmap")]
+mod store;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testutils;
+
+pub use adaptive::AdaptiveCompressor;
+pub use analysis::{analyze_gaps, GapStatistics, SizeHistogram};
+#[cfg(feature = "arithmetic")]
+pub use arithmetic::ArithmeticCompressor;
+pub use base_offset::BaseOffsetCompressor;
+pub use batch::{CompressedSetVec, CompressedSetVecIter};
+pub use bitmap::BitmapSetCompressor;
+pub use block_delta::BlockDeltaCompressor;
+pub use budget::{estimate_batch_size, CompressionBudget};
+pub use builder::{compress_set_append, CompressedSetBuilder};
+pub use checkpointed::{decompress_checkpointed, CheckpointedCompressor};
+pub use codec::Codec;
+pub use compressed_set::{CompressedSet, CompressedSetWithHash};
+#[cfg(feature = "concurrent")]
+pub use concurrent::ConcurrentCompressedIndex;
+pub use contextual::ContextualRocCompressor;
+pub use convert::{from_sorted_bitmap, to_sorted_bitmap};
+#[cfg(debug_assertions)]
+pub use debug::dump_compressed_set_hex;
+pub use diff::{apply_diff, diff, explain_diff, DiffSet};
+pub use elias_codes::{EliasDeltaCompressor, EliasGammaCompressor};
+pub use elias_fano::{EliasFanoCompressor, EliasFanoWithSelect};
 pub use error::CompressionError;
-pub use roc::RocCompressor;
-pub use traits::IdSetCompressor;
+pub use exception_delta::ExceptionBasedDeltaCompressor;
+pub use fibonacci::FibonacciCompressor;
+pub use fingerprint::fingerprint;
+pub use fixed_width::{bits_needed, compress_fixed_width, decompress_fixed_width};
+pub use histogram::GapHistogram;
+pub use huffman::HuffmanCompressor;
+pub use index::{merge_compressed_indices, CompressedIndex};
+pub use inspector::{ByteSpan, CompressedSetInspector, SpanMeaning};
+pub use interpolative::InterpolativeCompressor;
+pub use io::{CompressorWriter, DecompressorReader, DEFAULT_BATCH_SIZE};
+pub use iter::{k_way_union, DecompressIter};
+pub use map::CompressedSetMap;
+pub use max_size::MaxSizeCompressor;
+pub use merge::sorted_merge_compress;
+pub use multiset::{MultisetCompressor, RocMultisetCompressor};
+pub use nibble::NibbleCompressor;
+pub use oracle::DensityOracle;
+pub use pfor::PForDeltaCompressor;
+#[cfg(any(feature = "brotli", feature = "zstd"))]
+pub use post_compress::{PostCompression, PostCompressor};
+#[cfg(feature = "postcard")]
+pub use postcard::{from_postcard_bytes, to_postcard_bytes, BoundedCompressedSet};
+pub use posting::{wand_intersect, PeekableCompressedSet};
+pub use random_access::RandomAccessCompressedSet;
+pub use recording::{CompressionRecord, Operation, RecordingCompressor, RecordingSummary};
+pub use registry::CompressorRegistry;
+pub use remap::RemappingCompressor;
+#[cfg(feature = "roaring")]
+pub use roaring::RoaringBitmapCompressor;
+pub use roc::{
+    compress_deltas_only, decompress_deltas_only, CompressionLevel, RocCompressor, ValidationMode,
+};
+pub use sampled::SampledIndex;
+pub use segmented::SegmentedCompressor;
+pub use selector::{CompressionMethodSelector, MethodStats};
+pub use self_codec::SplitEliasFanoCompressor;
+pub use simple16::Simple16Compressor;
+pub use stack::StackCompressor;
+#[cfg(feature = "mmap")]
+pub use store::CompressedSetStore;
+pub use traits::{IdSetCompressor, IdType};
+pub use transcode::recompress;
+pub use two_level_ef::TwoLevelEliasFanoCompressor;
+pub use types::{IdCount, Universe};
+pub use verifying::VerifyingCompressor;
+pub use windowed::WindowedCompressor;
+pub use xor_delta::XorDeltaCompressor;
+pub use zigzag::ZigzagDeltaCompressor;
 
 /// Compression method selection.
+///
+/// The discriminants are the tag bytes of [`CompressorRegistry`], so
+/// `method as u8` names the method in tagged data.
+///
+/// The default is [`Roc`](Self::Roc). It used to be no compression, so
+/// code that never chose a method silently stored raw IDs; ask for
+/// [`Uncompressed`](Self::Uncompressed) explicitly to keep that.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
 pub enum IdCompressionMethod {
     /// No compression (uncompressed storage).
-    #[default]
-    None,
+    Uncompressed = 4,
     /// Elias-Fano encoding (baseline, sorted sequences).
-    EliasFano,
+    EliasFano = 1,
     /// Random Order Coding (optimal for sets, uses bits-back with ANS).
-    Roc,
+    #[default]
+    Roc = 0,
     /// Wavelet tree (full random access, future).
-    WaveletTree,
+    WaveletTree = 5,
+    /// Roaring bitmap (dense sets, requires the `roaring` feature).
+    RoaringBitmap = 6,
+    /// Binary interpolative coding (clustered sets).
+    Interpolative = 3,
+    /// Plain bitset (sets covering more than half the universe).
+    Bitset = 2,
+}
+
+impl IdCompressionMethod {
+    /// Former name of [`Uncompressed`](Self::Uncompressed), easily misread
+    /// as "no method chosen".
+    #[deprecated(note = "renamed to `IdCompressionMethod::Uncompressed`")]
+    #[allow(non_upper_case_globals)]
+    pub const None: Self = Self::Uncompressed;
+
+    /// Density above which a bitmap representation beats delta encoding.
+    const ROARING_DENSITY_THRESHOLD: f64 = 0.05;
+
+    /// Density above which a plain bitset beats roaring containers.
+    const BITSET_DENSITY_THRESHOLD: f64 = 0.5;
+
+    /// Pick a compression method from the set density.
+    ///
+    /// Very dense sets (more than half the universe) use [`IdCompressionMethod::Bitset`],
+    /// dense sets (more than 5%) use [`IdCompressionMethod::RoaringBitmap`]
+    /// when the `roaring` feature is enabled, and everything else uses
+    /// [`IdCompressionMethod::Roc`]. The result always converts to a
+    /// [`Codec`] in the current build.
+    pub fn auto_select(num_ids: usize, universe_size: u32) -> Self {
+        let density = if universe_size > 0 {
+            num_ids as f64 / universe_size as f64
+        } else {
+            0.0
+        };
+        if density > Self::BITSET_DENSITY_THRESHOLD {
+            IdCompressionMethod::Bitset
+        } else if cfg!(feature = "roaring") && density > Self::ROARING_DENSITY_THRESHOLD {
+            IdCompressionMethod::RoaringBitmap
+        } else {
+            IdCompressionMethod::Roc
+        }
+    }
 }
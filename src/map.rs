@@ -0,0 +1,163 @@
+//! Maps from compressed ID sets to payload values.
+//!
+//! Query engines attach metadata to each posting list: the maximum term
+//! score WAND pivots on, a document frequency, an on-disk offset.
+//! [`CompressedSetMap`] keys such values by the compressed set itself,
+//! kept sorted so that lookups are a binary search over the bytes.
+
+use std::cmp::Ordering;
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Values keyed by compressed ID sets.
+///
+/// A key is the compressed bytes together with the universe they were
+/// compressed for, so one map can hold sets from several universes. Every
+/// set must be compressed by the same codec for lookups by IDs to find it.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressedSetMap, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let mut map = CompressedSetMap::new();
+/// map.insert(&[1, 5, 9], 100, 0.75f32, &roc).unwrap();
+/// map.insert(&[2, 3], 100, 0.5, &roc).unwrap();
+///
+/// assert_eq!(map.get_by_ids(&[1, 5, 9], 100, &roc), Some(&0.75));
+/// assert_eq!(map.get_by_ids(&[1, 5], 100, &roc), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompressedSetMap<V> {
+    /// `(compressed, universe, value)`, sorted by `(compressed, universe)`.
+    entries: Vec<(Vec<u8>, u32, V)>,
+}
+
+impl<V> Default for CompressedSetMap<V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<V> CompressedSetMap<V> {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of the key `(compressed, universe)`, or where to insert it.
+    fn search(&self, compressed: &[u8], universe: u32) -> Result<usize, usize> {
+        self.entries.binary_search_by(|(bytes, u, _)| {
+            bytes
+                .as_slice()
+                .cmp(compressed)
+                .then_with(|| u.cmp(&universe))
+        })
+    }
+
+    /// Compress `ids` with `c` and map the set to `value`, replacing any
+    /// previous value for the same set.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from the compressor; the map is left unchanged.
+    pub fn insert(
+        &mut self,
+        ids: &[u32],
+        universe: u32,
+        value: V,
+        c: &dyn IdSetCompressor,
+    ) -> Result<(), CompressionError> {
+        let compressed = c.compress_set(ids, universe)?;
+        match self.search(&compressed, universe) {
+            Ok(i) => self.entries[i].2 = value,
+            Err(i) => self.entries.insert(i, (compressed, universe, value)),
+        }
+        Ok(())
+    }
+
+    /// The value of the set `ids`, compressing it with `c` to search.
+    ///
+    /// Returns `None` if the set is not in the map or `c` rejects it.
+    pub fn get_by_ids(&self, ids: &[u32], universe: u32, c: &dyn IdSetCompressor) -> Option<&V> {
+        let compressed = c.compress_set(ids, universe).ok()?;
+        let i = self.search(&compressed, universe).ok()?;
+        Some(&self.entries[i].2)
+    }
+
+    /// The value of a set given in compressed form, in any universe.
+    ///
+    /// If the same bytes were inserted for several universes, the value for
+    /// the smallest one is returned.
+    pub fn get_by_compressed(&self, compressed: &[u8]) -> Option<&V> {
+        let i = self
+            .entries
+            .partition_point(|(bytes, _, _)| bytes.as_slice().cmp(compressed) == Ordering::Less);
+        match self.entries.get(i) {
+            Some((bytes, _, value)) if bytes == compressed => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Decompress every set with `c`, in key order, paired with its value.
+    pub fn iter<'a>(
+        &'a self,
+        c: &'a dyn IdSetCompressor,
+    ) -> impl Iterator<Item = Result<(Vec<u32>, &'a V), CompressionError>> + 'a {
+        self.entries
+            .iter()
+            .map(move |(bytes, universe, value)| Ok((c.decompress_set(bytes, *universe)?, value)))
+    }
+
+    /// Number of sets.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the map holds no sets.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_insert_replaces_and_keys_by_universe() {
+        let roc = RocCompressor::new();
+        let mut map = CompressedSetMap::new();
+        map.insert(&[1, 2], 10, "a", &roc).unwrap();
+        map.insert(&[1, 2], 10, "b", &roc).unwrap();
+        map.insert(&[1, 2], 20, "c", &roc).unwrap();
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get_by_ids(&[1, 2], 10, &roc), Some(&"b"));
+        assert_eq!(map.get_by_ids(&[1, 2], 20, &roc), Some(&"c"));
+        assert_eq!(map.get_by_ids(&[1, 2], 30, &roc), None);
+        // Invalid queries are simply absent
+        assert_eq!(map.get_by_ids(&[2, 1], 10, &roc), None);
+
+        let compressed = roc.compress_set(&[1u32, 2], 10).unwrap();
+        assert_eq!(map.get_by_compressed(&compressed), Some(&"b"));
+        assert_eq!(map.get_by_compressed(&compressed[..2]), None);
+        assert!(map.insert(&[10], 10, "d", &roc).is_err());
+    }
+
+    #[test]
+    fn test_iter_decompresses_in_key_order() {
+        let roc = RocCompressor::new();
+        let mut map = CompressedSetMap::new();
+        for (ids, value) in [(vec![3u32, 4], 1), (vec![1], 2), (vec![], 3)] {
+            map.insert(&ids, 100, value, &roc).unwrap();
+        }
+        let pairs: Vec<(Vec<u32>, &i32)> = map.iter(&roc).collect::<Result<_, _>>().unwrap();
+        assert_eq!(pairs, [(vec![], &3), (vec![1], &2), (vec![3, 4], &1)]);
+    }
+}
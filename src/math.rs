@@ -0,0 +1,310 @@
+//! Information-theoretic sizes of sets and sequences.
+//!
+//! `n` IDs from a universe of `N` written as a sequence cost `n * log2(N)`
+//! bits. As a set, the order carries no information, and the bound drops
+//! to `log2(C(N, n))`, which for `n << N` is close to
+//! `n * log2(N) - log2(n!)`. The `log2(n!)` bits saved are what set coders
+//! such as ROC exploit.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cnk::math::{theoretical_bits_sequence, theoretical_bits_set};
+//!
+//! let (n, universe) = (1000, 1_000_000);
+//! let multiplier = theoretical_bits_sequence(n, universe) / theoretical_bits_set(n, universe);
+//! assert!(multiplier > 1.5);
+//! ```
+
+use std::f64::consts::{E, PI};
+
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+
+/// Below this, `log2(n!)` is summed exactly.
+const EXACT_FACTORIAL_LIMIT: u64 = 256;
+
+/// `log2(n!)`.
+///
+/// Exact summation for small `n`, Stirling's series with two correction
+/// terms above, which is accurate to well under `1e-9` bits.
+pub fn log2_factorial(n: u64) -> f64 {
+    if n < EXACT_FACTORIAL_LIMIT {
+        return (2..=n).map(|k| (k as f64).log2()).sum();
+    }
+    let x = n as f64;
+    let ln =
+        x * x.ln() - x + 0.5 * (2.0 * PI * x).ln() + 1.0 / (12.0 * x) - 1.0 / (360.0 * x * x * x);
+    ln * E.log2()
+}
+
+/// Bits saved by coding `n` distinct IDs as a set rather than a sequence:
+/// `log2(n!)`, one per possible ordering.
+///
+/// Zero for `n` of 0 or 1.
+pub fn theoretical_set_vs_sequence_savings(n: usize) -> f64 {
+    log2_factorial(n as u64)
+}
+
+/// Bits to write `n` IDs from `[0, universe)` as a sequence:
+/// `n * log2(universe)`.
+pub fn theoretical_bits_sequence(n: usize, universe: u32) -> f64 {
+    if n == 0 || universe == 0 {
+        return 0.0;
+    }
+    n as f64 * (universe as f64).log2()
+}
+
+/// Bits to write a set of `n` distinct IDs from `[0, universe)`:
+/// `log2(C(universe, n))`.
+///
+/// Zero if `n > universe`, where no such set exists.
+pub fn theoretical_bits_set(n: usize, universe: u32) -> f64 {
+    let (n, universe) = (n as u64, universe as u64);
+    if n > universe {
+        return 0.0;
+    }
+    let bits = log2_factorial(universe) - log2_factorial(n) - log2_factorial(universe - n);
+    bits.max(0.0)
+}
+
+/// `log2(C(n, k))`, summed term by term.
+///
+/// Adds `log2((n - i) / (k - i))` for `i` in `0..min(k, n - k)`, so no
+/// intermediate value overflows and there is no series truncation, unlike
+/// the Stirling approximation behind [`theoretical_bits_set`]. The cost is
+/// linear in `min(k, n - k)`, which is fine up to `k` of a few million.
+///
+/// Zero if `k > n`, where no such subset exists.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::math::log2_binomial_exact;
+///
+/// assert!((log2_binomial_exact(20, 10) - 184_756f64.log2()).abs() < 1e-12);
+/// assert!((log2_binomial_exact(1_000_000, 1000) - 11_401.45).abs() < 0.01);
+/// ```
+pub fn log2_binomial_exact(n: u64, k: u64) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    let k = k.min(n - k);
+    (0..k)
+        .map(|i| ((n - i) as f64 / (k - i) as f64).log2())
+        .sum()
+}
+
+/// `C(n, k)` exactly, or `None` if it does not fit in a `u128`.
+///
+/// Zero if `k > n`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::math::binomial_coeff_u128;
+///
+/// assert_eq!(binomial_coeff_u128(20, 10), Some(184_756));
+/// assert_eq!(binomial_coeff_u128(1000, 500), None);
+/// ```
+pub fn binomial_coeff_u128(n: u32, k: u32) -> Option<u128> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k) as u128;
+    let n = n as u128;
+    // C(n, i + 1) = C(n, i) * (n - i) / (i + 1). With g = gcd(C(n, i), i + 1),
+    // (i + 1) / g divides n - i, so both divisions are exact and the
+    // product is the next coefficient itself: it overflows only if that does.
+    let mut c = 1u128;
+    for i in 0..k {
+        let g = gcd(c, i + 1);
+        c = (c / g).checked_mul((n - i) / ((i + 1) / g))?;
+    }
+    Some(c)
+}
+
+/// Greatest common divisor, by Euclid's algorithm.
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Fraction of the universe a set covers: `ids.len() / universe`.
+///
+/// Zero for an empty universe. Above 0.5, a plain bitset
+/// ([`BitmapSetCompressor`](crate::BitmapSetCompressor)) beats every gap code.
+pub fn density(ids: &[u32], universe: u32) -> f64 {
+    if universe == 0 {
+        return 0.0;
+    }
+    ids.len() as f64 / universe as f64
+}
+
+/// Best possible ratio of raw `u32` storage to set coding:
+/// `32 * n / log2(C(universe, n))`.
+///
+/// Zero if `n` is 0 or exceeds `universe`; infinite if `n == universe`,
+/// where the set is known without storing anything.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::math::{achievable_compression_ratio, theoretical_compression_ratio};
+///
+/// // About 11.4 bits per ID against 32
+/// let best = theoretical_compression_ratio(1000, 1_000_000);
+/// assert!((best - 2.807).abs() < 0.001);
+/// assert!(achievable_compression_ratio(1000, 1_000_000) < best);
+/// ```
+pub fn theoretical_compression_ratio(num_ids: usize, universe: u32) -> f64 {
+    if num_ids == 0 || num_ids as u64 > universe as u64 {
+        return 0.0;
+    }
+    if num_ids as u64 == universe as u64 {
+        return f64::INFINITY;
+    }
+    (num_ids * 32) as f64 / log2_binomial_exact(universe as u64, num_ids as u64)
+}
+
+/// Ratio of raw `u32` storage to the size [`RocCompressor`] estimates for
+/// `num_ids` IDs.
+///
+/// Uses its [`estimate_size`](crate::IdSetCompressor::estimate_size), the
+/// set bound plus 1.5 bytes of varint overhead per ID, so it is well under
+/// [`theoretical_compression_ratio`] for sparse sets. Zero if `num_ids` is 0
+/// or exceeds `universe`.
+pub fn achievable_compression_ratio(num_ids: usize, universe: u32) -> f64 {
+    if num_ids == 0 || num_ids as u64 > universe as u64 {
+        return 0.0;
+    }
+    let bytes = IdSetCompressor::<u32>::estimate_size(&RocCompressor::new(), num_ids, universe);
+    (num_ids * 32) as f64 / (bytes * 8) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log2_factorial_matches_exact_sum() {
+        for n in [0u64, 1, 2, 10, 255, 256, 1000, 100_000] {
+            let exact: f64 = (2..=n).map(|k| (k as f64).log2()).sum();
+            assert!(
+                (log2_factorial(n) - exact).abs() < 1e-6 * exact.max(1.0),
+                "n = {}",
+                n
+            );
+        }
+        assert_eq!(log2_factorial(3), 6f64.log2());
+    }
+
+    #[test]
+    fn test_set_bits_are_sequence_minus_savings_for_sparse_sets() {
+        // log2(C(N, n)) = log2(N! / (N - n)!) - log2(n!), and the first term
+        // approaches n * log2(N) when n << N
+        let (n, universe) = (100, 1 << 30);
+        let approx =
+            theoretical_bits_sequence(n, universe) - theoretical_set_vs_sequence_savings(n);
+        assert!((theoretical_bits_set(n, universe) - approx).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_set_vs_sequence_table() {
+        let universe = 1_000_000;
+        println!(
+            "{:>6} {:>14} {:>14} {:>14} {:>10}",
+            "n", "sequence bits", "set bits", "savings", "multiplier"
+        );
+        let mut prev_multiplier = 1.0;
+        for n in [10, 100, 1000, 10_000] {
+            let sequence = theoretical_bits_sequence(n, universe);
+            let set = theoretical_bits_set(n, universe);
+            let savings = theoretical_set_vs_sequence_savings(n);
+            let multiplier = sequence / set;
+            println!(
+                "{:>6} {:>14.1} {:>14.1} {:>14.1} {:>10.3}",
+                n, sequence, set, savings, multiplier
+            );
+
+            assert!(set < sequence);
+            assert!(multiplier > prev_multiplier);
+            prev_multiplier = multiplier;
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(theoretical_set_vs_sequence_savings(0), 0.0);
+        assert_eq!(theoretical_set_vs_sequence_savings(1), 0.0);
+        assert_eq!(theoretical_bits_sequence(0, 100), 0.0);
+        assert_eq!(theoretical_bits_set(0, 100), 0.0);
+        assert_eq!(theoretical_bits_set(100, 100), 0.0);
+        assert_eq!(theoretical_bits_set(101, 100), 0.0);
+        assert!((theoretical_bits_set(1, 1024) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_binomial_coeff_u128() {
+        assert_eq!(binomial_coeff_u128(20, 10), Some(184_756));
+        assert_eq!(binomial_coeff_u128(0, 0), Some(1));
+        assert_eq!(binomial_coeff_u128(5, 6), Some(0));
+        assert_eq!(binomial_coeff_u128(u32::MAX, 1), Some(u32::MAX as u128));
+        // C(130, 65) ~ 9.5e37 fits; C(132, 66) ~ 3.8e38 is past u128::MAX ~ 3.4e38
+        assert!(binomial_coeff_u128(130, 65).is_some());
+        assert_eq!(binomial_coeff_u128(132, 66), None);
+
+        // Pascal's rule across a full row
+        for k in 1..60 {
+            assert_eq!(
+                binomial_coeff_u128(60, k),
+                Some(binomial_coeff_u128(59, k - 1).unwrap() + binomial_coeff_u128(59, k).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_log2_binomial_exact() {
+        for (n, k) in [(20, 10), (100, 3), (128, 64), (60, 59)] {
+            let exact = (binomial_coeff_u128(n, k).unwrap() as f64).log2();
+            assert!(
+                (log2_binomial_exact(n as u64, k as u64) - exact).abs() < 1e-9,
+                "C({}, {})",
+                n,
+                k
+            );
+        }
+        assert!((log2_binomial_exact(1_000_000, 1000) - 11_401.449_698_7).abs() < 1e-6);
+        assert_eq!(log2_binomial_exact(10, 0), 0.0);
+        assert_eq!(log2_binomial_exact(10, 11), 0.0);
+
+        // Agrees with the Stirling bound at the sizes it is meant for
+        let (n, k) = (1u64 << 32, 1u64 << 20);
+        let stirling = log2_factorial(n) - log2_factorial(k) - log2_factorial(n - k);
+        assert!((log2_binomial_exact(n, k) - stirling).abs() < 1e-6 * stirling);
+    }
+
+    #[test]
+    fn test_compression_ratios() {
+        // log2(C(10^6, 1000)) ~ 11_401 bits, against 32_000 raw
+        let theoretical = theoretical_compression_ratio(1000, 1_000_000);
+        assert!((theoretical - 32_000.0 / 11_401.45).abs() < 1e-3);
+
+        // 1425 bytes of set bound plus 1500 of varint overhead
+        let achievable = achievable_compression_ratio(1000, 1_000_000);
+        assert!((achievable - 4000.0 / 2925.0).abs() < 1e-9);
+        assert!(achievable > 1.0 && achievable < theoretical);
+
+        // Denser sets compress better
+        assert!(theoretical_compression_ratio(100_000, 1_000_000) > 6.0);
+        assert!(theoretical_compression_ratio(1000, u32::MAX) < theoretical);
+
+        assert_eq!(theoretical_compression_ratio(0, 100), 0.0);
+        assert_eq!(theoretical_compression_ratio(101, 100), 0.0);
+        assert_eq!(theoretical_compression_ratio(100, 100), f64::INFINITY);
+        assert_eq!(achievable_compression_ratio(0, 100), 0.0);
+        assert_eq!(achievable_compression_ratio(101, 100), 0.0);
+    }
+}
@@ -0,0 +1,250 @@
+//! Compressed sets with a hard byte budget.
+//!
+//! Some paths have room for a fixed number of bytes and no more, e.g. a
+//! posting list update carried in one network packet. [`MaxSizeCompressor`]
+//! keeps as many of the smallest IDs as fit and records that the rest were
+//! dropped, so readers can tell a best-effort prefix from a whole set.
+//!
+//! # Format
+//!
+//! ```text
+//! [truncated: u8 = 0] [inner payload]
+//! [truncated: u8 = 1] [original_count: varint] [inner payload of a prefix]
+//! ```
+//!
+//! The empty set is encoded as zero bytes.
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Wraps a compressor and cuts sets short to fit `max_bytes`.
+///
+/// A set that fits is stored whole. Otherwise the longest prefix that fits
+/// is kept, found by binary search over prefix lengths, which assumes the
+/// inner output grows with the number of IDs. `decompress_set` fails with
+/// [`CompressionError::Truncated`] on a cut set;
+/// [`decompress_prefix`](Self::decompress_prefix) returns the kept IDs.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressionError, IdSetCompressor, MaxSizeCompressor, RocCompressor};
+///
+/// let compressor = MaxSizeCompressor::new(RocCompressor::new(), 16);
+/// let ids: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+/// let compressed = compressor.compress_set(&ids, 100_000).unwrap();
+/// assert!(compressed.len() <= 16);
+///
+/// let err = compressor.decompress_set(&compressed, 100_000).unwrap_err();
+/// assert!(matches!(err, CompressionError::Truncated { original_count: 100, .. }));
+///
+/// let (prefix, original_count) = compressor.decompress_prefix(&compressed, 100_000).unwrap();
+/// assert_eq!(original_count, 100);
+/// assert_eq!(prefix, ids[..prefix.len()]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MaxSizeCompressor<C> {
+    inner: C,
+    max_bytes: usize,
+}
+
+impl<C: IdSetCompressor> MaxSizeCompressor<C> {
+    /// Wrap `inner`, limiting every compressed set to `max_bytes`.
+    pub fn new(inner: C, max_bytes: usize) -> Self {
+        Self { inner, max_bytes }
+    }
+
+    /// The wrapped compressor.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// The byte budget.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Decode the IDs kept in `compressed`, whether or not it was truncated.
+    ///
+    /// Returns the IDs and the size of the set before truncation, which
+    /// equals their count if nothing was dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` if the header is
+    /// malformed, or any error from the inner compressor.
+    pub fn decompress_prefix(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<(Vec<u32>, usize), CompressionError> {
+        let (&flag, rest) = match compressed.split_first() {
+            Some(split) => split,
+            None => return Ok((Vec::new(), 0)),
+        };
+
+        match flag {
+            0 => {
+                let ids = self.inner.decompress_set(rest, universe_size)?;
+                let len = ids.len();
+                Ok((ids, len))
+            }
+            1 => {
+                let (original_count, consumed) = decode_varint(rest)?;
+                let ids = self
+                    .inner
+                    .decompress_set(&rest[consumed..], universe_size)?;
+                if original_count <= ids.len() as u64 {
+                    return Err(CompressionError::DecompressionFailed(format!(
+                        "Truncated set claims {} original IDs but stores {}",
+                        original_count,
+                        ids.len()
+                    )));
+                }
+                Ok((ids, original_count as usize))
+            }
+            _ => Err(CompressionError::DecompressionFailed(format!(
+                "Unknown truncation flag {}",
+                flag
+            ))),
+        }
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor for MaxSizeCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let full = self.inner.compress_set(ids, universe_size)?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        if full.len() < self.max_bytes {
+            let mut encoded = Vec::with_capacity(1 + full.len());
+            encoded.push(0);
+            encoded.extend_from_slice(&full);
+            return Ok(encoded);
+        }
+
+        let mut encoded = vec![1];
+        encode_varint(ids.len() as u64, &mut encoded);
+        let budget = self.max_bytes.saturating_sub(encoded.len());
+
+        // Largest prefix that fits: `lo` always fits, `hi` never does
+        let prefix = |k: usize| self.inner.compress_set(&ids[..k], universe_size);
+        let (mut lo, mut hi) = (0, ids.len());
+        let mut best = prefix(0)?;
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            let candidate = prefix(mid)?;
+            if candidate.len() <= budget {
+                lo = mid;
+                best = candidate;
+            } else {
+                hi = mid;
+            }
+        }
+
+        if encoded.len() + best.len() > self.max_bytes {
+            return Err(CompressionError::CompressionFailed(format!(
+                "Budget of {} bytes cannot hold the header of a truncated set",
+                self.max_bytes
+            )));
+        }
+        encoded.extend_from_slice(&best);
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let (ids, original_count) = self.decompress_prefix(compressed, universe_size)?;
+        if ids.len() < original_count {
+            return Err(CompressionError::Truncated {
+                stored_count: ids.len(),
+                original_count,
+            });
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+        (1 + self.inner.estimate_size(num_ids, universe_size)).min(self.max_bytes)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        self.inner.theoretical_bits_per_id(num_ids, universe_size)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.inner.requires_sorted_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_fitting_set_is_stored_whole() {
+        let compressor = MaxSizeCompressor::new(RocCompressor::new(), 64);
+        let ids = vec![1u32, 2, 3];
+        let compressed = compressor.compress_set(&ids, 10).unwrap();
+        assert_eq!(compressed[0], 0);
+        assert_eq!(compressor.decompress_set(&compressed, 10).unwrap(), ids);
+        assert_eq!(compressor.compress_set(&[], 10).unwrap(), []);
+    }
+
+    #[test]
+    fn test_truncated_prefix_is_longest_that_fits() {
+        // Header: flag, count; then level, count, one byte per ID
+        let compressor = MaxSizeCompressor::new(RocCompressor::new(), 8);
+        let ids: Vec<u32> = (0..10).collect();
+        let compressed = compressor.compress_set(&ids, 10).unwrap();
+        assert_eq!(compressed.len(), 8);
+
+        let (prefix, original_count) = compressor.decompress_prefix(&compressed, 10).unwrap();
+        assert_eq!(prefix, [0, 1, 2, 3]);
+        assert_eq!(original_count, 10);
+        assert_eq!(
+            compressor.decompress_set(&compressed, 10).unwrap_err(),
+            CompressionError::Truncated {
+                stored_count: 4,
+                original_count: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_budget_too_small_for_header() {
+        let compressor = MaxSizeCompressor::new(RocCompressor::new(), 1);
+        assert!(matches!(
+            compressor.compress_set(&[1u32, 2], 10),
+            Err(CompressionError::CompressionFailed(_))
+        ));
+
+        // A bare header keeps no IDs
+        let compressor = MaxSizeCompressor::new(RocCompressor::new(), 2);
+        let compressed = compressor.compress_set(&[1u32, 2], 10).unwrap();
+        assert_eq!(compressed, [1, 2]);
+        assert_eq!(
+            compressor.decompress_prefix(&compressed, 10).unwrap(),
+            (vec![], 2)
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_header() {
+        let compressor = MaxSizeCompressor::new(RocCompressor::new(), 64);
+        assert!(compressor.decompress_set(&[2], 10).is_err());
+        // Claims fewer original IDs than it stores
+        let mut compressed = vec![1, 1];
+        compressed.extend(RocCompressor::new().compress_set(&[1u32, 2], 10).unwrap());
+        assert!(compressor.decompress_prefix(&compressed, 10).is_err());
+    }
+}
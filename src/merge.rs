@@ -0,0 +1,107 @@
+//! Single-pass merge of sorted ID lists into one compressed set.
+//!
+//! Merging inverted-index segments combines many sorted posting lists for
+//! the same term. [`sorted_merge_compress`] heap-merges the inputs and
+//! streams the union into [`IdSetCompressor::compress_sorted_iter`], so no
+//! merged `Vec` is built; with [`RocCompressor`](crate::RocCompressor) the
+//! IDs go straight into a [`CompressedSetBuilder`](crate::CompressedSetBuilder).
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Merge sorted slices and compress their union.
+///
+/// IDs present in several slices are written once. Beyond the output, the
+/// merge holds one `(id, slice)` heap entry and one cursor per slice.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if a slice is not sorted, or any
+/// error from the compressor (e.g. an ID outside the universe).
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{sorted_merge_compress, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let segments: [&[u32]; 3] = [&[1, 5, 9], &[2, 5], &[9, 10]];
+///
+/// let merged = sorted_merge_compress(&roc, &segments, 100).unwrap();
+/// let ids: Vec<u32> = roc.decompress_set(&merged, 100).unwrap();
+/// assert_eq!(ids, vec![1, 2, 5, 9, 10]);
+/// ```
+pub fn sorted_merge_compress<C: IdSetCompressor>(
+    compressor: &C,
+    sorted_slices: &[&[u32]],
+    universe: u32,
+) -> Result<Vec<u8>, CompressionError> {
+    let mut cursors = vec![0usize; sorted_slices.len()];
+    let mut heap: BinaryHeap<Reverse<(u32, usize)>> = sorted_slices
+        .iter()
+        .enumerate()
+        .filter_map(|(i, slice)| slice.first().map(|&id| Reverse((id, i))))
+        .collect();
+
+    let mut last = None;
+    let merged = std::iter::from_fn(|| {
+        while let Some(Reverse((id, i))) = heap.pop() {
+            cursors[i] += 1;
+            if let Some(&next) = sorted_slices[i].get(cursors[i]) {
+                heap.push(Reverse((next, i)));
+            }
+            if last != Some(id) {
+                last = Some(id);
+                return Some(id);
+            }
+        }
+        None
+    });
+
+    // An unsorted slice makes the merged stream decrease, which the
+    // compressor rejects
+    compressor.compress_sorted_iter(merged, universe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HuffmanCompressor, RocCompressor};
+
+    #[test]
+    fn test_merge_with_overlap() {
+        let roc = RocCompressor::new();
+        let slices: [&[u32]; 4] = [&[3, 4, 8], &[], &[1, 3, 9], &[4]];
+
+        let merged = sorted_merge_compress(&roc, &slices, 10).unwrap();
+        assert_eq!(merged, roc.compress_set(&[1u32, 3, 4, 8, 9], 10).unwrap());
+    }
+
+    #[test]
+    fn test_other_compressor() {
+        let huffman = HuffmanCompressor::new();
+        let slices: [&[u32]; 2] = [&[0, 2, 4], &[1, 3, 5]];
+
+        let merged = sorted_merge_compress(&huffman, &slices, 6).unwrap();
+        assert_eq!(
+            huffman.decompress_set(&merged, 6).unwrap(),
+            [0, 1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsorted_slice() {
+        let roc = RocCompressor::new();
+        let slices: [&[u32]; 2] = [&[5, 2], &[3]];
+        assert!(sorted_merge_compress(&roc, &slices, 10).is_err());
+    }
+
+    #[test]
+    fn test_empty() {
+        let roc = RocCompressor::new();
+        assert!(sorted_merge_compress(&roc, &[], 10).unwrap().is_empty());
+    }
+}
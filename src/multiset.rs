@@ -0,0 +1,226 @@
+//! Multiset compression (IDs with occurrence counts).
+//!
+//! Posting lists frequently carry a payload per document, most commonly the
+//! term frequency. A multiset over universe `[N]` is a sorted sequence of
+//! `(id, count)` pairs with unique IDs and `count >= 1`.
+
+use crate::error::CompressionError;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Trait for compressing multisets of IDs, i.e. `(id, count)` pairs.
+///
+/// # Requirements
+///
+/// - Input pairs must be sorted by ID, with unique IDs
+/// - Every count must be at least 1
+/// - Decompression should return pairs sorted by ID
+pub trait MultisetCompressor {
+    /// Compress a multiset of `(id, count)` pairs.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - `(id, count)` pairs sorted by unique ID, counts >= 1
+    /// * `universe` - Maximum possible ID value
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if input is invalid or compression fails.
+    fn compress_multiset(
+        &self,
+        ids: &[(u32, u32)],
+        universe: u32,
+    ) -> Result<Vec<u8>, CompressionError>;
+
+    /// Decompress a multiset of `(id, count)` pairs.
+    ///
+    /// # Arguments
+    ///
+    /// * `compressed` - Compressed byte vector
+    /// * `universe` - Maximum possible ID value (must match compression)
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if decompression fails.
+    fn decompress_multiset(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+    ) -> Result<Vec<(u32, u32)>, CompressionError>;
+}
+
+/// Delta + varint multiset compressor.
+///
+/// Uses the same layout as [`RocCompressor`](crate::RocCompressor) without
+/// the level byte, with the count of each ID varint-encoded immediately
+/// after its delta:
+///
+/// `[len, first_id, count_0, delta_1, count_1, ...]`
+///
+/// Counts are stored as `count - 1` since zero is not a valid count.
+#[derive(Clone, Debug, Default)]
+pub struct RocMultisetCompressor;
+
+impl RocMultisetCompressor {
+    /// Create a new multiset compressor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Validate that IDs are sorted, unique and in range, and counts are non-zero.
+    fn validate(ids: &[(u32, u32)], universe: u32) -> Result<(), CompressionError> {
+        for (i, &(id, count)) in ids.iter().enumerate() {
+            if count == 0 {
+                return Err(CompressionError::InvalidInput(format!(
+                    "Count for ID {} must be non-zero",
+                    id
+                )));
+            }
+            if id >= universe {
+                return Err(CompressionError::InvalidInput(format!(
+                    "ID {} exceeds universe size {}",
+                    id, universe
+                )));
+            }
+            if i > 0 && id <= ids[i - 1].0 {
+                return Err(CompressionError::InvalidInput(format!(
+                    "IDs must be sorted and unique, found {} <= {}",
+                    id,
+                    ids[i - 1].0
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MultisetCompressor for RocMultisetCompressor {
+    fn compress_multiset(
+        &self,
+        ids: &[(u32, u32)],
+        universe: u32,
+    ) -> Result<Vec<u8>, CompressionError> {
+        Self::validate(ids, universe)?;
+
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+
+        let mut prev = 0u32;
+        for (i, &(id, count)) in ids.iter().enumerate() {
+            let delta = if i == 0 { id } else { id - prev };
+            encode_varint(delta as u64, &mut encoded);
+            encode_varint((count - 1) as u64, &mut encoded);
+            prev = id;
+        }
+
+        Ok(encoded)
+    }
+
+    fn decompress_multiset(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+    ) -> Result<Vec<(u32, u32)>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, mut offset) = decode_varint(compressed)?;
+        let mut ids = Vec::new();
+        let mut prev = 0u64;
+
+        for i in 0..num_ids {
+            let (delta, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            let (count, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+
+            if i > 0 && delta == 0 {
+                return Err(CompressionError::DecompressionFailed(
+                    "Zero delta between multiset IDs".to_string(),
+                ));
+            }
+            let id = prev + delta;
+            if id >= universe as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    id, universe
+                )));
+            }
+            let count = count + 1;
+            if count > u32::MAX as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Count {} overflows u32",
+                    count
+                )));
+            }
+
+            ids.push((id as u32, count as u32));
+            prev = id;
+        }
+
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let compressor = RocMultisetCompressor::new();
+        let ids = vec![(1u32, 3u32), (5, 1), (10, 200), (999, 1)];
+
+        let compressed = compressor.compress_multiset(&ids, 1000).unwrap();
+        let decompressed = compressor.decompress_multiset(&compressed, 1000).unwrap();
+
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_empty_multiset() {
+        let compressor = RocMultisetCompressor::new();
+        let compressed = compressor.compress_multiset(&[], 1000).unwrap();
+        assert!(compressed.is_empty());
+        assert!(compressor
+            .decompress_multiset(&[], 1000)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_zero_count_rejected() {
+        let compressor = RocMultisetCompressor::new();
+        assert!(compressor
+            .compress_multiset(&[(1, 1), (2, 0)], 1000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_unsorted_rejected() {
+        let compressor = RocMultisetCompressor::new();
+        assert!(compressor
+            .compress_multiset(&[(5, 1), (2, 1)], 1000)
+            .is_err());
+        assert!(compressor
+            .compress_multiset(&[(5, 1), (5, 2)], 1000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_id_exceeds_universe() {
+        let compressor = RocMultisetCompressor::new();
+        assert!(compressor.compress_multiset(&[(1000, 1)], 1000).is_err());
+    }
+}
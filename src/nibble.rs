@@ -0,0 +1,250 @@
+//! Nibble coding of small gaps.
+//!
+//! After k-means, an IVF cluster tends to hold runs of nearby vectors, so
+//! most gaps in its posting list fall in `1..=14`. [`NibbleCompressor`]
+//! stores each such gap as one 4-bit code, two per byte, and spends the
+//! code `15` as an escape for anything larger. The escaped gaps are kept as
+//! varints after the nibbles, so the nibble stream has a fixed length and
+//! decoding it never tests a continuation bit.
+//!
+//! # Format
+//!
+//! ```text
+//! [count: varint] [first_id: varint]
+//! [nibbles: ceil((count - 1) / 2) bytes]   gap or 15, high nibble first, zero-padded
+//! [escaped gaps: varint * number of 15 nibbles]
+//! ```
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint, varint_len};
+
+/// Nibble marking a gap stored as a varint after the nibble stream.
+const ESCAPE: u8 = 15;
+
+/// Compressor packing gaps of `1..=14` into half a byte each.
+///
+/// # Performance
+///
+/// - Best for: clustered lists where nearly every gap is below 15, at half
+///   the size of a one-byte varint per gap
+/// - Larger gaps cost half a byte more than a plain varint
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, NibbleCompressor};
+///
+/// let nibble = NibbleCompressor::new();
+/// let ids = [3u32, 4, 6, 9, 13, 500];
+/// let compressed = nibble.compress_set(&ids, 1000).unwrap();
+/// // count, first, 3 bytes of nibbles, escaped gap of 487
+/// assert_eq!(compressed.len(), 1 + 1 + 3 + 2);
+/// assert_eq!(nibble.decompress_set(&compressed, 1000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NibbleCompressor;
+
+impl NibbleCompressor {
+    /// Create a new nibble compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for NibbleCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        encode_varint(first as u64, &mut encoded);
+
+        let mut escaped = Vec::new();
+        let codes: Vec<u8> = ids
+            .windows(2)
+            .map(|w| {
+                let gap = w[1] - w[0];
+                if gap < ESCAPE as u32 {
+                    gap as u8
+                } else {
+                    encode_varint(gap as u64, &mut escaped);
+                    ESCAPE
+                }
+            })
+            .collect();
+        encoded.extend(
+            codes
+                .chunks(2)
+                .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)),
+        );
+        encoded.extend(escaped);
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, mut offset) = decode_varint(compressed)?;
+        if num_ids == 0 || num_ids > universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for universe size {}",
+                num_ids, universe_size
+            )));
+        }
+        let (first_id, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+
+        let num_gaps = num_ids as usize - 1;
+        let nibbles = compressed
+            .get(offset..offset + num_gaps.div_ceil(2))
+            .ok_or_else(|| {
+                CompressionError::DecompressionFailed(
+                    "Unexpected end of compressed data".to_string(),
+                )
+            })?;
+        offset += nibbles.len();
+
+        let mut ids = Vec::with_capacity(num_ids as usize);
+        let mut prev = first_id;
+        for i in 0..num_ids as usize {
+            if i > 0 {
+                let byte = nibbles[(i - 1) / 2];
+                let code = if i % 2 == 1 { byte >> 4 } else { byte & 0x0f };
+                let gap = match code {
+                    0 => {
+                        return Err(CompressionError::DecompressionFailed(
+                            "Invalid zero gap".to_string(),
+                        ))
+                    }
+                    ESCAPE => {
+                        let (gap, consumed) = decode_varint(&compressed[offset..])?;
+                        offset += consumed;
+                        // Smaller gaps always fit a nibble, and zero would repeat an ID
+                        if gap < ESCAPE as u64 {
+                            return Err(CompressionError::DecompressionFailed(format!(
+                                "Escaped gap {} fits a nibble",
+                                gap
+                            )));
+                        }
+                        gap
+                    }
+                    _ => code as u64,
+                };
+                prev = prev.saturating_add(gap);
+            }
+            if prev >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev as u32);
+        }
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Evenly spread IDs: all gaps nibbles, or all escaped
+        let avg_gap = (universe_size as u64 / num_ids as u64).max(1);
+        let escaped = if avg_gap < ESCAPE as u64 {
+            0
+        } else {
+            (num_ids - 1) * varint_len(avg_gap)
+        };
+        10 + (num_ids - 1).div_ceil(2) + escaped
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/nibble")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packs_high_nibble_first() {
+        let nibble = NibbleCompressor::new();
+        // Gaps 1, 14, 15 (escaped)
+        let compressed = nibble.compress_set(&[0u32, 1, 15, 30], 100).unwrap();
+        assert_eq!(compressed, [4, 0, 0x1e, 0xf0, 15]);
+        assert_eq!(
+            nibble.decompress_set(&compressed, 100).unwrap(),
+            [0, 1, 15, 30]
+        );
+    }
+
+    #[test]
+    fn test_beats_varint_on_small_gaps() {
+        // 95% of gaps in 1..=14, the rest a few hundred
+        let mut ids = vec![0u32];
+        for i in 0..10_000u32 {
+            let gap = if i % 20 == 0 { 300 } else { 1 + i % 14 };
+            ids.push(ids[ids.len() - 1] + gap);
+        }
+        let universe = ids[ids.len() - 1] + 1;
+
+        let nibble = NibbleCompressor::new()
+            .compress_set(&ids, universe)
+            .unwrap();
+        let roc = RocCompressor::new().compress_set(&ids, universe).unwrap();
+        assert!(
+            nibble.len() * 10 < roc.len() * 6,
+            "nibble {} vs roc {}",
+            nibble.len(),
+            roc.len()
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        let nibble = NibbleCompressor::new();
+        // Zero gap nibble
+        assert!(nibble.decompress_set(&[2, 0, 0x00], 100).is_err());
+        // Escape without its varint
+        assert!(nibble.decompress_set(&[2, 0, 0xf0], 100).is_err());
+        // Escaped gaps that fit a nibble, including a duplicate ID
+        assert!(nibble.decompress_set(&[2, 0, 0xf0, 0], 100).is_err());
+        assert!(nibble.decompress_set(&[2, 0, 0xf0, 3], 100).is_err());
+        // Missing nibble byte, and a trailing byte
+        assert!(nibble.decompress_set(&[3, 0], 100).is_err());
+        assert!(nibble.decompress_set(&[2, 0, 0x10, 0], 100).is_err());
+    }
+}
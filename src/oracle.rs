@@ -0,0 +1,97 @@
+//! Deciding whether a set is worth compressing.
+//!
+//! A [`CompressedSet`](crate::CompressedSet) carries a fixed header, and a
+//! [`RocCompressor`] payload its own level byte and length. For a handful of
+//! IDs that overhead outweighs what delta coding saves, and storing the IDs
+//! as raw little-endian `u32`s is smaller. [`DensityOracle`] makes that call
+//! from the compressed size estimate, without compressing.
+
+use crate::compressed_set::HEADER_LEN;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::varint_len;
+
+/// Bytes per ID stored raw.
+const RAW_ID_BYTES: usize = 4;
+
+/// Compress-or-not decision for [`RocCompressor`] against raw `u32` storage.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::DensityOracle;
+///
+/// assert!(!DensityOracle::should_compress(&[42], 1_000_000));
+///
+/// let ids: Vec<u32> = (0..1000).map(|i| i * 1000).collect();
+/// assert!(DensityOracle::should_compress(&ids, 1_000_000));
+/// assert!(ids.len() >= DensityOracle::breakeven_size(1_000_000));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DensityOracle;
+
+impl DensityOracle {
+    /// Whether a [`CompressedSet`](crate::CompressedSet) of `ids` would be
+    /// smaller than `ids.len() * 4` raw bytes.
+    ///
+    /// `ids` is assumed sorted and unique; the estimate is exact for such
+    /// input.
+    pub fn should_compress(ids: &[u32], universe: u32) -> bool {
+        let compressed = RocCompressor::new().estimate_compressed_size_bytes(ids, universe);
+        HEADER_LEN + compressed < ids.len() * RAW_ID_BYTES
+    }
+
+    /// Smallest set size from `universe` for which compressing pays off.
+    ///
+    /// Assumes evenly spaced IDs `0, g, 2g, ...` with `g = universe / n`, so
+    /// each gap costs `varint_len(g)` bytes; clustered sets break even sooner.
+    /// Returns `usize::MAX` if no set from `universe` is worth compressing.
+    pub fn breakeven_size(universe: u32) -> usize {
+        (1..=universe as usize)
+            .find(|&n| {
+                let gap = (universe as usize / n) as u64;
+                let compressed = 1 + varint_len(n as u64) + 1 + (n - 1) * varint_len(gap);
+                HEADER_LEN + compressed < n * RAW_ID_BYTES
+            })
+            .unwrap_or(usize::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_id_is_not_worth_compressing() {
+        assert!(!DensityOracle::should_compress(&[0], 1));
+        assert!(!DensityOracle::should_compress(&[999_999], 1_000_000));
+        assert!(!DensityOracle::should_compress(&[], 1_000_000));
+    }
+
+    #[test]
+    fn test_large_set_is_worth_compressing() {
+        let ids: Vec<u32> = (0..1000).map(|i| i * 997).collect();
+        assert!(DensityOracle::should_compress(&ids, 1_000_000));
+    }
+
+    #[test]
+    fn test_breakeven_size() {
+        let spread = |n: usize, universe: u32| -> Vec<u32> {
+            let gap = universe / n as u32;
+            (0..n as u32).map(|i| i * gap).collect()
+        };
+        for universe in [100, 1_000_000, u32::MAX] {
+            let n = DensityOracle::breakeven_size(universe);
+            assert!(DensityOracle::should_compress(
+                &spread(n, universe),
+                universe
+            ));
+            assert!(!DensityOracle::should_compress(
+                &spread(n - 1, universe),
+                universe
+            ));
+        }
+        // Header alone outweighs any set from a tiny universe
+        assert_eq!(DensityOracle::breakeven_size(4), usize::MAX);
+    }
+}
@@ -0,0 +1,296 @@
+//! General-purpose compression on top of a set codec.
+//!
+//! Delta + varint spends whole bytes on every gap, so on random-looking sets
+//! its output still has slack that an entropy coder can take out.
+//! [`PostCompressor`] runs Brotli or Zstandard over the bytes of any inner
+//! codec.
+//!
+//! # Format
+//!
+//! ```text
+//! [post: u8] [payload]
+//! ```
+//!
+//! `post` is 1 for Brotli and 2 for Zstandard. When the pass would not make
+//! the bytes smaller, as for small sets, they are stored as-is with `post`
+//! 0, so the wrapper costs at most one byte. The empty set is zero bytes.
+//!
+//! Decompression stops once the pass has produced more bytes than any
+//! inner codec needs for a set in the universe, so a small malicious
+//! payload cannot expand without bound.
+
+use std::io::Read;
+#[cfg(feature = "brotli")]
+use std::io::Write;
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Header of bytes stored without a post-compression pass.
+const STORED: u8 = 0;
+
+/// Brotli quality, 0 to 11.
+#[cfg(feature = "brotli")]
+const BROTLI_QUALITY: u32 = 11;
+
+/// Brotli window of `2^22` bytes, the format's default.
+#[cfg(feature = "brotli")]
+const BROTLI_LGWIN: u32 = 22;
+
+/// Zstandard level, 1 to 22.
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 19;
+
+/// Upper bound on the bytes any codec spends per ID: a full-width
+/// `u64` varint, wider than every gap code in the crate.
+const MAX_BYTES_PER_ID: u64 = 9;
+
+/// Slack for codec headers and padding.
+const MAX_HEADER_BYTES: u64 = 64;
+
+/// General-purpose compressor applied after the set codec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum PostCompression {
+    /// Brotli at quality 11 (`brotli` feature).
+    #[cfg(feature = "brotli")]
+    Brotli = 1,
+    /// Zstandard at level 19 (`zstd` feature).
+    #[cfg(feature = "zstd")]
+    Zstd = 2,
+}
+
+impl PostCompression {
+    fn compress(self, bytes: &[u8]) -> Result<Vec<u8>, CompressionError> {
+        match self {
+            #[cfg(feature = "brotli")]
+            PostCompression::Brotli => {
+                let mut out = Vec::new();
+                let mut writer =
+                    brotli::CompressorWriter::new(&mut out, 4096, BROTLI_QUALITY, BROTLI_LGWIN);
+                writer.write_all(bytes)?;
+                drop(writer);
+                Ok(out)
+            }
+            #[cfg(feature = "zstd")]
+            PostCompression::Zstd => Ok(zstd::bulk::compress(bytes, ZSTD_LEVEL)?),
+        }
+    }
+
+    /// Undo the pass named by header byte `post`, failing once the output
+    /// exceeds `limit` bytes.
+    fn decompress(post: u8, payload: &[u8], limit: u64) -> Result<Vec<u8>, CompressionError> {
+        let failed = |e: std::io::Error| {
+            CompressionError::DecompressionFailed(format!("Post-compression pass: {}", e))
+        };
+        let read_limited = |reader: &mut dyn Read| {
+            let mut out = Vec::new();
+            reader
+                .take(limit.saturating_add(1))
+                .read_to_end(&mut out)
+                .map_err(failed)?;
+            if out.len() as u64 > limit {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Post-compression pass expands past {} bytes",
+                    limit
+                )));
+            }
+            Ok(out)
+        };
+        match post {
+            #[cfg(feature = "brotli")]
+            1 => read_limited(&mut brotli::Decompressor::new(payload, 4096)),
+            #[cfg(feature = "zstd")]
+            2 => read_limited(&mut zstd::stream::read::Decoder::new(payload).map_err(failed)?),
+            #[cfg(not(feature = "brotli"))]
+            1 => Err(CompressionError::DecompressionFailed(
+                "Brotli post-compression needs the `brotli` feature".to_string(),
+            )),
+            #[cfg(not(feature = "zstd"))]
+            2 => Err(CompressionError::DecompressionFailed(
+                "Zstandard post-compression needs the `zstd` feature".to_string(),
+            )),
+            _ => Err(CompressionError::DecompressionFailed(format!(
+                "Unknown post-compression header {}",
+                post
+            ))),
+        }
+    }
+}
+
+/// Wraps a compressor and post-compresses its output.
+///
+/// Worth it for large sets whose gaps are irregular, where the inner codec
+/// leaves redundancy; slower to compress and decompress than the inner
+/// codec alone.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "zstd")]
+/// # {
+/// use cnk::{IdSetCompressor, PostCompression, PostCompressor, RocCompressor};
+///
+/// let compressor = PostCompressor::new(RocCompressor::new(), PostCompression::Zstd);
+/// let ids: Vec<u32> = (0..1000).map(|i| i * 7).collect();
+/// let compressed = compressor.compress_set(&ids, 7000).unwrap();
+/// assert_eq!(compressor.decompress_set(&compressed, 7000).unwrap(), ids);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PostCompressor<C> {
+    inner: C,
+    post: PostCompression,
+}
+
+impl<C: IdSetCompressor> PostCompressor<C> {
+    /// Wrap `inner`, post-compressing its output with `post`.
+    pub fn new(inner: C, post: PostCompression) -> Self {
+        Self { inner, post }
+    }
+
+    /// The wrapped compressor.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// The pass applied to the inner codec's output.
+    pub fn post_compression(&self) -> PostCompression {
+        self.post
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor for PostCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let inner = self.inner.compress_set(ids, universe_size)?;
+        if inner.is_empty() {
+            return Ok(inner);
+        }
+        let packed = self.post.compress(&inner)?;
+        let (post, payload) = if packed.len() < inner.len() {
+            (self.post as u8, packed)
+        } else {
+            (STORED, inner)
+        };
+        let mut out = Vec::with_capacity(1 + payload.len());
+        out.push(post);
+        out.extend_from_slice(&payload);
+        Ok(out)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let Some((&post, payload)) = compressed.split_first() else {
+            return self.inner.decompress_set(compressed, universe_size);
+        };
+        if post == STORED {
+            return self.inner.decompress_set(payload, universe_size);
+        }
+        let limit = MAX_HEADER_BYTES + MAX_BYTES_PER_ID * universe_size as u64;
+        let inner = PostCompression::decompress(post, payload, limit)?;
+        self.inner.decompress_set(&inner, universe_size)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        1 + self.inner.estimate_size(num_ids, universe_size)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        self.inner.theoretical_bits_per_id(num_ids, universe_size)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.inner.requires_sorted_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::sample_ids;
+    use crate::RocCompressor;
+
+    fn passes() -> Vec<PostCompression> {
+        vec![
+            #[cfg(feature = "brotli")]
+            PostCompression::Brotli,
+            #[cfg(feature = "zstd")]
+            PostCompression::Zstd,
+        ]
+    }
+
+    /// 10 000 uniformly random IDs from a universe of `10^8`.
+    fn random_ids() -> Vec<u32> {
+        sample_ids(100_000_000, 10_000, 0x2545_F491_4F6C_DD1D).unwrap()
+    }
+
+    #[test]
+    fn test_random_sets_get_smaller() {
+        let roc = RocCompressor::new();
+        let ids = random_ids();
+        let plain = roc.compress_set(&ids, 100_000_000).unwrap();
+        for post in passes() {
+            let compressor = PostCompressor::new(roc.clone(), post);
+            let compressed = compressor.compress_set(&ids, 100_000_000).unwrap();
+            assert_eq!(compressed[0], post as u8);
+            assert!(
+                compressed.len() < plain.len(),
+                "{:?}: {} bytes, plain {}",
+                post,
+                compressed.len(),
+                plain.len()
+            );
+            assert_eq!(
+                compressor.decompress_set(&compressed, 100_000_000).unwrap(),
+                ids
+            );
+        }
+    }
+
+    #[test]
+    fn test_small_sets_are_stored() {
+        for post in passes() {
+            let compressor = PostCompressor::new(RocCompressor::new(), post);
+            let compressed = compressor.compress_set(&[3, 9], 10).unwrap();
+            assert_eq!(
+                compressed,
+                [
+                    &[STORED][..],
+                    &RocCompressor::new().compress_set(&[3u32, 9], 10).unwrap()
+                ]
+                .concat()
+            );
+            assert_eq!(compressor.decompress_set(&compressed, 10).unwrap(), [3, 9]);
+            assert!(compressor.decompress_set(&[7, 1, 2], 10).is_err());
+            assert!(compressor.decompress_set(&[post as u8, 1, 2], 10).is_err());
+        }
+    }
+
+    #[test]
+    fn test_empty_set_is_zero_bytes() {
+        for post in passes() {
+            let compressor = PostCompressor::new(RocCompressor::new(), post);
+            assert!(compressor.compress_set(&[], 10).unwrap().is_empty());
+            assert!(compressor.decompress_set(&[], 10).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_expansion_is_capped_by_universe() {
+        // A megabyte of zeros packs into a few hundred bytes, far more than
+        // any set in a universe of 1000 needs
+        let bomb = vec![0u8; 1 << 20];
+        for post in passes() {
+            let mut compressed = vec![post as u8];
+            compressed.extend(post.compress(&bomb).unwrap());
+            assert!(compressed.len() < 2048);
+            let err = PostCompressor::new(RocCompressor::new(), post)
+                .decompress_set(&compressed, 1000)
+                .unwrap_err();
+            assert!(err.to_string().contains("expands past"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Serde support for compressed sets, and helpers for `postcard`.
+//!
+//! A [`CompressedSet`] serializes as its container bytes, so the
+//! compressed payload is carried through unchanged: with
+//! [`postcard`](https://crates.io/crates/postcard) that is a varint length
+//! followed by the raw bytes. Deserializing checks the container header as
+//! [`CompressedSet::from_bytes`] does.
+//!
+//! `postcard`'s `MaxSize` needs a bound known at compile time, which a
+//! `CompressedSet` of any universe does not have. [`BoundedCompressedSet`]
+//! supplies one from a const-generic universe limit.
+
+use std::fmt;
+
+use ::postcard::experimental::max_size::MaxSize;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::compressed_set::{CompressedSet, HEADER_LEN};
+use crate::error::CompressionError;
+
+/// Serialize a compressed set with `postcard`.
+///
+/// # Errors
+///
+/// Returns the `postcard` error if serialization fails.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{from_postcard_bytes, to_postcard_bytes, CompressedSet};
+///
+/// let set = CompressedSet::from_ids(&[1, 4, 9]).unwrap();
+/// let bytes = to_postcard_bytes(&set).unwrap();
+/// let back = from_postcard_bytes(&bytes).unwrap();
+/// assert_eq!(back.as_bytes(), set.as_bytes());
+/// ```
+pub fn to_postcard_bytes(cs: &CompressedSet) -> Result<Vec<u8>, ::postcard::Error> {
+    ::postcard::to_allocvec(cs)
+}
+
+/// Deserialize a compressed set written by [`to_postcard_bytes`].
+///
+/// # Errors
+///
+/// Returns the `postcard` error if the bytes are malformed or the container
+/// header is invalid.
+pub fn from_postcard_bytes(bytes: &[u8]) -> Result<CompressedSet, ::postcard::Error> {
+    ::postcard::from_bytes(bytes)
+}
+
+impl Serialize for CompressedSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for CompressedSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserializer.deserialize_byte_buf(BytesVisitor)?;
+        CompressedSet::from_bytes(bytes).map_err(de::Error::custom)
+    }
+}
+
+/// Accepts bytes in any of the forms serde formats produce.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("compressed set bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Bytes of a `postcard` varint holding `n`.
+const fn varint_size(mut n: usize) -> usize {
+    let mut size = 1;
+    while n >= 0x80 {
+        n >>= 7;
+        size += 1;
+    }
+    size
+}
+
+/// A [`CompressedSet`] whose universe is at most `MAX_UNIVERSE`.
+///
+/// The delta-varint payload of a set from a universe of `N` IDs is at most
+/// `N + 6` bytes: a level byte, a length varint of up to 5 bytes, then no
+/// more bytes per ID than its gap. That gives a compile-time bound on the
+/// container size, exposed through `postcard`'s `MaxSize` for callers that
+/// serialize into fixed buffers. Sets from other methods are accepted when
+/// they fit the same bound.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{BoundedCompressedSet, CompressedSet};
+/// use postcard::experimental::max_size::MaxSize;
+///
+/// type SmallSet = BoundedCompressedSet<1024>;
+///
+/// let set = SmallSet::try_from(CompressedSet::from_ids(&[3, 500, 1000]).unwrap()).unwrap();
+/// let mut buf = [0u8; SmallSet::POSTCARD_MAX_SIZE];
+/// let used = postcard::to_slice(&set, &mut buf).unwrap();
+/// let back: SmallSet = postcard::from_bytes(used).unwrap();
+/// assert_eq!(back.as_set().decompress().unwrap(), [3, 500, 1000]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BoundedCompressedSet<const MAX_UNIVERSE: u32> {
+    set: CompressedSet,
+}
+
+impl<const MAX_UNIVERSE: u32> BoundedCompressedSet<MAX_UNIVERSE> {
+    /// Largest container, in bytes, of a bounded set.
+    pub const MAX_BYTES: usize = HEADER_LEN + MAX_UNIVERSE as usize + 6;
+
+    /// The wrapped set.
+    pub fn as_set(&self) -> &CompressedSet {
+        &self.set
+    }
+
+    /// Unwrap the set.
+    pub fn into_inner(self) -> CompressedSet {
+        self.set
+    }
+}
+
+impl<const MAX_UNIVERSE: u32> TryFrom<CompressedSet> for BoundedCompressedSet<MAX_UNIVERSE> {
+    type Error = CompressionError;
+
+    /// Wrap `set` if its universe and size are within the bound.
+    fn try_from(set: CompressedSet) -> Result<Self, Self::Error> {
+        if set.universe() > MAX_UNIVERSE {
+            return Err(CompressionError::InvalidInput(format!(
+                "Universe {} exceeds bound {}",
+                set.universe(),
+                MAX_UNIVERSE
+            )));
+        }
+        if set.as_bytes().len() > Self::MAX_BYTES {
+            return Err(CompressionError::InvalidInput(format!(
+                "Compressed set of {} bytes exceeds bound of {} bytes",
+                set.as_bytes().len(),
+                Self::MAX_BYTES
+            )));
+        }
+        Ok(Self { set })
+    }
+}
+
+impl<const MAX_UNIVERSE: u32> MaxSize for BoundedCompressedSet<MAX_UNIVERSE> {
+    const POSTCARD_MAX_SIZE: usize = varint_size(Self::MAX_BYTES) + Self::MAX_BYTES;
+}
+
+impl<const MAX_UNIVERSE: u32> Serialize for BoundedCompressedSet<MAX_UNIVERSE> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.set.serialize(serializer)
+    }
+}
+
+impl<'de, const MAX_UNIVERSE: u32> Deserialize<'de> for BoundedCompressedSet<MAX_UNIVERSE> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let set = CompressedSet::deserialize(deserializer)?;
+        Self::try_from(set).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HuffmanCompressor, RocCompressor};
+
+    #[test]
+    fn test_round_trip_preserves_bytes() {
+        for c in [
+            &RocCompressor::new() as &dyn crate::IdSetCompressor,
+            &HuffmanCompressor::new(),
+        ] {
+            let set = CompressedSet::new(&[0, 7, 8, 9, 4000], 5000, c).unwrap();
+            let bytes = to_postcard_bytes(&set).unwrap();
+
+            // A varint length, then the container verbatim
+            assert_eq!(bytes[0] as usize, set.as_bytes().len());
+            assert_eq!(&bytes[1..], set.as_bytes());
+            assert_eq!(
+                from_postcard_bytes(&bytes).unwrap().as_bytes(),
+                set.as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_header() {
+        let bytes = ::postcard::to_allocvec(&vec![1u8, 2, 3]).unwrap();
+        assert!(from_postcard_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_bounded_set_worst_case_fits() {
+        type Bounded = BoundedCompressedSet<300>;
+
+        // Every ID present: one byte per gap
+        let ids: Vec<u32> = (0..300).collect();
+        let set = CompressedSet::new(&ids, 300, &RocCompressor::new()).unwrap();
+        let bounded = Bounded::try_from(set).unwrap();
+        assert!(to_postcard_bytes(bounded.as_set()).unwrap().len() <= Bounded::POSTCARD_MAX_SIZE);
+
+        let too_big = CompressedSet::new(&[1], 301, &RocCompressor::new()).unwrap();
+        assert!(Bounded::try_from(too_big).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Posting list cursors for dynamic pruning.
+//!
+//! Top-k query algorithms such as WAND (Broder et al., 2003) move one cursor
+//! per query term over its posting list, repeatedly jumping every cursor to
+//! a pivot document. [`PeekableCompressedSet`] is such a cursor over a
+//! [`RocCompressor`](crate::RocCompressor) set, decoding lazily through
+//! [`DecompressIter`].
+//!
+//! # References
+//!
+//! - Broder, A. Z., Carmel, D., Herscovici, M., Soffer, A. & Zien, J. (2003).
+//!   "Efficient query evaluation using a two-level retrieval process"
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::error::CompressionError;
+use crate::iter::DecompressIter;
+
+/// Cursor over a compressed set, positioned on its current ID.
+///
+/// A malformed set ends the cursor early; the error is kept and available
+/// from [`error`](Self::error).
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, PeekableCompressedSet, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let compressed = roc.compress_set(&[2u32, 3, 5, 7, 11], 100).unwrap();
+/// let mut cursor = PeekableCompressedSet::new(roc.iter(&compressed, 100).unwrap());
+///
+/// assert_eq!(cursor.current(), Some(2));
+/// assert_eq!(cursor.advance(), Some(3));
+/// assert_eq!(cursor.advance_past(5), Some(7));
+/// assert_eq!(cursor.advance_past(11), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PeekableCompressedSet<'a> {
+    iter: DecompressIter<'a>,
+    /// Lookahead: the ID the cursor is on, `None` once exhausted.
+    current: Option<u32>,
+    error: Option<CompressionError>,
+}
+
+impl<'a> PeekableCompressedSet<'a> {
+    /// Wrap `iter`, positioning the cursor on its first ID.
+    pub fn new(iter: DecompressIter<'a>) -> Self {
+        let mut cursor = Self {
+            iter,
+            current: None,
+            error: None,
+        };
+        cursor.current = cursor.pull();
+        cursor
+    }
+
+    /// Decode the next ID, recording the first error.
+    fn pull(&mut self) -> Option<u32> {
+        match self.iter.next()? {
+            Ok(id) => Some(id),
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    /// The ID the cursor is on, or `None` once the set is exhausted.
+    pub fn current(&self) -> Option<u32> {
+        self.current
+    }
+
+    /// Move to the next ID and return it.
+    pub fn advance(&mut self) -> Option<u32> {
+        if self.current.is_some() {
+            self.current = self.pull();
+        }
+        self.current
+    }
+
+    /// Skip every ID `<= threshold` and return the first ID `> threshold`.
+    ///
+    /// The cursor does not move if it is already past `threshold`.
+    pub fn advance_past(&mut self, threshold: u32) -> Option<u32> {
+        while let Some(id) = self.current {
+            if id > threshold {
+                break;
+            }
+            self.current = self.pull();
+        }
+        self.current
+    }
+
+    /// The error that ended the cursor, if the set was malformed.
+    pub fn error(&self) -> Option<&CompressionError> {
+        self.error.as_ref()
+    }
+}
+
+/// Score of a document; a stub until term weights are available.
+fn score(id: u32) -> u32 {
+    id
+}
+
+/// Top-`k` documents matching every list, best score first, by WAND.
+///
+/// Each document scores its own ID, a stub for real term weights. With
+/// equal per-list upper bounds the WAND pivot is the largest current ID,
+/// and a document can only reach the threshold if every list contains it,
+/// so the cursors leapfrog with [`advance_past`](PeekableCompressedSet::advance_past)
+/// and only candidates that beat the current `k`-th best enter the heap.
+/// Lists that end with an error are treated as exhausted.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{wand_intersect, IdSetCompressor, PeekableCompressedSet, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let a = roc.compress_set(&[1u32, 3, 5, 7, 9], 100).unwrap();
+/// let b = roc.compress_set(&[3u32, 4, 5, 9, 10], 100).unwrap();
+/// let lists = vec![
+///     PeekableCompressedSet::new(roc.iter(&a, 100).unwrap()),
+///     PeekableCompressedSet::new(roc.iter(&b, 100).unwrap()),
+/// ];
+/// assert_eq!(wand_intersect(lists, 2), [9, 5]);
+/// ```
+pub fn wand_intersect(mut lists: Vec<PeekableCompressedSet<'_>>, k: usize) -> Vec<u32> {
+    if lists.is_empty() || k == 0 {
+        return Vec::new();
+    }
+
+    // Min-heap of the k best (score, id) pairs seen so far
+    let mut top: BinaryHeap<Reverse<(u32, u32)>> = BinaryHeap::with_capacity(k + 1);
+    'pivots: loop {
+        let mut pivot = 0;
+        for list in &lists {
+            match list.current() {
+                Some(id) => pivot = pivot.max(id),
+                None => break 'pivots,
+            }
+        }
+
+        // Move every cursor to the pivot or beyond
+        let mut aligned = true;
+        for list in &mut lists {
+            let target = match pivot.checked_sub(1) {
+                Some(before) => list.advance_past(before),
+                None => list.current(),
+            };
+            match target {
+                Some(id) if id == pivot => {}
+                Some(_) => aligned = false,
+                None => break 'pivots,
+            }
+        }
+        if !aligned {
+            continue;
+        }
+
+        let candidate = (score(pivot), pivot);
+        let threshold = match top.peek() {
+            Some(&Reverse(min)) if top.len() == k => Some(min),
+            _ => None,
+        };
+        match threshold {
+            Some(min) if candidate <= min => {}
+            _ => {
+                top.push(Reverse(candidate));
+                if top.len() > k {
+                    top.pop();
+                }
+            }
+        }
+        for list in &mut lists {
+            list.advance();
+        }
+    }
+
+    let mut best: Vec<(u32, u32)> = top.into_iter().map(|Reverse(entry)| entry).collect();
+    best.sort_unstable_by(|a, b| b.cmp(a));
+    best.into_iter().map(|(_, id)| id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdSetCompressor, RocCompressor};
+
+    fn cursor<'a>(roc: &RocCompressor, compressed: &'a [u8]) -> PeekableCompressedSet<'a> {
+        PeekableCompressedSet::new(roc.iter(compressed, 1000).unwrap())
+    }
+
+    #[test]
+    fn test_cursor_moves_and_stops() {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&[0u32, 10, 20], 1000).unwrap();
+        let mut c = cursor(&roc, &compressed);
+
+        assert_eq!(c.advance_past(0), Some(10));
+        assert_eq!(c.advance_past(5), Some(10));
+        assert_eq!(c.advance(), Some(20));
+        assert_eq!(c.advance(), None);
+        assert_eq!(c.advance(), None);
+        assert_eq!(c.current(), None);
+        assert!(c.error().is_none());
+
+        let empty = cursor(&roc, &[]);
+        assert_eq!(empty.current(), None);
+    }
+
+    #[test]
+    fn test_cursor_keeps_error() {
+        let roc = RocCompressor::new();
+        // Header claims three IDs but only one follows
+        let compressed = [5, 3, 4];
+        let mut c = cursor(&roc, &compressed);
+        assert_eq!(c.current(), Some(4));
+        assert_eq!(c.advance(), None);
+        assert!(c.error().is_some());
+    }
+
+    #[test]
+    fn test_wand_intersect_edge_cases() {
+        let roc = RocCompressor::new();
+        let a = roc.compress_set(&[0u32, 1, 2, 3], 1000).unwrap();
+        let b = roc.compress_set(&[0u32, 2, 3, 999], 1000).unwrap();
+        let lists = || vec![cursor(&roc, &a), cursor(&roc, &b)];
+
+        assert_eq!(wand_intersect(lists(), 10), [3, 2, 0]);
+        assert_eq!(wand_intersect(lists(), 1), [3]);
+        assert!(wand_intersect(lists(), 0).is_empty());
+        assert!(wand_intersect(Vec::new(), 3).is_empty());
+        assert!(wand_intersect(vec![cursor(&roc, &a), cursor(&roc, &[])], 3).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! Access by position into a delta-varint compressed set.
+//!
+//! Each ID of a delta-encoded set depends on every gap before it, so the
+//! `k`-th ID normally costs decoding `k` varints. [`RandomAccessCompressedSet`]
+//! keeps every `skip_interval`-th ID in memory with the byte offset of the
+//! gap after it, so `get(k)` starts from the checkpoint at or before `k`
+//! and decodes fewer than `skip_interval` gaps. Like
+//! [`SampledIndex`](crate::SampledIndex), which samples the same way to
+//! search by value, it leaves the compressed bytes unchanged.
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::sampled::sample_stream;
+use crate::traits::IdSetCompressor;
+use crate::transcode::recompress;
+use crate::varint::decode_varint;
+
+/// A delta-varint compressed set with checkpoints for access by index.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RandomAccessCompressedSet, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let ids: Vec<u32> = (0..1000).map(|i| i * 5).collect();
+/// let compressed = roc.compress_set(&ids, 5000).unwrap();
+///
+/// let set = RandomAccessCompressedSet::build(&compressed, 5000, 64, &roc).unwrap();
+/// assert_eq!(set.get(501).unwrap(), Some(2505));
+/// assert_eq!(set.get(1000).unwrap(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RandomAccessCompressedSet {
+    /// Set in the [`RocCompressor`] format.
+    compressed: Vec<u8>,
+    skip_interval: usize,
+    len: usize,
+    /// The ID at each multiple of `skip_interval` and the byte offset of
+    /// the gap after it.
+    checkpoints: Vec<(u32, usize)>,
+}
+
+impl RandomAccessCompressedSet {
+    /// Copy `compressed` and checkpoint every `skip_interval`-th ID.
+    ///
+    /// Sets not already in the [`RocCompressor`] format are transcoded to
+    /// it, since checkpoints need byte-addressable gaps.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if `compressed` cannot be decoded by `c`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `skip_interval` is zero.
+    pub fn build(
+        compressed: &[u8],
+        universe: u32,
+        skip_interval: usize,
+        c: &dyn IdSetCompressor,
+    ) -> Result<Self, CompressionError> {
+        assert!(skip_interval > 0, "skip_interval must be non-zero");
+
+        let roc = RocCompressor::new();
+        let compressed = if c.format_id() == IdSetCompressor::<u32>::format_id(&roc) {
+            compressed.to_vec()
+        } else {
+            recompress(compressed, universe, c, &roc)?
+        };
+        let (checkpoints, len) = sample_stream(&compressed, universe, skip_interval)?;
+        Ok(Self {
+            compressed,
+            skip_interval,
+            len,
+            checkpoints,
+        })
+    }
+
+    /// The `k`-th smallest ID, or `None` if `k >= self.len()`.
+    ///
+    /// Jumps to checkpoint `k / skip_interval` and decodes the
+    /// `k % skip_interval` gaps after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the decoded gaps are malformed.
+    pub fn get(&self, k: usize) -> Result<Option<u32>, CompressionError> {
+        if k >= self.len {
+            return Ok(None);
+        }
+        let (mut id, mut offset) = self.checkpoints[k / self.skip_interval];
+        for _ in 0..k % self.skip_interval {
+            let (delta, consumed) = decode_varint(&self.compressed[offset..])?;
+            offset += consumed;
+            id += delta as u32;
+        }
+        Ok(Some(id))
+    }
+
+    /// Number of IDs in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of IDs between consecutive checkpoints.
+    pub fn skip_interval(&self) -> usize {
+        self.skip_interval
+    }
+
+    /// The set in the [`RocCompressor`] format.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.compressed
+    }
+
+    /// Drop the checkpoints and return the compressed bytes.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.compressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EliasFanoCompressor;
+
+    #[test]
+    fn test_get_matches_decompressed() {
+        let roc = RocCompressor::new();
+        let mut ids: Vec<u32> = (0..1000u32).map(|i| i * i % 100_003).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let compressed = roc.compress_set(&ids, 100_003).unwrap();
+
+        for skip_interval in [1, 7, 64, 5000] {
+            let set = RandomAccessCompressedSet::build(&compressed, 100_003, skip_interval, &roc)
+                .unwrap();
+            assert_eq!(set.len(), ids.len());
+            for (k, &id) in ids.iter().enumerate() {
+                assert_eq!(set.get(k).unwrap(), Some(id), "k = {}", k);
+            }
+            assert_eq!(set.get(ids.len()).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_transcodes_other_formats() {
+        let ef = EliasFanoCompressor::new();
+        let ids = vec![3u32, 4, 5, 100, 101, 900];
+        let compressed = ef.compress_set(&ids, 1000).unwrap();
+
+        let set = RandomAccessCompressedSet::build(&compressed, 1000, 4, &ef).unwrap();
+        assert_eq!(
+            set.as_bytes(),
+            RocCompressor::new().compress_set(&ids, 1000).unwrap()
+        );
+        assert_eq!(set.get(4).unwrap(), Some(101));
+    }
+
+    #[test]
+    fn test_empty_and_corrupt() {
+        let roc = RocCompressor::new();
+        let set = RandomAccessCompressedSet::build(&[], 10, 4, &roc).unwrap();
+        assert!(set.is_empty());
+        assert_eq!(set.get(0).unwrap(), None);
+
+        let mut compressed = roc.compress_set(&[1u32, 2, 3], 10).unwrap();
+        compressed.push(0);
+        assert!(RandomAccessCompressedSet::build(&compressed, 10, 4, &roc).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Operation log for debugging and auditing compressors.
+//!
+//! [`RecordingCompressor`] forwards every call to an inner compressor and
+//! appends a [`CompressionRecord`] for each successful compression or
+//! decompression, so a slow or oversized posting list can be traced back to
+//! the call that produced it.
+
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Kind of operation recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// A call to [`IdSetCompressor::compress_set`].
+    Compress,
+    /// A call to [`IdSetCompressor::decompress_set`].
+    Decompress,
+}
+
+/// One recorded compression or decompression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressionRecord {
+    /// Wall-clock start time, in nanoseconds since the Unix epoch.
+    pub timestamp_ns: u64,
+    /// Number of IDs compressed or produced.
+    pub num_ids: usize,
+    /// Universe size passed to the call.
+    pub universe: u32,
+    /// Size of the compressed representation.
+    pub compressed_bytes: usize,
+    /// Time spent in the inner compressor.
+    pub duration_ns: u64,
+    /// Whether this was a compression or a decompression.
+    pub operation: Operation,
+}
+
+/// Aggregate statistics over recorded operations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecordingSummary {
+    /// Number of recorded compressions.
+    pub total_compress_ops: usize,
+    /// Number of recorded decompressions.
+    pub total_decompress_ops: usize,
+    /// Smallest compressed size seen, or 0 if nothing was recorded.
+    pub min_compressed_bytes: usize,
+    /// Largest compressed size seen, or 0 if nothing was recorded.
+    pub max_compressed_bytes: usize,
+    /// Mean compressed size, or 0.0 if nothing was recorded.
+    pub avg_compressed_bytes: f64,
+}
+
+/// Wraps a compressor and records every compression and decompression.
+///
+/// Failed calls are passed through but not recorded.
+///
+/// # Thread safety
+///
+/// Unlike the other compressors this type has interior mutability: the log
+/// sits behind a `Mutex`, so concurrent calls through a shared reference
+/// are safe but serialize briefly on each append. It is `Send + Sync` when
+/// `C` is, but not `Clone`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RecordingCompressor, RocCompressor};
+///
+/// let mut compressor = RecordingCompressor::new(RocCompressor::new());
+/// let compressed = compressor.compress_set(&[1, 2, 3], 10).unwrap();
+/// compressor.decompress_set(&compressed, 10).unwrap();
+///
+/// let summary = compressor.summary();
+/// assert_eq!(summary.total_compress_ops, 1);
+/// assert_eq!(summary.total_decompress_ops, 1);
+/// assert_eq!(compressor.drain_records().len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct RecordingCompressor<C> {
+    inner: C,
+    records: Mutex<Vec<CompressionRecord>>,
+}
+
+impl<C: IdSetCompressor> RecordingCompressor<C> {
+    /// Wrap `inner` with an empty log.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The wrapped compressor.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Take all records, leaving the log empty.
+    pub fn drain_records(&mut self) -> Vec<CompressionRecord> {
+        std::mem::take(self.records_mut())
+    }
+
+    /// Aggregate statistics over the current log.
+    pub fn summary(&self) -> RecordingSummary {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.is_empty() {
+            return RecordingSummary::default();
+        }
+
+        let sizes = records.iter().map(|r| r.compressed_bytes);
+        RecordingSummary {
+            total_compress_ops: records
+                .iter()
+                .filter(|r| r.operation == Operation::Compress)
+                .count(),
+            total_decompress_ops: records
+                .iter()
+                .filter(|r| r.operation == Operation::Decompress)
+                .count(),
+            min_compressed_bytes: sizes.clone().min().unwrap_or(0),
+            max_compressed_bytes: sizes.clone().max().unwrap_or(0),
+            avg_compressed_bytes: sizes.sum::<usize>() as f64 / records.len() as f64,
+        }
+    }
+
+    fn records_mut(&mut self) -> &mut Vec<CompressionRecord> {
+        self.records.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Time `op` and append a record if it succeeds.
+    fn record<R>(
+        &self,
+        operation: Operation,
+        universe: u32,
+        op: impl FnOnce() -> Result<R, CompressionError>,
+        sizes: impl FnOnce(&R) -> (usize, usize),
+    ) -> Result<R, CompressionError> {
+        let timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let start = Instant::now();
+        let result = op()?;
+        let duration_ns = start.elapsed().as_nanos() as u64;
+
+        let (num_ids, compressed_bytes) = sizes(&result);
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(CompressionRecord {
+                timestamp_ns,
+                num_ids,
+                universe,
+                compressed_bytes,
+                duration_ns,
+                operation,
+            });
+        Ok(result)
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor for RecordingCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        self.record(
+            Operation::Compress,
+            universe_size,
+            || self.inner.compress_set(ids, universe_size),
+            |compressed| (ids.len(), compressed.len()),
+        )
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        self.record(
+            Operation::Decompress,
+            universe_size,
+            || self.inner.decompress_set(compressed, universe_size),
+            |ids| (ids.len(), compressed.len()),
+        )
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        self.inner.estimate_size(num_ids, universe_size)
+    }
+
+    fn estimate_compressed_size_bytes(&self, ids: &[u32], universe_size: u32) -> usize {
+        self.inner
+            .estimate_compressed_size_bytes(ids, universe_size)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        self.inner.theoretical_bits_per_id(num_ids, universe_size)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.inner.requires_sorted_input()
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        self.inner.format_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_counts_100_compressions() {
+        let compressor = RecordingCompressor::new(RocCompressor::new());
+        for i in 0..100u32 {
+            let ids: Vec<u32> = (0..i).collect();
+            compressor.compress_set(&ids, 1000).unwrap();
+        }
+
+        let summary = compressor.summary();
+        assert_eq!(summary.total_compress_ops, 100);
+        assert_eq!(summary.total_decompress_ops, 0);
+        assert_eq!(summary.min_compressed_bytes, 0);
+        assert!(summary.max_compressed_bytes > summary.min_compressed_bytes);
+    }
+
+    #[test]
+    fn test_records_fields_and_drains() {
+        let mut compressor = RecordingCompressor::new(RocCompressor::new());
+        let compressed = compressor.compress_set(&[5, 9], 10).unwrap();
+        compressor.decompress_set(&compressed, 10).unwrap();
+        assert!(compressor.compress_set(&[9, 5], 10).is_err());
+
+        let records = compressor.drain_records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].operation, Operation::Compress);
+        assert_eq!(records[1].operation, Operation::Decompress);
+        for record in &records {
+            assert_eq!(record.num_ids, 2);
+            assert_eq!(record.universe, 10);
+            assert_eq!(record.compressed_bytes, compressed.len());
+        }
+        assert_eq!(compressor.summary(), RecordingSummary::default());
+    }
+}
@@ -0,0 +1,172 @@
+//! Self-describing compressed sets.
+//!
+//! A compressed set on disk is useless without knowing the codec that wrote
+//! it. [`CompressorRegistry`] maps a tag byte to a compressor and writes the
+//! tag in front of each set, so the reader picks the codec from the data.
+//! The built-in tags are the [`IdCompressionMethod`] discriminants.
+//!
+//! Unlike [`CompressedSet`](crate::CompressedSet), tagged data carries no
+//! magic, universe or checksum: one byte is the whole overhead.
+
+use std::collections::HashMap;
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+use crate::{
+    BitmapSetCompressor, EliasFanoCompressor, IdCompressionMethod, InterpolativeCompressor,
+    RocCompressor,
+};
+
+/// Compressors keyed by the tag byte that prefixes their output.
+///
+/// The default registry holds the crate's main codecs:
+///
+/// | Tag | Method | Compressor |
+/// |-----|--------|------------|
+/// | 0 | [`IdCompressionMethod::Roc`] | [`RocCompressor`] |
+/// | 1 | [`IdCompressionMethod::EliasFano`] | [`EliasFanoCompressor`] |
+/// | 2 | [`IdCompressionMethod::Bitset`] | [`BitmapSetCompressor`] |
+/// | 3 | [`IdCompressionMethod::Interpolative`] | [`InterpolativeCompressor`] |
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressorRegistry, IdCompressionMethod};
+///
+/// let registry = CompressorRegistry::default();
+/// let tagged = registry
+///     .compress_tagged(IdCompressionMethod::EliasFano as u8, &[3, 9, 27], 100)
+///     .unwrap();
+/// assert_eq!(tagged[0], 1);
+/// // The reader needs no idea of the codec
+/// assert_eq!(registry.decompress_tagged(&tagged, 100).unwrap(), [3, 9, 27]);
+/// ```
+pub struct CompressorRegistry {
+    compressors: HashMap<u8, Box<dyn IdSetCompressor>>,
+}
+
+impl Default for CompressorRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(
+            IdCompressionMethod::Roc as u8,
+            Box::new(RocCompressor::new()),
+        );
+        registry.register(
+            IdCompressionMethod::EliasFano as u8,
+            Box::new(EliasFanoCompressor::new()),
+        );
+        registry.register(
+            IdCompressionMethod::Bitset as u8,
+            Box::new(BitmapSetCompressor::new()),
+        );
+        registry.register(
+            IdCompressionMethod::Interpolative as u8,
+            Box::new(InterpolativeCompressor::new()),
+        );
+        registry
+    }
+}
+
+impl CompressorRegistry {
+    /// A registry with no compressors.
+    pub fn empty() -> Self {
+        Self {
+            compressors: HashMap::new(),
+        }
+    }
+
+    /// Use `compressor` for `tag`, replacing any compressor already there.
+    ///
+    /// Custom codecs should pick tags from 128 up, clear of the
+    /// [`IdCompressionMethod`] discriminants.
+    pub fn register(&mut self, tag: u8, compressor: Box<dyn IdSetCompressor>) {
+        self.compressors.insert(tag, compressor);
+    }
+
+    /// The compressor registered for `tag`.
+    pub fn get(&self, tag: u8) -> Option<&dyn IdSetCompressor> {
+        self.compressors.get(&tag).map(|c| c.as_ref())
+    }
+
+    /// Compress `ids` with the compressor for `tag`, prefixed by the tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if no compressor is
+    /// registered for `tag`, and any error from the compressor.
+    pub fn compress_tagged(
+        &self,
+        tag: u8,
+        ids: &[u32],
+        universe: u32,
+    ) -> Result<Vec<u8>, CompressionError> {
+        let compressor = self.get(tag).ok_or_else(|| {
+            CompressionError::InvalidInput(format!("No compressor registered for tag {}", tag))
+        })?;
+        let compressed = compressor.compress_set(ids, universe)?;
+        let mut tagged = Vec::with_capacity(1 + compressed.len());
+        tagged.push(tag);
+        tagged.extend_from_slice(&compressed);
+        Ok(tagged)
+    }
+
+    /// Decompress a set written by [`compress_tagged`](Self::compress_tagged),
+    /// with the compressor its tag names.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` if `compressed` is
+    /// empty or its tag is not registered, and any error from the compressor.
+    pub fn decompress_tagged(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let (&tag, payload) = compressed.split_first().ok_or_else(|| {
+            CompressionError::DecompressionFailed("Missing compressor tag".to_string())
+        })?;
+        let compressor = self.get(tag).ok_or_else(|| {
+            CompressionError::DecompressionFailed(format!("Unknown compressor tag {}", tag))
+        })?;
+        compressor.decompress_set(payload, universe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NibbleCompressor;
+
+    #[test]
+    fn test_default_tags_round_trip() {
+        let registry = CompressorRegistry::default();
+        let ids = [0u32, 7, 8, 9, 500, 999];
+        for method in [
+            IdCompressionMethod::Roc,
+            IdCompressionMethod::EliasFano,
+            IdCompressionMethod::Bitset,
+            IdCompressionMethod::Interpolative,
+        ] {
+            let tag = method.clone() as u8;
+            let tagged = registry.compress_tagged(tag, &ids, 1000).unwrap();
+            assert_eq!(tagged[0], tag, "{:?}", method);
+            assert_eq!(registry.decompress_tagged(&tagged, 1000).unwrap(), ids);
+        }
+        assert_eq!(IdCompressionMethod::Roc as u8, 0);
+        assert_eq!(IdCompressionMethod::Interpolative as u8, 3);
+    }
+
+    #[test]
+    fn test_unknown_tags_and_custom_codecs() {
+        let mut registry = CompressorRegistry::default();
+        assert!(registry.compress_tagged(200, &[1], 10).is_err());
+        assert!(registry.decompress_tagged(&[200, 1, 1], 10).is_err());
+        assert!(registry.decompress_tagged(&[], 10).is_err());
+
+        registry.register(200, Box::new(NibbleCompressor::new()));
+        let tagged = registry.compress_tagged(200, &[1, 2, 3], 10).unwrap();
+        assert_eq!(registry.decompress_tagged(&tagged, 10).unwrap(), [1, 2, 3]);
+        assert!(CompressorRegistry::empty().get(0).is_none());
+    }
+}
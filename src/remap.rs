@@ -0,0 +1,181 @@
+//! Compression over a sparse space of valid IDs.
+//!
+//! ANN libraries often leave holes in their ID space: deleted vectors keep
+//! their IDs, and shards hand out IDs from disjoint ranges. Codecs sized by
+//! the universe then pay for IDs that can never occur. [`RemappingCompressor`]
+//! replaces each ID by its rank among the valid IDs, so the inner compressor
+//! sees the dense universe `[0, valid_ids.len())`.
+//!
+//! # Format
+//!
+//! The inner compressor's output for the ranks, with nothing added. The
+//! valid IDs are not stored: decompress with a compressor built from the
+//! same list.
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+
+/// Wraps a compressor so IDs are stored as ranks among a set of valid IDs.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{BitmapSetCompressor, IdSetCompressor, RemappingCompressor};
+///
+/// // 256 valid IDs spread over millions: a bitset over their ranks takes 32 bytes
+/// let valid_ids: Vec<u32> = (0..256).map(|i| i * 40_000 + 7).collect();
+/// let compressor = RemappingCompressor::new(BitmapSetCompressor::new(), &valid_ids).unwrap();
+///
+/// let ids: Vec<u32> = valid_ids.iter().copied().step_by(3).collect();
+/// let compressed = compressor.compress_set(&ids, 10_240_000).unwrap();
+/// assert!(compressed.len() <= 33);
+/// assert_eq!(compressor.decompress_set(&compressed, 10_240_000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RemappingCompressor<C> {
+    inner: C,
+    valid_ids: Vec<u32>,
+}
+
+impl<C: IdSetCompressor> RemappingCompressor<C> {
+    /// Wrap `inner`, storing IDs as their rank in `valid_ids`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `valid_ids` is not sorted
+    /// and unique, or has more than `u32::MAX` IDs.
+    pub fn new(inner: C, valid_ids: &[u32]) -> Result<Self, CompressionError> {
+        RocCompressor::validate_ids(valid_ids)?;
+        if valid_ids.len() > u32::MAX as usize {
+            return Err(CompressionError::InvalidInput(
+                "More than u32::MAX valid IDs".to_string(),
+            ));
+        }
+        Ok(Self {
+            inner,
+            valid_ids: valid_ids.to_vec(),
+        })
+    }
+
+    /// The IDs this compressor accepts, sorted.
+    pub fn valid_ids(&self) -> &[u32] {
+        &self.valid_ids
+    }
+
+    /// The wrapped compressor.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Universe seen by the inner compressor.
+    fn inner_universe(&self) -> u32 {
+        self.valid_ids.len() as u32
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor for RemappingCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let ranks = ids
+            .iter()
+            .map(|&id| {
+                if id >= universe_size {
+                    return Err(CompressionError::InvalidInput(format!(
+                        "ID {} exceeds universe size {}",
+                        id, universe_size
+                    )));
+                }
+                let rank = self.valid_ids.binary_search(&id).map_err(|_| {
+                    CompressionError::InvalidInput(format!("ID {} is not a valid ID", id))
+                })?;
+                Ok(rank as u32)
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+        self.inner.compress_set(&ranks, self.inner_universe())
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let ranks = self
+            .inner
+            .decompress_set(compressed, self.inner_universe())?;
+        ranks
+            .into_iter()
+            .map(|rank| match self.valid_ids.get(rank as usize) {
+                Some(&id) if id < universe_size => Ok(id),
+                _ => Err(CompressionError::DecompressionFailed(format!(
+                    "Rank {} maps to no valid ID below universe size {}",
+                    rank, universe_size
+                ))),
+            })
+            .collect()
+    }
+
+    fn estimate_size(&self, num_ids: usize, _universe_size: u32) -> usize {
+        self.inner.estimate_size(num_ids, self.inner_universe())
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, _universe_size: u32) -> f64 {
+        self.inner
+            .theoretical_bits_per_id(num_ids, self.inner_universe())
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.inner.requires_sorted_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitmapSetCompressor, EliasFanoCompressor};
+
+    #[test]
+    fn test_round_trip_over_holes() {
+        // Every third ID deleted
+        let valid_ids: Vec<u32> = (0..3000).filter(|i| i % 3 != 2).collect();
+        let compressor = RemappingCompressor::new(RocCompressor::new(), &valid_ids).unwrap();
+        let ids: Vec<u32> = valid_ids.iter().copied().step_by(5).collect();
+
+        let compressed = compressor.compress_set(&ids, 3000).unwrap();
+        assert_eq!(compressor.decompress_set(&compressed, 3000).unwrap(), ids);
+        assert!(compressor.compress_set(&[], 3000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dense_ranks_shrink_universe_sized_codecs() {
+        let valid_ids: Vec<u32> = (0..256).map(|i| i * 1_000_003).collect();
+        let ids = &valid_ids[..128];
+        let universe = 300_000_000;
+
+        let plain = BitmapSetCompressor::new()
+            .compress_set(ids, universe)
+            .unwrap();
+        let remapped = RemappingCompressor::new(BitmapSetCompressor::new(), &valid_ids)
+            .unwrap()
+            .compress_set(ids, universe)
+            .unwrap();
+        assert!(remapped.len() * 1000 < plain.len());
+
+        let ef = RemappingCompressor::new(EliasFanoCompressor::new(), &valid_ids).unwrap();
+        let compressed = ef.compress_set(ids, universe).unwrap();
+        assert_eq!(ef.decompress_set(&compressed, universe).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        assert!(RemappingCompressor::new(RocCompressor::new(), &[5, 3]).is_err());
+
+        let compressor = RemappingCompressor::new(RocCompressor::new(), &[2, 4, 8]).unwrap();
+        assert!(compressor.compress_set(&[3], 10).is_err());
+        assert!(compressor.compress_set(&[8], 8).is_err());
+        assert!(compressor.compress_set(&[4, 2], 10).is_err());
+
+        // Rank 2 maps to ID 8, outside a universe of 5
+        let compressed = compressor.compress_set(&[2, 8], 10).unwrap();
+        assert!(compressor.decompress_set(&compressed, 5).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Conversions between [`RoaringBitmap`] and [`CompressedSet`].
+//!
+//! Code already doing set algebra on `roaring` bitmaps in memory can store
+//! them as compressed sets in the `roaring/portable` format, and read them
+//! back, without going through `Vec<u32>` by hand.
+
+use ::roaring::RoaringBitmap;
+
+use crate::compressed_set::CompressedSet;
+use crate::error::CompressionError;
+use crate::roaring::RoaringBitmapCompressor;
+
+impl From<&RoaringBitmap> for CompressedSet {
+    /// Compress with [`RoaringBitmapCompressor`], inferring the universe.
+    ///
+    /// The universe is `max + 1`, or 1 for the empty bitmap, as in
+    /// [`CompressedSet::from_ids`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmap contains `u32::MAX`, which leaves no room for
+    /// the universe.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::CompressedSet;
+    /// use roaring::RoaringBitmap;
+    ///
+    /// let bitmap: RoaringBitmap = [2, 3, 5, 7].into_iter().collect();
+    /// let set = CompressedSet::from(&bitmap);
+    /// assert_eq!(set.universe(), 8);
+    ///
+    /// let back = RoaringBitmap::try_from(&set).unwrap();
+    /// assert_eq!(back, bitmap);
+    /// ```
+    fn from(bitmap: &RoaringBitmap) -> Self {
+        let universe = match bitmap.max() {
+            Some(max) => max
+                .checked_add(1)
+                .expect("bitmap containing u32::MAX has no inferable universe"),
+            None => 1,
+        };
+        let ids: Vec<u32> = bitmap.iter().collect();
+        CompressedSet::new(&ids, universe, &RoaringBitmapCompressor::new())
+            .expect("IDs of a bitmap are sorted, unique and below its universe")
+    }
+}
+
+impl TryFrom<&CompressedSet> for RoaringBitmap {
+    type Error = CompressionError;
+
+    /// Decompress the set, whatever its method, into a bitmap.
+    fn try_from(set: &CompressedSet) -> Result<Self, Self::Error> {
+        let ids = set.decompress()?;
+        RoaringBitmap::from_sorted_iter(ids)
+            .map_err(|e| CompressionError::DecompressionFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RocCompressor;
+
+    #[test]
+    fn test_empty_bitmap() {
+        let set = CompressedSet::from(&RoaringBitmap::new());
+        assert_eq!(set.universe(), 1);
+        assert!(RoaringBitmap::try_from(&set).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bitmap_from_other_methods() {
+        let set = CompressedSet::new(&[1, 10, 100], 1000, &RocCompressor::new()).unwrap();
+        let bitmap = RoaringBitmap::try_from(&set).unwrap();
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), [1, 10, 100]);
+    }
+
+    #[test]
+    #[should_panic(expected = "u32::MAX")]
+    fn test_max_id_panics() {
+        let bitmap: RoaringBitmap = [u32::MAX].into_iter().collect();
+        let _ = CompressedSet::from(&bitmap);
+    }
+}
@@ -11,9 +11,74 @@
 //!
 //! The current implementation uses delta encoding as a practical baseline.
 //! Full ROC with bits-back ANS would achieve near-optimal compression.
+//!
+//! # Format
+//!
+//! ```text
+//! [level: u8] [len] [first_id: varint] [deltas: varint * (len - 1)]
+//! ```
+//!
+//! The level byte records the [`CompressionLevel`] the set was written with,
+//! so decoding never needs it from the caller. `len` is a varint at
+//! [`CompressionLevel::Default`] and a little-endian `u32` at
+//! [`CompressionLevel::Fastest`]. The empty set is encoded as zero bytes.
+//!
+//! [`compress_deltas_only`] writes just `[first_id] [deltas]`, for callers
+//! that keep the length elsewhere.
+//!
+//! `u8` and `u16` IDs skip the level byte and varints, which cannot shrink
+//! one- or two-byte values, and use `[len: T LE] [ids: T LE * len]`.
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::ops::ControlFlow;
 
+use crate::builder::CompressedSetBuilder;
 use crate::error::CompressionError;
+use crate::iter::DecompressIter;
+use crate::trace::{self, Timer};
 use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint, varint_len, write_varint};
+
+/// Stack buffer size of [`RocCompressor`]'s `streaming_compress`.
+const STREAM_CHUNK_LEN: usize = 4096;
+
+/// Speed versus compression ratio tradeoff for [`RocCompressor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum CompressionLevel {
+    /// Fixed-width length header for the cheapest possible decode setup.
+    Fastest = 1,
+    /// Delta + varint encoding.
+    #[default]
+    Default = 5,
+    /// Maximum compression. Uses the [`Default`](Self::Default) encoding
+    /// until the full ROC/ANS path is implemented.
+    Best = 9,
+}
+
+impl CompressionLevel {
+    /// Level tag actually written to the header.
+    ///
+    /// `Best` shares the `Default` encoding for now, so it is tagged as such.
+    fn encoded_tag(self) -> u8 {
+        match self {
+            CompressionLevel::Fastest => CompressionLevel::Fastest as u8,
+            CompressionLevel::Default | CompressionLevel::Best => CompressionLevel::Default as u8,
+        }
+    }
+}
+
+/// How [`RocCompressor`] treats input that is not sorted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ValidationMode {
+    /// Reject unsorted input with `CompressionError::InvalidInput`.
+    #[default]
+    Strict,
+    /// Sort a copy of unsorted input before encoding, e.g. IDs collected
+    /// from a `HashSet`. Duplicates are still rejected.
+    AutoSort,
+}
 
 /// Random Order Coding compressor for sets.
 ///
@@ -25,10 +90,19 @@ use crate::traits::IdSetCompressor;
 /// - Compression ratio: 2-4x for typical workloads
 /// - Optimal for: IVF clusters, HNSW neighbor lists
 /// - Full ROC (future) would achieve 5-7x
+///
+/// # Thread safety
+///
+/// Holds only configuration, so a single `&RocCompressor` can be shared by
+/// any number of threads compressing at once.
+#[derive(Clone, Debug)]
 pub struct RocCompressor {
     /// ANS quantization precision (for future full ROC).
-    #[allow(dead_code)]
     ans_precision: u32,
+    /// Speed versus ratio tradeoff used when compressing.
+    level: CompressionLevel,
+    /// Whether unsorted input is rejected or sorted.
+    validation: ValidationMode,
 }
 
 impl RocCompressor {
@@ -36,9 +110,27 @@ impl RocCompressor {
     pub fn new() -> Self {
         Self {
             ans_precision: 1 << 12, // 4096, good balance
+            level: CompressionLevel::Default,
+            validation: ValidationMode::Strict,
+        }
+    }
+
+    /// Create a ROC compressor writing at `level`.
+    ///
+    /// Decompression reads the level from the compressed bytes, so any
+    /// `RocCompressor` can decode output written at any level.
+    pub fn with_level(level: CompressionLevel) -> Self {
+        Self {
+            level,
+            ..Self::new()
         }
     }
 
+    /// The level used when compressing.
+    pub fn level(&self) -> CompressionLevel {
+        self.level
+    }
+
     /// Create ROC compressor with custom ANS precision.
     ///
     /// # Arguments
@@ -47,11 +139,92 @@ impl RocCompressor {
     pub fn with_precision(precision: u32) -> Self {
         Self {
             ans_precision: precision,
+            ..Self::new()
+        }
+    }
+
+    /// The ANS quantization precision.
+    pub fn ans_precision(&self) -> u32 {
+        self.ans_precision
+    }
+
+    /// Create a ROC compressor handling unsorted input as `mode` says.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::{IdSetCompressor, RocCompressor, ValidationMode};
+    ///
+    /// let roc = RocCompressor::with_validation(ValidationMode::AutoSort);
+    /// let compressed = roc.compress_set(&[42u32, 7, 19], 100).unwrap();
+    /// assert_eq!(roc.decompress_set(&compressed, 100).unwrap(), [7u32, 19, 42]);
+    /// ```
+    pub fn with_validation(mode: ValidationMode) -> Self {
+        Self {
+            validation: mode,
+            ..Self::new()
+        }
+    }
+
+    /// How unsorted input is handled.
+    pub fn validation(&self) -> ValidationMode {
+        self.validation
+    }
+
+    /// `ids`, sorted into a copy if unsorted and the mode allows it.
+    fn sorted_input<'a, T: Ord + Copy>(&self, ids: &'a [T]) -> Cow<'a, [T]> {
+        if self.validation == ValidationMode::AutoSort && ids.windows(2).any(|w| w[1] < w[0]) {
+            let mut sorted = ids.to_vec();
+            sorted.sort_unstable();
+            Cow::Owned(sorted)
+        } else {
+            Cow::Borrowed(ids)
+        }
+    }
+
+    /// Write the level tag and set length.
+    pub(crate) fn write_header(level: CompressionLevel, num_ids: u64, out: &mut Vec<u8>) {
+        let tag = level.encoded_tag();
+        out.push(tag);
+        if tag == CompressionLevel::Fastest as u8 {
+            out.extend_from_slice(&(num_ids as u32).to_le_bytes());
+        } else {
+            encode_varint(num_ids, out);
+        }
+    }
+
+    /// Read the level tag and set length, returning `(len, header_bytes)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` for an unknown level
+    /// tag or a truncated header.
+    pub(crate) fn read_header(compressed: &[u8]) -> Result<(u64, usize), CompressionError> {
+        let truncated =
+            || CompressionError::DecompressionFailed("Unexpected end of compressed data".into());
+
+        let tag = *compressed.first().ok_or_else(truncated)?;
+        if tag == CompressionLevel::Fastest as u8 {
+            let len = compressed.get(1..5).ok_or_else(truncated)?;
+            Ok((u32::from_le_bytes(len.try_into().unwrap()) as u64, 5))
+        } else if tag == CompressionLevel::Default as u8 {
+            let (len, consumed) = decode_varint(&compressed[1..])?;
+            Ok((len, 1 + consumed))
+        } else {
+            Err(CompressionError::DecompressionFailed(format!(
+                "Unknown compression level tag {}",
+                tag
+            )))
         }
     }
 
     /// Validate that IDs are sorted and unique.
-    fn validate_ids(ids: &[u32]) -> Result<(), CompressionError> {
+    ///
+    /// Delta encoders rely on this: once `ids[i] > ids[i - 1]` holds, the
+    /// `u32` subtraction `ids[i] - ids[i - 1]` cannot wrap, even for IDs
+    /// near `u32::MAX`. Unsorted input such as `[u32::MAX, 0]` would
+    /// otherwise wrap to a small, silently wrong delta.
+    pub(crate) fn validate_ids(ids: &[u32]) -> Result<(), CompressionError> {
         if ids.is_empty() {
             return Ok(());
         }
@@ -69,84 +242,267 @@ impl RocCompressor {
         Ok(())
     }
 
-    /// Calculate theoretical bits for a set.
+    /// Calculate theoretical bits for a set: `log2(C(N, n))`.
     ///
-    /// Uses Stirling's approximation: log(C(N, n)) ≈ n * log(N/n) + O(n)
-    fn theoretical_bits(num_ids: usize, universe_size: u32) -> f64 {
-        if num_ids == 0 {
+    /// The empty and the full set are the only sets of their size and take
+    /// 0 bits; a single ID takes `log2(N)`.
+    pub(crate) fn theoretical_bits(num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 || num_ids >= universe_size as usize {
             return 0.0;
         }
+        if num_ids == 1 {
+            return (universe_size as f64).log2();
+        }
+        crate::math::theoretical_bits_set(num_ids, universe_size)
+    }
 
-        let n = num_ids as f64;
-        let n_val = universe_size as f64;
+    /// Sort `ids` in place, then compress them.
+    ///
+    /// Unlike [`ValidationMode::AutoSort`], which sorts a copy, this reuses
+    /// the caller's buffer. With the `simd-sort` feature the sort is a radix
+    /// sort, faster than `sort_unstable` on large inputs; without it,
+    /// `sort_unstable`. The output equals that of
+    /// [`compress_set`](IdSetCompressor::compress_set) on the sorted IDs.
+    ///
+    /// # Errors
+    ///
+    /// Same as `compress_set`, e.g. `CompressionError::InvalidInput` for
+    /// duplicate IDs. `ids` is left sorted either way.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::{IdSetCompressor, RocCompressor};
+    ///
+    /// let roc = RocCompressor::new();
+    /// let mut ids = vec![40, 2, 15, 8];
+    /// let compressed = roc.compress_set_with_sort(&mut ids, 100).unwrap();
+    /// assert_eq!(ids, [2, 8, 15, 40]);
+    /// assert_eq!(compressed, roc.compress_set(&ids, 100).unwrap());
+    /// ```
+    pub fn compress_set_with_sort(
+        &self,
+        ids: &mut [u32],
+        universe: u32,
+    ) -> Result<Vec<u8>, CompressionError> {
+        #[cfg(feature = "simd-sort")]
+        rdst::RadixSort::radix_sort_unstable(ids);
+        #[cfg(not(feature = "simd-sort"))]
+        ids.sort_unstable();
+        self.compress_set(ids, universe)
+    }
 
-        if n > n_val {
-            return 0.0;
-        }
+    /// Iterate lazily over the IDs of a compressed set.
+    ///
+    /// Only the header is read up front; each ID is decoded on demand.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the header is malformed.
+    pub fn iter<'a>(
+        &self,
+        compressed: &'a [u8],
+        universe: u32,
+    ) -> Result<DecompressIter<'a>, CompressionError> {
+        DecompressIter::new(compressed, universe)
+    }
 
-        let ratio = n_val / n;
-        if ratio <= 1.0 {
-            return 0.0;
+    /// Number of IDs in a compressed set, read from its header alone.
+    ///
+    /// Constant time: no deltas are decoded, so a corrupt body goes
+    /// unnoticed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::DecompressionFailed` if the header is
+    /// malformed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::{IdSetCompressor, RocCompressor};
+    ///
+    /// let compressor = RocCompressor::new();
+    /// let compressed = compressor.compress_set(&[2u32, 8, 15, 40], 100).unwrap();
+    /// assert_eq!(compressor.count_elements(&compressed).unwrap(), 4);
+    /// assert_eq!(compressor.count_in_range(&compressed, 100, 5, 20u32).unwrap(), 2);
+    /// ```
+    pub fn count_elements(&self, compressed: &[u8]) -> Result<usize, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(0);
         }
-
-        n * ratio.ln() / 2.0_f64.ln()
+        Ok(Self::read_header(compressed)?.0 as usize)
     }
 
-    /// Encode a u64 as varint into the buffer.
-    #[inline]
-    fn encode_varint(value: u64, buf: &mut Vec<u8>) {
-        let mut val = value;
-        while val >= 0x80 {
-            buf.push((val as u8) | 0x80);
-            val >>= 7;
+    /// Decode IDs in order, calling `visit` on each until it returns `Break`.
+    ///
+    /// Only the prefix up to the break point is decoded and validated.
+    fn scan(
+        compressed: &[u8],
+        universe_size: u32,
+        mut visit: impl FnMut(u32) -> ControlFlow<()>,
+    ) -> Result<(), CompressionError> {
+        for id in DecompressIter::new(compressed, universe_size)? {
+            if visit(id?).is_break() {
+                break;
+            }
         }
-        buf.push(val as u8);
+        Ok(())
     }
 
-    /// Decode a varint from the buffer, returning (value, bytes_consumed).
-    #[inline]
-    fn decode_varint(buf: &[u8]) -> Result<(u64, usize), CompressionError> {
-        let mut value = 0u64;
-        let mut shift = 0;
-        let mut offset = 0;
+    /// Decode IDs in order, calling `visit` on those in `[lo, hi)` and
+    /// stopping at the first ID `>= hi`.
+    ///
+    /// The loop of `decode`, not `scan`: going through `DecompressIter`
+    /// costs more per ID than decoding the whole set, which would lose for
+    /// ranges near the end.
+    fn scan_range(
+        compressed: &[u8],
+        universe_size: u32,
+        lo: u32,
+        hi: u32,
+        mut visit: impl FnMut(u32),
+    ) -> Result<(), CompressionError> {
+        if compressed.is_empty() || lo >= hi {
+            return Ok(());
+        }
 
-        loop {
-            if offset >= buf.len() {
+        let (num_ids, mut offset) = Self::read_header(compressed)?;
+        let mut prev = 0u64;
+        for i in 0..num_ids {
+            let (value, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            if i > 0 && value == 0 {
                 return Err(CompressionError::DecompressionFailed(
-                    "Unexpected end of compressed data".to_string(),
+                    "Zero delta produces duplicate ID".to_string(),
                 ));
             }
+            let id = if i == 0 { value } else { prev + value };
+            if id >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    id, universe_size
+                )));
+            }
+            if id >= hi as u64 {
+                return Ok(());
+            }
+            if id >= lo as u64 {
+                visit(id as u32);
+            }
+            prev = id;
+        }
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+        Ok(())
+    }
 
-            if shift > 56 {
-                return Err(CompressionError::DecompressionFailed(
-                    "Varint encoding too large".to_string(),
-                ));
+    /// Find the first ID `>= x` in a compressed set.
+    ///
+    /// Decodes from the start and stops at the first match, so the cost is
+    /// proportional to the position of the result rather than the set size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the decoded prefix is malformed.
+    pub fn next_geq(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+        x: u32,
+    ) -> Result<Option<u32>, CompressionError> {
+        let mut found = None;
+        Self::scan(compressed, universe, |id| {
+            if id >= x {
+                found = Some(id);
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
             }
+        })?;
+        Ok(found)
+    }
 
-            let byte = buf[offset];
-            offset += 1;
-            value |= ((byte & 0x7F) as u64) << shift;
+    /// Find the last ID `<= x` in a compressed set.
+    ///
+    /// Decodes every ID up to and including `x`, stopping at the first ID
+    /// greater than `x`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the decoded prefix is malformed.
+    pub fn prev_leq(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+        x: u32,
+    ) -> Result<Option<u32>, CompressionError> {
+        let mut found = None;
+        Self::scan(compressed, universe, |id| {
+            if id <= x {
+                found = Some(id);
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })?;
+        Ok(found)
+    }
 
-            if (byte & 0x80) == 0 {
-                break;
+    /// Split a compressed set into the IDs `< threshold` and those `>= threshold`.
+    ///
+    /// Decodes once and re-encodes both halves as it goes. Both halves keep
+    /// `universe` and are written at [`CompressionLevel::Default`], whatever
+    /// the level of the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if `compressed` is malformed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cnk::{IdSetCompressor, RocCompressor};
+    ///
+    /// let compressor = RocCompressor::new();
+    /// let compressed = compressor.compress_set(&[2u32, 8, 15, 40], 100).unwrap();
+    /// let (close, far) = compressor.partition_by(&compressed, 100, 15).unwrap();
+    ///
+    /// let close: Vec<u32> = compressor.decompress_set(&close, 100).unwrap();
+    /// let far: Vec<u32> = compressor.decompress_set(&far, 100).unwrap();
+    /// assert_eq!(close, [2, 8]);
+    /// assert_eq!(far, [15, 40]);
+    /// ```
+    pub fn partition_by(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+        threshold: u32,
+    ) -> Result<(Vec<u8>, Vec<u8>), CompressionError> {
+        let mut left = CompressedSetBuilder::new(universe);
+        let mut right = CompressedSetBuilder::new(universe);
+        for id in DecompressIter::new(compressed, universe)? {
+            let id = id?;
+            if id < threshold {
+                left.push(id)?;
+            } else {
+                right.push(id)?;
             }
-            shift += 7;
         }
-
-        Ok((value, offset))
+        Ok((left.finish()?, right.finish()?))
     }
-}
 
-impl IdSetCompressor for RocCompressor {
-    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+    /// Check that `ids` can be encoded: sorted, unique, inside the
+    /// universe, and few enough for the level's count header.
+    fn check_encodable(&self, ids: &[u32], universe_size: u32) -> Result<(), CompressionError> {
         Self::validate_ids(ids)?;
 
-        if ids.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        // Check bounds
-        if let Some(&max_id) = ids.iter().max() {
+        // Sorted, so the last ID is the largest
+        if let Some(&max_id) = ids.last() {
             if max_id >= universe_size {
                 return Err(CompressionError::InvalidInput(format!(
                     "ID {} exceeds universe size {}",
@@ -155,46 +511,61 @@ impl IdSetCompressor for RocCompressor {
             }
         }
 
+        if self.level == CompressionLevel::Fastest && ids.len() > u32::MAX as usize {
+            return Err(CompressionError::InvalidInput(format!(
+                "Fastest level stores at most {} IDs",
+                u32::MAX
+            )));
+        }
+        Ok(())
+    }
+
+    /// Delta + varint encode `ids`, the body of `compress_set`.
+    fn encode(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        self.check_encodable(ids, universe_size)?;
+
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
         let mut encoded = Vec::new();
 
-        // Store number of IDs
-        Self::encode_varint(ids.len() as u64, &mut encoded);
+        // Store level tag and number of IDs
+        Self::write_header(self.level, ids.len() as u64, &mut encoded);
 
         // Delta encode IDs
         if let Some(&first) = ids.first() {
-            Self::encode_varint(first as u64, &mut encoded);
+            encode_varint(first as u64, &mut encoded);
 
             for i in 1..ids.len() {
+                // Checked by validate_ids, so the subtraction cannot wrap
+                debug_assert!(ids[i] > ids[i - 1]);
                 let delta = ids[i] - ids[i - 1];
-                Self::encode_varint(delta as u64, &mut encoded);
+                // Widen before encoding; gaps near u32::MAX take 5 bytes
+                encode_varint(delta as u64, &mut encoded);
             }
         }
 
         Ok(encoded)
     }
 
-    fn decompress_set(
-        &self,
-        compressed: &[u8],
-        universe_size: u32,
-    ) -> Result<Vec<u32>, CompressionError> {
+    /// Decode a delta + varint set, the body of `decompress_set`.
+    fn decode(compressed: &[u8], universe_size: u32) -> Result<Vec<u32>, CompressionError> {
         if compressed.is_empty() {
             return Ok(Vec::new());
         }
 
         let mut ids = Vec::new();
-        let mut offset = 0;
 
-        // Decode number of IDs
-        let (num_ids, consumed) = Self::decode_varint(&compressed[offset..])?;
-        offset += consumed;
+        // Decode level tag and number of IDs
+        let (num_ids, mut offset) = Self::read_header(compressed)?;
 
         if num_ids == 0 {
             return Ok(ids);
         }
 
         // Decode first ID
-        let (first_id, consumed) = Self::decode_varint(&compressed[offset..])?;
+        let (first_id, consumed) = decode_varint(&compressed[offset..])?;
         offset += consumed;
 
         if first_id >= universe_size as u64 {
@@ -207,21 +578,29 @@ impl IdSetCompressor for RocCompressor {
 
         // Decode deltas
         for _ in 1..num_ids {
-            let (delta, consumed) = Self::decode_varint(&compressed[offset..])?;
+            let (delta, consumed) = decode_varint(&compressed[offset..])?;
             offset += consumed;
 
-            let next_id = ids.last().unwrap() + delta as u32;
-            if next_id >= universe_size {
+            if delta == 0 {
+                return Err(CompressionError::DecompressionFailed(
+                    "Zero delta produces duplicate ID".to_string(),
+                ));
+            }
+
+            // Accumulate in u64 so crafted deltas cannot wrap around
+            let next_id = *ids.last().unwrap() as u64 + delta;
+            if next_id >= universe_size as u64 {
                 return Err(CompressionError::DecompressionFailed(format!(
                     "ID {} exceeds universe size {}",
                     next_id, universe_size
                 )));
             }
-            ids.push(next_id);
+            ids.push(next_id as u32);
         }
 
         // Verify we consumed all data
         if offset < compressed.len() {
+            trace::trailing_data("cnk/delta-varint", compressed.len() - offset);
             return Err(CompressionError::DecompressionFailed(format!(
                 "Extra data after decompression: {} bytes",
                 compressed.len() - offset
@@ -230,6 +609,161 @@ impl IdSetCompressor for RocCompressor {
 
         Ok(ids)
     }
+}
+
+impl IdSetCompressor for RocCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let timer = Timer::start();
+        let encoded = self.encode(&self.sorted_input(ids), universe_size)?;
+        timer.compressed("cnk/delta-varint", ids.len(), universe_size, encoded.len());
+        Ok(encoded)
+    }
+
+    fn compress_sorted_iter<I>(
+        &self,
+        iter: I,
+        universe_size: u32,
+    ) -> Result<Vec<u8>, CompressionError>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        if self.level == CompressionLevel::Fastest || self.validation == ValidationMode::AutoSort {
+            let ids: Vec<u32> = iter.into_iter().collect();
+            return self.compress_set(&ids, universe_size);
+        }
+
+        // Streams deltas straight into the output without buffering IDs
+        let mut builder = CompressedSetBuilder::new(universe_size);
+        for id in iter {
+            builder.push(id)?;
+        }
+        builder.finish()
+    }
+
+    fn compress_set_reuse<'a>(
+        &self,
+        ids: &[u32],
+        universe_size: u32,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], CompressionError> {
+        let timer = Timer::start();
+        let ids = &*self.sorted_input(ids);
+        self.check_encodable(ids, universe_size)?;
+
+        // Only size the set exactly when the buffer might not hold the
+        // largest possible encoding: a 5-byte header and 5-byte varints
+        let worst_case = 1 + 5 * (ids.len() + 1);
+        if buf.len() < worst_case {
+            let needed = self.estimate_compressed_size_bytes(ids, universe_size);
+            if needed > buf.len() {
+                return Err(CompressionError::BufferTooSmall {
+                    needed,
+                    available: buf.len(),
+                });
+            }
+        }
+
+        // Same bytes as encode, written in place without allocating
+        let mut len = 0;
+        if let Some(&first) = ids.first() {
+            let tag = self.level.encoded_tag();
+            buf[0] = tag;
+            len = 1;
+            if tag == CompressionLevel::Fastest as u8 {
+                buf[1..5].copy_from_slice(&(ids.len() as u32).to_le_bytes());
+                len += 4;
+            } else {
+                len += write_varint(ids.len() as u64, &mut buf[len..]);
+            }
+            len += write_varint(first as u64, &mut buf[len..]);
+            for w in ids.windows(2) {
+                len += write_varint((w[1] - w[0]) as u64, &mut buf[len..]);
+            }
+        }
+        timer.compressed("cnk/delta-varint", ids.len(), universe_size, len);
+        Ok(&buf[..len])
+    }
+
+    fn streaming_compress<W: Write>(
+        &self,
+        ids: &[u32],
+        universe_size: u32,
+        writer: &mut W,
+    ) -> Result<usize, CompressionError> {
+        let timer = Timer::start();
+        let ids = &*self.sorted_input(ids);
+        self.check_encodable(ids, universe_size)?;
+
+        // Encode through a small stack buffer, flushed whenever the next
+        // header or varint might not fit
+        let mut chunk = [0u8; STREAM_CHUNK_LEN];
+        let mut len = 0;
+        let mut written = 0;
+        if let Some(&first) = ids.first() {
+            let tag = self.level.encoded_tag();
+            chunk[0] = tag;
+            len = 1;
+            if tag == CompressionLevel::Fastest as u8 {
+                chunk[1..5].copy_from_slice(&(ids.len() as u32).to_le_bytes());
+                len += 4;
+            } else {
+                len += write_varint(ids.len() as u64, &mut chunk[len..]);
+            }
+            len += write_varint(first as u64, &mut chunk[len..]);
+            for w in ids.windows(2) {
+                if len + 5 > STREAM_CHUNK_LEN {
+                    writer.write_all(&chunk[..len])?;
+                    written += len;
+                    len = 0;
+                }
+                len += write_varint((w[1] - w[0]) as u64, &mut chunk[len..]);
+            }
+        }
+        writer.write_all(&chunk[..len])?;
+        written += len;
+        timer.compressed("cnk/delta-varint", ids.len(), universe_size, written);
+        Ok(written)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let timer = Timer::start();
+        let ids = Self::decode(compressed, universe_size)?;
+        timer.decompressed(
+            "cnk/delta-varint",
+            ids.len(),
+            universe_size,
+            compressed.len(),
+        );
+        Ok(ids)
+    }
+
+    fn decompress_range(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+        lo: u32,
+        hi: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        let mut ids = Vec::new();
+        Self::scan_range(compressed, universe_size, lo, hi, |id| ids.push(id))?;
+        Ok(ids)
+    }
+
+    fn count_in_range(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+        lo: u32,
+        hi: u32,
+    ) -> Result<usize, CompressionError> {
+        let mut count = 0;
+        Self::scan_range(compressed, universe_size, lo, hi, |_| count += 1)?;
+        Ok(count)
+    }
 
     fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
         if num_ids == 0 {
@@ -241,20 +775,259 @@ impl IdSetCompressor for RocCompressor {
         ((bits / 8.0) as usize) + varint_overhead
     }
 
-    fn bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+    fn estimate_compressed_size_bytes(&self, ids: &[u32], _universe_size: u32) -> usize {
+        let ids = &*self.sorted_input(ids);
+        let first = match ids.first() {
+            Some(&first) => first,
+            None => return 0,
+        };
+
+        // Exact for valid input: the same header and varints compress_set writes
+        let header = if self.level == CompressionLevel::Fastest {
+            5
+        } else {
+            1 + varint_len(ids.len() as u64)
+        };
+        let deltas: usize = ids
+            .windows(2)
+            .map(|w| varint_len(w[1].saturating_sub(w[0]) as u64))
+            .sum();
+        header + varint_len(first as u64) + deltas
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
         if num_ids == 0 {
             return 0.0;
         }
         Self::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
     }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.validation == ValidationMode::Strict
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/delta-varint")
+    }
+}
+
+/// Fixed-width encoding for ID types of one or two bytes.
+///
+/// Varint saves nothing on values this small, so IDs are stored as-is:
+/// `[len: T LE] [ids: T LE * len]`. IDs are below `universe_size <= T::MAX`,
+/// so `len` always fits in a `T`.
+macro_rules! impl_fixed_width {
+    ($t:ty, $format:literal) => {
+        impl IdSetCompressor<$t> for RocCompressor {
+            fn compress_set(
+                &self,
+                ids: &[$t],
+                universe_size: $t,
+            ) -> Result<Vec<u8>, CompressionError> {
+                let ids = &*self.sorted_input(ids);
+                for w in ids.windows(2) {
+                    if w[1] <= w[0] {
+                        return Err(CompressionError::InvalidInput(format!(
+                            "IDs must be sorted and unique, found {} <= {}",
+                            w[1], w[0]
+                        )));
+                    }
+                }
+                let last = match ids.last() {
+                    Some(&last) => last,
+                    None => return Ok(Vec::new()),
+                };
+                if last >= universe_size {
+                    return Err(CompressionError::InvalidInput(format!(
+                        "ID {} exceeds universe size {}",
+                        last, universe_size
+                    )));
+                }
+
+                let width = std::mem::size_of::<$t>();
+                let mut encoded = Vec::with_capacity((ids.len() + 1) * width);
+                encoded.extend_from_slice(&(ids.len() as $t).to_le_bytes());
+                for &id in ids {
+                    encoded.extend_from_slice(&id.to_le_bytes());
+                }
+                Ok(encoded)
+            }
+
+            fn decompress_set(
+                &self,
+                compressed: &[u8],
+                universe_size: $t,
+            ) -> Result<Vec<$t>, CompressionError> {
+                if compressed.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                let width = std::mem::size_of::<$t>();
+                let mut words = compressed
+                    .chunks(width)
+                    .map(|chunk| chunk.try_into().map(<$t>::from_le_bytes));
+                let len = match words.next() {
+                    Some(Ok(len)) => len as usize,
+                    _ => {
+                        return Err(CompressionError::DecompressionFailed(
+                            "Unexpected end of compressed data".to_string(),
+                        ))
+                    }
+                };
+                let expected = (len + 1) * width;
+                if compressed.len() < expected {
+                    return Err(CompressionError::DecompressionFailed(
+                        "Unexpected end of compressed data".to_string(),
+                    ));
+                }
+                if compressed.len() > expected {
+                    return Err(CompressionError::DecompressionFailed(format!(
+                        "Extra data after decompression: {} bytes",
+                        compressed.len() - expected
+                    )));
+                }
+
+                let ids: Vec<$t> = words.map(|w| w.unwrap()).collect();
+                for w in ids.windows(2) {
+                    if w[1] <= w[0] {
+                        return Err(CompressionError::DecompressionFailed(format!(
+                            "IDs must be sorted and unique, found {} <= {}",
+                            w[1], w[0]
+                        )));
+                    }
+                }
+                if let Some(&last) = ids.last() {
+                    if last >= universe_size {
+                        return Err(CompressionError::DecompressionFailed(format!(
+                            "ID {} exceeds universe size {}",
+                            last, universe_size
+                        )));
+                    }
+                }
+                Ok(ids)
+            }
+
+            fn estimate_size(&self, num_ids: usize, _universe_size: $t) -> usize {
+                if num_ids == 0 {
+                    return 0;
+                }
+                (num_ids + 1) * std::mem::size_of::<$t>()
+            }
+
+            fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: $t) -> f64 {
+                if num_ids == 0 {
+                    return 0.0;
+                }
+                Self::theoretical_bits(num_ids, universe_size as u32) / (num_ids as f64)
+            }
+
+            fn requires_sorted_input(&self) -> bool {
+                self.validation == ValidationMode::Strict
+            }
+
+            fn format_id(&self) -> Option<&'static str> {
+                Some($format)
+            }
+        }
+    };
 }
 
+impl_fixed_width!(u8, "cnk/fixed-u8");
+impl_fixed_width!(u16, "cnk/fixed-u16");
+
 impl Default for RocCompressor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Encode sorted, unique `ids` as `[first_id: varint] [deltas: varint]`,
+/// without the level byte and length header of [`RocCompressor`].
+///
+/// For an upper layer that already stores each set's length, such as a
+/// cluster cardinality in its own metadata, this saves the 1-5 header
+/// bytes per set. The caller is responsible for storing the count and the
+/// universe: [`decompress_deltas_only`] needs the count, and neither
+/// function knows the universe, so nothing checks that IDs fall within it.
+///
+/// The bytes are those of [`CompressionLevel::Default`] output after its
+/// header.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `ids` is not sorted and unique.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{compress_deltas_only, decompress_deltas_only};
+///
+/// let ids = vec![3u32, 7, 200];
+/// let compressed = compress_deltas_only(&ids).unwrap();
+/// assert_eq!(compressed, [3, 4, 193, 1]);
+/// assert_eq!(decompress_deltas_only(&compressed, ids.len()).unwrap(), ids);
+/// ```
+pub fn compress_deltas_only(ids: &[u32]) -> Result<Vec<u8>, CompressionError> {
+    RocCompressor::validate_ids(ids)?;
+    let mut out = Vec::with_capacity(ids.len() * 2);
+    if let Some(&first) = ids.first() {
+        encode_varint(first as u64, &mut out);
+    }
+    for w in ids.windows(2) {
+        encode_varint((w[1] - w[0]) as u64, &mut out);
+    }
+    Ok(out)
+}
+
+/// Decode `count` IDs written by [`compress_deltas_only`].
+///
+/// The first ID is read from the bytes, like the deltas after it, so
+/// there is no `first_id` parameter. Keeping it inline is what lets the
+/// bytes equal [`RocCompressor`] output minus its header. A caller-supplied
+/// first ID would duplicate a value the bytes already hold, and the two
+/// could disagree.
+///
+/// # Errors
+///
+/// Returns `CompressionError::DecompressionFailed` if the bytes hold fewer
+/// than `count` varints or more bytes after them, or if a delta is zero
+/// or carries an ID past `u32::MAX`.
+pub fn decompress_deltas_only(
+    compressed: &[u8],
+    count: usize,
+) -> Result<Vec<u32>, CompressionError> {
+    // Every varint takes at least one byte
+    let mut ids = Vec::with_capacity(count.min(compressed.len()));
+    let mut offset = 0;
+    let mut prev = 0u64;
+    for i in 0..count {
+        let (value, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+        if i > 0 && value == 0 {
+            return Err(CompressionError::DecompressionFailed(
+                "Zero delta produces duplicate ID".to_string(),
+            ));
+        }
+        let id = if i == 0 { value } else { prev + value };
+        if id > u32::MAX as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds u32::MAX",
+                id
+            )));
+        }
+        ids.push(id as u32);
+        prev = id;
+    }
+    if offset != compressed.len() {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "{} bytes of trailing data after {} IDs",
+            compressed.len() - offset,
+            count
+        )));
+    }
+    Ok(ids)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,13 +1046,45 @@ mod tests {
         assert_eq!(ids, decompressed);
     }
 
+    #[test]
+    fn test_deltas_only_is_default_output_without_header() {
+        let compressor = RocCompressor::new();
+        for ids in [
+            vec![],
+            vec![0u32],
+            vec![1, 5, 10, 300, 70_000],
+            vec![u32::MAX - 2, u32::MAX - 1],
+        ] {
+            let compressed = compressor.compress_set(&ids, u32::MAX).unwrap();
+            let header = if compressed.is_empty() {
+                0
+            } else {
+                RocCompressor::read_header(&compressed).unwrap().1
+            };
+            let deltas = compress_deltas_only(&ids).unwrap();
+            assert_eq!(deltas, compressed[header..]);
+            // The first ID comes back from the bytes, not from the caller
+            assert_eq!(decompress_deltas_only(&deltas, ids.len()).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn test_deltas_only_errors() {
+        assert!(compress_deltas_only(&[5, 5]).is_err());
+        let deltas = compress_deltas_only(&[1, 2, 3]).unwrap();
+        assert!(decompress_deltas_only(&deltas, 4).is_err());
+        assert!(decompress_deltas_only(&deltas, 2).is_err());
+        assert!(decompress_deltas_only(&[1, 0], 2).is_err());
+        assert!(decompress_deltas_only(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1], 2).is_err());
+    }
+
     #[test]
     fn test_empty_set() {
         let compressor = RocCompressor::new();
-        let compressed = compressor.compress_set(&[], 1000).unwrap();
+        let compressed = compressor.compress_set(&[], 1000u32).unwrap();
         assert!(compressed.is_empty());
 
-        let decompressed = compressor.decompress_set(&[], 1000).unwrap();
+        let decompressed = compressor.decompress_set(&[], 1000u32).unwrap();
         assert!(decompressed.is_empty());
     }
 
@@ -324,6 +1129,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_decompress_range() {
+        let compressor = RocCompressor::new();
+        let ids = [2u32, 8, 15, 40, 99];
+        let compressed = compressor.compress_set(&ids, 100).unwrap();
+        let range = |lo, hi| {
+            compressor
+                .decompress_range(&compressed, 100, lo, hi)
+                .unwrap()
+        };
+        assert_eq!(range(8, 40), [8, 15]);
+        assert_eq!(range(0, 100), ids);
+        assert_eq!(range(41, 99), []);
+        assert_eq!(range(40, 8), []);
+
+        // Decoding stops at the first ID past the range, before the bad tail
+        let mut corrupt = compressed.clone();
+        *corrupt.last_mut().unwrap() = 0;
+        assert_eq!(
+            compressor
+                .decompress_range(&corrupt, 100, 0, 16u32)
+                .unwrap(),
+            [2, 8, 15]
+        );
+        assert!(compressor
+            .decompress_range(&corrupt, 100, 0, 100u32)
+            .is_err());
+    }
+
+    #[test]
+    fn test_count_elements_reads_only_header() {
+        let ids: Vec<u32> = (0..300).map(|i| i * 7).collect();
+        for level in [CompressionLevel::Default, CompressionLevel::Fastest] {
+            let compressor = RocCompressor::with_level(level);
+            let compressed = compressor.compress_set(&ids, 3000).unwrap();
+            assert_eq!(compressor.count_elements(&compressed).unwrap(), 300);
+            // The body is never looked at
+            assert_eq!(compressor.count_elements(&compressed[..6]).unwrap(), 300);
+        }
+        assert_eq!(RocCompressor::new().count_elements(&[]).unwrap(), 0);
+        assert!(RocCompressor::new().count_elements(&[7, 1]).is_err());
+    }
+
     #[test]
     fn test_single_id() {
         let compressor = RocCompressor::new();
@@ -343,4 +1191,259 @@ mod tests {
         let result = compressor.compress_set(&ids, 1000);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_compress_sorted_iter_from_generator() {
+        let compressor = RocCompressor::new();
+        let compressed = compressor
+            .compress_sorted_iter((0..1000).map(|i| i * 3), 3000u32)
+            .unwrap();
+
+        let ids: Vec<u32> = (0..1000).map(|i| i * 3).collect();
+        assert_eq!(compressed, compressor.compress_set(&ids, 3000).unwrap());
+        assert!(compressor
+            .compress_sorted_iter([1, 2, 3000], 3000u32)
+            .is_err());
+    }
+
+    #[test]
+    fn test_actual_bits_per_id() {
+        let compressor = RocCompressor::new();
+        let ids: Vec<u32> = (0..100).map(|i| i * 10).collect();
+
+        // level (1) + count (1) + first (1) + 99 one-byte deltas
+        let actual = compressor.actual_bits_per_id(&ids, 1000).unwrap();
+        assert_eq!(actual, 102.0 * 8.0 / 100.0);
+        assert!(actual > compressor.theoretical_bits_per_id(100, 1000u32));
+        assert_eq!(compressor.actual_bits_per_id(&[], 1000u32).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_compress_set_reuse_matches_compress_set() {
+        let ids: Vec<u32> = (0..500).map(|i| i * i).collect();
+        let mut arena = vec![0xffu8; 4096];
+        for level in [CompressionLevel::Default, CompressionLevel::Fastest] {
+            let roc = RocCompressor::with_level(level);
+            let expected = roc.compress_set(&ids, 1_000_000).unwrap();
+            let needed = roc.estimate_compressed_size_bytes(&ids, 1_000_000);
+            assert_eq!(needed, expected.len());
+
+            let written = roc
+                .compress_set_reuse(&ids, 1_000_000, &mut arena[..needed])
+                .unwrap();
+            assert_eq!(written, &expected[..]);
+            assert!(roc
+                .compress_set_reuse(&[], 10u32, &mut [])
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                roc.compress_set_reuse(&ids, 1_000_000, &mut arena[..needed - 1]),
+                Err(CompressionError::BufferTooSmall {
+                    needed,
+                    available: needed - 1
+                })
+            );
+        }
+
+        // The default implementation copies out of compress_set
+        let pfor = crate::PForDeltaCompressor::default();
+        let expected = pfor.compress_set(&ids, 1_000_000).unwrap();
+        let written = pfor
+            .compress_set_reuse(&ids, 1_000_000, &mut arena)
+            .unwrap();
+        assert_eq!(written, &expected[..]);
+        assert!(matches!(
+            pfor.compress_set_reuse(&ids, 1_000_000, &mut arena[..1]),
+            Err(CompressionError::BufferTooSmall { available: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn theoretical_bits_full_set_is_zero() {
+        for universe in [1u32, 2, 10, 1000] {
+            assert_eq!(
+                RocCompressor::theoretical_bits(universe as usize, universe),
+                0.0
+            );
+            assert_eq!(RocCompressor::theoretical_bits(0, universe), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_theoretical_bits_known_values() {
+        // C(N, 1) = N
+        assert_eq!(RocCompressor::theoretical_bits(1, 1024), 10.0);
+        // C(10, 3) = 120, and C(N, N - 1) = N
+        assert!((RocCompressor::theoretical_bits(3, 10) - 120f64.log2()).abs() < 1e-9);
+        assert!((RocCompressor::theoretical_bits(999, 1000) - 1000f64.log2()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_levels_are_self_describing() {
+        let ids: Vec<u32> = (0..300).map(|i| i * 7).collect();
+        let default = RocCompressor::new();
+
+        for level in [
+            CompressionLevel::Fastest,
+            CompressionLevel::Default,
+            CompressionLevel::Best,
+        ] {
+            let compressor = RocCompressor::with_level(level);
+            assert_eq!(compressor.level(), level);
+
+            let compressed = compressor.compress_set(&ids, 3000).unwrap();
+            assert_eq!(
+                compressed.len(),
+                compressor.estimate_compressed_size_bytes(&ids, 3000)
+            );
+            // Any compressor decodes any level
+            assert_eq!(default.decompress_set(&compressed, 3000).unwrap(), ids);
+        }
+    }
+
+    #[test]
+    fn test_level_header() {
+        let fastest = RocCompressor::with_level(CompressionLevel::Fastest)
+            .compress_set(&[3], 10u32)
+            .unwrap();
+        assert_eq!(fastest, [1, 1, 0, 0, 0, 3]);
+
+        let default = RocCompressor::new().compress_set(&[3], 10u32).unwrap();
+        assert_eq!(default, [5, 1, 3]);
+
+        assert!(RocCompressor::new()
+            .decompress_set(&[7, 1, 3], 10u32)
+            .is_err());
+    }
+
+    #[test]
+    fn test_next_geq_prev_leq() {
+        let compressor = RocCompressor::new();
+        let ids = vec![3u32, 10, 11, 40];
+        let compressed = compressor.compress_set(&ids, 100).unwrap();
+
+        assert_eq!(compressor.next_geq(&compressed, 100, 0).unwrap(), Some(3));
+        assert_eq!(compressor.next_geq(&compressed, 100, 11).unwrap(), Some(11));
+        assert_eq!(compressor.next_geq(&compressed, 100, 12).unwrap(), Some(40));
+        assert_eq!(compressor.next_geq(&compressed, 100, 41).unwrap(), None);
+
+        assert_eq!(compressor.prev_leq(&compressed, 100, 2).unwrap(), None);
+        assert_eq!(compressor.prev_leq(&compressed, 100, 10).unwrap(), Some(10));
+        assert_eq!(compressor.prev_leq(&compressed, 100, 39).unwrap(), Some(11));
+        assert_eq!(compressor.prev_leq(&compressed, 100, 99).unwrap(), Some(40));
+
+        assert_eq!(compressor.next_geq(&[], 100, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_overflowing_delta_is_rejected() {
+        let compressor = RocCompressor::new();
+        // level = Default, count = 2, first = 1, delta = u32::MAX (would wrap to 0 in u32)
+        let mut crafted = vec![5u8, 2, 1];
+        crate::varint::encode_varint(u32::MAX as u64, &mut crafted);
+        assert!(compressor.decompress_set(&crafted, u32::MAX).is_err());
+
+        // Largest delta the varint decoder accepts; must not overflow u64
+        let mut crafted = vec![5u8, 2, 1];
+        crate::varint::encode_varint((1 << 63) - 1, &mut crafted);
+        assert!(compressor.decompress_set(&crafted, u32::MAX).is_err());
+
+        // level = Default, count = 2, first = 1, delta = 0 (duplicate ID)
+        assert!(compressor.decompress_set(&[5, 2, 1, 0], 1000u32).is_err());
+    }
+
+    #[test]
+    fn test_u8_fixed_width() {
+        let compressor = RocCompressor::new();
+        let ids: Vec<u8> = (0..255).collect();
+
+        let compressed = compressor.compress_set(&ids, 255u8).unwrap();
+        assert_eq!(compressed.len(), 1 + 255);
+        assert_eq!(compressor.decompress_set(&compressed, 255u8).unwrap(), ids);
+        assert!(compressor.decompress_set(&compressed, 254u8).is_err());
+        assert!(compressor.compress_set(&[3u8, 2], 10).is_err());
+    }
+
+    #[test]
+    fn test_u16_fixed_width() {
+        let compressor = RocCompressor::new();
+        let ids = vec![0u16, 7, 300, 65_534];
+
+        let compressed = compressor.compress_set(&ids, u16::MAX).unwrap();
+        assert_eq!(compressed, [4, 0, 0, 0, 7, 0, 44, 1, 254, 255]);
+        assert_eq!(
+            compressor.decompress_set(&compressed, u16::MAX).unwrap(),
+            ids
+        );
+
+        assert!(compressor
+            .decompress_set(&compressed[..compressed.len() - 2], u16::MAX)
+            .is_err());
+        let mut padded = compressed.clone();
+        padded.extend_from_slice(&[0, 0]);
+        assert!(compressor.decompress_set(&padded, u16::MAX).is_err());
+    }
+
+    #[test]
+    fn test_ids_near_u32_max() {
+        let compressor = RocCompressor::new();
+        let ids = vec![0u32, u32::MAX / 2 + 1, u32::MAX - 2, u32::MAX - 1];
+
+        let compressed = compressor.compress_set(&ids, u32::MAX).unwrap();
+        // level + count + first + three deltas; the 2^31 gap takes 5 bytes
+        assert_eq!(compressed.len(), 1 + 1 + 1 + 5 + 5 + 1);
+        assert_eq!(
+            compressor.decompress_set(&compressed, u32::MAX).unwrap(),
+            ids
+        );
+    }
+
+    #[test]
+    fn test_wrapping_delta_is_rejected() {
+        // Unchecked, u32::MAX - 1 followed by 1 would wrap to a delta of 3
+        let err = RocCompressor::new()
+            .compress_set(&[u32::MAX - 1, 1], u32::MAX)
+            .unwrap_err();
+        assert!(matches!(err, CompressionError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_partition_by_extreme_thresholds() {
+        let compressor = RocCompressor::with_level(CompressionLevel::Fastest);
+        let ids = vec![0u32, 3, 9];
+        let compressed = compressor.compress_set(&ids, 10).unwrap();
+        let default = RocCompressor::new().compress_set(&ids, 10).unwrap();
+
+        // Both halves are written at the default level
+        let (left, right) = compressor.partition_by(&compressed, 10, 0).unwrap();
+        assert!(left.is_empty());
+        assert_eq!(right, default);
+
+        let (left, right) = compressor.partition_by(&compressed, 10, u32::MAX).unwrap();
+        assert_eq!(left, default);
+        assert!(right.is_empty());
+    }
+
+    #[test]
+    fn test_auto_sort_validation() {
+        let strict = RocCompressor::new();
+        let auto = RocCompressor::with_validation(ValidationMode::AutoSort);
+        assert_eq!(strict.validation(), ValidationMode::Strict);
+        assert!(!IdSetCompressor::<u32>::requires_sorted_input(&auto));
+
+        let ids = [9u32, 3, 5];
+        assert!(strict.compress_set(&ids, 10).is_err());
+        let compressed = auto.compress_set(&ids, 10).unwrap();
+        assert_eq!(compressed, strict.compress_set(&[3u32, 5, 9], 10).unwrap());
+        assert_eq!(
+            auto.estimate_compressed_size_bytes(&ids, 10),
+            compressed.len()
+        );
+        assert_eq!(auto.compress_sorted_iter(ids, 10).unwrap(), compressed);
+
+        // Duplicates and narrow ID types
+        assert!(auto.compress_set(&[4u32, 1, 4], 10).is_err());
+        let narrow = auto.compress_set(&[200u8, 3], 255).unwrap();
+        assert_eq!(auto.decompress_set(&narrow, 255u8).unwrap(), [3, 200]);
+    }
 }
@@ -0,0 +1,235 @@
+//! Skip pointers over an existing compressed set.
+//!
+//! [`SampledIndex`] keeps every `interval`-th ID of a delta-varint stream in
+//! memory together with the byte offset of the delta that follows it.
+//! `next_geq` binary-searches the samples and decodes at most `interval`
+//! deltas, instead of the whole prefix as [`RocCompressor::next_geq`] does.
+//! Unlike [`BlockDeltaCompressor`](crate::BlockDeltaCompressor) the on-disk
+//! bytes are unchanged; the skip table is rebuilt when the set is loaded.
+//!
+//! This is the building block for WAND-style query processing, where many
+//! posting lists are advanced to a common target document.
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::transcode::recompress;
+use crate::varint::decode_varint;
+
+/// Validate a [`RocCompressor`] stream, returning every `interval`-th ID
+/// with the byte offset of the delta after it, and the number of IDs.
+pub(crate) fn sample_stream(
+    bytes: &[u8],
+    universe_size: u32,
+    interval: usize,
+) -> Result<(Vec<(u32, usize)>, usize), CompressionError> {
+    let mut samples = Vec::new();
+    if bytes.is_empty() {
+        return Ok((samples, 0));
+    }
+
+    let (num_ids, mut offset) = RocCompressor::read_header(bytes)?;
+    let mut prev = 0u64;
+    let mut next_sample = 0u64;
+    for i in 0..num_ids {
+        let (value, consumed) = decode_varint(&bytes[offset..])?;
+        offset += consumed;
+        if i > 0 && value == 0 {
+            return Err(CompressionError::DecompressionFailed(
+                "Zero delta produces duplicate ID".to_string(),
+            ));
+        }
+        let id = if i == 0 { value } else { prev + value };
+        if id >= universe_size as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "ID {} exceeds universe size {}",
+                id, universe_size
+            )));
+        }
+        prev = id;
+
+        if i == next_sample {
+            samples.push((id as u32, offset));
+            next_sample += interval as u64;
+        }
+    }
+    if offset < bytes.len() {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Extra data after decompression: {} bytes",
+            bytes.len() - offset
+        )));
+    }
+    Ok((samples, num_ids as usize))
+}
+
+/// A delta-varint compressed set with an in-memory skip table.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RocCompressor, SampledIndex};
+///
+/// let roc = RocCompressor::new();
+/// let ids: Vec<u32> = (0..1000).map(|i| i * 5).collect();
+/// let compressed = roc.compress_set(&ids, 5000).unwrap();
+///
+/// let index = SampledIndex::new(compressed, 5000, &roc, 64).unwrap();
+/// assert_eq!(index.next_geq(2501).unwrap(), Some(2505));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SampledIndex {
+    /// Set in the [`RocCompressor`] format.
+    compressed: Vec<u8>,
+    universe_size: u32,
+    interval: usize,
+    len: usize,
+    /// Every `interval`-th ID and the byte offset of the delta after it.
+    samples: Vec<(u32, usize)>,
+}
+
+impl SampledIndex {
+    /// Build a skip table over `compressed`, sampling every `interval`-th ID.
+    ///
+    /// `compressed` may use any compressor's format; sets not already in the
+    /// [`RocCompressor`] delta-varint format are transcoded to it, since skip
+    /// pointers need byte-addressable deltas.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if `compressed` cannot be decoded by `c`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(
+        compressed: Vec<u8>,
+        universe_size: u32,
+        c: &dyn IdSetCompressor,
+        interval: usize,
+    ) -> Result<Self, CompressionError> {
+        assert!(interval > 0, "interval must be non-zero");
+
+        let roc = RocCompressor::new();
+        let compressed = if c.format_id() == IdSetCompressor::<u32>::format_id(&roc) {
+            compressed
+        } else {
+            recompress(&compressed, universe_size, c, &roc)?
+        };
+
+        let mut index = Self {
+            compressed,
+            universe_size,
+            interval,
+            len: 0,
+            samples: Vec::new(),
+        };
+        index.build_samples()?;
+        Ok(index)
+    }
+
+    /// Decode the whole stream once, recording a sample every `interval` IDs.
+    fn build_samples(&mut self) -> Result<(), CompressionError> {
+        let (samples, len) = sample_stream(&self.compressed, self.universe_size, self.interval)?;
+        self.samples = samples;
+        self.len = len;
+        Ok(())
+    }
+
+    /// Find the first ID `>= x`.
+    ///
+    /// Binary-searches the samples, then decodes at most `interval` deltas.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the decoded deltas are malformed.
+    pub fn next_geq(&self, x: u32) -> Result<Option<u32>, CompressionError> {
+        // Last sample <= x; the answer lies in its run or is the next sample
+        let s = self.samples.partition_point(|&(id, _)| id <= x);
+        if s == 0 {
+            return Ok(self.samples.first().map(|&(id, _)| id));
+        }
+
+        let (mut id, mut offset) = self.samples[s - 1];
+        if id >= x {
+            return Ok(Some(id));
+        }
+        let run_end = (s * self.interval).min(self.len);
+        for _ in (s - 1) * self.interval + 1..run_end {
+            let (delta, consumed) = decode_varint(&self.compressed[offset..])?;
+            offset += consumed;
+            id += delta as u32;
+            if id >= x {
+                return Ok(Some(id));
+            }
+        }
+        Ok(self.samples.get(s).map(|&(id, _)| id))
+    }
+
+    /// Number of IDs in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of IDs between consecutive samples.
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// The set in the [`RocCompressor`] format.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.compressed
+    }
+
+    /// Drop the skip table and return the compressed bytes.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.compressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HuffmanCompressor;
+
+    #[test]
+    fn test_next_geq_matches_scan() {
+        let roc = RocCompressor::new();
+        let mut ids: Vec<u32> = (0..500).map(|i| i * i % 10_007).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let compressed = roc.compress_set(&ids, 10_007).unwrap();
+
+        let index = SampledIndex::new(compressed, 10_007, &roc, 16).unwrap();
+        assert_eq!(index.len(), ids.len());
+        for x in (0..10_100).step_by(7) {
+            let expected = ids.iter().copied().find(|&id| id >= x);
+            assert_eq!(index.next_geq(x).unwrap(), expected, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_transcodes_other_formats() {
+        let huffman = HuffmanCompressor::new();
+        let ids = vec![3u32, 4, 5, 100, 101, 900];
+        let compressed = huffman.compress_set(&ids, 1000).unwrap();
+
+        let index = SampledIndex::new(compressed, 1000, &huffman, 2).unwrap();
+        assert_eq!(
+            index.as_bytes(),
+            RocCompressor::new().compress_set(&ids, 1000).unwrap()
+        );
+        assert_eq!(index.next_geq(6).unwrap(), Some(100));
+    }
+
+    #[test]
+    fn test_empty() {
+        let index = SampledIndex::new(Vec::new(), 10, &RocCompressor::new(), 4).unwrap();
+        assert!(index.is_empty());
+        assert_eq!(index.next_geq(0).unwrap(), None);
+    }
+}
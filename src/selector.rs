@@ -0,0 +1,403 @@
+//! Codec selection by measurement on sample data.
+//!
+//! [`IdCompressionMethod::auto_select`] guesses from density alone. When a
+//! representative sample of posting lists is at hand,
+//! [`CompressionMethodSelector`] compresses it with every available method
+//! and picks from measured sizes and timings instead.
+//!
+//! # Sample file format
+//!
+//! ```text
+//! [universe: u32 LE] [num_sets: u32 LE] [(len: u32 LE, ids: u32 LE * len) * num_sets]
+//! ```
+
+use std::path::Path;
+use std::time::Instant;
+
+use crate::analysis::GapStatistics;
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+use crate::{BitmapSetCompressor, IdCompressionMethod, RocCompressor, SplitEliasFanoCompressor};
+
+/// Methods measured by [`CompressionMethodSelector::calibrate`], in order.
+fn candidates() -> Vec<IdCompressionMethod> {
+    #[allow(unused_mut)]
+    let mut methods = vec![
+        IdCompressionMethod::Uncompressed,
+        IdCompressionMethod::EliasFano,
+        IdCompressionMethod::Roc,
+        IdCompressionMethod::Bitset,
+    ];
+    #[cfg(feature = "roaring")]
+    methods.push(IdCompressionMethod::RoaringBitmap);
+    methods
+}
+
+/// Compress `ids` with `method`.
+fn encode(
+    method: &IdCompressionMethod,
+    ids: &[u32],
+    universe: u32,
+) -> Result<Vec<u8>, CompressionError> {
+    match method {
+        IdCompressionMethod::Uncompressed => {
+            Ok(ids.iter().flat_map(|id| id.to_le_bytes()).collect())
+        }
+        IdCompressionMethod::EliasFano => {
+            // A single block covers any u32 universe, leaving plain Elias-Fano
+            let ids: Vec<u64> = ids.iter().map(|&id| id as u64).collect();
+            SplitEliasFanoCompressor::new(32).compress_set(&ids, universe as u64)
+        }
+        IdCompressionMethod::Roc => RocCompressor::new().compress_set(ids, universe),
+        IdCompressionMethod::Bitset => BitmapSetCompressor::new().compress_set(ids, universe),
+        #[cfg(feature = "roaring")]
+        IdCompressionMethod::RoaringBitmap => {
+            crate::RoaringBitmapCompressor::new().compress_set(ids, universe)
+        }
+        _ => Err(CompressionError::InvalidInput(format!(
+            "{:?} is not available",
+            method
+        ))),
+    }
+}
+
+/// Decompress `bytes` written by [`encode`], returning the number of IDs.
+fn decode(
+    method: &IdCompressionMethod,
+    bytes: &[u8],
+    universe: u32,
+) -> Result<usize, CompressionError> {
+    match method {
+        IdCompressionMethod::Uncompressed => {
+            let ids: Vec<u32> = bytes
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                .collect();
+            Ok(ids.len())
+        }
+        IdCompressionMethod::EliasFano => Ok(SplitEliasFanoCompressor::new(32)
+            .decompress_set(bytes, universe as u64)?
+            .len()),
+        IdCompressionMethod::Roc => Ok(RocCompressor::new().decompress_set(bytes, universe)?.len()),
+        IdCompressionMethod::Bitset => Ok(BitmapSetCompressor::new()
+            .decompress_set(bytes, universe)?
+            .len()),
+        #[cfg(feature = "roaring")]
+        IdCompressionMethod::RoaringBitmap => Ok(crate::RoaringBitmapCompressor::new()
+            .decompress_set(bytes, universe)?
+            .len()),
+        _ => Err(CompressionError::InvalidInput(format!(
+            "{:?} is not available",
+            method
+        ))),
+    }
+}
+
+/// Measured cost of one method on the calibration sample.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodStats {
+    /// The method measured.
+    pub method: IdCompressionMethod,
+    /// Total compressed bytes divided by total IDs in the sample.
+    pub avg_bytes_per_id: f64,
+    /// Mean time to compress one sample set.
+    pub avg_encode_ns: f64,
+    /// Mean time to decompress one sample set.
+    pub avg_decode_ns: f64,
+}
+
+impl MethodStats {
+    /// Mean time for one compression plus one decompression.
+    fn round_trip_ns(&self) -> f64 {
+        self.avg_encode_ns + self.avg_decode_ns
+    }
+}
+
+/// Picks a compression method from measurements on sample sets.
+///
+/// Before [`calibrate`](Self::calibrate) is called, or if the sample holds
+/// no IDs, every query falls back to [`IdCompressionMethod::Roc`].
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{CompressionMethodSelector, IdCompressionMethod};
+///
+/// let sample: Vec<Vec<u32>> = (0..20).map(|i| (0..100).map(|j| i + j * 37).collect()).collect();
+///
+/// let mut selector = CompressionMethodSelector::new();
+/// selector.calibrate(&sample, 10_000);
+///
+/// assert_ne!(selector.best_by_size(), IdCompressionMethod::Uncompressed);
+/// assert!(!selector.pareto_optimal().is_empty());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CompressionMethodSelector {
+    stats: Vec<MethodStats>,
+    gaps: GapStatistics,
+}
+
+impl CompressionMethodSelector {
+    /// Create an uncalibrated selector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure every available method on `sample_sets`.
+    ///
+    /// Replaces any earlier measurements. Sets that a method rejects (e.g.
+    /// unsorted, or IDs outside the universe) are skipped for every method.
+    pub fn calibrate(&mut self, sample_sets: &[Vec<u32>], universe: u32) {
+        let roc = RocCompressor::new();
+        let sets: Vec<&[u32]> = sample_sets
+            .iter()
+            .filter(|ids| roc.compress_set(ids, universe).is_ok())
+            .map(|ids| ids.as_slice())
+            .collect();
+        let total_ids: usize = sets.iter().map(|ids| ids.len()).sum();
+
+        self.gaps = GapStatistics::from_gaps(
+            sets.iter()
+                .flat_map(|ids| ids.windows(2).map(|w| w[1] - w[0])),
+        );
+        self.stats.clear();
+        if total_ids == 0 {
+            return;
+        }
+        for method in candidates() {
+            let mut bytes = 0usize;
+            let mut encode_ns = 0u128;
+            let mut decode_ns = 0u128;
+            for ids in &sets {
+                let start = Instant::now();
+                let Ok(compressed) = encode(&method, ids, universe) else {
+                    continue;
+                };
+                encode_ns += start.elapsed().as_nanos();
+
+                let start = Instant::now();
+                let decoded = decode(&method, &compressed, universe);
+                decode_ns += start.elapsed().as_nanos();
+                debug_assert_eq!(decoded.ok(), Some(ids.len()));
+                bytes += compressed.len();
+            }
+            self.stats.push(MethodStats {
+                method,
+                avg_bytes_per_id: bytes as f64 / total_ids as f64,
+                avg_encode_ns: encode_ns as f64 / sets.len() as f64,
+                avg_decode_ns: decode_ns as f64 / sets.len() as f64,
+            });
+        }
+    }
+
+    /// Read sample sets from `path` and [`calibrate`](Self::calibrate) on them.
+    ///
+    /// The file holds the universe, the number of sets, then each set as
+    /// its length and IDs, all as little-endian `u32`:
+    ///
+    /// ```text
+    /// [universe: u32 LE] [num_sets: u32 LE] [(len: u32 LE, ids: u32 LE * len) * num_sets]
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the file cannot be read, or
+    /// `CompressionError::InvalidInput` if it is truncated or has trailing
+    /// bytes.
+    pub fn calibrate_from_file(&mut self, path: &Path) -> Result<(), CompressionError> {
+        let bytes = std::fs::read(path)?;
+        if bytes.len() % 4 != 0 {
+            return Err(CompressionError::InvalidInput(
+                "Sample file length is not a multiple of 4 bytes".to_string(),
+            ));
+        }
+        let mut words = bytes
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()));
+        let mut next = || {
+            words
+                .next()
+                .ok_or_else(|| CompressionError::InvalidInput("Truncated sample file".to_string()))
+        };
+
+        let universe = next()?;
+        let num_sets = next()?;
+        let mut sets = Vec::new();
+        for _ in 0..num_sets {
+            let len = next()?;
+            let ids = (0..len).map(|_| next()).collect::<Result<Vec<u32>, _>>()?;
+            sets.push(ids);
+        }
+        if next().is_ok() {
+            return Err(CompressionError::InvalidInput(
+                "Trailing data after sample sets".to_string(),
+            ));
+        }
+
+        self.calibrate(&sets, universe);
+        Ok(())
+    }
+
+    /// Measurements from the last calibration, one entry per method.
+    pub fn stats(&self) -> &[MethodStats] {
+        &self.stats
+    }
+
+    /// Gaps of the last calibration sample, pooled over all its sets.
+    pub fn gap_statistics(&self) -> &GapStatistics {
+        &self.gaps
+    }
+
+    /// Explain the last calibration in a few sentences, for logs or a CLI.
+    ///
+    /// Describes the sample's gaps, what their shape suggests, and the
+    /// smallest method measured.
+    pub fn advice(&self) -> String {
+        if self.stats.is_empty() {
+            return "No sample IDs measured; Roc is used by default.".to_string();
+        }
+        let gaps = &self.gaps;
+        let shape = if gaps.max_gap == 0 {
+            "No sample set has two IDs, so there are no gaps to learn from."
+        } else if gaps.fraction_unit_gaps >= 0.5 {
+            "Most IDs are consecutive, so the sets are runs that a bitset or \
+             run-aware codec stores best."
+        } else if gaps.std_dev <= 0.1 * gaps.mean_gap {
+            "Gaps are nearly constant, so a fixed-width or Elias-Fano layout \
+             loses little."
+        } else {
+            "Gaps are irregular, so gap codes such as Roc or Elias-Fano, which \
+             spend bits by the size of each gap, fit best."
+        };
+        let best = self.best_by_size();
+        let best_bytes = self
+            .stats
+            .iter()
+            .find(|s| s.method == best)
+            .map_or(0.0, |s| s.avg_bytes_per_id);
+        format!(
+            "Gaps range from {} to {} (mean {:.1}, std dev {:.1}) with {:.2} bits \
+             of entropy each; {:.0}% are 1 and {:.0}% are powers of two.\n{}\n\
+             Smallest measured: {:?} at {:.2} bytes per ID.",
+            gaps.min_gap,
+            gaps.max_gap,
+            gaps.mean_gap,
+            gaps.std_dev,
+            gaps.entropy_bits,
+            gaps.fraction_unit_gaps * 100.0,
+            gaps.fraction_power_of_two_gaps * 100.0,
+            shape,
+            best,
+            best_bytes
+        )
+    }
+
+    /// Method with the fewest compressed bytes per ID.
+    pub fn best_by_size(&self) -> IdCompressionMethod {
+        self.best_by(|s| s.avg_bytes_per_id)
+    }
+
+    /// Method with the fastest compression plus decompression.
+    pub fn best_by_speed(&self) -> IdCompressionMethod {
+        self.best_by(MethodStats::round_trip_ns)
+    }
+
+    /// Methods not beaten on both size and speed by any other method.
+    ///
+    /// Returns `(method, avg_bytes_per_id, avg_round_trip_ns)`, smallest
+    /// first.
+    pub fn pareto_optimal(&self) -> Vec<(IdCompressionMethod, f64, f64)> {
+        let mut frontier: Vec<_> = self
+            .stats
+            .iter()
+            .filter(|s| {
+                !self.stats.iter().any(|o| {
+                    o.avg_bytes_per_id <= s.avg_bytes_per_id
+                        && o.round_trip_ns() <= s.round_trip_ns()
+                        && (o.avg_bytes_per_id < s.avg_bytes_per_id
+                            || o.round_trip_ns() < s.round_trip_ns())
+                })
+            })
+            .map(|s| (s.method.clone(), s.avg_bytes_per_id, s.round_trip_ns()))
+            .collect();
+        frontier.sort_by(|a, b| a.1.total_cmp(&b.1));
+        frontier
+    }
+
+    fn best_by(&self, cost: impl Fn(&MethodStats) -> f64) -> IdCompressionMethod {
+        self.stats
+            .iter()
+            .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+            .map_or(IdCompressionMethod::Roc, |s| s.method.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dense_sample_prefers_compact_method() {
+        let sample: Vec<Vec<u32>> = (0..10)
+            .map(|i| (i * 1000..i * 1000 + 500).collect())
+            .collect();
+        let mut selector = CompressionMethodSelector::new();
+        selector.calibrate(&sample, 10_000);
+
+        assert_eq!(selector.stats().len(), candidates().len());
+        let none = &selector.stats()[0];
+        assert_eq!(none.method, IdCompressionMethod::Uncompressed);
+        assert_eq!(none.avg_bytes_per_id, 4.0);
+        assert_ne!(selector.best_by_size(), IdCompressionMethod::Uncompressed);
+
+        assert_eq!(selector.gap_statistics().fraction_unit_gaps, 1.0);
+        let advice = selector.advice();
+        assert!(advice.contains("consecutive"), "{}", advice);
+        assert!(advice.contains(&format!("{:?}", selector.best_by_size())));
+    }
+
+    #[test]
+    fn test_pareto_frontier_contains_best() {
+        let sample: Vec<Vec<u32>> = (1..30).map(|i| (0..200).map(|j| j * i).collect()).collect();
+        let mut selector = CompressionMethodSelector::new();
+        selector.calibrate(&sample, 10_000);
+
+        let frontier = selector.pareto_optimal();
+        assert!(frontier.iter().any(|f| f.0 == selector.best_by_size()));
+        assert!(frontier.iter().any(|f| f.0 == selector.best_by_speed()));
+        assert!(frontier.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+
+    #[test]
+    fn test_calibrate_from_file() {
+        let sets = [vec![1u32, 4, 9], vec![], vec![2, 3]];
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&10u32.to_le_bytes());
+        bytes.extend_from_slice(&(sets.len() as u32).to_le_bytes());
+        for ids in &sets {
+            bytes.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+            bytes.extend(ids.iter().flat_map(|id| id.to_le_bytes()));
+        }
+        let path = std::env::temp_dir().join(format!("cnk-selector-{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut from_file = CompressionMethodSelector::new();
+        from_file.calibrate_from_file(&path).unwrap();
+        let mut direct = CompressionMethodSelector::new();
+        direct.calibrate(&sets, 10);
+        assert_eq!(from_file.best_by_size(), direct.best_by_size());
+
+        std::fs::write(&path, &bytes[..bytes.len() - 2]).unwrap();
+        assert!(from_file.calibrate_from_file(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_uncalibrated_falls_back_to_roc() {
+        let selector = CompressionMethodSelector::new();
+        assert_eq!(selector.best_by_size(), IdCompressionMethod::Roc);
+        assert_eq!(selector.best_by_speed(), IdCompressionMethod::Roc);
+        assert!(selector.pareto_optimal().is_empty());
+        assert!(selector.advice().contains("Roc"));
+    }
+}
@@ -0,0 +1,381 @@
+//! Split Elias-Fano (SELF) for very large universes.
+//!
+//! Plain Elias-Fano spends `log2(u / n)` low bits on every element, which is
+//! wasteful when IDs come from a 64-bit space (hashed IDs, URL fingerprints)
+//! and are clustered. Following Ottaviano & Venturini (2014), the universe is
+//! split into blocks of `2^block_bits` IDs; only non-empty blocks are stored,
+//! each as an independent Elias-Fano list over its own small universe.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [block_bits: u8] [num_blocks: varint]
+//! [(block_id_delta: varint, block_len: varint, block_bytes: varint) * num_blocks]
+//! [Elias-Fano payload of each block, byte-aligned, in order]
+//! ```
+//!
+//! # References
+//!
+//! - Ottaviano, G. & Venturini, R. (2014). "Partitioned Elias-Fano indexes"
+
+use crate::bits::BitWriter;
+use crate::elias_fano;
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Two-level Elias-Fano compressor for 64-bit ID sets.
+///
+/// # Performance
+///
+/// - Clustered IDs: each cluster pays only for its own block universe
+/// - `next_geq_u64` binary-searches the block directory and decodes one block
+#[derive(Clone, Debug)]
+pub struct SplitEliasFanoCompressor {
+    /// log2 of the number of IDs covered by each block.
+    block_bits: u8,
+}
+
+/// Directory entry for one non-empty block.
+#[derive(Clone, Copy, Debug)]
+struct BlockEntry {
+    id: u64,
+    len: usize,
+    offset: usize,
+    bytes: usize,
+}
+
+/// Parsed header: block directory plus the payload it indexes.
+struct Directory<'a> {
+    block_bits: u8,
+    len: u64,
+    blocks: Vec<BlockEntry>,
+    payload: &'a [u8],
+}
+
+impl<'a> Directory<'a> {
+    fn parse(compressed: &'a [u8], universe: u64) -> Result<Self, CompressionError> {
+        let truncated =
+            || CompressionError::DecompressionFailed("Unexpected end of compressed data".into());
+
+        let (len, mut offset) = decode_varint(compressed)?;
+        let block_bits = *compressed.get(offset).ok_or_else(truncated)?;
+        offset += 1;
+        if !(1..=63).contains(&block_bits) {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid block_bits {}",
+                block_bits
+            )));
+        }
+        let (num_blocks, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+
+        let mut blocks: Vec<BlockEntry> = Vec::new();
+        let mut total_len = 0u64;
+        let mut payload_len = 0usize;
+        for i in 0..num_blocks {
+            let (delta, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            let (block_len, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            let (bytes, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+
+            let id = match blocks.last() {
+                None => Some(delta),
+                Some(prev) if delta > 0 => prev.id.checked_add(delta),
+                Some(_) => None,
+            }
+            .filter(|&id| id <= (universe.saturating_sub(1) >> block_bits))
+            .ok_or_else(|| {
+                CompressionError::DecompressionFailed(format!("Invalid block id in entry {}", i))
+            })?;
+            if block_len == 0
+                || block_len > 1 << block_bits
+                || bytes > (compressed.len() - offset) as u64
+            {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Invalid block entry {}",
+                    i
+                )));
+            }
+
+            total_len = total_len.checked_add(block_len).ok_or_else(|| {
+                CompressionError::DecompressionFailed(format!("Invalid block entry {}", i))
+            })?;
+            blocks.push(BlockEntry {
+                id,
+                len: block_len as usize,
+                offset: payload_len,
+                bytes: bytes as usize,
+            });
+            payload_len += bytes as usize;
+        }
+
+        let payload = &compressed[offset..];
+        if total_len != len || payload_len != payload.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Block directory does not match data: {} IDs in {} bytes",
+                total_len, payload_len
+            )));
+        }
+
+        Ok(Self {
+            block_bits,
+            len,
+            blocks,
+            payload,
+        })
+    }
+
+    /// Decode the IDs of one block.
+    fn decode_block(
+        &self,
+        block: &BlockEntry,
+        universe: u64,
+    ) -> Result<Vec<u64>, CompressionError> {
+        let base = block.id << self.block_bits;
+        let block_universe =
+            SplitEliasFanoCompressor::block_universe(base, self.block_bits, universe);
+        let bytes = &self.payload[block.offset..block.offset + block.bytes];
+        let (values, _) = elias_fano::decode(bytes, 0, block.len, block_universe)?;
+        Ok(values.into_iter().map(|v| base + v).collect())
+    }
+}
+
+impl SplitEliasFanoCompressor {
+    /// Create a compressor with blocks of `2^block_bits` IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_bits` is not in `1..=63`.
+    pub fn new(block_bits: u8) -> Self {
+        assert!(
+            (1..=63).contains(&block_bits),
+            "block_bits must be in 1..=63"
+        );
+        Self { block_bits }
+    }
+
+    /// log2 of the number of IDs covered by each block.
+    pub fn block_bits(&self) -> u8 {
+        self.block_bits
+    }
+
+    /// Universe of the block starting at `base`, clipped to the global universe.
+    fn block_universe(base: u64, block_bits: u8, universe: u64) -> u64 {
+        (1u64 << block_bits).min(universe - base)
+    }
+
+    /// Find the first ID `>= x` in a compressed set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the header or the visited block is malformed.
+    pub fn next_geq_u64(
+        &self,
+        compressed: &[u8],
+        universe: u64,
+        x: u64,
+    ) -> Result<Option<u64>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(None);
+        }
+
+        let dir = Directory::parse(compressed, universe)?;
+        let target = x >> dir.block_bits;
+        let b = dir.blocks.partition_point(|block| block.id < target);
+        for block in &dir.blocks[b..] {
+            let ids = dir.decode_block(block, universe)?;
+            if let Some(&id) = ids.iter().find(|&&id| id >= x) {
+                return Ok(Some(id));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Default for SplitEliasFanoCompressor {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+impl IdSetCompressor<u64> for SplitEliasFanoCompressor {
+    fn compress_set(&self, ids: &[u64], universe_size: u64) -> Result<Vec<u8>, CompressionError> {
+        if let Some(i) = (1..ids.len()).find(|&i| ids[i] <= ids[i - 1]) {
+            return Err(CompressionError::InvalidInput(format!(
+                "IDs must be sorted and unique, found {} <= {}",
+                ids[i],
+                ids[i - 1]
+            )));
+        }
+        let last = match ids.last() {
+            Some(&last) => last,
+            None => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut directory = Vec::new();
+        let mut payload = Vec::new();
+        let mut num_blocks = 0u64;
+        let mut prev_block = 0u64;
+        let mut rest = ids;
+        while let Some(&first) = rest.first() {
+            let block_id = first >> self.block_bits;
+            let split = rest.partition_point(|&id| id >> self.block_bits == block_id);
+            let (block, tail) = rest.split_at(split);
+            rest = tail;
+
+            let base = block_id << self.block_bits;
+            let block_universe = Self::block_universe(base, self.block_bits, universe_size);
+            let values: Vec<u64> = block.iter().map(|&id| id - base).collect();
+            let mut writer = BitWriter::new();
+            elias_fano::encode(&values, block_universe, &mut writer);
+            let bytes = writer.finish();
+
+            encode_varint(block_id - prev_block, &mut directory);
+            encode_varint(block.len() as u64, &mut directory);
+            encode_varint(bytes.len() as u64, &mut directory);
+            payload.extend_from_slice(&bytes);
+            prev_block = block_id;
+            num_blocks += 1;
+        }
+
+        let mut encoded = Vec::with_capacity(12 + directory.len() + payload.len());
+        encode_varint(ids.len() as u64, &mut encoded);
+        encoded.push(self.block_bits);
+        encode_varint(num_blocks, &mut encoded);
+        encoded.extend_from_slice(&directory);
+        encoded.extend_from_slice(&payload);
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u64,
+    ) -> Result<Vec<u64>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let dir = Directory::parse(compressed, universe_size)?;
+        // Every element takes at least one high bit
+        let mut ids = Vec::with_capacity((dir.len as usize).min(compressed.len() * 8));
+        for block in &dir.blocks {
+            ids.extend(dir.decode_block(block, universe_size)?);
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u64) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Worst case: IDs spread evenly, one Elias-Fano list over each touched block
+        let n = num_ids as u64;
+        let num_blocks = n.min((universe_size >> self.block_bits).max(1));
+        let per_block = n.div_ceil(num_blocks);
+        let block_bytes = elias_fano::encoded_bits(per_block, 1u64 << self.block_bits).div_ceil(8);
+        (num_blocks * (block_bytes + 4) + 12) as usize
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u64) -> f64 {
+        if num_ids == 0 || num_ids as u64 >= universe_size {
+            return 0.0;
+        }
+        // Stirling approximation of log2(C(N, n)) / n
+        (universe_size as f64 / num_ids as f64).log2()
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/split-elias-fano")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_64_bit() {
+        let compressor = SplitEliasFanoCompressor::new(20);
+        let ids = vec![0u64, 5, 1 << 20, (1 << 20) + 7, 1 << 50, u64::MAX - 1];
+
+        let compressed = compressor.compress_set(&ids, u64::MAX).unwrap();
+        let decompressed = compressor.decompress_set(&compressed, u64::MAX).unwrap();
+
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_decodes_with_any_block_bits() {
+        let ids: Vec<u64> = (0..100).map(|i| i * 1_000_003).collect();
+        let compressed = SplitEliasFanoCompressor::new(8)
+            .compress_set(&ids, 1 << 40)
+            .unwrap();
+
+        let decompressed = SplitEliasFanoCompressor::new(30)
+            .decompress_set(&compressed, 1 << 40)
+            .unwrap();
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_next_geq_u64() {
+        let compressor = SplitEliasFanoCompressor::new(4);
+        let ids = vec![3u64, 9, 40, 41, 1000];
+        let compressed = compressor.compress_set(&ids, 2000).unwrap();
+
+        assert_eq!(
+            compressor.next_geq_u64(&compressed, 2000, 0).unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            compressor.next_geq_u64(&compressed, 2000, 10).unwrap(),
+            Some(40)
+        );
+        assert_eq!(
+            compressor.next_geq_u64(&compressed, 2000, 42).unwrap(),
+            Some(1000)
+        );
+        assert_eq!(
+            compressor.next_geq_u64(&compressed, 2000, 1001).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_clustered_beats_flat_universe() {
+        // Two tight clusters far apart in a 64-bit universe
+        let mut ids: Vec<u64> = (0..500).map(|i| (1 << 40) + i * 3).collect();
+        ids.extend((0..500).map(|i| (1 << 60) + i * 3));
+
+        let split = SplitEliasFanoCompressor::new(16)
+            .compress_set(&ids, u64::MAX)
+            .unwrap();
+        let flat_bits = elias_fano::encoded_bits(ids.len() as u64, u64::MAX);
+        assert!((split.len() as u64) * 8 < flat_bits);
+    }
+
+    #[test]
+    fn test_huge_claimed_block_is_an_error() {
+        let compressor = SplitEliasFanoCompressor::new(16);
+        for block_bits in [16u8, 63] {
+            let mut header = Vec::new();
+            encode_varint(1 << 40, &mut header);
+            header.push(block_bits);
+            header.extend([1, 0]);
+            encode_varint(1 << 40, &mut header);
+            header.push(0);
+            assert!(compressor.decompress_set(&header, u64::MAX).is_err());
+        }
+    }
+}
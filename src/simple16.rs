@@ -0,0 +1,297 @@
+//! Simple-16 word-aligned packing of delta values.
+//!
+//! Each 32-bit word holds a 4-bit selector and 28 payload bits. The selector
+//! picks one of 16 layouts that split the payload into 1 to 28 integers of
+//! fixed widths; the encoder greedily uses the layout that packs the most
+//! upcoming values (Zhang, Long & Suel, 2008). Decoding is a table lookup
+//! and a few shifts per word with no per-integer continuation branches, and
+//! the output is a plain `u32` array as expected by C/C++ IR toolkits.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: u32 LE] [words: u32 LE...]
+//! word = selector << 28 | payload, integers packed from the low bits up
+//! ```
+//!
+//! The integers are the first ID followed by `gap - 1` for each later ID.
+//! Values of `2^28 - 1` or more are escaped: a selector-15 word with all
+//! payload bits set, followed by the raw value in the next word. When fewer
+//! integers remain than a layout holds, the unused slots are zero.
+//!
+//! # References
+//!
+//! - Zhang, J., Long, X. & Suel, T. (2008). "Performance of compressed
+//!   inverted list caching in search engines"
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+
+/// Layouts for each selector as `(num_integers, bits_per_int)` runs.
+const SELECTORS: [&[(u32, u32)]; 16] = [
+    &[(28, 1)],
+    &[(7, 2), (14, 1)],
+    &[(7, 1), (7, 2), (7, 1)],
+    &[(14, 1), (7, 2)],
+    &[(14, 2)],
+    &[(1, 4), (8, 3)],
+    &[(1, 3), (4, 4), (3, 3)],
+    &[(7, 4)],
+    &[(4, 5), (2, 4)],
+    &[(2, 4), (4, 5)],
+    &[(3, 6), (2, 5)],
+    &[(2, 5), (3, 6)],
+    &[(4, 7)],
+    &[(1, 10), (2, 9)],
+    &[(2, 14)],
+    &[(1, 28)],
+];
+
+/// Payload of a selector-15 word announcing a raw value in the next word.
+const ESCAPE: u32 = (1 << 28) - 1;
+
+/// Number of integers a selector's layout holds.
+fn slots(selector: usize) -> usize {
+    SELECTORS[selector].iter().map(|&(n, _)| n as usize).sum()
+}
+
+/// Iterate over the slot widths of a selector's layout.
+fn widths(selector: usize) -> impl Iterator<Item = u32> {
+    SELECTORS[selector]
+        .iter()
+        .flat_map(|&(n, bits)| (0..n).map(move |_| bits))
+}
+
+/// Pack `values` into Simple-16 words.
+fn encode_words(values: &[u32], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < values.len() {
+        if values[i] >= ESCAPE {
+            out.extend_from_slice(&(15 << 28 | ESCAPE).to_le_bytes());
+            out.extend_from_slice(&values[i].to_le_bytes());
+            i += 1;
+            continue;
+        }
+
+        // First (i.e. densest) layout whose slots fit every upcoming value
+        let rest = &values[i..];
+        let selector = (0..16)
+            .find(|&s| {
+                widths(s)
+                    .zip(rest)
+                    .all(|(bits, &v)| v < ESCAPE && v >> bits == 0)
+            })
+            .expect("selector 15 fits any value below the escape");
+
+        let mut word = (selector as u32) << 28;
+        let mut shift = 0;
+        for (bits, &v) in widths(selector).zip(rest) {
+            word |= v << shift;
+            shift += bits;
+        }
+        out.extend_from_slice(&word.to_le_bytes());
+        i += slots(selector).min(rest.len());
+    }
+}
+
+/// Unpack `n` integers from Simple-16 words.
+fn decode_words(words: &[u8], n: usize) -> Result<Vec<u32>, CompressionError> {
+    let mut offset = 0;
+    let mut next_word = || {
+        let word = words.get(offset..offset + 4).ok_or_else(|| {
+            CompressionError::DecompressionFailed("Unexpected end of compressed data".into())
+        })?;
+        offset += 4;
+        Ok::<u32, CompressionError>(u32::from_le_bytes(word.try_into().unwrap()))
+    };
+
+    let mut values = Vec::with_capacity(n.min(words.len() * 7));
+    while values.len() < n {
+        let word = next_word()?;
+        let selector = (word >> 28) as usize;
+        let payload = word & ESCAPE;
+
+        if selector == 15 && payload == ESCAPE {
+            values.push(next_word()?);
+            continue;
+        }
+
+        let mut shift = 0;
+        for bits in widths(selector).take(n - values.len()) {
+            values.push((payload >> shift) & ((1 << bits) - 1));
+            shift += bits;
+        }
+    }
+    if offset < words.len() {
+        return Err(CompressionError::DecompressionFailed(format!(
+            "Extra data after decompression: {} bytes",
+            words.len() - offset
+        )));
+    }
+    Ok(values)
+}
+
+/// Simple-16 compressor for sets, producing 32-bit aligned output.
+///
+/// # Performance
+///
+/// - Decoding: one selector lookup per word instead of a branch per byte
+/// - Compression ratio: between varint and bit-level codes
+#[derive(Clone, Debug, Default)]
+pub struct Simple16Compressor;
+
+impl Simple16Compressor {
+    /// Create a new Simple-16 compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for Simple16Compressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut values = Vec::with_capacity(ids.len());
+        values.push(first);
+        values.extend(ids.windows(2).map(|w| w[1] - w[0] - 1));
+
+        let mut encoded = Vec::with_capacity(4 + ids.len());
+        encoded.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+        encode_words(&values, &mut encoded);
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let len = compressed.get(..4).ok_or_else(|| {
+            CompressionError::DecompressionFailed("Unexpected end of compressed data".into())
+        })?;
+        let num_ids = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if num_ids == 0 || num_ids > universe_size as usize {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for universe size {}",
+                num_ids, universe_size
+            )));
+        }
+
+        let values = decode_words(&compressed[4..], num_ids)?;
+        let mut ids = Vec::with_capacity(num_ids);
+        let mut prev = values[0] as u64;
+        for (i, &v) in values.iter().enumerate() {
+            if i > 0 {
+                prev += v as u64 + 1;
+            }
+            if prev >= universe_size as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev as u32);
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Evenly spread gaps: the layout with the widest slots that fit the
+        // average gap decides how many gaps share a word
+        let avg_gap = (universe_size as usize / num_ids).max(1) as u32;
+        let bits = 32 - (avg_gap - 1).leading_zeros();
+        let per_word = (0..16)
+            .filter(|&s| widths(s).all(|w| w >= bits))
+            .map(slots)
+            .max()
+            .unwrap_or(1);
+        4 + 4 * num_ids.div_ceil(per_word)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/simple16")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector_table_fills_payload() {
+        for s in 0..16 {
+            assert_eq!(widths(s).sum::<u32>(), 28, "selector {}", s);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let compressor = Simple16Compressor::new();
+        let ids = vec![0u32, 1, 2, 10, 100, 1000, 65_536, 1 << 28, u32::MAX - 1];
+
+        let compressed = compressor.compress_set(&ids, u32::MAX).unwrap();
+        assert_eq!(compressed.len() % 4, 0);
+        let decompressed = compressor.decompress_set(&compressed, u32::MAX).unwrap();
+
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_consecutive_ids_pack_28_per_word() {
+        let compressor = Simple16Compressor::new();
+        let ids: Vec<u32> = (0..280).collect();
+
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        // len + 10 words of 28 one-bit slots
+        assert_eq!(compressed.len(), 4 + 40);
+    }
+
+    #[test]
+    fn test_escape_word() {
+        let mut out = Vec::new();
+        encode_words(&[ESCAPE, 7], &mut out);
+        assert_eq!(out.len(), 12);
+        assert_eq!(decode_words(&out, 2).unwrap(), [ESCAPE, 7]);
+    }
+
+    #[test]
+    fn test_truncated_and_trailing() {
+        let compressor = Simple16Compressor::new();
+        let ids: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+        let compressed = compressor.compress_set(&ids, 100_000).unwrap();
+
+        assert!(compressor
+            .decompress_set(&compressed[..compressed.len() - 4], 100_000)
+            .is_err());
+        let mut padded = compressed.clone();
+        padded.extend_from_slice(&[0; 4]);
+        assert!(compressor.decompress_set(&padded, 100_000).is_err());
+    }
+}
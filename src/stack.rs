@@ -0,0 +1,200 @@
+//! Compression into fixed-size arrays.
+//!
+//! Hot query paths and embedded targets handle many small sets, a few dozen
+//! IDs each, where allocating a `Vec` per set costs more than encoding it.
+//! [`StackCompressor`] writes the [`RocCompressor`] format into a `[u8; BUF]`
+//! and decodes into a `[u32; M]`, so a successful call never touches the
+//! heap.
+
+use crate::error::CompressionError;
+use crate::roc::{CompressionLevel, RocCompressor};
+use crate::varint::{decode_varint, varint_len, write_varint};
+
+/// Delta-varint compressor over caller-sized arrays.
+///
+/// The bytes are those of `RocCompressor::new().compress_set`, so either
+/// side can decode what the other wrote. Only errors allocate, for their
+/// messages.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::StackCompressor;
+///
+/// let stack = StackCompressor::<16>::new();
+/// let mut buf = [0u8; 16];
+/// let len = stack.compress_fixed(&[3, 9, 200], 1000, &mut buf).unwrap();
+///
+/// let (ids, count) = stack.decompress_fixed::<8>(&buf[..len], 1000).unwrap();
+/// assert_eq!(ids[..count], [3, 9, 200]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StackCompressor<const BUF: usize>;
+
+impl<const BUF: usize> StackCompressor<BUF> {
+    /// Create a compressor writing into `[u8; BUF]`.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Compress `ids` into the start of `buf`, returning the bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if the set takes more than
+    /// `BUF` bytes, and `CompressionError::InvalidInput` if `ids` is not
+    /// sorted and unique or reaches past the universe.
+    pub fn compress_fixed(
+        &self,
+        ids: &[u32],
+        universe: u32,
+        buf: &mut [u8; BUF],
+    ) -> Result<usize, CompressionError> {
+        RocCompressor::validate_ids(ids)?;
+        let (first, last) = match (ids.first(), ids.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(0),
+        };
+        if last >= universe {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe
+            )));
+        }
+
+        let needed = 1
+            + varint_len(ids.len() as u64)
+            + varint_len(first as u64)
+            + ids
+                .windows(2)
+                .map(|w| varint_len((w[1] - w[0]) as u64))
+                .sum::<usize>();
+        if needed > BUF {
+            return Err(CompressionError::BufferTooSmall {
+                needed,
+                available: BUF,
+            });
+        }
+
+        buf[0] = CompressionLevel::Default as u8;
+        let mut len = 1;
+        len += write_varint(ids.len() as u64, &mut buf[len..]);
+        len += write_varint(first as u64, &mut buf[len..]);
+        for w in ids.windows(2) {
+            len += write_varint((w[1] - w[0]) as u64, &mut buf[len..]);
+        }
+        Ok(len)
+    }
+
+    /// Decompress a set into an array of `M` IDs, returning it with the
+    /// number of IDs filled in; the rest are zero.
+    ///
+    /// Accepts any [`RocCompressor`] output, at either level tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if the set holds more than
+    /// `M` IDs, with the counts in IDs rather than bytes, and
+    /// `CompressionError::DecompressionFailed` if `compressed` is malformed.
+    pub fn decompress_fixed<const M: usize>(
+        &self,
+        compressed: &[u8],
+        universe: u32,
+    ) -> Result<([u32; M], usize), CompressionError> {
+        let mut ids = [0u32; M];
+        if compressed.is_empty() {
+            return Ok((ids, 0));
+        }
+
+        let (count, mut offset) = RocCompressor::read_header(compressed)?;
+        if count == 0 || count > universe as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for universe size {}",
+                count, universe
+            )));
+        }
+        if count > M as u64 {
+            return Err(CompressionError::BufferTooSmall {
+                needed: count as usize,
+                available: M,
+            });
+        }
+
+        let mut prev = 0u64;
+        for (i, slot) in ids[..count as usize].iter_mut().enumerate() {
+            let (value, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            if i > 0 && value == 0 {
+                return Err(CompressionError::DecompressionFailed(
+                    "Zero delta produces duplicate ID".to_string(),
+                ));
+            }
+            let id = if i == 0 { value } else { prev + value };
+            if id >= universe as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    id, universe
+                )));
+            }
+            *slot = id as u32;
+            prev = id;
+        }
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+        Ok((ids, count as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdSetCompressor;
+
+    #[test]
+    fn test_matches_roc_bytes() {
+        let roc = RocCompressor::new();
+        let stack = StackCompressor::<64>::new();
+        let ids = [0u32, 1, 130, 20_000, 3_000_000];
+        let mut buf = [0u8; 64];
+        let len = stack.compress_fixed(&ids, 4_000_000, &mut buf).unwrap();
+        assert_eq!(buf[..len], roc.compress_set(&ids, 4_000_000).unwrap()[..]);
+
+        // And reads both level tags back
+        for level in [CompressionLevel::Default, CompressionLevel::Fastest] {
+            let compressed = RocCompressor::with_level(level)
+                .compress_set(&ids, 4_000_000)
+                .unwrap();
+            let (decoded, count) = stack.decompress_fixed::<5>(&compressed, 4_000_000).unwrap();
+            assert_eq!((decoded, count), (ids, 5));
+        }
+    }
+
+    #[test]
+    fn test_reports_small_buffers() {
+        let stack = StackCompressor::<4>::new();
+        let mut buf = [0u8; 4];
+        assert_eq!(stack.compress_fixed(&[], 10, &mut buf).unwrap(), 0);
+        assert_eq!(stack.compress_fixed(&[1, 2], 10, &mut buf).unwrap(), 4);
+        assert!(matches!(
+            stack.compress_fixed(&[1, 2, 3], 10, &mut buf),
+            Err(CompressionError::BufferTooSmall {
+                needed: 5,
+                available: 4
+            })
+        ));
+        assert!(matches!(
+            stack.decompress_fixed::<1>(&buf, 10),
+            Err(CompressionError::BufferTooSmall {
+                needed: 2,
+                available: 1
+            })
+        ));
+        assert!(stack.compress_fixed(&[2, 1], 10, &mut buf).is_err());
+        assert!(stack.decompress_fixed::<4>(&[5, 2, 1, 0], 10).is_err());
+        assert_eq!(stack.decompress_fixed::<0>(&[], 10).unwrap().1, 0);
+    }
+}
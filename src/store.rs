@@ -0,0 +1,261 @@
+//! Memory-mapped on-disk store of compressed posting lists.
+//!
+//! Serving an index means reading a few posting lists out of a file that can
+//! be far larger than memory. [`CompressedSetStore`] maps the file and only
+//! touches the pages of the lists that are actually requested.
+//!
+//! # Format
+//!
+//! ```text
+//! [magic: b"CNKSTORE"]
+//! [count: u32 LE]
+//! [(offset: u32 LE, length: u32 LE) * count]   (offsets relative to payload start)
+//! [payload: compressed sets, concatenated]
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+
+/// File magic identifying a store.
+const MAGIC: &[u8; 8] = b"CNKSTORE";
+
+/// Size of the magic plus the count field.
+const HEADER_LEN: usize = 12;
+
+/// Read-only store of compressed sets backed by a memory-mapped file.
+///
+/// The compressor `C` must match the one passed to [`build`](Self::build);
+/// it defaults to [`RocCompressor`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::path::Path;
+/// use cnk::{CompressedSetStore, RocCompressor};
+///
+/// let path = Path::new("postings.cnk");
+/// let sets = vec![vec![1u32, 5, 9], vec![], vec![2, 3]];
+/// CompressedSetStore::build(path, &sets, 100, &RocCompressor::new()).unwrap();
+///
+/// let store = CompressedSetStore::open(path, 100).unwrap();
+/// assert_eq!(store.get(2).unwrap(), Some(vec![2, 3]));
+/// assert_eq!(store.get(3).unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct CompressedSetStore<C = RocCompressor> {
+    mmap: Mmap,
+    universe_size: u32,
+    count: usize,
+    compressor: C,
+}
+
+impl CompressedSetStore<RocCompressor> {
+    /// Open a store whose sets were compressed with [`RocCompressor`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if the file cannot be mapped, or
+    /// `CompressionError::DecompressionFailed` if the header or index is
+    /// malformed.
+    pub fn open(path: &Path, universe: u32) -> Result<Self, CompressionError> {
+        Self::open_with_compressor(path, universe, RocCompressor::new())
+    }
+
+    /// Write `sets` to a new store file at `path`, compressing each with `c`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if a set cannot be compressed, the payload
+    /// exceeds 4 GiB, or the file cannot be written.
+    pub fn build(
+        path: &Path,
+        sets: &[Vec<u32>],
+        universe: u32,
+        c: &dyn IdSetCompressor,
+    ) -> Result<(), CompressionError> {
+        let too_large =
+            || CompressionError::CompressionFailed("Store exceeds 4 GiB limit".to_string());
+
+        let mut index = Vec::with_capacity(8 * sets.len());
+        let mut payload = Vec::new();
+        for ids in sets {
+            let compressed = c.compress_set(ids, universe)?;
+            let offset = u32::try_from(payload.len()).map_err(|_| too_large())?;
+            let len = u32::try_from(compressed.len()).map_err(|_| too_large())?;
+            index.extend_from_slice(&offset.to_le_bytes());
+            index.extend_from_slice(&len.to_le_bytes());
+            payload.extend_from_slice(&compressed);
+        }
+        let count = u32::try_from(sets.len()).map_err(|_| too_large())?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&count.to_le_bytes())?;
+        writer.write_all(&index)?;
+        writer.write_all(&payload)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<C: IdSetCompressor> CompressedSetStore<C> {
+    /// Open a store whose sets were compressed with `compressor`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`open`](CompressedSetStore::open).
+    pub fn open_with_compressor(
+        path: &Path,
+        universe: u32,
+        compressor: C,
+    ) -> Result<Self, CompressionError> {
+        let file = File::open(path)?;
+        // Safety: the store is read-only; callers must not truncate or modify
+        // the file while it is mapped, as with any mmap-backed index.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err(CompressionError::DecompressionFailed(
+                "Not a compressed set store".to_string(),
+            ));
+        }
+        let count = u32::from_le_bytes(mmap[8..12].try_into().unwrap()) as usize;
+        let payload_start = HEADER_LEN + 8 * count;
+        if payload_start > mmap.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Store index for {} sets exceeds file size {}",
+                count,
+                mmap.len()
+            )));
+        }
+
+        let store = Self {
+            mmap,
+            universe_size: universe,
+            count,
+            compressor,
+        };
+        let payload_len = (store.mmap.len() - payload_start) as u64;
+        for idx in 0..count {
+            let (offset, len) = store.entry(idx);
+            if offset as u64 + len as u64 > payload_len {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Set {} extends past end of store",
+                    idx
+                )));
+            }
+        }
+        Ok(store)
+    }
+
+    /// Number of sets in the store.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether the store holds no sets.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Decompress set `idx`, or `None` if `idx` is out of range.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the stored bytes cannot be decompressed.
+    pub fn get(&self, idx: usize) -> Result<Option<Vec<u32>>, CompressionError> {
+        self.get_compressed(idx)
+            .map(|bytes| self.compressor.decompress_set(bytes, self.universe_size))
+            .transpose()
+    }
+
+    /// Compressed bytes of set `idx`, borrowed from the mapping.
+    pub fn get_compressed(&self, idx: usize) -> Option<&[u8]> {
+        if idx >= self.count {
+            return None;
+        }
+        let (offset, len) = self.entry(idx);
+        let start = HEADER_LEN + 8 * self.count + offset as usize;
+        Some(&self.mmap[start..start + len as usize])
+    }
+
+    /// Index entry `(offset, length)` for set `idx`.
+    fn entry(&self, idx: usize) -> (u32, u32) {
+        let at = HEADER_LEN + 8 * idx;
+        let read_u32 = |at: usize| u32::from_le_bytes(self.mmap[at..at + 4].try_into().unwrap());
+        (read_u32(at), read_u32(at + 4))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HuffmanCompressor;
+    use std::path::PathBuf;
+
+    /// A per-test path in the system temp directory, removed on drop.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("cnk-{}-{}.store", name, std::process::id())))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_build_and_reopen_1000_lists() {
+        let path = TempPath::new("reopen");
+        let universe = 100_000;
+        let sets: Vec<Vec<u32>> = (0..1000u32)
+            .map(|i| (0..i % 50).map(|j| j * 997 + i).collect())
+            .collect();
+
+        CompressedSetStore::build(&path.0, &sets, universe, &RocCompressor::new()).unwrap();
+        let store = CompressedSetStore::open(&path.0, universe).unwrap();
+
+        assert_eq!(store.len(), 1000);
+        for (i, ids) in sets.iter().enumerate() {
+            assert_eq!(store.get(i).unwrap().as_ref(), Some(ids));
+        }
+        assert_eq!(store.get(1000).unwrap(), None);
+    }
+
+    #[test]
+    fn test_custom_compressor() {
+        let path = TempPath::new("huffman");
+        let sets = vec![vec![1u32, 2, 3, 4, 8], vec![9]];
+
+        CompressedSetStore::build(&path.0, &sets, 10, &HuffmanCompressor::new()).unwrap();
+        let store = CompressedSetStore::open_with_compressor(&path.0, 10, HuffmanCompressor::new())
+            .unwrap();
+        assert_eq!(store.get(0).unwrap(), Some(sets[0].clone()));
+    }
+
+    #[test]
+    fn test_rejects_corrupt_files() {
+        let path = TempPath::new("corrupt");
+        std::fs::write(&path.0, b"NOTASTORE...").unwrap();
+        assert!(CompressedSetStore::open(&path.0, 10).is_err());
+
+        // Valid magic, index claims one set past the end of the file
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        std::fs::write(&path.0, &bytes).unwrap();
+        assert!(CompressedSetStore::open(&path.0, 10).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+//! Generators of valid test sets (`test-utils` feature).
+//!
+//! Every compressor expects sorted, unique IDs below the universe. These
+//! helpers produce such sets, for tests in this crate and in crates built on
+//! it: [`sorted_unique_ids_proptest`] as a `proptest` strategy, and
+//! [`random_posting_list`] and [`zipf_posting_list`] as plain functions that
+//! are deterministic in their seed. [`sample_ids`], and the [`Lcg`] behind
+//! it for other random inputs, are shared by benchmarks and tests, fixed
+//! across platforms and releases.
+//!
+//! # Example
+//!
+//! ```rust
+//! use cnk::testutils::{random_posting_list, zipf_posting_list};
+//!
+//! let ids = random_posting_list(7, 100, 1000);
+//! assert_eq!(ids, random_posting_list(7, 100, 1000));
+//! assert!(ids.windows(2).all(|w| w[0] < w[1]));
+//!
+//! let skewed = zipf_posting_list(7, 100, 1_000_000, 1.2);
+//! assert!(skewed.iter().all(|&id| id < 1_000_000));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::CompressionError;
+
+use proptest::prelude::*;
+
+/// Strategy for `(ids, universe)` with `1..=max_len` sorted, unique IDs.
+///
+/// The universe is `universe`, raised to `max_len` if smaller so that every
+/// length fits. Sets close to filling the universe are slow to generate, so
+/// keep `max_len` well below `universe`.
+pub fn sorted_unique_ids_proptest(
+    max_len: usize,
+    universe: u32,
+) -> impl Strategy<Value = (Vec<u32>, u32)> {
+    (1..=max_len).prop_flat_map(move |len| {
+        let universe = universe.max(len as u32);
+        proptest::collection::btree_set(0..universe, len)
+            .prop_map(move |set| (set.into_iter().collect(), universe))
+    })
+}
+
+/// `n` IDs drawn uniformly without replacement from `[0, universe)`.
+///
+/// # Panics
+///
+/// Panics if `n > universe`.
+pub fn random_posting_list(seed: u64, n: usize, universe: u32) -> Vec<u32> {
+    assert!(
+        n as u64 <= universe as u64,
+        "cannot draw {} unique IDs from a universe of {}",
+        n,
+        universe
+    );
+
+    // Floyd's algorithm: one draw per ID, whatever the density
+    let mut rng = SplitMix64(seed);
+    let mut chosen = HashSet::with_capacity(n);
+    for j in (universe - n as u32)..universe {
+        let t = rng.below(j as u64 + 1) as u32;
+        if !chosen.insert(t) {
+            chosen.insert(j);
+        }
+    }
+
+    let mut ids: Vec<u32> = chosen.into_iter().collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// `n` IDs drawn uniformly without replacement from `[0, universe)`,
+/// identical for a given seed on every platform.
+///
+/// A partial Fisher-Yates shuffle of `[0, universe)` driven by a 64-bit
+/// LCG, storing only the swapped slots. Unlike [`random_posting_list`],
+/// whose output may change between releases, the sequence is part of the
+/// API, so benchmark inputs stay comparable over time.
+///
+/// # Errors
+///
+/// Returns `CompressionError::InvalidInput` if `n > universe`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::testutils::sample_ids;
+///
+/// let ids = sample_ids(1000, 100, 42).unwrap();
+/// assert_eq!(ids.len(), 100);
+/// assert!(ids.windows(2).all(|w| w[0] < w[1]));
+/// assert!(sample_ids(10, 11, 42).is_err());
+/// ```
+pub fn sample_ids(universe: u32, n: usize, seed: u64) -> Result<Vec<u32>, CompressionError> {
+    if n as u64 > universe as u64 {
+        return Err(CompressionError::InvalidInput(format!(
+            "Cannot draw {} unique IDs from a universe of {}",
+            n, universe
+        )));
+    }
+
+    // Slot i of the virtual array [0, universe) holds moved[i], or i itself
+    let mut rng = Lcg::new(seed);
+    let mut moved: HashMap<u32, u32> = HashMap::with_capacity(n);
+    let mut ids = Vec::with_capacity(n);
+    for i in 0..n as u32 {
+        let j = i + rng.below(universe - i);
+        let at_i = moved.get(&i).copied().unwrap_or(i);
+        ids.push(moved.insert(j, at_i).unwrap_or(j));
+    }
+    ids.sort_unstable();
+    Ok(ids)
+}
+
+/// `n` IDs from `[0, universe)` whose gaps follow a Zipf law.
+///
+/// Each gap `g` in `1..=universe / n` is drawn with probability about
+/// `g^-exponent`, as in posting lists of frequent terms: mostly small gaps,
+/// with occasional long jumps. An exponent of 0 gives uniform gaps. Gaps
+/// are capped so that the list always fits the universe.
+///
+/// # Panics
+///
+/// Panics if `n > universe` or `exponent` is negative or not finite.
+pub fn zipf_posting_list(seed: u64, n: usize, universe: u32, exponent: f64) -> Vec<u32> {
+    assert!(
+        n as u64 <= universe as u64,
+        "cannot draw {} unique IDs from a universe of {}",
+        n,
+        universe
+    );
+    assert!(
+        exponent.is_finite() && exponent >= 0.0,
+        "Zipf exponent must be finite and non-negative, got {}",
+        exponent
+    );
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let max_gap = (universe as u64 / n as u64) as f64;
+    let mut rng = SplitMix64(seed);
+    let mut gap = || -> u32 {
+        // Inverse CDF of the continuous power law on [1, max_gap + 1)
+        let u = rng.next_f64();
+        let x = if (exponent - 1.0).abs() < 1e-9 {
+            (max_gap + 1.0).powf(u)
+        } else {
+            let a = 1.0 - exponent;
+            (1.0 + u * ((max_gap + 1.0).powf(a) - 1.0)).powf(1.0 / a)
+        };
+        (x.floor() as u32).clamp(1, max_gap as u32)
+    };
+
+    // n gaps of at most universe / n sum to below the universe
+    let mut id = gap() - 1;
+    let mut ids = Vec::with_capacity(n);
+    ids.push(id);
+    for _ in 1..n {
+        id += gap();
+        ids.push(id);
+    }
+    ids
+}
+
+/// SplitMix64 (Steele et al., 2014), enough for reproducible test data.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, bound)`, by multiply-shift.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Knuth's MMIX LCG, whose output is fixed by integer arithmetic alone.
+///
+/// The generator behind [`sample_ids`], for test data that is not a set:
+/// gap sequences, shuffles, query streams. Its sequence for a seed is part
+/// of the API. Statistically weak in the low bits, so the methods draw from
+/// the high ones.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::testutils::Lcg;
+///
+/// let mut rng = Lcg::new(7);
+/// let gaps: Vec<u32> = (0..100).map(|_| 1 + rng.below(200)).collect();
+/// assert!(gaps.iter().all(|&g| (1..=200).contains(&g)));
+/// assert_eq!(Lcg::new(7).below(200), gaps[0] - 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lcg(u64);
+
+impl Lcg {
+    /// Generator starting from state `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Advance and return the full 64-bit state.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0
+    }
+
+    /// The high 32 bits of the next state.
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform in `[0, bound)`, from the high 32 bits by multiply-shift.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        ((self.next_u32() as u64 * bound as u64) >> 32) as u32
+    }
+
+    /// Uniform in `[0, 1)`, from the high 53 bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_valid(ids: &[u32], n: usize, universe: u32) {
+        assert_eq!(ids.len(), n);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert!(ids.iter().all(|&id| id < universe));
+    }
+
+    proptest! {
+        #[test]
+        fn strategy_yields_valid_sets((ids, universe) in sorted_unique_ids_proptest(100, 10_000)) {
+            prop_assert!(!ids.is_empty() && universe >= ids.len() as u32);
+            assert_valid(&ids, ids.len(), universe);
+        }
+
+        #[test]
+        fn random_lists_are_valid(seed: u64, universe in 1u32..10_000, fill in 0.0f64..=1.0) {
+            let n = (universe as f64 * fill) as usize;
+            assert_valid(&random_posting_list(seed, n, universe), n, universe);
+        }
+
+        #[test]
+        fn sampled_ids_are_valid(seed: u64, universe in 1u32..10_000, fill in 0.0f64..=1.0) {
+            let n = (universe as f64 * fill) as usize;
+            assert_valid(&sample_ids(universe, n, seed).unwrap(), n, universe);
+        }
+
+        #[test]
+        fn zipf_lists_are_valid(
+            seed: u64,
+            universe in 1u32..=u32::MAX,
+            n in 0usize..2000,
+            exponent in 0.0f64..4.0,
+        ) {
+            let n = n.min(universe as usize);
+            assert_valid(&zipf_posting_list(seed, n, universe, exponent), n, universe);
+        }
+    }
+
+    #[test]
+    fn test_deterministic_in_seed() {
+        assert_eq!(
+            random_posting_list(1, 50, 1000),
+            random_posting_list(1, 50, 1000)
+        );
+        assert_ne!(
+            random_posting_list(1, 50, 1000),
+            random_posting_list(2, 50, 1000)
+        );
+        assert_eq!(
+            zipf_posting_list(1, 50, 1000, 1.0),
+            zipf_posting_list(1, 50, 1000, 1.0)
+        );
+
+        // The whole universe leaves no choice
+        assert_eq!(random_posting_list(3, 10, 10), (0..10).collect::<Vec<_>>());
+        assert_eq!(
+            zipf_posting_list(3, 10, 10, 2.0),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_sample_ids_is_fixed() {
+        // Pinned: the LCG uses only wrapping integer arithmetic, so these
+        // values hold on every platform
+        let ids = sample_ids(1000, 100, 42).unwrap();
+        assert_eq!(ids, sample_ids(1000, 100, 42).unwrap());
+        assert_eq!(ids[..10], [3, 25, 27, 28, 30, 31, 33, 34, 42, 44]);
+        assert_eq!(
+            ids[90..],
+            [897, 909, 911, 921, 935, 939, 942, 964, 974, 987]
+        );
+        assert_valid(&ids, 100, 1000);
+
+        assert_ne!(sample_ids(1000, 100, 43).unwrap(), ids);
+        assert_eq!(sample_ids(10, 10, 1).unwrap(), (0..10).collect::<Vec<_>>());
+        assert!(sample_ids(u32::MAX, 0, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sample_ids_rejects_overfull() {
+        assert!(matches!(
+            sample_ids(10, 11, 0),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_higher_exponent_means_smaller_gaps() {
+        let span = |exponent| {
+            *zipf_posting_list(9, 1000, 1_000_000, exponent)
+                .last()
+                .unwrap()
+        };
+        assert!(span(2.0) < span(1.0));
+        assert!(span(1.0) < span(0.0));
+    }
+}
@@ -0,0 +1,102 @@
+//! Structured trace events for compression calls (`tracing` feature).
+//!
+//! With the feature enabled, [`RocCompressor`](crate::RocCompressor) emits a
+//! `trace` event per successful `compress_set` and `decompress_set` call,
+//! under the `cnk` target, with the fields:
+//!
+//! - `codec`: the format ID
+//! - `ids_count`, `universe_size`
+//! - `compressed_bytes`
+//! - `duration_ns`
+//! - `compression_ratio`: raw `u32` bytes over compressed bytes
+//!
+//! Decoding warns with `trailing_bytes` before rejecting input that has
+//! bytes left over. Without the feature every hook here compiles to nothing.
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// Times one call and reports it on completion.
+pub(crate) struct Timer {
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl Timer {
+    /// Start timing.
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+        }
+    }
+
+    /// Report a finished compression.
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn compressed(
+        self,
+        codec: &'static str,
+        ids_count: usize,
+        universe_size: u32,
+        compressed_bytes: usize,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            target: "cnk",
+            codec,
+            ids_count,
+            universe_size,
+            compressed_bytes,
+            duration_ns = self.start.elapsed().as_nanos() as u64,
+            compression_ratio = ratio(ids_count, compressed_bytes),
+            "compressed set"
+        );
+    }
+
+    /// Report a finished decompression.
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn decompressed(
+        self,
+        codec: &'static str,
+        ids_count: usize,
+        universe_size: u32,
+        compressed_bytes: usize,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            target: "cnk",
+            codec,
+            ids_count,
+            universe_size,
+            compressed_bytes,
+            duration_ns = self.start.elapsed().as_nanos() as u64,
+            compression_ratio = ratio(ids_count, compressed_bytes),
+            "decompressed set"
+        );
+    }
+}
+
+/// Warn that `trailing_bytes` were left after decoding a complete set.
+#[inline]
+#[allow(unused_variables)]
+pub(crate) fn trailing_data(codec: &'static str, trailing_bytes: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        target: "cnk",
+        codec,
+        trailing_bytes,
+        "trailing data after compressed set"
+    );
+}
+
+/// Raw `u32` size over compressed size; 1 for the empty set.
+#[cfg(feature = "tracing")]
+fn ratio(ids_count: usize, compressed_bytes: usize) -> f64 {
+    if compressed_bytes == 0 {
+        return 1.0;
+    }
+    (ids_count * 4) as f64 / compressed_bytes as f64
+}
@@ -1,6 +1,42 @@
 //! Compression trait definitions.
 
+use std::fmt;
+use std::hash::Hash;
+use std::io::Write;
+
+use crate::analysis::SizeHistogram;
 use crate::error::CompressionError;
+use crate::types::Universe;
+
+/// Unsigned integer types usable as IDs.
+///
+/// Implemented for `u8`, `u16`, `u32` and `u64`. Most compressors work on
+/// `u32`, which is the default ID type of [`IdSetCompressor`]; `u64` is used
+/// for hashed or fingerprint IDs from very large universes.
+pub trait IdType: Copy + Ord + Hash + fmt::Debug + fmt::Display + Send + Sync + 'static {
+    /// Largest representable ID.
+    const MAX: Self;
+
+    /// Widen to `u64`.
+    fn to_u64(self) -> u64;
+}
+
+macro_rules! impl_id_type {
+    ($($t:ty),*) => {
+        $(
+            impl IdType for $t {
+                const MAX: Self = <$t>::MAX;
+
+                #[inline]
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_id_type!(u8, u16, u32, u64);
 
 /// Trait for compressing sets of IDs where order doesn't matter.
 ///
@@ -9,9 +45,11 @@ use crate::error::CompressionError;
 ///
 /// # Requirements
 ///
-/// - Input IDs must be sorted and unique
+/// - Input IDs must be sorted and unique, unless
+///   [`requires_sorted_input`](Self::requires_sorted_input) returns `false`
 /// - Compression should exploit ordering invariance
-/// - Decompression should return sorted IDs
+/// - Decompression should return sorted IDs (or the input order, for
+///   compressors that accept unsorted input)
 ///
 /// # Theoretical Background
 ///
@@ -20,7 +58,13 @@ use crate::error::CompressionError;
 /// This is significantly less than encoding a sequence (`N^n` possibilities).
 ///
 /// Implementations should aim to approach this bound.
-pub trait IdSetCompressor {
+///
+/// # ID Types
+///
+/// The trait is generic over the ID type `T`, defaulting to `u32`, so
+/// `dyn IdSetCompressor` means `dyn IdSetCompressor<u32>`. Compressors for
+/// 64-bit universes implement `IdSetCompressor<u64>`.
+pub trait IdSetCompressor<T: IdType = u32> {
     /// Compress a set of IDs (order-invariant).
     ///
     /// # Arguments
@@ -35,7 +79,111 @@ pub trait IdSetCompressor {
     /// # Errors
     ///
     /// Returns `CompressionError` if input is invalid or compression fails.
-    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError>;
+    fn compress_set(&self, ids: &[T], universe_size: T) -> Result<Vec<u8>, CompressionError>;
+
+    /// Compress IDs from an iterator in ascending order.
+    ///
+    /// Accepts IDs from merges, cursors or generators without requiring a
+    /// slice. The default collects into a `Vec` and calls
+    /// [`compress_set`](Self::compress_set); compressors with a streaming
+    /// encoder override it to avoid materialising the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if the IDs are not strictly
+    /// ascending or exceed the universe.
+    fn compress_sorted_iter<I>(
+        &self,
+        iter: I,
+        universe_size: T,
+    ) -> Result<Vec<u8>, CompressionError>
+    where
+        I: IntoIterator<Item = T>,
+        Self: Sized,
+    {
+        let ids: Vec<T> = iter.into_iter().collect();
+        self.compress_set(&ids, universe_size)
+    }
+
+    /// Compress a set, appending the bytes to `out`.
+    ///
+    /// Lets callers that write many sets reuse one buffer. Returns the number
+    /// of bytes appended. On error, `out` is left unchanged. The default
+    /// calls [`compress_set`](Self::compress_set) and copies the result.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`compress_set`](Self::compress_set).
+    fn compress_into(
+        &self,
+        ids: &[T],
+        universe_size: T,
+        out: &mut Vec<u8>,
+    ) -> Result<usize, CompressionError> {
+        let compressed = self.compress_set(ids, universe_size)?;
+        out.extend_from_slice(&compressed);
+        Ok(compressed.len())
+    }
+
+    /// Compress a set into the start of `buf`, returning the written prefix.
+    ///
+    /// For writing many sets contiguously into a preallocated arena. Size
+    /// `buf` with [`estimate_compressed_size_bytes`](Self::estimate_compressed_size_bytes),
+    /// which is exact for the delta-varint [`RocCompressor`](crate::RocCompressor).
+    /// The default compresses with [`compress_set`](Self::compress_set) and
+    /// copies; compressors that can encode in place override it to avoid
+    /// allocating.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::BufferTooSmall` if the compressed set does
+    /// not fit in `buf`, and otherwise the same errors as
+    /// [`compress_set`](Self::compress_set).
+    fn compress_set_reuse<'a>(
+        &self,
+        ids: &[T],
+        universe_size: T,
+        buf: &'a mut [u8],
+    ) -> Result<&'a [u8], CompressionError> {
+        let compressed = self.compress_set(ids, universe_size)?;
+        if compressed.len() > buf.len() {
+            return Err(CompressionError::BufferTooSmall {
+                needed: compressed.len(),
+                available: buf.len(),
+            });
+        }
+        let out = &mut buf[..compressed.len()];
+        out.copy_from_slice(&compressed);
+        Ok(out)
+    }
+
+    /// Compress a set straight into `writer`, returning the bytes written.
+    ///
+    /// For sets of millions of IDs, where holding the whole compressed
+    /// output in memory as well would double the peak. The default
+    /// compresses with [`compress_set`](Self::compress_set) and writes the
+    /// result; sequential encoders override it to write as they go. Wrap
+    /// unbuffered writers in a `BufWriter`, as overrides may issue many
+    /// small writes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::Io` if writing fails, and otherwise the
+    /// same errors as [`compress_set`](Self::compress_set). Input is
+    /// validated before anything is written.
+    fn streaming_compress<W: Write>(
+        &self,
+        ids: &[T],
+        universe_size: T,
+        writer: &mut W,
+    ) -> Result<usize, CompressionError>
+    where
+        Self: Sized,
+    {
+        let compressed = self.compress_set(ids, universe_size)?;
+        writer.write_all(&compressed)?;
+        Ok(compressed.len())
+    }
 
     /// Decompress a set of IDs.
     ///
@@ -54,8 +202,85 @@ pub trait IdSetCompressor {
     fn decompress_set(
         &self,
         compressed: &[u8],
-        universe_size: u32,
-    ) -> Result<Vec<u32>, CompressionError>;
+        universe_size: T,
+    ) -> Result<Vec<T>, CompressionError>;
+
+    /// Decompress only the IDs in `[lo, hi)`, in ascending order.
+    ///
+    /// The default decompresses the whole set and filters it; sequential
+    /// decoders override it to skip IDs below `lo` without collecting them
+    /// and to stop at the first ID `>= hi`. An empty range (`lo >= hi`)
+    /// yields no IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the decoded part of `compressed` is
+    /// malformed. Overrides that stop early do not check the rest.
+    fn decompress_range(
+        &self,
+        compressed: &[u8],
+        universe_size: T,
+        lo: T,
+        hi: T,
+    ) -> Result<Vec<T>, CompressionError> {
+        let mut ids = self.decompress_set(compressed, universe_size)?;
+        ids.retain(|&id| lo <= id && id < hi);
+        Ok(ids)
+    }
+
+    /// Count the IDs in `[lo, hi)` without collecting them.
+    ///
+    /// The default is the length of
+    /// [`decompress_range`](Self::decompress_range); sequential decoders
+    /// override it to count as they decode, without allocating.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`decompress_range`](Self::decompress_range).
+    fn count_in_range(
+        &self,
+        compressed: &[u8],
+        universe_size: T,
+        lo: T,
+        hi: T,
+    ) -> Result<usize, CompressionError> {
+        Ok(self
+            .decompress_range(compressed, universe_size, lo, hi)?
+            .len())
+    }
+
+    /// [`compress_set`](Self::compress_set) with the universe as a
+    /// [`Universe`], so it cannot be swapped with an ID by mistake.
+    ///
+    /// `compress_set` itself keeps `universe_size: T`, because the `u64`
+    /// compressors need universes wider than a [`Universe`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`compress_set`](Self::compress_set).
+    fn compress_set_in(&self, ids: &[T], universe: Universe) -> Result<Vec<u8>, CompressionError>
+    where
+        T: From<u32>,
+    {
+        self.compress_set(ids, T::from(universe.0))
+    }
+
+    /// [`decompress_set`](Self::decompress_set) with the universe as a
+    /// [`Universe`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`decompress_set`](Self::decompress_set).
+    fn decompress_set_in(
+        &self,
+        compressed: &[u8],
+        universe: Universe,
+    ) -> Result<Vec<T>, CompressionError>
+    where
+        T: From<u32>,
+    {
+        self.decompress_set(compressed, T::from(universe.0))
+    }
 
     /// Estimate compressed size without full compression.
     ///
@@ -69,9 +294,31 @@ pub trait IdSetCompressor {
     /// # Returns
     ///
     /// Estimated compressed size in bytes.
-    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize;
+    fn estimate_size(&self, num_ids: usize, universe_size: T) -> usize;
 
-    /// Get compression ratio (bits per ID).
+    /// Estimate compressed size from the IDs themselves.
+    ///
+    /// Tighter than [`estimate_size`](Self::estimate_size) because it can
+    /// look at the actual gap distribution. Implementations should make a
+    /// single pass over `ids` without allocating. The default falls back to
+    /// `estimate_size(ids.len(), universe_size)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - Sorted, unique IDs
+    /// * `universe_size` - Maximum possible ID value
+    ///
+    /// # Returns
+    ///
+    /// Estimated compressed size in bytes.
+    fn estimate_compressed_size_bytes(&self, ids: &[T], universe_size: T) -> usize {
+        self.estimate_size(ids.len(), universe_size)
+    }
+
+    /// Information-theoretic lower bound in bits per ID.
+    ///
+    /// This is what an optimal set coder could achieve, not what this
+    /// compressor produces; see [`actual_bits_per_id`](Self::actual_bits_per_id).
     ///
     /// # Arguments
     ///
@@ -81,5 +328,94 @@ pub trait IdSetCompressor {
     /// # Returns
     ///
     /// Average bits per ID (theoretical lower bound).
-    fn bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64;
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: T) -> f64;
+
+    /// Bits per ID this compressor actually spends on `ids`.
+    ///
+    /// Compresses the set and divides the output size in bits by the number
+    /// of IDs. Returns `0.0` for an empty set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if `ids` cannot be compressed.
+    fn actual_bits_per_id(&self, ids: &[T], universe_size: T) -> Result<f64, CompressionError> {
+        if ids.is_empty() {
+            return Ok(0.0);
+        }
+        let compressed = self.compress_set(ids, universe_size)?;
+        Ok((compressed.len() * 8) as f64 / ids.len() as f64)
+    }
+
+    /// Compress a set using a probability model chosen by `context`.
+    ///
+    /// `context` is any key the caller can reproduce when decoding, e.g. the
+    /// IVF centroid whose cluster the IDs belong to. The default ignores it
+    /// and calls [`compress_set`](Self::compress_set); context-aware
+    /// compressors such as [`ContextualRocCompressor`](crate::ContextualRocCompressor)
+    /// override it.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`compress_set`](Self::compress_set).
+    fn compress_with_context(
+        &self,
+        ids: &[T],
+        universe_size: T,
+        context: u64,
+    ) -> Result<Vec<u8>, CompressionError> {
+        let _ = context;
+        self.compress_set(ids, universe_size)
+    }
+
+    /// Decompress a set written by [`compress_with_context`](Self::compress_with_context).
+    ///
+    /// `context` must be the key used when compressing. The default ignores
+    /// it and calls [`decompress_set`](Self::decompress_set).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`decompress_set`](Self::decompress_set).
+    fn decompress_with_context(
+        &self,
+        compressed: &[u8],
+        universe_size: T,
+        context: u64,
+    ) -> Result<Vec<T>, CompressionError> {
+        let _ = context;
+        self.decompress_set(compressed, universe_size)
+    }
+
+    /// Compress each set and collect the distribution of compressed sizes.
+    ///
+    /// Sets that fail to compress (e.g. unsorted input) are skipped, so
+    /// [`SizeHistogram::count`] may be less than `sets.len()`.
+    fn analyze_batch(&self, sets: &[Vec<T>], universe_size: T) -> SizeHistogram {
+        let mut histogram = SizeHistogram::new();
+        for ids in sets {
+            if let Ok(compressed) = self.compress_set(ids, universe_size) {
+                histogram.observe(&compressed);
+            }
+        }
+        histogram
+    }
+
+    /// Whether `compress_set` requires sorted, unique input.
+    ///
+    /// Defaults to `true`. Sequence codecs such as
+    /// [`XorDeltaCompressor`](crate::XorDeltaCompressor) return `false`: they
+    /// accept IDs in any order and `decompress_set` returns them in that
+    /// same order.
+    fn requires_sorted_input(&self) -> bool {
+        true
+    }
+
+    /// Identifier of the byte format this compressor reads and writes.
+    ///
+    /// Compressors returning the same `Some(id)` must produce and accept
+    /// identical bytes, which lets callers such as
+    /// [`recompress`](crate::recompress) skip a decode/encode cycle. The
+    /// default of `None` means the format is unknown and never shared.
+    fn format_id(&self) -> Option<&'static str> {
+        None
+    }
 }
@@ -0,0 +1,77 @@
+//! Transcoding compressed sets between compression methods.
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Transcode a compressed set from one compressor's format to another's.
+///
+/// Decompresses with `src` and recompresses with `dst`. Compressors are taken
+/// as trait objects so the pair can be chosen at runtime (e.g. from index
+/// metadata). When both report the same [`IdSetCompressor::format_id`], the
+/// input bytes are returned unchanged without decoding.
+///
+/// # Errors
+///
+/// Returns `CompressionError` if `src_compressed` cannot be decompressed by
+/// `src` or the IDs cannot be compressed by `dst`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{recompress, HuffmanCompressor, IdSetCompressor, RocCompressor};
+///
+/// let roc = RocCompressor::new();
+/// let huffman = HuffmanCompressor::new();
+/// let ids = vec![1u32, 2, 3, 4, 10];
+///
+/// let old = roc.compress_set(&ids, 100).unwrap();
+/// let new = recompress(&old, 100, &roc, &huffman).unwrap();
+/// assert_eq!(huffman.decompress_set(&new, 100).unwrap(), ids);
+/// ```
+pub fn recompress(
+    src_compressed: &[u8],
+    universe: u32,
+    src: &dyn IdSetCompressor,
+    dst: &dyn IdSetCompressor,
+) -> Result<Vec<u8>, CompressionError> {
+    if let (Some(a), Some(b)) = (src.format_id(), dst.format_id()) {
+        if a == b {
+            return Ok(src_compressed.to_vec());
+        }
+    }
+
+    let ids = src.decompress_set(src_compressed, universe)?;
+    dst.compress_set(&ids, universe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HuffmanCompressor, RocCompressor};
+
+    #[test]
+    fn test_same_format_is_passthrough() {
+        let roc = RocCompressor::new();
+        // Not valid compressed data: the fast path must not decode it
+        let bytes = vec![0xFF, 0xFF];
+        assert_eq!(recompress(&bytes, 10, &roc, &roc).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_cross_format() {
+        let roc = RocCompressor::new();
+        let huffman = HuffmanCompressor::new();
+        let ids = vec![3u32, 4, 5, 9];
+
+        let src = huffman.compress_set(&ids, 10).unwrap();
+        let dst = recompress(&src, 10, &huffman, &roc).unwrap();
+        assert_eq!(dst, roc.compress_set(&ids, 10).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_source_is_error() {
+        let roc = RocCompressor::new();
+        let huffman = HuffmanCompressor::new();
+        assert!(recompress(&[0xFF], 10, &roc, &huffman).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Two-level Elias-Fano for IDs spread over the 64-bit space.
+//!
+//! Hashed IDs land anywhere in `[0, 2^64)`. [`TwoLevelEliasFanoCompressor`]
+//! cuts that space into blocks of `2^32` IDs. The top level is an
+//! Elias-Fano list of the indices of the non-empty blocks, over `[0, 2^32)`.
+//! Each non-empty block is an Elias-Fano list of the low 32 bits of its IDs.
+//! [`SplitEliasFanoCompressor`](crate::SplitEliasFanoCompressor) also
+//! partitions the universe, with a configurable block size, but lists the
+//! blocks as varint deltas. Here the block list is itself Elias-Fano coded,
+//! which pays off when many blocks are occupied.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [num_blocks: varint] [top_bytes: varint]
+//! [(block_len: varint, block_bytes: varint) * num_blocks]
+//! [top level: Elias-Fano of the block indices, top_bytes bytes]
+//! [Elias-Fano payload of each block, byte-aligned, in order]
+//! ```
+//!
+//! # References
+//!
+//! - Ottaviano, G. & Venturini, R. (2014). "Partitioned Elias-Fano indexes"
+
+use crate::bits::BitWriter;
+use crate::elias_fano;
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint, varint_len};
+
+/// log2 of the number of IDs in a block.
+const BLOCK_BITS: u32 = 32;
+
+/// Low 32 bits of an ID: its position within its block.
+const LOW_MASK: u64 = (1 << BLOCK_BITS) - 1;
+
+/// Two-level Elias-Fano compressor for 64-bit ID sets.
+///
+/// A `u64` universe cannot name `2^64`, so the whole hash space is
+/// `universe_size = u64::MAX`, which leaves out only the ID `u64::MAX`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, TwoLevelEliasFanoCompressor};
+///
+/// let compressor = TwoLevelEliasFanoCompressor::new();
+/// let ids = vec![7u64, 1 << 40, (1 << 40) + 3, u64::MAX - 1];
+/// let compressed = compressor.compress_set(&ids, u64::MAX).unwrap();
+///
+/// assert_eq!(compressor.decompress_set(&compressed, u64::MAX).unwrap(), ids);
+/// assert_eq!(
+///     compressor.next_geq_u64(&compressed, u64::MAX, 8).unwrap(),
+///     Some(1 << 40)
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TwoLevelEliasFanoCompressor;
+
+/// Directory entry for one non-empty block.
+#[derive(Clone, Copy, Debug)]
+struct BlockEntry {
+    len: usize,
+    offset: usize,
+    bytes: usize,
+}
+
+/// Parsed header, with the top level and block payloads it indexes.
+struct Levels<'a> {
+    len: u64,
+    blocks: Vec<BlockEntry>,
+    /// Elias-Fano list of the indices of the non-empty blocks.
+    top: &'a [u8],
+    top_universe: u64,
+    payload: &'a [u8],
+}
+
+impl<'a> Levels<'a> {
+    fn parse(compressed: &'a [u8], universe: u64) -> Result<Self, CompressionError> {
+        let top_universe = TwoLevelEliasFanoCompressor::top_universe(universe);
+        let (len, mut offset) = decode_varint(compressed)?;
+        let (num_blocks, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+        let (top_bytes, consumed) = decode_varint(&compressed[offset..])?;
+        offset += consumed;
+        if num_blocks == 0 || num_blocks > top_universe {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid block count {}",
+                num_blocks
+            )));
+        }
+
+        let mut blocks = Vec::new();
+        let mut total_len = 0u64;
+        let mut payload_len = 0usize;
+        for i in 0..num_blocks {
+            let (block_len, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            let (bytes, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            if block_len == 0 || block_len > 1 << BLOCK_BITS || bytes > compressed.len() as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Invalid block entry {}",
+                    i
+                )));
+            }
+            total_len += block_len;
+            blocks.push(BlockEntry {
+                len: block_len as usize,
+                offset: payload_len,
+                bytes: bytes as usize,
+            });
+            payload_len += bytes as usize;
+        }
+
+        let rest = &compressed[offset..];
+        if total_len != len || top_bytes.saturating_add(payload_len as u64) != rest.len() as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Block directory does not match data: {} IDs in {} bytes",
+                total_len, payload_len
+            )));
+        }
+        let (top, payload) = rest.split_at(top_bytes as usize);
+
+        Ok(Self {
+            len,
+            blocks,
+            top,
+            top_universe,
+            payload,
+        })
+    }
+
+    /// Decode the indices of all non-empty blocks.
+    fn block_ids(&self) -> Result<Vec<u64>, CompressionError> {
+        let (block_ids, _) = elias_fano::decode(self.top, 0, self.blocks.len(), self.top_universe)?;
+        Ok(block_ids)
+    }
+
+    /// Position and index of the first non-empty block with index `>= target`.
+    fn next_block(&self, target: u64) -> Result<Option<(usize, u64)>, CompressionError> {
+        elias_fano::next_geq(self.top, 0, self.blocks.len(), self.top_universe, target)
+    }
+
+    /// Values in block `b`, with index `block_id`, that are `>= low`.
+    fn next_in_block(
+        &self,
+        b: usize,
+        block_id: u64,
+        universe: u64,
+        low: u64,
+    ) -> Result<Option<u64>, CompressionError> {
+        let entry = &self.blocks[b];
+        let bytes = &self.payload[entry.offset..entry.offset + entry.bytes];
+        let base = block_id << BLOCK_BITS;
+        let block_universe = TwoLevelEliasFanoCompressor::block_universe(base, universe);
+        Ok(elias_fano::next_geq(bytes, 0, entry.len, block_universe, low)?.map(|(_, v)| base + v))
+    }
+
+    /// Decode block `b`, with index `block_id`.
+    fn decode_block(
+        &self,
+        b: usize,
+        block_id: u64,
+        universe: u64,
+    ) -> Result<Vec<u64>, CompressionError> {
+        let entry = &self.blocks[b];
+        let bytes = &self.payload[entry.offset..entry.offset + entry.bytes];
+        let base = block_id << BLOCK_BITS;
+        let block_universe = TwoLevelEliasFanoCompressor::block_universe(base, universe);
+        let (values, _) = elias_fano::decode(bytes, 0, entry.len, block_universe)?;
+        Ok(values.into_iter().map(|v| base + v).collect())
+    }
+}
+
+impl TwoLevelEliasFanoCompressor {
+    /// Create a new two-level Elias-Fano compressor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Number of blocks the universe spans, at most `2^32`.
+    fn top_universe(universe: u64) -> u64 {
+        universe.div_ceil(1 << BLOCK_BITS)
+    }
+
+    /// Universe of the block starting at `base`, clipped to the global universe.
+    fn block_universe(base: u64, universe: u64) -> u64 {
+        (1u64 << BLOCK_BITS).min(universe - base)
+    }
+
+    /// Find the first ID `>= x` in a compressed set.
+    ///
+    /// Both levels are searched the same way: skip the high buckets below
+    /// that of the target, reading no low bits, then compare values from
+    /// there. The top level gives the block of `x`, and the block gives the
+    /// answer, unless every ID in it is smaller, in which case the answer
+    /// is the first ID of the next block.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError` if the header or the visited block is malformed.
+    pub fn next_geq_u64(
+        &self,
+        compressed: &[u8],
+        universe: u64,
+        x: u64,
+    ) -> Result<Option<u64>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(None);
+        }
+
+        let levels = Levels::parse(compressed, universe)?;
+        let target = x >> BLOCK_BITS;
+        let Some((mut b, mut block_id)) = levels.next_block(target)? else {
+            return Ok(None);
+        };
+        loop {
+            let low = if block_id == target { x & LOW_MASK } else { 0 };
+            if let Some(id) = levels.next_in_block(b, block_id, universe, low)? {
+                return Ok(Some(id));
+            }
+            // Every ID of the block of x is below it: take the next block's first
+            match levels.next_block(block_id + 1)? {
+                Some(next) => (b, block_id) = next,
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl IdSetCompressor<u64> for TwoLevelEliasFanoCompressor {
+    fn compress_set(&self, ids: &[u64], universe_size: u64) -> Result<Vec<u8>, CompressionError> {
+        if let Some(i) = (1..ids.len()).find(|&i| ids[i] <= ids[i - 1]) {
+            return Err(CompressionError::InvalidInput(format!(
+                "IDs must be sorted and unique, found {} <= {}",
+                ids[i],
+                ids[i - 1]
+            )));
+        }
+        let last = match ids.last() {
+            Some(&last) => last,
+            None => return Ok(Vec::new()),
+        };
+        if last >= universe_size {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe_size
+            )));
+        }
+
+        let mut block_ids = Vec::new();
+        let mut directory = Vec::new();
+        let mut payload = Vec::new();
+        let mut rest = ids;
+        while let Some(&first) = rest.first() {
+            let block_id = first >> BLOCK_BITS;
+            let split = rest.partition_point(|&id| id >> BLOCK_BITS == block_id);
+            let (block, tail) = rest.split_at(split);
+            rest = tail;
+
+            let base = block_id << BLOCK_BITS;
+            let values: Vec<u64> = block.iter().map(|&id| id & LOW_MASK).collect();
+            let mut writer = BitWriter::new();
+            elias_fano::encode(
+                &values,
+                Self::block_universe(base, universe_size),
+                &mut writer,
+            );
+            let bytes = writer.finish();
+
+            encode_varint(block.len() as u64, &mut directory);
+            encode_varint(bytes.len() as u64, &mut directory);
+            payload.extend_from_slice(&bytes);
+            block_ids.push(block_id);
+        }
+
+        let mut writer = BitWriter::new();
+        elias_fano::encode(&block_ids, Self::top_universe(universe_size), &mut writer);
+        let top = writer.finish();
+
+        let mut encoded = Vec::with_capacity(30 + directory.len() + top.len() + payload.len());
+        encode_varint(ids.len() as u64, &mut encoded);
+        encode_varint(block_ids.len() as u64, &mut encoded);
+        encode_varint(top.len() as u64, &mut encoded);
+        encoded.extend_from_slice(&directory);
+        encoded.extend_from_slice(&top);
+        encoded.extend_from_slice(&payload);
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u64,
+    ) -> Result<Vec<u64>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let levels = Levels::parse(compressed, universe_size)?;
+        let mut ids = Vec::with_capacity(levels.len as usize);
+        for (b, block_id) in levels.block_ids()?.into_iter().enumerate() {
+            ids.extend(levels.decode_block(b, block_id, universe_size)?);
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u64) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Worst case: IDs spread evenly, one Elias-Fano list over each touched block
+        let n = num_ids as u64;
+        let top_universe = Self::top_universe(universe_size).max(1);
+        let num_blocks = n.min(top_universe);
+        let per_block = n.div_ceil(num_blocks);
+        let block_bytes = elias_fano::encoded_bits(per_block, 1 << BLOCK_BITS).div_ceil(8);
+        let top_bytes = elias_fano::encoded_bits(num_blocks, top_universe).div_ceil(8);
+        let entry_bytes = (varint_len(per_block) + varint_len(block_bytes)) as u64;
+        (num_blocks * (block_bytes + entry_bytes) + top_bytes + 30) as usize
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u64) -> f64 {
+        if num_ids == 0 || num_ids as u64 >= universe_size {
+            return 0.0;
+        }
+        // Stirling approximation of log2(C(N, n)) / n
+        (universe_size as f64 / num_ids as f64).log2()
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/two-level-elias-fano")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_full_space() {
+        let compressor = TwoLevelEliasFanoCompressor::new();
+        let ids = vec![
+            0u64,
+            5,
+            LOW_MASK,
+            1 << 32,
+            (1 << 32) + 7,
+            1 << 50,
+            u64::MAX - 1,
+        ];
+
+        let compressed = compressor.compress_set(&ids, u64::MAX).unwrap();
+        let decompressed = compressor.decompress_set(&compressed, u64::MAX).unwrap();
+        assert_eq!(ids, decompressed);
+
+        assert!(compressor.compress_set(&[], u64::MAX).unwrap().is_empty());
+        assert!(compressor.compress_set(&[3, 3], u64::MAX).is_err());
+        assert!(compressor.compress_set(&[u64::MAX], u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_small_universe() {
+        let compressor = TwoLevelEliasFanoCompressor::new();
+        let ids = vec![1u64, 2, 900];
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        assert_eq!(compressor.decompress_set(&compressed, 1000).unwrap(), ids);
+        assert_eq!(
+            compressor.next_geq_u64(&compressed, 1000, 3).unwrap(),
+            Some(900)
+        );
+    }
+
+    #[test]
+    fn test_next_geq_u64() {
+        let compressor = TwoLevelEliasFanoCompressor::new();
+        let ids = vec![3u64, 9, (5 << 32) + 40, (5 << 32) + 41, 9 << 32];
+        let compressed = compressor.compress_set(&ids, u64::MAX).unwrap();
+        let next_geq = |x| compressor.next_geq_u64(&compressed, u64::MAX, x).unwrap();
+
+        assert_eq!(next_geq(0), Some(3));
+        assert_eq!(next_geq(10), Some((5 << 32) + 40));
+        assert_eq!(next_geq(5 << 32), Some((5 << 32) + 40));
+        assert_eq!(next_geq((5 << 32) + 41), Some((5 << 32) + 41));
+        // Past the last ID of block 5: first ID of the next block
+        assert_eq!(next_geq((5 << 32) + 42), Some(9 << 32));
+        assert_eq!(next_geq((9 << 32) + 1), None);
+        assert_eq!(compressor.next_geq_u64(&[], u64::MAX, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_data() {
+        let compressor = TwoLevelEliasFanoCompressor::new();
+        let mut compressed = compressor
+            .compress_set(&[1u64, 1 << 33, 1 << 40], u64::MAX)
+            .unwrap();
+        // Block 256 holds 1 << 40, outside a universe of four blocks
+        assert!(compressor.decompress_set(&compressed, 1 << 34).is_err());
+
+        compressed.push(0);
+        assert!(compressor.decompress_set(&compressed, u64::MAX).is_err());
+        compressed.truncate(compressed.len() - 2);
+        assert!(compressor.decompress_set(&compressed, u64::MAX).is_err());
+        assert!(compressor.decompress_set(&[3, 0, 0], 10).is_err());
+    }
+}
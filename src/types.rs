@@ -0,0 +1,89 @@
+//! Newtypes for the integers that are not IDs.
+//!
+//! A universe size, a set length and an ID are all `u32`, so nothing stops
+//! one being passed for another. [`Universe`] and [`IdCount`] give the
+//! first two their own types; [`IdSetCompressor::compress_set_in`] and
+//! [`IdSetCompressor::decompress_set_in`] take a [`Universe`], so swapping
+//! it with an ID there no longer compiles.
+//!
+//! [`IdSetCompressor::compress_set_in`]: crate::IdSetCompressor::compress_set_in
+//! [`IdSetCompressor::decompress_set_in`]: crate::IdSetCompressor::decompress_set_in
+
+use crate::error::CompressionError;
+
+/// Size of the ID universe `[0, N)`.
+///
+/// Only [`compress_set_in`] and [`decompress_set_in`] take a `Universe`.
+/// The rest of [`IdSetCompressor`], including `compress_set` and
+/// `decompress_set`, still takes `universe_size: T`: the trait is generic
+/// over the ID type, and the `u64` compressors need universes wider than a
+/// `u32`. Changing those signatures would also break every existing caller.
+/// New code compressing `u32` sets should prefer the `_in` methods.
+///
+/// [`compress_set_in`]: crate::IdSetCompressor::compress_set_in
+/// [`decompress_set_in`]: crate::IdSetCompressor::decompress_set_in
+/// [`IdSetCompressor`]: crate::IdSetCompressor
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RocCompressor, Universe};
+///
+/// let roc = RocCompressor::new();
+/// let compressed = roc.compress_set_in(&[1u32, 5, 10], Universe(1000)).unwrap();
+/// let ids: Vec<u32> = roc.decompress_set_in(&compressed, Universe(1000)).unwrap();
+/// assert_eq!(ids, [1, 5, 10]);
+///
+/// assert!(Universe::new_checked(0).is_err());
+/// assert_eq!(u32::from(Universe::MAX), u32::MAX);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Universe(pub u32);
+
+impl Universe {
+    /// The largest universe, `u32::MAX`.
+    pub const MAX: Universe = Universe(u32::MAX);
+
+    /// A universe of `size` IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CompressionError::InvalidInput` if `size` is 0, which holds
+    /// no IDs at all.
+    pub fn new_checked(size: u32) -> Result<Self, CompressionError> {
+        if size == 0 {
+            return Err(CompressionError::InvalidInput(
+                "Universe size must be at least 1".to_string(),
+            ));
+        }
+        Ok(Universe(size))
+    }
+}
+
+impl From<u32> for Universe {
+    fn from(size: u32) -> Self {
+        Universe(size)
+    }
+}
+
+impl From<Universe> for u32 {
+    fn from(universe: Universe) -> Self {
+        universe.0
+    }
+}
+
+/// Number of IDs in a set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IdCount(pub u32);
+
+impl From<u32> for IdCount {
+    fn from(count: u32) -> Self {
+        IdCount(count)
+    }
+}
+
+impl From<IdCount> for u32 {
+    fn from(count: IdCount) -> Self {
+        count.0
+    }
+}
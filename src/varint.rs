@@ -0,0 +1,116 @@
+//! LEB128-style variable-length integer encoding.
+//!
+//! Each byte carries 7 bits of payload; the high bit is set on every byte
+//! except the last. Small values (< 128) take a single byte, which is what
+//! makes delta encoding of dense ID sets effective.
+//!
+//! These are the primitives behind [`RocCompressor`](crate::RocCompressor)
+//! and are exposed for fuzzing and for custom formats built on the same
+//! encoding.
+
+use crate::error::CompressionError;
+
+/// Encode a u64 as varint into the buffer.
+#[inline]
+pub fn encode_varint(value: u64, buf: &mut Vec<u8>) {
+    let mut val = value;
+    while val >= 0x80 {
+        buf.push((val as u8) | 0x80);
+        val >>= 7;
+    }
+    buf.push(val as u8);
+}
+
+/// Encode a varint into the start of `out`, returning the bytes written.
+///
+/// `out` must hold at least [`varint_len`]`(value)` bytes.
+#[inline]
+pub(crate) fn write_varint(value: u64, out: &mut [u8]) -> usize {
+    let mut val = value;
+    let mut i = 0;
+    while val >= 0x80 {
+        out[i] = (val as u8) | 0x80;
+        val >>= 7;
+        i += 1;
+    }
+    out[i] = val as u8;
+    i + 1
+}
+
+/// Number of bytes [`encode_varint`] writes for `value`.
+#[inline]
+pub fn varint_len(value: u64) -> usize {
+    // ceil(significant_bits / 7), with zero taking one byte
+    (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
+
+/// Decode a varint from the buffer, returning (value, bytes_consumed).
+///
+/// # Errors
+///
+/// Returns `CompressionError::DecompressionFailed` if the buffer ends before
+/// the final byte or the encoding is longer than nine bytes.
+#[inline]
+pub fn decode_varint(buf: &[u8]) -> Result<(u64, usize), CompressionError> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    let mut offset = 0;
+
+    loop {
+        if offset >= buf.len() {
+            return Err(CompressionError::DecompressionFailed(
+                "Unexpected end of compressed data".to_string(),
+            ));
+        }
+
+        if shift > 56 {
+            return Err(CompressionError::DecompressionFailed(
+                "Varint encoding too large".to_string(),
+            ));
+        }
+
+        let byte = buf[offset];
+        offset += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+
+        if (byte & 0x80) == 0 {
+            break;
+        }
+        shift += 7;
+    }
+
+    Ok((value, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_round_trip() {
+        for value in [0u64, 1, 127, 128, 300, 16_383, 16_384, u32::MAX as u64] {
+            let mut buf = Vec::new();
+            encode_varint(value, &mut buf);
+            let (decoded, consumed) = decode_varint(&buf).unwrap();
+            assert_eq!(decoded, value);
+            assert_eq!(consumed, buf.len());
+            assert_eq!(varint_len(value), buf.len());
+        }
+    }
+
+    #[test]
+    fn test_varint_len_boundaries() {
+        assert_eq!(varint_len(0), 1);
+        assert_eq!(varint_len(u64::MAX), 10);
+        for shift in 1..9 {
+            assert_eq!(varint_len((1 << (7 * shift)) - 1), shift);
+            assert_eq!(varint_len(1 << (7 * shift)), shift + 1);
+        }
+    }
+
+    #[test]
+    fn test_varint_truncated() {
+        assert!(decode_varint(&[0x80]).is_err());
+        assert!(decode_varint(&[]).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Round-trip checking of compressor output.
+//!
+//! A codec bug that produces decodable but wrong bytes is silent until the
+//! data is read back, possibly much later. [`VerifyingCompressor`] decodes
+//! every set it compresses and panics on the spot if the IDs differ, which
+//! turns such bugs into test failures at the call that caused them.
+
+use crate::error::CompressionError;
+use crate::traits::IdSetCompressor;
+
+/// Wraps a compressor and checks that its output decodes to the input.
+///
+/// Verification runs in debug builds, and in release builds when enabled
+/// with [`with_release_verification`](Self::with_release_verification).
+/// Otherwise calls are forwarded unchanged and the output is byte-for-byte
+/// that of the inner compressor.
+///
+/// # Panics
+///
+/// `compress_set` panics if verification is on and the compressed bytes
+/// fail to decode or decode to different IDs.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, RocCompressor, VerifyingCompressor};
+///
+/// let compressor = VerifyingCompressor::with_inner(RocCompressor::new());
+/// let compressed = compressor.compress_set(&[1, 5, 9], 10).unwrap();
+/// assert_eq!(compressed, RocCompressor::new().compress_set(&[1u32, 5, 9], 10).unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct VerifyingCompressor<C> {
+    inner: C,
+    debug_verify: bool,
+}
+
+impl<C: IdSetCompressor + Clone> VerifyingCompressor<C> {
+    /// Wrap `inner`, verifying in debug builds only.
+    pub fn with_inner(inner: C) -> Self {
+        Self {
+            inner,
+            debug_verify: false,
+        }
+    }
+
+    /// Wrap `inner`, verifying in every build.
+    pub fn with_release_verification(inner: C) -> Self {
+        Self {
+            inner,
+            debug_verify: true,
+        }
+    }
+
+    /// The wrapped compressor.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Whether verification also runs in release builds.
+    pub fn debug_verify(&self) -> bool {
+        self.debug_verify
+    }
+
+    fn verify(&self, ids: &[u32], compressed: &[u8], universe_size: u32) {
+        match self.inner.decompress_set(compressed, universe_size) {
+            Ok(decoded) if decoded == ids => {}
+            // Codecs that accept unsorted input either keep its order, as
+            // sequence codecs do, or sort it, as `ValidationMode::AutoSort` does
+            Ok(decoded) if !self.inner.requires_sorted_input() && decoded == sorted_unique(ids) => {
+            }
+            Ok(decoded) => panic!(
+                "{:?} round trip mismatch: {} IDs in, {} out, first difference at index {}",
+                self.inner.format_id(),
+                ids.len(),
+                decoded.len(),
+                ids.iter()
+                    .zip(&decoded)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or(ids.len().min(decoded.len()))
+            ),
+            Err(e) => panic!(
+                "{:?} cannot decode its own output of {} IDs: {}",
+                self.inner.format_id(),
+                ids.len(),
+                e
+            ),
+        }
+    }
+}
+
+/// Sorted, deduplicated copy of `ids`.
+fn sorted_unique(ids: &[u32]) -> Vec<u32> {
+    let mut sorted = ids.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    sorted
+}
+
+impl<C: IdSetCompressor + Clone> IdSetCompressor for VerifyingCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let compressed = self.inner.compress_set(ids, universe_size)?;
+        if cfg!(debug_assertions) || self.debug_verify {
+            self.verify(ids, &compressed, universe_size);
+        }
+        Ok(compressed)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        self.inner.decompress_set(compressed, universe_size)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        self.inner.estimate_size(num_ids, universe_size)
+    }
+
+    fn estimate_compressed_size_bytes(&self, ids: &[u32], universe_size: u32) -> usize {
+        self.inner
+            .estimate_compressed_size_bytes(ids, universe_size)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        self.inner.theoretical_bits_per_id(num_ids, universe_size)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        self.inner.requires_sorted_input()
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        self.inner.format_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RocCompressor, ValidationMode, XorDeltaCompressor};
+
+    /// Compressor whose output decodes to the wrong IDs.
+    #[derive(Clone)]
+    struct OffByOne;
+
+    impl IdSetCompressor for OffByOne {
+        fn compress_set(&self, ids: &[u32], universe: u32) -> Result<Vec<u8>, CompressionError> {
+            let shifted: Vec<u32> = ids.iter().map(|&id| id + 1).collect();
+            RocCompressor::new().compress_set(&shifted, universe + 1)
+        }
+        fn decompress_set(
+            &self,
+            bytes: &[u8],
+            universe: u32,
+        ) -> Result<Vec<u32>, CompressionError> {
+            RocCompressor::new().decompress_set(bytes, universe + 1)
+        }
+        fn estimate_size(&self, _: usize, _: u32) -> usize {
+            0
+        }
+        fn theoretical_bits_per_id(&self, _: usize, _: u32) -> f64 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn test_forwards_unchanged() {
+        let roc = RocCompressor::new();
+        let verifying = VerifyingCompressor::with_release_verification(roc.clone());
+        let ids = vec![0u32, 7, 8, 1000];
+
+        let compressed = verifying.compress_set(&ids, 1001).unwrap();
+        assert_eq!(compressed, roc.compress_set(&ids, 1001).unwrap());
+        assert_eq!(verifying.decompress_set(&compressed, 1001).unwrap(), ids);
+    }
+
+    #[test]
+    #[should_panic(expected = "round trip mismatch")]
+    fn test_panics_on_wrong_ids() {
+        let verifying = VerifyingCompressor::with_release_verification(OffByOne);
+        let _ = verifying.compress_set(&[1, 2, 3], 10);
+    }
+
+    #[test]
+    fn test_inner_errors_pass_through() {
+        let verifying = VerifyingCompressor::with_inner(RocCompressor::new());
+        assert!(verifying.compress_set(&[3u32, 1], 10).is_err());
+    }
+
+    #[test]
+    fn test_unsorted_input_to_codecs_that_accept_it() {
+        let auto_sort = VerifyingCompressor::with_release_verification(
+            RocCompressor::with_validation(ValidationMode::AutoSort),
+        );
+        let compressed = auto_sort.compress_set(&[3, 1], 10).unwrap();
+        assert_eq!(auto_sort.decompress_set(&compressed, 10).unwrap(), [1, 3]);
+
+        // Sequence codecs keep the input order
+        let xor = VerifyingCompressor::with_release_verification(XorDeltaCompressor::new());
+        let compressed = xor.compress_set(&[9, 2, 5], 10).unwrap();
+        assert_eq!(xor.decompress_set(&compressed, 10).unwrap(), [9, 2, 5]);
+    }
+}
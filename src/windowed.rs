@@ -0,0 +1,361 @@
+//! Fixed-size windows over a large universe, each compressed independently.
+//!
+//! IDs mapped from a 64-bit space (hash fingerprints, URL hashes) are spread
+//! over a universe of `2^32` or more, which inflates every per-ID cost that
+//! scales with `log2(universe / n)`. [`WindowedCompressor`] cuts the universe
+//! into windows of `2^block_bits` IDs, stores only the non-empty ones, and
+//! compresses each window's offsets with an inner `u32` codec over a universe
+//! of `2^block_bits`.
+//!
+//! This is the block layout of
+//! [`SplitEliasFanoCompressor`](crate::SplitEliasFanoCompressor) with the
+//! Elias-Fano lists swapped for any [`IdSetCompressor`], by default
+//! [`RocCompressor`].
+//!
+//! # Format
+//!
+//! ```text
+//! [block_bits: u8] [num_blocks: varint]
+//! [(block_id_delta: varint, block_bytes: varint) * num_blocks]
+//! [inner compressor output of each block, in order]
+//! ```
+//!
+//! The first block ID is stored as is and later ones as the difference from
+//! the previous block ID. A block's byte offset is the sum of the
+//! `block_bytes` before it.
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Compressor that splits the universe into windows of `2^block_bits` IDs.
+///
+/// Implements both `IdSetCompressor<u32>` and `IdSetCompressor<u64>`; the
+/// format is the same, so `u32` sets can be read back as `u64`.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, WindowedCompressor};
+///
+/// // Two clusters of hashed IDs, far apart
+/// let ids = vec![7u64, 100, 4_000, 1 << 40, (1 << 40) + 3];
+/// let compressor = WindowedCompressor::new(16);
+///
+/// let compressed = compressor.compress_set(&ids, u64::MAX).unwrap();
+/// assert_eq!(compressor.decompress_set(&compressed, u64::MAX).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug)]
+pub struct WindowedCompressor<C = RocCompressor> {
+    inner: C,
+    block_bits: u8,
+}
+
+impl WindowedCompressor {
+    /// Create a compressor with windows of `2^block_bits` IDs, compressed
+    /// with [`RocCompressor`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_bits` is not in `1..=31`.
+    pub fn new(block_bits: u8) -> Self {
+        Self::with_inner(RocCompressor::new(), block_bits)
+    }
+}
+
+impl<C: IdSetCompressor> WindowedCompressor<C> {
+    /// Create a compressor with windows of `2^block_bits` IDs, compressed
+    /// with `inner`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_bits` is not in `1..=31`, so a window's universe
+    /// fits in a `u32`.
+    pub fn with_inner(inner: C, block_bits: u8) -> Self {
+        assert!(
+            (1..=31).contains(&block_bits),
+            "block_bits must be in 1..=31"
+        );
+        Self { inner, block_bits }
+    }
+
+    /// log2 of the number of IDs covered by each window.
+    pub fn block_bits(&self) -> u8 {
+        self.block_bits
+    }
+
+    /// The compressor used for each window.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn compress_u64(&self, ids: &[u64], universe: u64) -> Result<Vec<u8>, CompressionError> {
+        if let Some(i) = (1..ids.len()).find(|&i| ids[i] <= ids[i - 1]) {
+            return Err(CompressionError::InvalidInput(format!(
+                "IDs must be sorted and unique, found {} <= {}",
+                ids[i],
+                ids[i - 1]
+            )));
+        }
+        let last = match ids.last() {
+            Some(&last) => last,
+            None => return Ok(Vec::new()),
+        };
+        if last >= universe {
+            return Err(CompressionError::InvalidInput(format!(
+                "ID {} exceeds universe size {}",
+                last, universe
+            )));
+        }
+
+        let mask = (1u64 << self.block_bits) - 1;
+        let mut directory = Vec::new();
+        let mut payload = Vec::new();
+        let mut num_blocks = 0u64;
+        let mut prev_block = 0u64;
+        let mut offsets = Vec::new();
+        let mut rest = ids;
+        while let Some(&first) = rest.first() {
+            let block_id = first >> self.block_bits;
+            let split = rest.partition_point(|&id| id >> self.block_bits == block_id);
+            let (block, tail) = rest.split_at(split);
+            rest = tail;
+
+            offsets.clear();
+            offsets.extend(block.iter().map(|&id| (id & mask) as u32));
+            let bytes = self.inner.compress_set(&offsets, 1 << self.block_bits)?;
+
+            encode_varint(block_id - prev_block, &mut directory);
+            encode_varint(bytes.len() as u64, &mut directory);
+            payload.extend_from_slice(&bytes);
+            prev_block = block_id;
+            num_blocks += 1;
+        }
+
+        let mut encoded = Vec::with_capacity(11 + directory.len() + payload.len());
+        encoded.push(self.block_bits);
+        encode_varint(num_blocks, &mut encoded);
+        encoded.extend_from_slice(&directory);
+        encoded.extend_from_slice(&payload);
+        Ok(encoded)
+    }
+
+    fn decompress_u64(
+        &self,
+        compressed: &[u8],
+        universe: u64,
+    ) -> Result<Vec<u64>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Use the stored block size, so sets written with another one still
+        // decode
+        let block_bits = compressed[0];
+        if !(1..=31).contains(&block_bits) {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid block_bits {}",
+                block_bits
+            )));
+        }
+        let (num_blocks, consumed) = decode_varint(&compressed[1..])?;
+        let mut offset = 1 + consumed;
+
+        let max_block = universe.saturating_sub(1) >> block_bits;
+        let mut blocks: Vec<(u64, usize)> = Vec::new();
+        let mut payload_len = 0usize;
+        for i in 0..num_blocks {
+            let (delta, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            let (bytes, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+
+            let id = match blocks.last() {
+                None => Some(delta),
+                Some(&(prev, _)) if delta > 0 => prev.checked_add(delta),
+                Some(_) => None,
+            }
+            .filter(|&id| id <= max_block)
+            .ok_or_else(|| {
+                CompressionError::DecompressionFailed(format!("Invalid block id in entry {}", i))
+            })?;
+            if bytes == 0 || bytes > (compressed.len() - offset) as u64 {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "Invalid block entry {}",
+                    i
+                )));
+            }
+            blocks.push((id, bytes as usize));
+            payload_len += bytes as usize;
+        }
+
+        let payload = &compressed[offset..];
+        if payload_len != payload.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Block directory covers {} bytes, payload has {}",
+                payload_len,
+                payload.len()
+            )));
+        }
+
+        let mut ids = Vec::new();
+        let mut start = 0;
+        for (block_id, bytes) in blocks {
+            let base = block_id << block_bits;
+            let offsets = self
+                .inner
+                .decompress_set(&payload[start..start + bytes], 1 << block_bits)?;
+            start += bytes;
+            for id in offsets.into_iter().map(|o| base + o as u64) {
+                if id >= universe {
+                    return Err(CompressionError::DecompressionFailed(format!(
+                        "ID {} exceeds universe size {}",
+                        id, universe
+                    )));
+                }
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    fn estimate_u64(&self, num_ids: usize, universe: u64) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Worst case: IDs spread evenly over as many windows as possible
+        let n = num_ids as u64;
+        let num_blocks = n.min((universe >> self.block_bits).max(1));
+        let per_block = n.div_ceil(num_blocks) as usize;
+        let block_bytes = self.inner.estimate_size(per_block, 1 << self.block_bits);
+        (num_blocks as usize) * (block_bytes + 4) + 11
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor<u64> for WindowedCompressor<C> {
+    fn compress_set(&self, ids: &[u64], universe_size: u64) -> Result<Vec<u8>, CompressionError> {
+        self.compress_u64(ids, universe_size)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u64,
+    ) -> Result<Vec<u64>, CompressionError> {
+        self.decompress_u64(compressed, universe_size)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u64) -> usize {
+        self.estimate_u64(num_ids, universe_size)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u64) -> f64 {
+        if num_ids == 0 || num_ids as u64 >= universe_size {
+            return 0.0;
+        }
+        // Stirling approximation of log2(C(N, n)) / n
+        (universe_size as f64 / num_ids as f64).log2()
+    }
+}
+
+impl<C: IdSetCompressor> IdSetCompressor for WindowedCompressor<C> {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let ids: Vec<u64> = ids.iter().map(|&id| id as u64).collect();
+        self.compress_u64(&ids, universe_size as u64)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        // IDs are checked against the universe, so they fit in a u32
+        let ids = self.decompress_u64(compressed, universe_size as u64)?;
+        Ok(ids.into_iter().map(|id| id as u32).collect())
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        self.estimate_u64(num_ids, universe_size as u64)
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PForDeltaCompressor;
+
+    #[test]
+    fn test_round_trip_u32() {
+        let compressor = WindowedCompressor::new(12);
+        let ids = vec![0u32, 1, 4095, 4096, 1 << 20, u32::MAX - 1];
+
+        let compressed = compressor.compress_set(&ids, u32::MAX).unwrap();
+        assert_eq!(compressed[0], 12);
+        assert_eq!(
+            compressor.decompress_set(&compressed, u32::MAX).unwrap(),
+            ids
+        );
+    }
+
+    #[test]
+    fn test_round_trip_u64_with_other_inner() {
+        let compressor = WindowedCompressor::with_inner(PForDeltaCompressor::default(), 20);
+        let ids = vec![3u64, 1 << 21, (1 << 21) + 9, 1 << 50, u64::MAX - 1];
+
+        let compressed = compressor.compress_set(&ids, u64::MAX).unwrap();
+        assert_eq!(
+            compressor.decompress_set(&compressed, u64::MAX).unwrap(),
+            ids
+        );
+    }
+
+    #[test]
+    fn test_decodes_with_stored_block_bits() {
+        let ids: Vec<u64> = (0..100).map(|i| i * 1_000_003).collect();
+        let compressed = WindowedCompressor::new(8)
+            .compress_set(&ids, 1 << 40)
+            .unwrap();
+
+        let decompressed = WindowedCompressor::new(30)
+            .decompress_set(&compressed, 1 << 40)
+            .unwrap();
+        assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_data() {
+        let compressor = WindowedCompressor::new(4);
+        let ids = vec![3u64, 9, 40, 41, 1000];
+        let compressed = compressor.compress_set(&ids, 2000u64).unwrap();
+
+        assert!(compressor.decompress_set(&compressed, 1000u64).is_err());
+        assert!(compressor
+            .decompress_set(&compressed[..compressed.len() - 1], 2000u64)
+            .is_err());
+        let mut padded = compressed.clone();
+        padded.push(0);
+        assert!(compressor.decompress_set(&padded, 2000u64).is_err());
+        let mut bad_bits = compressed;
+        bad_bits[0] = 40;
+        assert!(compressor.decompress_set(&bad_bits, 2000u64).is_err());
+    }
+
+    #[test]
+    fn test_empty() {
+        let compressor = WindowedCompressor::new(16);
+        assert!(compressor
+            .compress_set(&[] as &[u64], 10)
+            .unwrap()
+            .is_empty());
+        assert!(compressor.decompress_set(&[], 10u64).unwrap().is_empty());
+    }
+}
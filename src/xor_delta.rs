@@ -0,0 +1,179 @@
+//! XOR delta encoding.
+//!
+//! Each ID is stored as the XOR with its predecessor instead of the
+//! arithmetic difference. For IDs built by bit-interleaving coordinates
+//! (Z-order / Morton codes), neighbours in space share high bits, so their
+//! XOR is small even when the arithmetic gap is large or negative.
+//!
+//! Because XOR is its own inverse, the sequence is reconstructed exactly in
+//! its original order; sorted input is not required.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [first_id: u32 LE] [(id[i] ^ id[i-1]): varint * (len - 1)]
+//! ```
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// XOR delta compressor for ID sequences in any order.
+///
+/// `decompress_set` returns the IDs in the order they were compressed, which
+/// is sorted only if the input was.
+#[derive(Clone, Debug, Default)]
+pub struct XorDeltaCompressor;
+
+impl XorDeltaCompressor {
+    /// Create a new XOR delta compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for XorDeltaCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        let first = match ids.first() {
+            Some(&first) => first,
+            None => return Ok(Vec::new()),
+        };
+        if let Some(&max_id) = ids.iter().max() {
+            if max_id >= universe_size {
+                return Err(CompressionError::InvalidInput(format!(
+                    "ID {} exceeds universe size {}",
+                    max_id, universe_size
+                )));
+            }
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        encoded.extend_from_slice(&first.to_le_bytes());
+        for w in ids.windows(2) {
+            encode_varint((w[1] ^ w[0]) as u64, &mut encoded);
+        }
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, mut offset) = decode_varint(compressed)?;
+        let first = compressed.get(offset..offset + 4).ok_or_else(|| {
+            CompressionError::DecompressionFailed("Unexpected end of compressed data".into())
+        })?;
+        offset += 4;
+        // Every XOR delta takes at least one byte
+        if num_ids == 0 || num_ids - 1 > (compressed.len() - offset) as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for {} bytes of data",
+                num_ids,
+                compressed.len()
+            )));
+        }
+
+        let mut ids = Vec::with_capacity(num_ids as usize);
+        let mut prev = u32::from_le_bytes(first.try_into().unwrap());
+        for i in 0..num_ids {
+            if i > 0 {
+                let (value, consumed) = decode_varint(&compressed[offset..])?;
+                offset += consumed;
+                let xor = u32::try_from(value).map_err(|_| {
+                    CompressionError::DecompressionFailed(format!(
+                        "XOR delta {} exceeds 32 bits",
+                        value
+                    ))
+                })?;
+                prev ^= xor;
+            }
+            if prev >= universe_size {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev);
+        }
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // Assume XOR deltas run about one bit wider than arithmetic gaps
+        let bits = RocCompressor::theoretical_bits(num_ids, universe_size) + num_ids as f64;
+        (bits / 8.0).ceil() as usize + num_ids + 4
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        false
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/xor-delta")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsorted_round_trip() {
+        let compressor = XorDeltaCompressor::new();
+        let ids = vec![500u32, 3, 3, 999, 0, 42];
+
+        let compressed = compressor.compress_set(&ids, 1000).unwrap();
+        assert_eq!(compressor.decompress_set(&compressed, 1000).unwrap(), ids);
+        assert!(!compressor.requires_sorted_input());
+    }
+
+    #[test]
+    fn test_shared_high_bits_cost_one_byte() {
+        // Z-order cells of one tile share high bits but are visited out of
+        // numeric order; every XOR delta stays below 128.
+        let tile = 0xABCD_0000u32;
+        let ids: Vec<u32> = [5u32, 0, 63, 17, 90, 3, 127, 64]
+            .iter()
+            .map(|&cell| tile | cell)
+            .collect();
+
+        let compressed = XorDeltaCompressor::new()
+            .compress_set(&ids, u32::MAX)
+            .unwrap();
+        // len (1) + first (4) + one byte per delta
+        assert_eq!(compressed.len(), 1 + 4 + ids.len() - 1);
+    }
+
+    #[test]
+    fn test_rejects_out_of_universe() {
+        let compressor = XorDeltaCompressor::new();
+        assert!(compressor.compress_set(&[5, 10], 10).is_err());
+
+        let compressed = compressor.compress_set(&[5, 9], 10).unwrap();
+        assert!(compressor.decompress_set(&compressed, 8).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Zigzag delta encoding.
+//!
+//! Each ID is stored as its signed difference from the previous one, mapped
+//! to an unsigned value by zigzag encoding (`0, -1, 1, -2, 2, ...` become
+//! `0, 1, 2, 3, 4, ...`) and written as a varint. Small steps in either
+//! direction cost one byte, which suits sequences that drift up and down,
+//! such as temporal deltas in streaming windows.
+//!
+//! Differences wrap modulo 2^32, so every `u32` sequence round-trips
+//! exactly in its original order; sorted input is not required.
+//!
+//! # Format
+//!
+//! ```text
+//! [len: varint] [zigzag(id[i] - id[i-1]): varint * len]    (id[-1] = 0)
+//! ```
+
+use crate::error::CompressionError;
+use crate::roc::RocCompressor;
+use crate::traits::IdSetCompressor;
+use crate::varint::{decode_varint, encode_varint};
+
+/// Map a signed delta to an unsigned value, small magnitudes first.
+#[inline]
+fn zigzag(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+/// Inverse of [`zigzag`].
+#[inline]
+fn unzigzag(z: u32) -> i32 {
+    ((z >> 1) as i32) ^ -((z & 1) as i32)
+}
+
+/// Zigzag delta compressor for ID sequences in any order.
+///
+/// `decompress_set` returns the IDs in the order they were compressed, which
+/// is sorted only if the input was.
+///
+/// # Example
+///
+/// ```rust
+/// use cnk::{IdSetCompressor, ZigzagDeltaCompressor};
+///
+/// let compressor = ZigzagDeltaCompressor::new();
+/// let ids = vec![1000, 998, 1003, 1001, 1001];
+/// let compressed = compressor.compress_set(&ids, 2000).unwrap();
+/// // len, then the first ID (2 bytes) and one byte per step
+/// assert_eq!(compressed.len(), 1 + 2 + 4);
+/// assert_eq!(compressor.decompress_set(&compressed, 2000).unwrap(), ids);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ZigzagDeltaCompressor;
+
+impl ZigzagDeltaCompressor {
+    /// Create a new zigzag delta compressor.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl IdSetCompressor for ZigzagDeltaCompressor {
+    fn compress_set(&self, ids: &[u32], universe_size: u32) -> Result<Vec<u8>, CompressionError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(&max_id) = ids.iter().max() {
+            if max_id >= universe_size {
+                return Err(CompressionError::InvalidInput(format!(
+                    "ID {} exceeds universe size {}",
+                    max_id, universe_size
+                )));
+            }
+        }
+
+        let mut encoded = Vec::new();
+        encode_varint(ids.len() as u64, &mut encoded);
+        let mut prev = 0u32;
+        for &id in ids {
+            encode_varint(zigzag(id.wrapping_sub(prev) as i32) as u64, &mut encoded);
+            prev = id;
+        }
+        Ok(encoded)
+    }
+
+    fn decompress_set(
+        &self,
+        compressed: &[u8],
+        universe_size: u32,
+    ) -> Result<Vec<u32>, CompressionError> {
+        if compressed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (num_ids, mut offset) = decode_varint(compressed)?;
+        // Every delta takes at least one byte
+        if num_ids == 0 || num_ids > (compressed.len() - offset) as u64 {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Invalid set length {} for {} bytes of data",
+                num_ids,
+                compressed.len()
+            )));
+        }
+
+        let mut ids = Vec::with_capacity(num_ids as usize);
+        let mut prev = 0u32;
+        for _ in 0..num_ids {
+            let (value, consumed) = decode_varint(&compressed[offset..])?;
+            offset += consumed;
+            let z = u32::try_from(value).map_err(|_| {
+                CompressionError::DecompressionFailed(format!(
+                    "Zigzag delta {} exceeds 32 bits",
+                    value
+                ))
+            })?;
+            prev = prev.wrapping_add(unzigzag(z) as u32);
+            if prev >= universe_size {
+                return Err(CompressionError::DecompressionFailed(format!(
+                    "ID {} exceeds universe size {}",
+                    prev, universe_size
+                )));
+            }
+            ids.push(prev);
+        }
+        if offset < compressed.len() {
+            return Err(CompressionError::DecompressionFailed(format!(
+                "Extra data after decompression: {} bytes",
+                compressed.len() - offset
+            )));
+        }
+        Ok(ids)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe_size: u32) -> usize {
+        if num_ids == 0 {
+            return 0;
+        }
+
+        // The sign costs one extra bit per delta
+        let bits = RocCompressor::theoretical_bits(num_ids, universe_size) + num_ids as f64;
+        (bits / 8.0).ceil() as usize + num_ids + 1
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe_size: u32) -> f64 {
+        if num_ids == 0 {
+            return 0.0;
+        }
+        RocCompressor::theoretical_bits(num_ids, universe_size) / (num_ids as f64)
+    }
+
+    fn requires_sorted_input(&self) -> bool {
+        false
+    }
+
+    fn format_id(&self) -> Option<&'static str> {
+        Some("cnk/zigzag-delta")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zigzag_mapping() {
+        for (n, z) in [(0, 0), (-1, 1), (1, 2), (-2, 3), (2, 4)] {
+            assert_eq!(zigzag(n), z);
+            assert_eq!(unzigzag(z), n);
+        }
+        assert_eq!(zigzag(i32::MIN), u32::MAX);
+        assert_eq!(unzigzag(u32::MAX), i32::MIN);
+        assert_eq!(unzigzag(u32::MAX - 1), i32::MAX);
+    }
+
+    #[test]
+    fn test_unsorted_round_trip() {
+        let compressor = ZigzagDeltaCompressor::new();
+        let ids = vec![500u32, 3, 3, u32::MAX - 1, 0, 42];
+
+        let compressed = compressor.compress_set(&ids, u32::MAX).unwrap();
+        assert_eq!(
+            compressor.decompress_set(&compressed, u32::MAX).unwrap(),
+            ids
+        );
+        assert!(!compressor.requires_sorted_input());
+    }
+
+    #[test]
+    fn test_rejects_out_of_universe() {
+        let compressor = ZigzagDeltaCompressor::new();
+        assert!(compressor.compress_set(&[5, 10], 10).is_err());
+
+        let compressed = compressor.compress_set(&[9, 5], 10).unwrap();
+        assert!(compressor.decompress_set(&compressed, 8).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Allocation counts of the in-place encoders.
+//!
+//! Kept in a test binary of its own: the counting allocator applies to the
+//! whole binary.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use cnk::{IdSetCompressor, RocCompressor, StackCompressor};
+
+/// System allocator that counts allocations made by the current thread.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+// SAFETY: defers every call to `System`
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[cfg(unix)]
+#[test]
+fn test_streaming_compress_does_not_buffer_output() {
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    let ids: Vec<u32> = (0..1_000_000).map(|i| i * 5).collect();
+    let roc = RocCompressor::new();
+    let mut sink = BufWriter::new(File::create("/dev/null").unwrap());
+
+    let before = allocations();
+    let written = roc.streaming_compress(&ids, 5_000_000, &mut sink).unwrap();
+    assert_eq!(allocations(), before);
+    assert_eq!(written, roc.estimate_compressed_size_bytes(&ids, 5_000_000));
+}
+
+#[test]
+fn test_compress_set_reuse_does_not_allocate() {
+    let ids: Vec<u32> = (0..10_000).map(|i| i * 5).collect();
+    let roc = RocCompressor::new();
+    let mut arena = vec![0u8; 1 << 20];
+
+    let before = allocations();
+    let written = roc
+        .compress_set_reuse(&ids, 50_000, &mut arena)
+        .unwrap()
+        .len();
+    assert_eq!(allocations(), before);
+    assert_eq!(written, roc.estimate_compressed_size_bytes(&ids, 50_000));
+}
+
+#[test]
+fn test_stack_compressor_does_not_allocate() {
+    let ids: Vec<u32> = (0..64).map(|i| i * 37).collect();
+    let stack = StackCompressor::<128>::new();
+    let mut buf = [0u8; 128];
+
+    let before = allocations();
+    let len = stack.compress_fixed(&ids, 10_000, &mut buf).unwrap();
+    let (decoded, count) = stack.decompress_fixed::<64>(&buf[..len], 10_000).unwrap();
+    assert_eq!(allocations(), before);
+    assert_eq!(decoded[..count], ids[..]);
+}
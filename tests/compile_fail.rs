@@ -0,0 +1,7 @@
+//! Misuses of the public API that must not compile.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,42 @@
+//! Writes the fuzz corpus and checks the decoder on every file.
+
+use cnk::fuzz_corpus::generate_fuzz_corpus;
+use cnk::{IdSetCompressor, RocCompressor};
+
+#[test]
+fn generate_corpus() {
+    let dir = std::env::temp_dir().join(format!("cnk-fuzz-corpus-{}", std::process::id()));
+    let written = generate_fuzz_corpus(&dir).unwrap();
+    assert_eq!(written, 9);
+
+    let mut entries: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    entries.sort();
+    assert_eq!(entries.len(), written);
+
+    let roc = RocCompressor::new();
+    for path in &entries {
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let result = roc.decompress_set(&bytes, u32::MAX);
+        match name {
+            "empty" => assert_eq!(result.unwrap(), Vec::<u32>::new()),
+            // A zero count ends decoding before the payload is looked at
+            "zero_count_with_payload" => assert_eq!(result.unwrap(), Vec::<u32>::new()),
+            _ => assert!(result.is_err(), "{} decoded to {:?}", name, result),
+        }
+    }
+
+    let max_varint = std::fs::read(dir.join("max_varint")).unwrap();
+    assert_eq!(max_varint.len(), 1 + 9);
+    assert_eq!(
+        cnk::varint::decode_varint(&max_varint[1..]).unwrap(),
+        (0x7FFF_FFFF_FFFF_FFFF, 9)
+    );
+
+    // Regenerating overwrites in place
+    assert_eq!(generate_fuzz_corpus(&dir).unwrap(), written);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,112 @@
+//! Streaming round trips through `std::io::copy`.
+
+use std::io::{self, Read};
+
+use cnk::{
+    CompressorWriter, DecompressorReader, IdSetCompressor, PForDeltaCompressor, RocCompressor,
+};
+
+fn raw(ids: &[u32]) -> Vec<u8> {
+    ids.iter().flat_map(|id| id.to_le_bytes()).collect()
+}
+
+/// Pipe `ids` through a writer and back through a reader.
+fn pipe<C: IdSetCompressor + Clone>(
+    compressor: C,
+    ids: &[u32],
+    universe: u32,
+    batch: usize,
+) -> (Vec<u8>, Vec<u8>) {
+    let input = raw(ids);
+    let mut writer =
+        CompressorWriter::with_batch_size(compressor.clone(), Vec::new(), universe, batch);
+    io::copy(&mut input.as_slice(), &mut writer).unwrap();
+    let compressed = writer.finish().unwrap();
+
+    let mut reader = DecompressorReader::new(compressor, compressed.as_slice(), universe);
+    let mut output = Vec::new();
+    io::copy(&mut reader, &mut output).unwrap();
+    (compressed, output)
+}
+
+#[test]
+fn test_copy_round_trip() {
+    let ids: Vec<u32> = (0..50_000).map(|i| i * 7 + i % 3).collect();
+    let universe = 1_000_000;
+
+    for batch in [1, 100, 4096, 1_000_000] {
+        let (compressed, output) = pipe(RocCompressor::new(), &ids, universe, batch);
+        assert_eq!(output, raw(&ids), "batch size {}", batch);
+        if batch > 1 {
+            assert!(compressed.len() < 4 * ids.len());
+        }
+    }
+    let (_, output) = pipe(PForDeltaCompressor::default(), &ids, universe, 1000);
+    assert_eq!(output, raw(&ids));
+}
+
+#[test]
+fn test_copy_empty() {
+    let (compressed, output) = pipe(RocCompressor::new(), &[], 10, 16);
+    assert!(compressed.is_empty());
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_small_reads() {
+    let ids: Vec<u32> = (0..1000).map(|i| i * 2).collect();
+    let mut writer = CompressorWriter::with_batch_size(RocCompressor::new(), Vec::new(), 2000, 64);
+    io::copy(&mut raw(&ids).as_slice(), &mut writer).unwrap();
+    let compressed = writer.finish().unwrap();
+
+    let mut reader = DecompressorReader::new(RocCompressor::new(), compressed.as_slice(), 2000);
+    let mut output = Vec::new();
+    let mut buf = [0u8; 3];
+    loop {
+        let n = reader.read(&mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(output, raw(&ids));
+}
+
+#[test]
+fn test_corrupt_stream_is_invalid_data() {
+    let mut reader =
+        DecompressorReader::new(RocCompressor::new(), &[3u8, 0xFF, 0xFF, 0xFF][..], 10);
+    let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_streaming_compress_into_cursor() {
+    let ids: Vec<u32> = (0..100_000).map(|i| i * 8 + i % 7).collect();
+    let universe = 1_000_000;
+
+    let roc = RocCompressor::new();
+    let mut cursor = io::Cursor::new(Vec::new());
+    let written = roc.streaming_compress(&ids, universe, &mut cursor).unwrap();
+    let streamed = cursor.into_inner();
+    assert_eq!(written, streamed.len());
+    assert_eq!(streamed, roc.compress_set(&ids, universe).unwrap());
+    assert_eq!(roc.decompress_set(&streamed, universe).unwrap(), ids);
+
+    // The default implementation writes the output of compress_set
+    let pfor = PForDeltaCompressor::default();
+    let mut cursor = io::Cursor::new(Vec::new());
+    pfor.streaming_compress(&ids, universe, &mut cursor)
+        .unwrap();
+    assert_eq!(
+        pfor.decompress_set(cursor.get_ref(), universe).unwrap(),
+        ids
+    );
+
+    // Invalid input writes nothing
+    let mut cursor = io::Cursor::new(Vec::new());
+    assert!(roc
+        .streaming_compress(&[5, 1], universe, &mut cursor)
+        .is_err());
+    assert!(cursor.get_ref().is_empty());
+}
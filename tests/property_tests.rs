@@ -3,8 +3,33 @@
 //! These tests verify mathematical invariants that must hold for all inputs,
 //! using proptest to generate random test cases.
 
-use cnk::{IdSetCompressor, RocCompressor};
+use cnk::testutils::Lcg;
+#[cfg(feature = "arithmetic")]
+use cnk::ArithmeticCompressor;
+#[cfg(feature = "roaring")]
+use cnk::RoaringBitmapCompressor;
+use cnk::{
+    apply_diff, bits_needed, compress_deltas_only, compress_fixed_width, compress_set_append,
+    decompress_checkpointed, decompress_deltas_only, decompress_fixed_width, diff,
+    from_sorted_bitmap, k_way_union, merge_compressed_indices, recompress, sorted_merge_compress,
+    to_sorted_bitmap, wand_intersect, AdaptiveCompressor, BaseOffsetCompressor,
+    BitmapSetCompressor, BlockDeltaCompressor, CheckpointedCompressor, Codec, CompressedIndex,
+    CompressedSet, CompressedSetBuilder, CompressedSetMap, CompressedSetVec, CompressedSetWithHash,
+    CompressionError, CompressionLevel, CompressionMethodSelector, CompressorRegistry,
+    ContextualRocCompressor, EliasDeltaCompressor, EliasFanoCompressor, EliasFanoWithSelect,
+    EliasGammaCompressor, ExceptionBasedDeltaCompressor, FibonacciCompressor, GapHistogram,
+    HuffmanCompressor, IdCompressionMethod, IdSetCompressor, InterpolativeCompressor,
+    MaxSizeCompressor, MultisetCompressor, NibbleCompressor, PForDeltaCompressor,
+    PeekableCompressedSet, RandomAccessCompressedSet, RemappingCompressor, RocCompressor,
+    RocMultisetCompressor, SampledIndex, SegmentedCompressor, Simple16Compressor,
+    SplitEliasFanoCompressor, StackCompressor, TwoLevelEliasFanoCompressor, ValidationMode,
+    VerifyingCompressor, WindowedCompressor, XorDeltaCompressor, ZigzagDeltaCompressor,
+};
+#[cfg(any(feature = "brotli", feature = "zstd"))]
+use cnk::{PostCompression, PostCompressor};
 use proptest::prelude::*;
+#[cfg(feature = "roaring")]
+use roaring::RoaringBitmap;
 
 /// Generate a sorted, unique set of IDs within a universe.
 fn sorted_unique_ids(max_len: usize, universe_size: u32) -> impl Strategy<Value = (Vec<u32>, u32)> {
@@ -21,6 +46,19 @@ fn sorted_unique_ids(max_len: usize, universe_size: u32) -> impl Strategy<Value
     })
 }
 
+/// Bytes of `compressed` and what `c` decodes from them, for failure
+/// messages.
+#[cfg(debug_assertions)]
+fn dump(compressed: &[u8], universe: u32, c: &dyn IdSetCompressor) -> String {
+    cnk::dump_compressed_set_hex(compressed, universe, c)
+}
+
+/// Release builds have no hex dump; print the bytes as a list.
+#[cfg(not(debug_assertions))]
+fn dump(compressed: &[u8], universe: u32, _c: &dyn IdSetCompressor) -> String {
+    format!("compressed {:?} universe={}", compressed, universe)
+}
+
 /// Generate sparse IDs (large gaps, typical of inverted indexes).
 fn sparse_ids(max_len: usize) -> impl Strategy<Value = (Vec<u32>, u32)> {
     (1..=max_len).prop_flat_map(move |len| {
@@ -43,6 +81,15 @@ fn dense_ids(max_len: usize) -> impl Strategy<Value = (Vec<u32>, u32)> {
     })
 }
 
+/// Generate sorted `(id, count)` pairs with unique IDs and counts >= 1.
+fn multiset_pairs(
+    max_len: usize,
+    universe_size: u32,
+) -> impl Strategy<Value = (Vec<(u32, u32)>, u32)> {
+    proptest::collection::btree_map(0..universe_size, 1u32..=u32::MAX, 0..=max_len)
+        .prop_map(move |map| (map.into_iter().collect(), universe_size))
+}
+
 proptest! {
     #![proptest_config(ProptestConfig::with_cases(500))]
 
@@ -56,10 +103,14 @@ proptest! {
 
         let compressed = compressor.compress_set(&ids, universe)
             .expect("compression should succeed for valid input");
-        let decompressed = compressor.decompress_set(&compressed, universe)
-            .expect("decompression should succeed for valid compressed data");
+        let decompressed = compressor.decompress_set(&compressed, universe);
 
-        prop_assert_eq!(ids, decompressed, "roundtrip must preserve data");
+        prop_assert_eq!(
+            decompressed.as_ref().ok(),
+            Some(&ids),
+            "roundtrip must preserve data\n{}",
+            dump(&compressed, universe, &compressor)
+        );
     }
 
     #[test]
@@ -67,9 +118,14 @@ proptest! {
         let compressor = RocCompressor::new();
 
         let compressed = compressor.compress_set(&ids, universe)?;
-        let decompressed = compressor.decompress_set(&compressed, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe);
 
-        prop_assert_eq!(ids, decompressed);
+        prop_assert_eq!(
+            decompressed.as_ref().ok(),
+            Some(&ids),
+            "{}",
+            dump(&compressed, universe, &compressor)
+        );
     }
 
     #[test]
@@ -77,9 +133,14 @@ proptest! {
         let compressor = RocCompressor::new();
 
         let compressed = compressor.compress_set(&ids, universe)?;
-        let decompressed = compressor.decompress_set(&compressed, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe);
 
-        prop_assert_eq!(ids, decompressed);
+        prop_assert_eq!(
+            decompressed.as_ref().ok(),
+            Some(&ids),
+            "{}",
+            dump(&compressed, universe, &compressor)
+        );
     }
 
     // =======================================================================
@@ -240,66 +301,2229 @@ proptest! {
     }
 }
 
-// =======================================================================
-// STATISTICAL TESTS (not proptest, but important)
-// =======================================================================
+proptest! {
+    // =======================================================================
+    // MULTISETS
+    // =======================================================================
 
-#[test]
-fn compression_ratio_improves_with_density() {
-    let compressor = RocCompressor::new();
-    let universe = 100_000u32;
+    #[test]
+    fn roundtrip_multisets((pairs, universe) in multiset_pairs(100, 100_000)) {
+        let compressor = RocMultisetCompressor::new();
 
-    // Sparse set with large gaps (delta ~100)
-    let sparse: Vec<u32> = (0..1000).map(|i| i * 100).collect();
-    let sparse_compressed = compressor.compress_set(&sparse, universe).unwrap();
-    let sparse_bytes_per_id = sparse_compressed.len() as f64 / sparse.len() as f64;
+        let compressed = compressor.compress_multiset(&pairs, universe)?;
+        let decompressed = compressor.decompress_multiset(&compressed, universe)?;
 
-    // Dense set (consecutive, delta = 1)
-    let dense: Vec<u32> = (0..1000).collect();
-    let dense_compressed = compressor.compress_set(&dense, universe).unwrap();
-    let dense_bytes_per_id = dense_compressed.len() as f64 / dense.len() as f64;
+        prop_assert_eq!(pairs, decompressed);
+    }
 
-    // Dense should use fewer bytes per ID (delta=1 is 1 byte, delta=100 is 1 byte too in varint)
-    // Actually both fit in 1 byte varint, so let's use larger gaps
-    let very_sparse: Vec<u32> = (0..100).map(|i| i * 1000).collect();
-    let very_sparse_compressed = compressor.compress_set(&very_sparse, universe).unwrap();
-    let very_sparse_bytes_per_id = very_sparse_compressed.len() as f64 / very_sparse.len() as f64;
+    #[test]
+    fn multiset_with_unit_counts_matches_set_size((ids, universe) in sorted_unique_ids(100, 10000)) {
+        let pairs: Vec<(u32, u32)> = ids.iter().map(|&id| (id, 1)).collect();
 
-    // Very sparse (delta=1000, needs 2 bytes) should use more bytes per ID than dense (delta=1)
-    assert!(
-        very_sparse_bytes_per_id > dense_bytes_per_id,
-        "very sparse {} bytes/id should exceed dense {} bytes/id",
-        very_sparse_bytes_per_id,
-        dense_bytes_per_id
-    );
+        let set_bytes = RocCompressor::new().compress_set(&ids, universe)?;
+        let multiset_bytes = RocMultisetCompressor::new().compress_multiset(&pairs, universe)?;
 
-    println!("Dense: {:.2} bytes/id", dense_bytes_per_id);
-    println!("Sparse (delta=100): {:.2} bytes/id", sparse_bytes_per_id);
-    println!(
-        "Very sparse (delta=1000): {:.2} bytes/id",
-        very_sparse_bytes_per_id
-    );
+        // Each unit count costs exactly one extra byte; the set format also
+        // carries a one-byte level tag
+        prop_assert_eq!(multiset_bytes.len() + 1, set_bytes.len() + ids.len());
+    }
 }
 
-#[test]
-fn estimate_size_is_reasonable() {
-    let compressor = RocCompressor::new();
+proptest! {
+    // =======================================================================
+    // HUFFMAN
+    // =======================================================================
 
-    for num_ids in [10, 100, 1000] {
-        for universe in [10_000, 100_000, 1_000_000] {
-            let estimate = compressor.estimate_size(num_ids, universe);
+    #[test]
+    fn roundtrip_huffman_random((ids, universe) in sorted_unique_ids(200, 10000)) {
+        let compressor = HuffmanCompressor::new();
 
-            // Estimate should be positive for non-empty sets
-            assert!(estimate > 0, "estimate should be positive");
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
 
-            // Estimate should be less than raw storage
-            let raw_size = num_ids * 4;
-            assert!(
-                estimate <= raw_size * 2,
-                "estimate {} should be reasonable vs raw {}",
-                estimate,
-                raw_size
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_huffman_dense((ids, universe) in dense_ids(200)) {
+        let compressor = HuffmanCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_huffman_large_gaps((a, gap) in (0u32..1000, 1u32..u32::MAX / 2)) {
+        let b = a.saturating_add(gap);
+        let universe = b + 1;
+        let compressor = HuffmanCompressor::new();
+        let ids = vec![a, b];
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+}
+
+#[cfg(feature = "roaring")]
+proptest! {
+    // =======================================================================
+    // ROARING BITMAP
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_roaring_random((ids, universe) in sorted_unique_ids(200, 10000)) {
+        let compressor = RoaringBitmapCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_roaring_dense((ids, universe) in dense_ids(200)) {
+        let compressor = RoaringBitmapCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    /// Union and intersection agree on bitmaps and on bitmaps round-tripped
+    /// through compressed sets.
+    #[test]
+    fn roaring_interop_preserves_set_operations(
+        (a, _) in sorted_unique_ids(200, 100_000),
+        (b, _) in sorted_unique_ids(200, 100_000),
+    ) {
+        let a: RoaringBitmap = a.into_iter().collect();
+        let b: RoaringBitmap = b.into_iter().collect();
+        let a_set = CompressedSet::from(&a);
+        let b_set = CompressedSet::from(&b);
+        prop_assert_eq!(a_set.decompress()?, a.iter().collect::<Vec<_>>());
+
+        let a_back = RoaringBitmap::try_from(&a_set)?;
+        let b_back = RoaringBitmap::try_from(&b_set)?;
+        prop_assert_eq!(&a_back | &b_back, &a | &b);
+        prop_assert_eq!(&a_back & &b_back, &a & &b);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SKIP QUERIES
+    // =======================================================================
+
+    #[test]
+    fn next_geq_matches_linear_search(
+        (ids, universe) in sorted_unique_ids(100, 10000),
+        x in 0u32..11000,
+    ) {
+        let compressor = RocCompressor::new();
+        let compressed = compressor.compress_set(&ids, universe)?;
+
+        let expected = ids.iter().copied().find(|&v| v >= x);
+        prop_assert_eq!(compressor.next_geq(&compressed, universe, x)?, expected);
+    }
+
+    #[test]
+    fn prev_leq_matches_linear_search(
+        (ids, universe) in sorted_unique_ids(100, 10000),
+        x in 0u32..11000,
+    ) {
+        let compressor = RocCompressor::new();
+        let compressed = compressor.compress_set(&ids, universe)?;
+
+        let expected = ids.iter().copied().rev().find(|&v| v <= x);
+        prop_assert_eq!(compressor.prev_leq(&compressed, universe, x)?, expected);
+    }
+
+    /// The halves concatenate back to the original set, split exactly at
+    /// the threshold.
+    #[test]
+    fn partition_by_splits_at_threshold(
+        (ids, universe) in sorted_unique_ids(100, 10000),
+        threshold in 0u32..11000,
+    ) {
+        let compressor = RocCompressor::new();
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let (left, right) = compressor.partition_by(&compressed, universe, threshold)?;
+
+        let left: Vec<u32> = compressor.decompress_set(&left, universe)?;
+        let right: Vec<u32> = compressor.decompress_set(&right, universe)?;
+        prop_assert!(left.iter().all(|&id| id < threshold));
+        prop_assert!(right.iter().all(|&id| id >= threshold));
+        prop_assert_eq!([left, right].concat(), ids);
+    }
+
+    #[test]
+    fn compress_set_with_sort_matches_sorting_first(
+        shuffled in sorted_unique_ids(300, 10000)
+            .prop_flat_map(|(ids, universe)| (Just(ids).prop_shuffle(), Just(universe))),
+    ) {
+        let (mut ids, universe) = shuffled;
+        let roc = RocCompressor::new();
+        let mut sorted = ids.clone();
+        sorted.sort_unstable();
+        prop_assert_eq!(roc.compress_set_with_sort(&mut ids, universe)?, roc.compress_set(&sorted, universe)?);
+        prop_assert_eq!(ids, sorted);
+    }
+
+    /// Both the early-stopping override and the filtering default return
+    /// exactly the IDs in `[lo, hi)`.
+    #[test]
+    fn decompress_range_matches_filter(
+        (ids, universe) in sorted_unique_ids(100, 10000),
+        lo in 0u32..11000,
+        hi in 0u32..11000,
+    ) {
+        let expected: Vec<u32> = ids.iter().copied().filter(|&x| lo <= x && x < hi).collect();
+        let compressors: [&dyn IdSetCompressor; 3] = [
+            &RocCompressor::new(),
+            &RocCompressor::with_level(CompressionLevel::Fastest),
+            &EliasFanoCompressor::new(),
+        ];
+        for compressor in compressors {
+            let compressed = compressor.compress_set(&ids, universe)?;
+            prop_assert_eq!(
+                &compressor.decompress_range(&compressed, universe, lo, hi)?,
+                &expected
             );
         }
     }
+
+    #[test]
+    fn count_in_range_matches_decompress_range(
+        (ids, universe) in sorted_unique_ids(100, 10000),
+        lo in 0u32..11000,
+        hi in 0u32..11000,
+        fastest in any::<bool>(),
+    ) {
+        let compressor = if fastest {
+            RocCompressor::with_level(CompressionLevel::Fastest)
+        } else {
+            RocCompressor::new()
+        };
+        let compressed = compressor.compress_set(&ids, universe)?;
+        prop_assert_eq!(
+            compressor.count_in_range(&compressed, universe, lo, hi)?,
+            compressor.decompress_range(&compressed, universe, lo, hi)?.len()
+        );
+        prop_assert_eq!(compressor.count_elements(&compressed)?, ids.len());
+        prop_assert_eq!(
+            EliasFanoCompressor::new().count_in_range(
+                &EliasFanoCompressor::new().compress_set(&ids, universe)?,
+                universe,
+                lo,
+                hi
+            )?,
+            ids.iter().filter(|&&x| lo <= x && x < hi).count()
+        );
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // INCREMENTAL BUILDER
+    // =======================================================================
+
+    #[test]
+    fn builder_matches_compress_set((ids, universe) in sorted_unique_ids(200, 100_000)) {
+        let mut builder = CompressedSetBuilder::new(universe);
+        for &id in &ids {
+            builder.push(id)?;
+        }
+
+        let expected = RocCompressor::new().compress_set(&ids, universe)?;
+        prop_assert_eq!(builder.finish()?, expected);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // UNTRUSTED INPUT (see also fuzz/)
+    // =======================================================================
+
+    #[test]
+    fn decompress_arbitrary_bytes_never_panics(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u32..=u32::MAX,
+    ) {
+        let compressor = RocCompressor::new();
+        if let Ok(ids) = compressor.decompress_set(&bytes, universe) {
+            prop_assert!(ids.windows(2).all(|w| w[0] < w[1]));
+            prop_assert!(ids.iter().all(|&id| id < universe));
+        }
+    }
+}
+
+/// Every `IdSetCompressor` implementation in the crate.
+fn all_codecs() -> Vec<Box<dyn IdSetCompressor>> {
+    #[allow(unused_mut)]
+    let mut codecs: Vec<Box<dyn IdSetCompressor>> = vec![
+        Box::new(RocCompressor::new()),
+        Box::new(HuffmanCompressor::new()),
+        Box::new(BlockDeltaCompressor::new(16)),
+        Box::new(PForDeltaCompressor::default()),
+        Box::new(ExceptionBasedDeltaCompressor::default()),
+        Box::new(FibonacciCompressor::new()),
+        Box::new(NibbleCompressor::new()),
+        Box::new(EliasGammaCompressor::new()),
+        Box::new(EliasDeltaCompressor::new()),
+        Box::new(XorDeltaCompressor::new()),
+        Box::new(Simple16Compressor::new()),
+        Box::new(SegmentedCompressor::default()),
+        Box::new(ZigzagDeltaCompressor::new()),
+        Box::new(EliasFanoCompressor::new()),
+        Box::new(InterpolativeCompressor::new()),
+        Box::new(BitmapSetCompressor::new()),
+    ];
+    #[cfg(feature = "roaring")]
+    codecs.push(Box::new(RoaringBitmapCompressor::new()));
+    codecs
+}
+
+proptest! {
+    // =======================================================================
+    // TRANSCODING
+    // =======================================================================
+
+    #[test]
+    fn recompress_between_all_codecs((ids, universe) in sorted_unique_ids(100, 10000)) {
+        let codecs = all_codecs();
+        for src in &codecs {
+            let compressed = src.compress_set(&ids, universe)?;
+            for dst in &codecs {
+                let transcoded = recompress(&compressed, universe, src.as_ref(), dst.as_ref())?;
+                let decompressed = dst.decompress_set(&transcoded, universe);
+                prop_assert_eq!(
+                    decompressed.as_ref().ok(),
+                    Some(&ids),
+                    "{:?} -> {:?}\n{}",
+                    src.format_id(),
+                    dst.format_id(),
+                    dump(&transcoded, universe, dst.as_ref())
+                );
+            }
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // BLOCK DELTA
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_block_delta(
+        (ids, universe) in sorted_unique_ids(300, 100_000),
+        block_size in 1usize..64,
+    ) {
+        let compressor = BlockDeltaCompressor::new(block_size);
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn block_next_geq_matches_linear_search(
+        (ids, universe) in sorted_unique_ids(300, 10000),
+        block_size in 1usize..64,
+        x in 0u32..11000,
+    ) {
+        let compressor = BlockDeltaCompressor::new(block_size);
+        let compressed = compressor.compress_set(&ids, universe)?;
+
+        let expected = ids.iter().copied().find(|&v| v >= x);
+        prop_assert_eq!(compressor.next_geq(&compressed, universe, x)?, expected);
+    }
+
+    #[test]
+    fn sampled_next_geq_matches_linear_search(
+        (ids, universe) in sorted_unique_ids(300, 10000),
+        interval in 1usize..64,
+        x in 0u32..11000,
+    ) {
+        for c in all_codecs() {
+            let compressed = c.compress_set(&ids, universe)?;
+            let index = SampledIndex::new(compressed, universe, c.as_ref(), interval)?;
+
+            let expected = ids.iter().copied().find(|&v| v >= x);
+            prop_assert_eq!(index.next_geq(x)?, expected);
+        }
+    }
+
+    #[test]
+    fn random_access_get_matches_decompressed(
+        (ids, universe) in sorted_unique_ids(300, 10000),
+        skip_interval in 1usize..64,
+    ) {
+        for c in all_codecs() {
+            let compressed = c.compress_set(&ids, universe)?;
+            let set = RandomAccessCompressedSet::build(&compressed, universe, skip_interval, c.as_ref())?;
+            prop_assert_eq!(set.len(), ids.len());
+            for (k, &id) in ids.iter().enumerate() {
+                prop_assert_eq!(set.get(k)?, Some(id));
+            }
+            prop_assert_eq!(set.get(ids.len())?, None);
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SPLIT ELIAS-FANO
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_split_elias_fano_64_bit(
+        ids in proptest::collection::btree_set(0u64..u64::MAX, 0..200),
+        block_bits in 1u8..64,
+    ) {
+        let ids: Vec<u64> = ids.into_iter().collect();
+        let compressor = SplitEliasFanoCompressor::new(block_bits);
+
+        let compressed = compressor.compress_set(&ids, u64::MAX)?;
+        let decompressed = compressor.decompress_set(&compressed, u64::MAX)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn split_elias_fano_clustered(
+        base in 0u64..(u64::MAX >> 1),
+        offsets in proptest::collection::btree_set(0u64..1_000_000, 1..300),
+        x in 0u64..1_100_000,
+    ) {
+        let ids: Vec<u64> = offsets.into_iter().map(|o| base + o).collect();
+        let compressor = SplitEliasFanoCompressor::default();
+
+        let compressed = compressor.compress_set(&ids, u64::MAX)?;
+        prop_assert_eq!(&compressor.decompress_set(&compressed, u64::MAX)?, &ids);
+
+        let x = base + x;
+        let expected = ids.iter().copied().find(|&v| v >= x);
+        prop_assert_eq!(compressor.next_geq_u64(&compressed, u64::MAX, x)?, expected);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // TWO-LEVEL ELIAS-FANO
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_two_level_elias_fano_64_bit(
+        ids in proptest::collection::btree_set(0u64..u64::MAX, 0..300),
+    ) {
+        let ids: Vec<u64> = ids.into_iter().collect();
+        let compressor = TwoLevelEliasFanoCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, u64::MAX)?;
+        prop_assert_eq!(compressor.decompress_set(&compressed, u64::MAX)?, ids);
+    }
+
+    #[test]
+    fn two_level_elias_fano_next_geq_matches_linear_search(
+        entries in proptest::collection::btree_set((0u64..8, 0u64..1000), 1..300),
+        x in 0u64..(9 << 32),
+    ) {
+        // A few crowded blocks, so searches land inside blocks as well as between them
+        let ids: Vec<u64> = entries
+            .into_iter()
+            .map(|(block, low)| (block << 32) | (low * 4_000_000))
+            .collect();
+        let compressor = TwoLevelEliasFanoCompressor::new();
+        let compressed = compressor.compress_set(&ids, u64::MAX)?;
+
+        let expected = |x: u64| ids.iter().copied().find(|&v| v >= x);
+        prop_assert_eq!(compressor.next_geq_u64(&compressed, u64::MAX, x)?, expected(x));
+        for &id in &ids {
+            prop_assert_eq!(compressor.next_geq_u64(&compressed, u64::MAX, id)?, Some(id));
+            prop_assert_eq!(
+                compressor.next_geq_u64(&compressed, u64::MAX, id + 1)?,
+                expected(id + 1)
+            );
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // DIFFS
+    // =======================================================================
+
+    #[test]
+    fn apply_diff_reconstructs_new_set(
+        (old, universe) in sorted_unique_ids(200, 10000),
+        toggles in proptest::collection::vec(0u32..10000, 0..=20),
+    ) {
+        // `new` differs from `old` by at most 20 IDs added or removed
+        let mut new: std::collections::BTreeSet<u32> = old.iter().copied().collect();
+        for id in toggles.into_iter().map(|t| t % universe) {
+            if !new.remove(&id) {
+                new.insert(id);
+            }
+        }
+        let new: Vec<u32> = new.into_iter().collect();
+
+        for c in all_codecs() {
+            let old_compressed = c.compress_set(&old, universe)?;
+            let new_compressed = c.compress_set(&new, universe)?;
+
+            let delta = diff(&old_compressed, &new_compressed, universe, c.as_ref())?;
+            let patched = apply_diff(&old_compressed, &delta, universe, c.as_ref())?;
+            prop_assert_eq!(
+                c.decompress_set(&patched, universe)?,
+                c.decompress_set(&new_compressed, universe)?
+            );
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // PFOR-DELTA
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_pfor_auto((ids, universe) in sparse_ids(1000)) {
+        let compressor = PForDeltaCompressor::default();
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_pfor_fixed_frame(
+        (ids, universe) in sorted_unique_ids(500, 100_000),
+        frame_bits in 0u8..=32,
+    ) {
+        let compressor = PForDeltaCompressor::new(Some(frame_bits));
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn pfor_dense_ids_cost_no_frame_bits((ids, universe) in dense_ids(1000)) {
+        let compressed = PForDeltaCompressor::default().compress_set(&ids, universe)?;
+        // Header plus two bytes per block of 128 gaps
+        let num_blocks = (ids.len() - 1).div_ceil(128);
+        prop_assert!(compressed.len() <= 8 + 2 * num_blocks);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SIZE ESTIMATES
+    // =======================================================================
+
+    #[test]
+    fn delta_size_estimates_are_exact((ids, universe) in sorted_unique_ids(500, 1_000_000)) {
+        let roc = RocCompressor::new();
+        prop_assert_eq!(
+            roc.estimate_compressed_size_bytes(&ids, universe),
+            roc.compress_set(&ids, universe)?.len()
+        );
+
+        let block = BlockDeltaCompressor::new(16);
+        prop_assert_eq!(
+            block.estimate_compressed_size_bytes(&ids, universe),
+            block.compress_set(&ids, universe)?.len()
+        );
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // ITERATOR INPUT
+    // =======================================================================
+
+    #[test]
+    fn compress_sorted_iter_matches_compress_set((ids, universe) in sorted_unique_ids(300, 100_000)) {
+        let roc = RocCompressor::new();
+        prop_assert_eq!(
+            roc.compress_sorted_iter(ids.iter().copied(), universe)?,
+            roc.compress_set(&ids, universe)?
+        );
+
+        // Default implementation
+        let huffman = HuffmanCompressor::new();
+        prop_assert_eq!(
+            huffman.compress_sorted_iter(ids.iter().copied(), universe)?,
+            huffman.compress_set(&ids, universe)?
+        );
+    }
+
+    #[test]
+    fn compress_sorted_iter_rejects_unsorted((mut ids, universe) in sorted_unique_ids(100, 10000)) {
+        prop_assume!(ids.len() >= 2);
+        ids.reverse();
+        prop_assert!(RocCompressor::new().compress_sorted_iter(ids, universe).is_err());
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // FIBONACCI
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_fibonacci_gaps_up_to_2_pow_24(
+        first in 0u32..1000,
+        gaps in proptest::collection::vec(1u32..=1 << 24, 0..200),
+    ) {
+        let mut ids = vec![first];
+        for gap in gaps {
+            let next = ids.last().unwrap().checked_add(gap);
+            prop_assume!(next.is_some());
+            ids.push(next.unwrap());
+        }
+        let universe = u32::MAX;
+        let compressor = FibonacciCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn fibonacci_never_panics_on_garbage(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u32..1_000_000,
+    ) {
+        let _ = FibonacciCompressor::new().decompress_set(&bytes, universe);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // ELIAS GAMMA AND DELTA
+    // =======================================================================
+
+    /// Any ID and gap a `u32` set can hold, from 1 up to `u32::MAX`.
+    #[test]
+    fn roundtrip_elias_any_u32_gaps(
+        first in any::<u32>(),
+        gaps in proptest::collection::vec(1u32..=u32::MAX, 0..50),
+    ) {
+        let mut ids = vec![first];
+        for gap in gaps {
+            match ids.last().unwrap().checked_add(gap) {
+                Some(next) if next < u32::MAX => ids.push(next),
+                _ => break,
+            }
+        }
+        prop_assume!(ids[ids.len() - 1] < u32::MAX);
+
+        for c in [
+            &EliasGammaCompressor::new() as &dyn IdSetCompressor,
+            &EliasDeltaCompressor::new(),
+        ] {
+            let compressed = c.compress_set(&ids, u32::MAX)?;
+            prop_assert_eq!(c.decompress_set(&compressed, u32::MAX)?, ids.clone());
+        }
+    }
+
+    #[test]
+    fn elias_never_panics_on_garbage(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u32..1_000_000,
+    ) {
+        let _ = EliasGammaCompressor::new().decompress_set(&bytes, universe);
+        let _ = EliasDeltaCompressor::new().decompress_set(&bytes, universe);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // XOR AND ZIGZAG DELTA
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_xor_delta_any_order(ids in proptest::collection::vec(any::<u32>(), 0..300)) {
+        prop_assume!(ids.iter().all(|&id| id < u32::MAX));
+        let compressor = XorDeltaCompressor::new();
+        prop_assert!(!compressor.requires_sorted_input());
+
+        let compressed = compressor.compress_set(&ids, u32::MAX)?;
+        let decompressed = compressor.decompress_set(&compressed, u32::MAX)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_zigzag_delta_any_order(ids in proptest::collection::vec(any::<u32>(), 0..300)) {
+        prop_assume!(ids.iter().all(|&id| id < u32::MAX));
+        let compressor = ZigzagDeltaCompressor::new();
+        prop_assert!(!compressor.requires_sorted_input());
+
+        let compressed = compressor.compress_set(&ids, u32::MAX)?;
+        let decompressed = compressor.decompress_set(&compressed, u32::MAX)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    /// Random walks with small steps in both directions cost about one byte
+    /// per ID.
+    #[test]
+    fn zigzag_delta_small_steps(
+        start in 1000u32..1_000_000,
+        steps in proptest::collection::vec(-60i32..=60, 1..300),
+    ) {
+        let ids: Vec<u32> = steps
+            .iter()
+            .scan(start, |id, &step| {
+                *id = id.wrapping_add(step as u32);
+                Some(*id)
+            })
+            .collect();
+        let compressor = ZigzagDeltaCompressor::new();
+        let compressed = compressor.compress_set(&ids, 2_000_000)?;
+
+        prop_assert!(compressed.len() <= 2 + 3 + ids.len());
+        prop_assert_eq!(compressor.decompress_set(&compressed, 2_000_000)?, ids);
+    }
+
+    #[test]
+    fn sorted_codecs_report_sorted_requirement((ids, universe) in sorted_unique_ids(50, 1000)) {
+        for c in all_codecs() {
+            if c.requires_sorted_input() {
+                let mut reversed = ids.clone();
+                reversed.reverse();
+                prop_assert!(ids.len() < 2 || c.compress_set(&reversed, universe).is_err());
+            }
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // COMPRESSION LEVELS
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_all_levels((ids, universe) in sorted_unique_ids(300, 100_000)) {
+        let reader = RocCompressor::new();
+        for level in [CompressionLevel::Fastest, CompressionLevel::Default, CompressionLevel::Best] {
+            let compressed = RocCompressor::with_level(level).compress_set(&ids, universe)?;
+            let decompressed = reader.decompress_set(&compressed, universe);
+            prop_assert_eq!(
+                decompressed.as_ref().ok(),
+                Some(&ids),
+                "{:?}\n{}",
+                level,
+                dump(&compressed, universe, &reader)
+            );
+            prop_assert_eq!(reader.next_geq(&compressed, universe, 0)?, ids.first().copied());
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SIMPLE-16
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_simple16_mixed_gap_widths(
+        first in any::<u32>(),
+        gaps in proptest::collection::vec(
+            prop_oneof![1u32..=4, 1u32..=1 << 10, 1u32..=u32::MAX],
+            0..300,
+        ),
+    ) {
+        let mut ids = vec![first];
+        for gap in gaps {
+            match ids.last().unwrap().checked_add(gap) {
+                Some(next) if next < u32::MAX => ids.push(next),
+                _ => break,
+            }
+        }
+        prop_assume!(ids[0] < u32::MAX);
+        let compressor = Simple16Compressor::new();
+
+        let compressed = compressor.compress_set(&ids, u32::MAX)?;
+        prop_assert_eq!(compressed.len() % 4, 0);
+        let decompressed = compressor.decompress_set(&compressed, u32::MAX)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn simple16_never_panics_on_garbage(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u32..1_000_000,
+    ) {
+        let _ = Simple16Compressor::new().decompress_set(&bytes, universe);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // DECOMPRESS ITERATOR
+    // =======================================================================
+
+    #[test]
+    fn iter_len_is_exact_throughout((ids, universe) in sorted_unique_ids(200, 100_000)) {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&ids, universe)?;
+
+        let mut iter = roc.iter(&compressed, universe)?;
+        prop_assert_eq!(iter.len(), ids.len());
+        for (consumed, &id) in ids.iter().enumerate() {
+            prop_assert_eq!(iter.len(), ids.len() - consumed);
+            prop_assert_eq!(iter.next().transpose()?, Some(id));
+        }
+        prop_assert_eq!(iter.len(), 0);
+        prop_assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn iter_rev_matches_reversed_forward((ids, universe) in sorted_unique_ids(200, 100_000)) {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&ids, universe)?;
+
+        let mut forward: Vec<u32> = roc.iter(&compressed, universe)?.collect::<Result<_, _>>()?;
+        let backward: Vec<u32> = roc.iter(&compressed, universe)?.rev().collect::<Result<_, _>>()?;
+        forward.reverse();
+
+        prop_assert_eq!(backward, forward);
+    }
+
+    #[test]
+    fn k_way_union_matches_set_union(
+        sets in proptest::collection::vec(proptest::collection::btree_set(0u32..5000, 0..100), 0..6),
+    ) {
+        let roc = RocCompressor::new();
+        let compressed: Vec<Vec<u8>> = sets
+            .iter()
+            .map(|set| roc.compress_set(&set.iter().copied().collect::<Vec<_>>(), 5000))
+            .collect::<Result<_, _>>()?;
+        let iters = compressed
+            .iter()
+            .map(|c| roc.iter(c, 5000))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let union: Vec<u32> = k_way_union(iters, 5000).collect::<Result<_, _>>()?;
+        let expected: Vec<u32> = sets.into_iter().flatten().collect::<std::collections::BTreeSet<_>>().into_iter().collect();
+        prop_assert_eq!(union, expected);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // FINGERPRINTS
+    // =======================================================================
+
+    #[test]
+    fn fingerprinted_set_round_trips((ids, universe) in sorted_unique_ids(200, 100_000)) {
+        let set = CompressedSet::new(&ids, universe, &RocCompressor::new())?;
+        let hashed = CompressedSetWithHash::from_bytes(set.with_fingerprint().into_bytes())?;
+
+        prop_assert!(CompressedSet::is_valid_compressed(hashed.as_bytes()));
+        prop_assert_eq!(hashed.into_verified()?.decompress()?, ids);
+    }
+
+    #[test]
+    fn single_bit_flip_fails_verification(
+        (ids, universe) in sorted_unique_ids(200, 100_000),
+        position in any::<proptest::sample::Index>(),
+        bit in 0u8..8,
+    ) {
+        let set = CompressedSet::new(&ids, universe, &RocCompressor::new())?;
+        let mut bytes = set.with_fingerprint().into_bytes();
+        let at = position.index(bytes.len());
+        bytes[at] ^= 1 << bit;
+
+        // Either the header no longer parses or the fingerprint mismatches
+        let verified = CompressedSetWithHash::from_bytes(bytes.clone())
+            .and_then(|hashed| hashed.verify_fingerprint());
+        prop_assert!(verified.is_err());
+        prop_assert!(!CompressedSet::is_valid_compressed(&bytes));
+    }
+
+    #[test]
+    fn roundtrip_compressed_set_from_ids(
+        (ids, _) in sorted_unique_ids(500, u32::MAX),
+    ) {
+        let set = CompressedSet::try_from(ids.as_slice())?;
+        prop_assert_eq!(set.universe(), ids.last().map_or(1, |&max| max + 1));
+
+        let decompressed: Vec<u32> = set.try_into()?;
+        prop_assert_eq!(ids, decompressed);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // BASE OFFSET
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_base_offset(
+        base in 0u32..1_000_000,
+        span in 1u32..100_000,
+        offsets in proptest::collection::btree_set(0u32..100_000, 0..200),
+    ) {
+        let universe = base + span;
+        let ids: Vec<u32> = offsets.into_iter().filter(|&o| o < span).map(|o| base + o).collect();
+
+        let roc = BaseOffsetCompressor::new(RocCompressor::new(), base);
+        let compressed = roc.compress_set(&ids, universe)?;
+        prop_assert_eq!(&roc.decompress_set(&compressed, universe)?, &ids);
+
+        let pfor = BaseOffsetCompressor::new(PForDeltaCompressor::default(), base);
+        let compressed = pfor.compress_set(&ids, universe)?;
+        prop_assert_eq!(&pfor.decompress_set(&compressed, universe)?, &ids);
+    }
+
+    #[test]
+    fn base_offset_shrinks_universe_cost(
+        base in 1_000u32..1_000_000,
+        (offsets, span) in sorted_unique_ids(200, 10_000),
+    ) {
+        prop_assume!(!offsets.is_empty());
+        let universe = base + span;
+        let ids: Vec<u32> = offsets.iter().map(|&o| base + o).collect();
+        let roc = RocCompressor::new();
+        let offset = BaseOffsetCompressor::new(RocCompressor::new(), base);
+
+        prop_assert!(
+            offset.theoretical_bits_per_id(ids.len(), universe)
+                < roc.theoretical_bits_per_id(ids.len(), universe)
+        );
+        // A bitmap pays for the whole universe, so dropping at least 1000
+        // IDs below the base saves far more than the 4-byte header
+        let bitmap = BitmapSetCompressor::new();
+        let full = bitmap.compress_set(&ids, universe)?;
+        let shifted = BaseOffsetCompressor::new(BitmapSetCompressor::new(), base)
+            .compress_set(&ids, universe)?;
+        prop_assert!(shifted.len() < full.len(), "{} vs {} bytes", shifted.len(), full.len());
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // IDS NEAR u32::MAX
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_upper_half_of_u32(
+        ids in proptest::collection::btree_set(u32::MAX / 2..u32::MAX, 0..100),
+    ) {
+        // Include 0 so the first gap spans more than u32::MAX / 2
+        let ids: Vec<u32> = std::iter::once(0).chain(ids).collect();
+        // A bitset of the whole u32 range is 512 MiB per case
+        for c in all_codecs().into_iter().filter(|c| c.format_id() != Some("cnk/bitset")) {
+            let compressed = c.compress_set(&ids, u32::MAX)?;
+            prop_assert_eq!(&c.decompress_set(&compressed, u32::MAX)?, &ids);
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SMALL ID TYPES
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_u8_one_byte_per_id(ids in proptest::collection::btree_set(0u8..255, 0..255)) {
+        let ids: Vec<u8> = ids.into_iter().collect();
+        let roc = RocCompressor::new();
+
+        let compressed = roc.compress_set(&ids, u8::MAX)?;
+        let expected_len = if ids.is_empty() { 0 } else { 1 + ids.len() };
+        prop_assert_eq!(compressed.len(), expected_len);
+        prop_assert_eq!(roc.decompress_set(&compressed, u8::MAX)?, ids);
+    }
+
+    #[test]
+    fn roundtrip_u16_two_bytes_per_id(ids in proptest::collection::btree_set(0u16..u16::MAX, 0..500)) {
+        let ids: Vec<u16> = ids.into_iter().collect();
+        let roc = RocCompressor::new();
+
+        let compressed = roc.compress_set(&ids, u16::MAX)?;
+        let expected_len = if ids.is_empty() { 0 } else { 2 * (1 + ids.len()) };
+        prop_assert_eq!(compressed.len(), expected_len);
+        prop_assert_eq!(roc.decompress_set(&compressed, u16::MAX)?, ids);
+    }
+
+    #[test]
+    fn roundtrip_u32_within_varint_bound((ids, universe) in sorted_unique_ids(500, 1_000_000)) {
+        let roc = RocCompressor::new();
+
+        let compressed = roc.compress_set(&ids, universe)?;
+        // Level byte, then at most 5 bytes for the length and each varint
+        prop_assert!(compressed.len() <= 1 + 5 * (1 + ids.len()));
+        prop_assert_eq!(roc.decompress_set(&compressed, universe)?, ids);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SORTED MERGE
+    // =======================================================================
+
+    #[test]
+    fn sorted_merge_matches_compressing_union(
+        sets in proptest::collection::vec(proptest::collection::btree_set(0u32..5000, 0..100), 0..8),
+    ) {
+        let slices: Vec<Vec<u32>> = sets.iter().map(|s| s.iter().copied().collect()).collect();
+        let slice_refs: Vec<&[u32]> = slices.iter().map(Vec::as_slice).collect();
+        let union: Vec<u32> = sets
+            .into_iter()
+            .flatten()
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let roc = RocCompressor::new();
+        prop_assert_eq!(
+            sorted_merge_compress(&roc, &slice_refs, 5000)?,
+            roc.compress_set(&union, 5000)?
+        );
+        let pfor = PForDeltaCompressor::default();
+        prop_assert_eq!(
+            sorted_merge_compress(&pfor, &slice_refs, 5000)?,
+            pfor.compress_set(&union, 5000)?
+        );
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SEGMENTED
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_segmented_bimodal(
+        dense_start in 0u32..1000,
+        dense_len in 0u32..500,
+        (sparse, _) in sorted_unique_ids(300, 1_000_000),
+        window in 1usize..100,
+    ) {
+        // Dense run followed by sparse IDs shifted past it
+        let mut ids: Vec<u32> = (dense_start..dense_start + dense_len).collect();
+        ids.extend(sparse.iter().map(|&id| id + 2000));
+        let compressor = SegmentedCompressor::new(window);
+
+        let compressed = compressor.compress_set(&ids, 1_002_000)?;
+        let decompressed = compressor.decompress_set(&compressed, 1_002_000)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn segmented_never_panics_on_garbage(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u32..1_000_000,
+    ) {
+        let _ = SegmentedCompressor::new(8).decompress_set(&bytes, universe);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // WINDOWED
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_windowed_64_bit_clusters(
+        bases in proptest::collection::btree_set(0u64..(u64::MAX >> 1), 1..8),
+        offsets in proptest::collection::vec(
+            proptest::collection::btree_set(0u64..100_000, 1..50),
+            8,
+        ),
+        block_bits in 1u8..=31,
+    ) {
+        // A few clusters far apart, each usually spanning several windows
+        let ids: std::collections::BTreeSet<u64> = bases
+            .iter()
+            .zip(&offsets)
+            .flat_map(|(&base, offsets)| offsets.iter().map(move |&o| base + o))
+            .collect();
+        let ids: Vec<u64> = ids.into_iter().collect();
+        let compressor = WindowedCompressor::new(block_bits);
+
+        let compressed = compressor.compress_set(&ids, u64::MAX)?;
+        let decompressed = compressor.decompress_set(&compressed, u64::MAX)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_windowed_u32(
+        (ids, universe) in sorted_unique_ids(500, 10_000_000),
+        block_bits in 1u8..=31,
+    ) {
+        let compressor = WindowedCompressor::new(block_bits);
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn windowed_never_panics_on_garbage(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u64..u64::MAX,
+    ) {
+        let _ = WindowedCompressor::new(8).decompress_set(&bytes, universe);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // VERIFYING
+    // =======================================================================
+
+    #[test]
+    fn verifying_roc_output_is_identical(
+        (ids, universe) in sorted_unique_ids(500, 1_000_000),
+    ) {
+        let roc = RocCompressor::new();
+        let verifying = VerifyingCompressor::with_release_verification(roc.clone());
+
+        prop_assert_eq!(
+            verifying.compress_set(&ids, universe)?,
+            roc.compress_set(&ids, universe)?
+        );
+    }
+}
+
+proptest! {
+    // Each case panics and prints a message, so keep the count low
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn verifying_panics_on_corrupting_inner(
+        (ids, universe) in sorted_unique_ids(200, 1_000_000),
+    ) {
+        let verifying = VerifyingCompressor::with_release_verification(TrailingByte);
+        let result = std::panic::catch_unwind(|| verifying.compress_set(&ids, universe));
+        prop_assert!(result.is_err());
+    }
+}
+
+/// Appends a byte to Roc output, which Roc then refuses to decode.
+#[derive(Clone)]
+struct TrailingByte;
+
+impl IdSetCompressor for TrailingByte {
+    fn compress_set(&self, ids: &[u32], universe: u32) -> Result<Vec<u8>, CompressionError> {
+        let mut bytes = RocCompressor::new().compress_set(ids, universe)?;
+        bytes.push(0);
+        Ok(bytes)
+    }
+
+    fn decompress_set(&self, bytes: &[u8], universe: u32) -> Result<Vec<u32>, CompressionError> {
+        RocCompressor::new().decompress_set(bytes, universe)
+    }
+
+    fn estimate_size(&self, num_ids: usize, universe: u32) -> usize {
+        RocCompressor::new().estimate_size(num_ids, universe) + 1
+    }
+
+    fn theoretical_bits_per_id(&self, num_ids: usize, universe: u32) -> f64 {
+        RocCompressor::new().theoretical_bits_per_id(num_ids, universe)
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    // =======================================================================
+    // METHOD SELECTOR
+    // =======================================================================
+
+    #[test]
+    fn selector_best_by_size_is_smallest(
+        sets in proptest::collection::vec(sorted_unique_ids(300, 100_000), 1..10),
+    ) {
+        let sets: Vec<Vec<u32>> = sets.into_iter().map(|(ids, _)| ids).collect();
+        let mut selector = CompressionMethodSelector::new();
+        selector.calibrate(&sets, 100_000);
+
+        let best = selector.best_by_size();
+        let stats = selector.stats();
+        let best_size = stats.iter().find(|s| s.method == best).unwrap().avg_bytes_per_id;
+        prop_assert!(stats.iter().all(|s| best_size <= s.avg_bytes_per_id));
+
+        // Cross-check against sizes computed directly
+        let total_ids: usize = sets.iter().map(|ids| ids.len()).sum();
+        let roc = RocCompressor::new();
+        let roc_bytes: usize = sets
+            .iter()
+            .map(|ids| roc.compress_set(ids, 100_000).unwrap().len())
+            .sum();
+        prop_assert!(best_size <= roc_bytes as f64 / total_ids as f64);
+        prop_assert!(best_size <= 4.0);
+    }
+}
+
+#[cfg(feature = "arithmetic")]
+proptest! {
+    // =======================================================================
+    // ARITHMETIC CODING
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_arithmetic(
+        (ids, universe) in sorted_unique_ids(1000, 100_000),
+    ) {
+        let compressor = ArithmeticCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, universe)?;
+        let decompressed = compressor.decompress_set(&compressed, universe)?;
+
+        prop_assert_eq!(ids, decompressed);
+    }
+
+    #[test]
+    fn roundtrip_arithmetic_dense_runs(
+        start in 0u32..1000,
+        runs in proptest::collection::vec((1u32..200, 0u32..200), 1..20),
+    ) {
+        // Alternating runs of present and absent IDs
+        let mut ids = Vec::new();
+        let mut id = start;
+        for (present, absent) in runs {
+            ids.extend(id..id + present);
+            id += present + absent;
+        }
+        let compressor = ArithmeticCompressor::new();
+
+        let compressed = compressor.compress_set(&ids, id + 1)?;
+        prop_assert_eq!(compressor.decompress_set(&compressed, id + 1)?, ids);
+    }
+
+    #[test]
+    fn arithmetic_never_panics_on_garbage(
+        bytes in proptest::collection::vec(any::<u8>(), 0..64),
+        universe in 1u32..100_000,
+    ) {
+        let _ = ArithmeticCompressor::new().decompress_set(&bytes, universe);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // CONTEXTUAL
+    // =======================================================================
+
+    /// Trained and untrained contexts both round-trip; an unmatched context
+    /// falls back to the uniform prior.
+    #[test]
+    fn prop_contextual_roundtrip(
+        (training, universe) in sorted_unique_ids(200, 100_000),
+        (ids, _) in sorted_unique_ids(200, 100_000),
+        context in any::<u64>(),
+    ) {
+        let mut compressor = ContextualRocCompressor::new();
+        compressor.train(context, &training, universe);
+
+        let trained = compressor.compress_with_context(&ids, universe, context).unwrap();
+        prop_assert_eq!(&compressor.decompress_with_context(&trained, universe, context).unwrap(), &ids);
+
+        let other = context.wrapping_add(1);
+        let prior = compressor.compress_with_context(&ids, universe, other).unwrap();
+        prop_assert_eq!(&prior, &compressor.compress_set(&ids, universe).unwrap());
+        prop_assert_eq!(&compressor.decompress_with_context(&prior, universe, other).unwrap(), &ids);
+        prop_assert_eq!(compressor.decompress_set(&prior, universe).unwrap(), ids);
+    }
+
+    /// Training on a set never makes that set larger than the uniform prior.
+    #[test]
+    fn prop_contextual_trained_not_larger((ids, universe) in sorted_unique_ids(300, 50_000)) {
+        let mut compressor = ContextualRocCompressor::new();
+        compressor.train(0, &ids, universe);
+        let trained = compressor.compress_with_context(&ids, universe, 0).unwrap();
+        let prior = compressor.compress_set(&ids, universe).unwrap();
+        prop_assert!(trained.len() <= prior.len());
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SET VS SEQUENCE BOUNDS
+    // =======================================================================
+
+    #[test]
+    fn set_savings_non_negative_and_monotone(n in 0usize..100_000) {
+        let savings = cnk::math::theoretical_set_vs_sequence_savings(n);
+        prop_assert!(savings >= 0.0);
+        prop_assert!(cnk::math::theoretical_set_vs_sequence_savings(n + 1) >= savings);
+        prop_assert_eq!(cnk::math::theoretical_set_vs_sequence_savings(0), 0.0);
+    }
+
+    #[test]
+    fn set_bits_never_exceed_sequence_bits(n in 0usize..10_000, universe in 1u32..=u32::MAX) {
+        let set = cnk::math::theoretical_bits_set(n, universe);
+        let sequence = cnk::math::theoretical_bits_sequence(n, universe);
+        prop_assert!(set >= 0.0);
+        // Allow for rounding in the Stirling terms at huge universes
+        prop_assert!(set <= sequence + 1e-3);
+    }
+
+    /// The summed binomial matches the exact integer wherever it fits, and
+    /// is symmetric in `k` and `n - k`.
+    #[test]
+    fn binomial_exact_matches_integer(n in 0u32..200, k in 0u32..200) {
+        let bits = cnk::math::log2_binomial_exact(n as u64, k as u64);
+        if k <= n {
+            prop_assert_eq!(bits, cnk::math::log2_binomial_exact(n as u64, (n - k) as u64));
+        }
+        if let Some(c) = cnk::math::binomial_coeff_u128(n, k) {
+            let expected = if c == 0 { 0.0 } else { (c as f64).log2() };
+            prop_assert!((bits - expected).abs() < 1e-9 * expected.max(1.0));
+        } else {
+            prop_assert!(bits >= 127.0);
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SHARED GAP HISTOGRAM
+    // =======================================================================
+
+    /// Any set round-trips through any histogram, trained or not, as long as
+    /// both sides use the same one.
+    #[test]
+    fn roundtrip_shared_histogram(
+        training in proptest::collection::vec(sorted_unique_ids(100, 5_000), 0..8),
+        (ids, universe) in sorted_unique_ids(300, 1_000_000),
+    ) {
+        let sets: Vec<Vec<u32>> = training.into_iter().map(|(ids, _)| ids).collect();
+        let histogram = GapHistogram::learn_from_sets(&sets);
+        let compressor = HuffmanCompressor::new();
+
+        let compressed = compressor.compress_with_histogram(&ids, universe, &histogram)?;
+        prop_assert_eq!(compressor.decompress_with_histogram(&compressed, universe, &histogram)?, ids);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // CODEC DISPATCH
+    // =======================================================================
+
+    /// A codec built from a method writes the same bytes as the compressor
+    /// it wraps, and maps back to that method.
+    #[test]
+    fn codec_matches_concrete_compressor((ids, universe) in sorted_unique_ids(200, 100_000)) {
+        let pairs: Vec<(IdCompressionMethod, Box<dyn IdSetCompressor>)> = vec![
+            (IdCompressionMethod::Roc, Box::new(RocCompressor::new())),
+            (IdCompressionMethod::EliasFano, Box::new(EliasFanoCompressor::new())),
+            (IdCompressionMethod::Interpolative, Box::new(InterpolativeCompressor::new())),
+        ];
+        for (method, concrete) in pairs {
+            let codec = Codec::try_from(method.clone()).unwrap();
+            let compressed = codec.compress_set(&ids, universe)?;
+            prop_assert_eq!(&compressed, &concrete.compress_set(&ids, universe)?);
+            prop_assert_eq!(&codec.decompress_set(&compressed, universe)?, &ids);
+            prop_assert_eq!(IdCompressionMethod::from(codec), method);
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // INDEX MERGE
+    // =======================================================================
+
+    /// Every term of the merged index holds the union of its lists in the
+    /// two segments.
+    #[test]
+    fn merged_index_holds_unions(
+        a_lists in proptest::collection::btree_map(0u32..20, sorted_unique_ids(50, 1000), 0..10),
+        b_lists in proptest::collection::btree_map(0u32..20, sorted_unique_ids(50, 1000), 0..10),
+    ) {
+        let universe = 1100;
+        let mut a = CompressedIndex::new(RocCompressor::new(), universe);
+        for (&term, (ids, _)) in &a_lists {
+            a.insert(term, ids)?;
+        }
+        let mut b = CompressedIndex::new(RocCompressor::new(), universe);
+        for (&term, (ids, _)) in &b_lists {
+            b.insert(term, ids)?;
+        }
+
+        let merged = merge_compressed_indices(a.clone(), b.clone(), universe)?;
+        let terms: Vec<u32> = merged.terms().collect();
+        let mut expected_terms: Vec<u32> = a_lists.keys().chain(b_lists.keys()).copied().collect();
+        expected_terms.sort_unstable();
+        expected_terms.dedup();
+        prop_assert_eq!(terms, expected_terms);
+
+        for term in 0..20 {
+            let mut expected: Vec<u32> = a.get(term)?.into_iter().chain(b.get(term)?).flatten().collect();
+            expected.sort_unstable();
+            expected.dedup();
+            match merged.get(term)? {
+                Some(ids) => prop_assert_eq!(ids, expected),
+                None => prop_assert!(a.get(term)?.is_none() && b.get(term)?.is_none()),
+            }
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // BATCHES
+    // =======================================================================
+
+    /// Iteration visits every set once, and lazy decoding matches
+    /// `decompress_batch`.
+    #[test]
+    fn batch_iteration_matches_decompress_batch(
+        sets in proptest::collection::vec(sorted_unique_ids(50, 1000), 0..20),
+    ) {
+        let sets: Vec<Vec<u32>> = sets.into_iter().map(|(ids, _)| ids).collect();
+        let roc = RocCompressor::new();
+        let batch = CompressedSetVec::compress_batch(&sets, 1000, &roc, None)?;
+
+        prop_assert_eq!(batch.iter().count(), batch.len());
+        for (i, bytes) in batch.iter() {
+            prop_assert_eq!(bytes, &roc.compress_set(&sets[i], 1000)?[..]);
+        }
+
+        let eager = batch.decompress_batch(1000, &roc)?;
+        let lazy: Result<Vec<Vec<u32>>, _> = batch.into_iter_decoded(1000, &roc).collect();
+        prop_assert_eq!(&lazy?, &eager);
+        prop_assert_eq!(eager, sets);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // CHECKPOINTED STREAMS
+    // =======================================================================
+
+    /// IDs fed in arbitrary pieces, with arbitrary early checkpoints, come
+    /// back in full.
+    #[test]
+    fn roundtrip_checkpointed(
+        (ids, universe) in sorted_unique_ids(500, 100_000),
+        cuts in proptest::collection::vec((0usize..500, any::<bool>()), 0..10),
+        chunk_size in 1usize..100,
+    ) {
+        let mut cuts: Vec<(usize, bool)> = cuts.into_iter().map(|(c, b)| (c.min(ids.len()), b)).collect();
+        cuts.sort_unstable();
+
+        let mut compressor = CheckpointedCompressor::new(universe, chunk_size);
+        let mut start = 0;
+        for (cut, checkpoint) in cuts {
+            compressor.feed(&ids[start..cut])?;
+            if checkpoint {
+                compressor.checkpoint();
+            }
+            start = cut;
+        }
+        compressor.feed(&ids[start..])?;
+        let compressed = compressor.finish();
+
+        let decoded: Result<Vec<u32>, _> = decompress_checkpointed(&compressed, universe).collect();
+        prop_assert_eq!(decoded?, ids);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // BYTE BUDGET
+    // =======================================================================
+
+    /// Output never exceeds the budget, and decodes to a prefix of the set
+    /// that is strict exactly when the set was reported truncated.
+    #[test]
+    fn max_size_keeps_a_prefix(
+        (ids, universe) in sorted_unique_ids(200, 100_000),
+        max_bytes in 3usize..300,
+    ) {
+        let compressor = MaxSizeCompressor::new(RocCompressor::new(), max_bytes);
+        let compressed = compressor.compress_set(&ids, universe)?;
+        prop_assert!(compressed.len() <= max_bytes);
+
+        let (prefix, original_count) = compressor.decompress_prefix(&compressed, universe)?;
+        prop_assert_eq!(original_count, ids.len());
+        prop_assert_eq!(&prefix[..], &ids[..prefix.len()]);
+        match compressor.decompress_set(&compressed, universe) {
+            Ok(decoded) => prop_assert_eq!(decoded, ids),
+            Err(CompressionError::Truncated { stored_count, original_count }) => {
+                prop_assert!(stored_count < original_count);
+                prop_assert_eq!(stored_count, prefix.len());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // FIXED WIDTH
+    // =======================================================================
+
+    /// Fixed-width packing round-trips and takes exactly the packed bits
+    /// plus the 4-byte count.
+    #[test]
+    fn fixed_width_roundtrip_and_size(
+        (ids, universe) in sorted_unique_ids(500, 1_000_000),
+    ) {
+        let compressed = compress_fixed_width(&ids, universe)?;
+        let bits = ids.len() * bits_needed(universe) as usize;
+        prop_assert_eq!(compressed.len(), bits.div_ceil(8) + 4);
+        prop_assert_eq!(decompress_fixed_width(&compressed, ids.len(), universe)?, ids);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // APPENDS
+    // =======================================================================
+
+    /// Appending IDs one by one gives the same bytes as compressing them all.
+    #[test]
+    fn append_matches_full_compression(
+        (ids, universe) in sorted_unique_ids(300, 100_000),
+        split in any::<prop::sample::Index>(),
+    ) {
+        let roc = RocCompressor::new();
+        let k = split.index(ids.len());
+        let mut compressed = roc.compress_set(&ids[..k], universe)?;
+        for &id in &ids[k..] {
+            compress_set_append(&mut compressed, universe, id)?;
+        }
+        prop_assert_eq!(compressed, roc.compress_set(&ids, universe)?);
+    }
+
+    /// Appending to the empty set is compressing a one-ID set.
+    #[test]
+    fn append_to_empty_matches_single_id(id in 0u32..100_000, extra in 1u32..1000) {
+        let universe = id + extra;
+        let mut compressed = Vec::new();
+        compress_set_append(&mut compressed, universe, id)?;
+        prop_assert_eq!(compressed, RocCompressor::new().compress_set(&[id], universe)?);
+    }
+
+    /// Building a set by appends alone matches batch compression after
+    /// every step, across the count growing from 127 to 128 IDs, where its
+    /// varint takes a second byte and the body shifts right. At the
+    /// Fastest level the count is fixed-width and is rewritten in place.
+    #[test]
+    fn append_one_by_one_across_count_varint_growth(
+        gaps in proptest::collection::vec(1u32..300, 120..140),
+        fastest in any::<bool>(),
+    ) {
+        let ids: Vec<u32> = gaps
+            .iter()
+            .scan(0u32, |id, &gap| {
+                *id += gap;
+                Some(*id)
+            })
+            .collect();
+        let universe = ids[ids.len() - 1] + 1;
+        let roc = if fastest {
+            RocCompressor::with_level(CompressionLevel::Fastest)
+        } else {
+            RocCompressor::new()
+        };
+
+        let mut compressed = roc.compress_set(&ids[..1], universe)?;
+        for n in 2..=ids.len() {
+            compress_set_append(&mut compressed, universe, ids[n - 1])?;
+            prop_assert_eq!(&compressed, &roc.compress_set(&ids[..n], universe)?);
+        }
+        prop_assert_eq!(roc.decompress_set(&compressed, universe)?, ids);
+    }
+
+    /// An ID not above the current last one is rejected, and the set is
+    /// left as it was.
+    #[test]
+    fn append_out_of_order_is_rejected(
+        (ids, universe) in sorted_unique_ids(100, 10_000),
+        pick in any::<prop::sample::Index>(),
+    ) {
+        let roc = RocCompressor::new();
+        let mut compressed = roc.compress_set(&ids, universe)?;
+        let before = compressed.clone();
+        let stale = ids[pick.index(ids.len())];
+        prop_assert!(compress_set_append(&mut compressed, universe, stale).is_err());
+        prop_assert_eq!(compressed, before);
+    }
+
+    /// An ID at or past the universe is rejected, and the set is left as
+    /// it was.
+    #[test]
+    fn append_outside_universe_is_rejected(
+        (ids, universe) in sorted_unique_ids(100, 10_000),
+        over in 0u32..1000,
+    ) {
+        let roc = RocCompressor::new();
+        let mut compressed = roc.compress_set(&ids, universe)?;
+        let before = compressed.clone();
+        prop_assert!(compress_set_append(&mut compressed, universe, universe + over).is_err());
+        prop_assert_eq!(&compressed, &before);
+
+        let mut empty = Vec::new();
+        prop_assert!(compress_set_append(&mut empty, universe, universe + over).is_err());
+        prop_assert!(empty.is_empty());
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // POSTING CURSORS
+    // =======================================================================
+
+    /// `advance_past(x)` lands on the smallest ID strictly greater than `x`.
+    #[test]
+    fn advance_past_finds_successor(
+        (ids, universe) in sorted_unique_ids(300, 10_000),
+        thresholds in prop::collection::vec(0u32..10_000, 1..20),
+    ) {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&ids, universe)?;
+        let mut thresholds = thresholds;
+        thresholds.sort_unstable();
+
+        let mut cursor = PeekableCompressedSet::new(roc.iter(&compressed, universe)?);
+        for x in thresholds {
+            let expected = ids.iter().copied().find(|&id| id > x);
+            prop_assert_eq!(cursor.advance_past(x), expected);
+            prop_assert_eq!(cursor.current(), expected);
+        }
+    }
+
+    /// With IDs as scores, WAND returns the largest `k` common IDs.
+    #[test]
+    fn wand_intersect_matches_naive(
+        (a, _) in sorted_unique_ids(100, 1000),
+        (b, _) in sorted_unique_ids(100, 1000),
+        k in 0usize..20,
+    ) {
+        let roc = RocCompressor::new();
+        let (ca, cb) = (roc.compress_set(&a, 1000u32)?, roc.compress_set(&b, 1000u32)?);
+        let lists = vec![
+            PeekableCompressedSet::new(roc.iter(&ca, 1000)?),
+            PeekableCompressedSet::new(roc.iter(&cb, 1000)?),
+        ];
+
+        let expected: Vec<u32> = a
+            .iter()
+            .rev()
+            .copied()
+            .filter(|id| b.binary_search(id).is_ok())
+            .take(k)
+            .collect();
+        prop_assert_eq!(wand_intersect(lists, k), expected);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // AUTO-SORT VALIDATION
+    // =======================================================================
+
+    /// Unsorted input round-trips to its sorted version under `AutoSort`.
+    #[test]
+    fn auto_sort_roundtrips_to_sorted(
+        ids in prop::collection::hash_set(0u32..100_000, 0..300),
+    ) {
+        let roc = RocCompressor::with_validation(ValidationMode::AutoSort);
+        let unsorted: Vec<u32> = ids.into_iter().collect();
+        let mut sorted = unsorted.clone();
+        sorted.sort_unstable();
+
+        let compressed = roc.compress_set(&unsorted, 100_000)?;
+        prop_assert_eq!(&compressed, &RocCompressor::new().compress_set(&sorted, 100_000)?);
+        prop_assert_eq!(roc.decompress_set(&compressed, 100_000)?, sorted);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // BITSETS
+    // =======================================================================
+
+    /// Dense sets round-trip through a bitset no larger than `ceil(N / 8)`.
+    #[test]
+    fn bitset_roundtrip_dense(members in proptest::collection::vec(any::<bool>(), 1..5000)) {
+        let universe = members.len() as u32;
+        let ids: Vec<u32> = (0..universe).filter(|&i| members[i as usize]).collect();
+        let bitmap = BitmapSetCompressor::new();
+
+        let compressed = bitmap.compress_set(&ids, universe)?;
+        prop_assert!(compressed.len() <= (universe as usize).div_ceil(8));
+        prop_assert!(compressed.last() != Some(&0));
+        prop_assert_eq!(bitmap.decompress_set(&compressed, universe)?, ids.clone());
+        prop_assert_eq!(cnk::math::density(&ids, universe), ids.len() as f64 / universe as f64);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // EXCEPTION DELTA
+    // =======================================================================
+
+    /// Bimodal gaps, tiny runs broken by section-boundary jumps, round-trip
+    /// and each large gap costs only its sentinel and position on top of an
+    /// all-inline encoding.
+    #[test]
+    fn roundtrip_exception_delta_bimodal_gaps(
+        first in 0u32..1000,
+        gaps in proptest::collection::vec(
+            prop_oneof![9 => 1u32..=5, 1 => 65_536u32..200_000],
+            0..300,
+        ),
+        threshold in prop_oneof![Just(65_536u32), 1u32..=6],
+    ) {
+        let mut ids = vec![first];
+        for gap in gaps {
+            ids.push(ids[ids.len() - 1] + gap);
+        }
+        let universe = ids[ids.len() - 1] + 1;
+
+        let compressor = ExceptionBasedDeltaCompressor::new(threshold);
+        let compressed = compressor.compress_set(&ids, universe)?;
+        prop_assert_eq!(&compressor.decompress_set(&compressed, universe)?, &ids);
+
+        // Each exception adds its sentinel byte and position to the
+        // all-inline encoding (and may widen the count); its value just moves
+        let inline = ExceptionBasedDeltaCompressor::new(u32::MAX).compress_set(&ids, universe)?;
+        let positions: Vec<usize> = ids
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| w[1] - w[0] >= threshold)
+            .map(|(i, _)| i)
+            .collect();
+        let overhead: usize = positions
+            .iter()
+            .map(|&i| 1 + cnk::varint::varint_len(i as u64))
+            .sum::<usize>()
+            + cnk::varint::varint_len(positions.len() as u64)
+            - 1;
+        prop_assert_eq!(compressed.len(), inline.len() + overhead);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // NIBBLES
+    // =======================================================================
+
+    /// Gaps on both sides of the escape, up to multi-byte varints, round-trip.
+    #[test]
+    fn roundtrip_nibble_all_gap_sizes(
+        first in 0u32..1000,
+        gaps in proptest::collection::vec(
+            prop_oneof![1u32..=14, 15u32..=16, 17u32..128, 128u32..100_000],
+            0..300,
+        ),
+    ) {
+        let mut ids = vec![first];
+        for gap in &gaps {
+            ids.push(ids[ids.len() - 1] + gap);
+        }
+        let universe = ids[ids.len() - 1] + 1;
+
+        let nibble = NibbleCompressor::new();
+        let compressed = nibble.compress_set(&ids, universe)?;
+        prop_assert_eq!(&nibble.decompress_set(&compressed, universe)?, &ids);
+
+        // Half a byte per gap, plus a varint for each escaped one
+        let escaped: usize = gaps
+            .iter()
+            .filter(|&&g| g >= 15)
+            .map(|&g| cnk::varint::varint_len(g as u64))
+            .sum();
+        let header = cnk::varint::varint_len(ids.len() as u64)
+            + cnk::varint::varint_len(first as u64);
+        prop_assert_eq!(compressed.len(), header + gaps.len().div_ceil(2) + escaped);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SET MAPS
+    // =======================================================================
+
+    /// Every inserted set maps to the value it was last inserted with.
+    #[test]
+    fn set_map_finds_every_inserted_set(
+        sets in proptest::collection::vec(sorted_unique_ids(20, 1000), 1..40),
+    ) {
+        let roc = RocCompressor::new();
+        let mut map = CompressedSetMap::new();
+        let mut expected = std::collections::BTreeMap::new();
+        for (value, (ids, universe)) in sets.iter().enumerate() {
+            map.insert(ids, *universe, value, &roc)?;
+            expected.insert((ids.clone(), *universe), value);
+        }
+
+        prop_assert_eq!(map.len(), expected.len());
+        for ((ids, universe), value) in &expected {
+            prop_assert_eq!(map.get_by_ids(ids, *universe, &roc), Some(value));
+        }
+        let mut values: Vec<usize> = map
+            .iter(&roc)
+            .map(|entry| entry.map(|(_, &value)| value))
+            .collect::<Result<_, _>>()?;
+        let mut expected_values: Vec<usize> = expected.into_values().collect();
+        values.sort_unstable();
+        expected_values.sort_unstable();
+        prop_assert_eq!(values, expected_values);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // BITMAP CONVERSIONS
+    // =======================================================================
+
+    /// Compressed -> bitset -> compressed keeps the set, and so the bytes.
+    #[test]
+    fn bitmap_conversion_round_trips_compressed((ids, universe) in sorted_unique_ids(100, 2000)) {
+        let roc = RocCompressor::new();
+        let compressed = roc.compress_set(&ids, universe)?;
+        let bitmap = to_sorted_bitmap(&compressed, universe, &roc)?;
+        prop_assert_eq!(&from_sorted_bitmap(&bitmap, universe, &roc)?, &compressed);
+    }
+
+    /// Bitset -> compressed -> bitset keeps the bits, and matches the bytes
+    /// of a full-length `BitmapSetCompressor` encoding.
+    #[test]
+    fn bitmap_conversion_round_trips_bitset(
+        bitmap in proptest::collection::vec(any::<u8>(), 0..100),
+    ) {
+        let universe = bitmap.len() as u32 * 8;
+        let elias_fano = EliasFanoCompressor::new();
+        let compressed = from_sorted_bitmap(&bitmap, universe, &elias_fano)?;
+        prop_assert_eq!(&to_sorted_bitmap(&compressed, universe, &elias_fano)?, &bitmap);
+
+        let raw = BitmapSetCompressor::new().compress_set(
+            &elias_fano.decompress_set(&compressed, universe)?,
+            universe,
+        )?;
+        prop_assert_eq!(&bitmap[..raw.len()], &raw[..]);
+        prop_assert!(bitmap[raw.len()..].iter().all(|&b| b == 0));
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // TAGGED REGISTRY
+    // =======================================================================
+
+    /// Every default tag round-trips, and the payload after the tag is the
+    /// compressor's own output.
+    #[test]
+    fn registry_tagged_round_trips((ids, universe) in sorted_unique_ids(100, 2000), tag in 0u8..4) {
+        let registry = CompressorRegistry::default();
+        let tagged = registry.compress_tagged(tag, &ids, universe)?;
+        prop_assert_eq!(tagged[0], tag);
+        let plain = registry.get(tag).unwrap().compress_set(&ids, universe)?;
+        prop_assert_eq!(&tagged[1..], &plain[..]);
+        prop_assert_eq!(registry.decompress_tagged(&tagged, universe)?, ids);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // ELIAS-FANO SELECT
+    // =======================================================================
+
+    /// Sampled select agrees with a linear scan for every query.
+    #[test]
+    fn elias_fano_select_next_geq_matches_scan(
+        (ids, universe) in sorted_unique_ids(300, 50_000),
+        queries in proptest::collection::vec(0u32..50_100, 1..20),
+    ) {
+        let compressed = EliasFanoCompressor::new().compress_set(&ids, universe)?;
+        let index = EliasFanoWithSelect::new(compressed, universe)?;
+        for x in queries {
+            let expected = ids.iter().copied().find(|&id| id >= x);
+            prop_assert_eq!(index.next_geq(x)?, expected);
+        }
+        for &id in &ids {
+            prop_assert_eq!(index.next_geq(id)?, Some(id));
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // SIMD VARINT DECODING
+    // =======================================================================
+
+    /// The batch decoder returns the encoded values, whatever their widths.
+    #[test]
+    fn simd_varint_batch_round_trips(
+        values in proptest::collection::vec(prop_oneof![0u64..128, 0u64..1 << 21, 0u64..1 << 62], 0..200),
+    ) {
+        let mut buf = Vec::new();
+        for &value in &values {
+            cnk::varint::encode_varint(value, &mut buf);
+        }
+        let mut decoded = Vec::new();
+        prop_assert_eq!(cnk::simd::decode_varint_batch(&buf, &mut decoded)?, values.len());
+        prop_assert_eq!(decoded, values);
+    }
+
+    /// Batch encoding writes the bytes of `encode_varint`, and batch
+    /// decoding reads them back.
+    #[test]
+    fn simd_varint_batch_encode_round_trips(
+        values in proptest::collection::vec(prop_oneof![0u64..128, 0u64..1 << 21, any::<u64>()], 0..200),
+    ) {
+        let mut buf = Vec::new();
+        cnk::simd::encode_varint_batch(&values, &mut buf);
+        let mut scalar = Vec::new();
+        cnk::simd::encode_varint_batch_scalar(&values, &mut scalar);
+        prop_assert_eq!(&buf, &scalar);
+
+        // decode_varint stops at nine bytes, short of values from 2^63
+        if values.iter().all(|&v| v < 1 << 63) {
+            let mut decoded = Vec::new();
+            cnk::simd::decode_varint_batch(&buf, &mut decoded)?;
+            prop_assert_eq!(decoded, values);
+        }
+    }
+
+    /// On arbitrary bytes the batch and scalar decoders agree, errors included.
+    #[test]
+    fn simd_varint_batch_matches_scalar(
+        bytes in proptest::collection::vec(prop_oneof![0u8..128, 128u8..=255], 0..100),
+    ) {
+        let mut batch = Vec::new();
+        let mut scalar = Vec::new();
+        match (
+            cnk::simd::decode_varint_batch(&bytes, &mut batch),
+            cnk::simd::decode_varint_batch_scalar(&bytes, &mut scalar),
+        ) {
+            (Ok(a), Ok(b)) => {
+                prop_assert_eq!(a, b);
+                prop_assert_eq!(batch, scalar);
+            }
+            (a, b) => prop_assert!(a.is_err() && b.is_err()),
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // STACK COMPRESSOR
+    // =======================================================================
+
+    /// Fixed-array compression writes Roc's bytes when they fit, and says
+    /// exactly how many bytes it needed when they do not.
+    #[test]
+    fn stack_compressor_matches_roc((ids, universe) in sorted_unique_ids(64, 100_000)) {
+        let stack = StackCompressor::<128>::new();
+        let roc = RocCompressor::new().compress_set(&ids, universe)?;
+        let mut buf = [0u8; 128];
+        match stack.compress_fixed(&ids, universe, &mut buf) {
+            Ok(len) => {
+                prop_assert_eq!(&buf[..len], &roc[..]);
+                let (decoded, count) = stack.decompress_fixed::<64>(&buf[..len], universe)?;
+                prop_assert_eq!(&decoded[..count], &ids[..]);
+            }
+            Err(CompressionError::BufferTooSmall { needed, available }) => {
+                prop_assert_eq!((needed, available), (roc.len(), 128));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // ADAPTIVE COMPRESSOR
+    // =======================================================================
+
+    /// Every output decodes, from the compressor that wrote it and from one
+    /// restored from its saved state, whether it was written during
+    /// calibration or after it.
+    #[test]
+    fn adaptive_round_trips_across_calibration(
+        sets in proptest::collection::vec(sorted_unique_ids(200, 5000), 1..12),
+        calibration_sets in 0usize..8,
+        save_after in 0usize..12,
+    ) {
+        let adaptive = AdaptiveCompressor::new(calibration_sets);
+        let mut restored = AdaptiveCompressor::new(1);
+        let mut written = Vec::new();
+        for (n, (ids, universe)) in sets.iter().enumerate() {
+            prop_assert_eq!(adaptive.winner().is_some(), n >= calibration_sets);
+            if n == save_after {
+                restored.load_state(&adaptive.save_state())?;
+            }
+            let compressed = adaptive.compress_set(ids, *universe)?;
+            prop_assert_eq!(&adaptive.decompress_set(&compressed, *universe)?, ids);
+            if let Some(winner) = adaptive.winner().filter(|_| n >= calibration_sets) {
+                prop_assert_eq!(compressed[0], winner as u8);
+            }
+            written.push(compressed);
+        }
+
+        for ((ids, universe), compressed) in sets.iter().zip(&written) {
+            prop_assert_eq!(&restored.decompress_set(compressed, *universe)?, ids);
+        }
+        // Restored mid-stream, it makes the same choices from there on
+        if save_after < sets.len() {
+            for (ids, universe) in &sets[save_after..] {
+                restored.compress_set(ids, *universe)?;
+            }
+            prop_assert_eq!(restored.save_state(), adaptive.save_state());
+        }
+    }
+}
+
+#[cfg(any(feature = "brotli", feature = "zstd"))]
+proptest! {
+    // =======================================================================
+    // POST-COMPRESSION
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_post_compressed(
+        (ids, universe) in sorted_unique_ids(1000, 100_000),
+    ) {
+        let passes = [
+            #[cfg(feature = "brotli")]
+            PostCompression::Brotli,
+            #[cfg(feature = "zstd")]
+            PostCompression::Zstd,
+        ];
+        let roc = RocCompressor::new();
+        for post in passes {
+            let compressor = PostCompressor::new(roc.clone(), post);
+            let compressed = compressor.compress_set(&ids, universe)?;
+            // Never more than one byte over the inner codec
+            prop_assert!(compressed.len() <= roc.compress_set(&ids, universe)?.len() + 1);
+            prop_assert_eq!(compressor.decompress_set(&compressed, universe)?, ids.clone());
+        }
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // HEADERLESS DELTAS
+    // =======================================================================
+
+    #[test]
+    fn deltas_only_is_roc_output_after_header(
+        (ids, universe) in sorted_unique_ids(500, 1_000_000),
+    ) {
+        let compressed = RocCompressor::new().compress_set(&ids, universe)?;
+        let deltas = compress_deltas_only(&ids)?;
+        // Level byte, then the count varint
+        let header = 1 + cnk::varint::varint_len(ids.len() as u64);
+        prop_assert_eq!(&deltas[..], &compressed[header..]);
+        prop_assert_eq!(decompress_deltas_only(&deltas, ids.len())?, ids);
+    }
+}
+
+proptest! {
+    // =======================================================================
+    // REMAPPING
+    // =======================================================================
+
+    #[test]
+    fn roundtrip_remapped_over_random_valid_ids(
+        valid in proptest::collection::btree_set(0u32..u32::MAX, 1..500),
+        keep in proptest::collection::vec(any::<bool>(), 500),
+    ) {
+        let valid_ids: Vec<u32> = valid.into_iter().collect();
+        let ids: Vec<u32> = valid_ids.iter().zip(&keep).filter(|(_, &k)| k).map(|(&id, _)| id).collect();
+
+        let roc = RemappingCompressor::new(RocCompressor::new(), &valid_ids)?;
+        let compressed = roc.compress_set(&ids, u32::MAX)?;
+        prop_assert_eq!(&roc.decompress_set(&compressed, u32::MAX)?, &ids);
+
+        // The bitset covers ranks, not the 32-bit ID space
+        let bitset = RemappingCompressor::new(BitmapSetCompressor::new(), &valid_ids)?;
+        let compressed = bitset.compress_set(&ids, u32::MAX)?;
+        prop_assert!(compressed.len() <= 8 + valid_ids.len().div_ceil(8));
+        prop_assert_eq!(&bitset.decompress_set(&compressed, u32::MAX)?, &ids);
+    }
+
+    #[test]
+    fn remapping_rejects_ids_outside_valid_set(
+        valid in proptest::collection::btree_set(0u32..10_000, 1..200),
+        id in 0u32..10_000,
+    ) {
+        let valid_ids: Vec<u32> = valid.into_iter().collect();
+        let compressor = RemappingCompressor::new(RocCompressor::new(), &valid_ids)?;
+        prop_assert_eq!(
+            compressor.compress_set(&[id], 10_000).is_ok(),
+            valid_ids.binary_search(&id).is_ok()
+        );
+    }
+}
+
+// =======================================================================
+// STATISTICAL TESTS (not proptest, but important)
+// =======================================================================
+
+#[test]
+fn compression_ratio_improves_with_density() {
+    let compressor = RocCompressor::new();
+    let universe = 100_000u32;
+
+    // Sparse set with large gaps (delta ~100)
+    let sparse: Vec<u32> = (0..1000).map(|i| i * 100).collect();
+    let sparse_compressed = compressor.compress_set(&sparse, universe).unwrap();
+    let sparse_bytes_per_id = sparse_compressed.len() as f64 / sparse.len() as f64;
+
+    // Dense set (consecutive, delta = 1)
+    let dense: Vec<u32> = (0..1000).collect();
+    let dense_compressed = compressor.compress_set(&dense, universe).unwrap();
+    let dense_bytes_per_id = dense_compressed.len() as f64 / dense.len() as f64;
+
+    // Dense should use fewer bytes per ID (delta=1 is 1 byte, delta=100 is 1 byte too in varint)
+    // Actually both fit in 1 byte varint, so let's use larger gaps
+    let very_sparse: Vec<u32> = (0..100).map(|i| i * 1000).collect();
+    let very_sparse_compressed = compressor.compress_set(&very_sparse, universe).unwrap();
+    let very_sparse_bytes_per_id = very_sparse_compressed.len() as f64 / very_sparse.len() as f64;
+
+    // Very sparse (delta=1000, needs 2 bytes) should use more bytes per ID than dense (delta=1)
+    assert!(
+        very_sparse_bytes_per_id > dense_bytes_per_id,
+        "very sparse {} bytes/id should exceed dense {} bytes/id",
+        very_sparse_bytes_per_id,
+        dense_bytes_per_id
+    );
+
+    println!("Dense: {:.2} bytes/id", dense_bytes_per_id);
+    println!("Sparse (delta=100): {:.2} bytes/id", sparse_bytes_per_id);
+    println!(
+        "Very sparse (delta=1000): {:.2} bytes/id",
+        very_sparse_bytes_per_id
+    );
+}
+
+#[test]
+fn estimate_size_is_reasonable() {
+    let compressor = RocCompressor::new();
+
+    for num_ids in [10, 100, 1000] {
+        for universe in [10_000, 100_000, 1_000_000] {
+            let estimate = compressor.estimate_size(num_ids, universe);
+
+            // Estimate should be positive for non-empty sets
+            assert!(estimate > 0, "estimate should be positive");
+
+            // Estimate should be less than raw storage
+            let raw_size = num_ids * 4;
+            assert!(
+                estimate <= raw_size * 2,
+                "estimate {} should be reasonable vs raw {}",
+                estimate,
+                raw_size
+            );
+
+            let step = universe as usize / num_ids;
+            let ids: Vec<u32> = (0..num_ids)
+                .map(|i| (i * step + (i * 7919) % step) as u32)
+                .collect();
+            let actual = compressor.actual_bits_per_id(&ids, universe).unwrap();
+
+            // The delta+varint codec stays near the entropy bound. The bound
+            // is an average over all sets, so one evenly spaced set whose
+            // gaps all fit a single varint byte can dip slightly below it
+            assert!(
+                actual >= 0.95 * compressor.theoretical_bits_per_id(num_ids, universe),
+                "actual {} bits/id is below the theoretical bound",
+                actual
+            );
+
+            // The distribution-aware estimate must be within 10% of the real size
+            if num_ids >= 100 {
+                let tight = compressor.estimate_compressed_size_bytes(&ids, universe) as f64 * 8.0
+                    / num_ids as f64;
+                assert!(
+                    (tight - actual).abs() <= actual * 0.1,
+                    "estimate {} bits/id should be within 10% of actual {}",
+                    tight,
+                    actual
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn huffman_beats_varint_on_skewed_gaps() {
+    // 80% of gaps are 1, the rest are spread over 2..=32
+    let mut rng = Lcg::new(0x2545_F491_4F6C_DD1D);
+    let mut ids = Vec::with_capacity(10_000);
+    let mut id = 0u32;
+    for _ in 0..10_000 {
+        let r = rng.next_u32();
+        id += if r % 10 < 8 { 1 } else { 2 + r % 31 };
+        ids.push(id);
+    }
+    let universe = id + 1;
+
+    let roc = RocCompressor::new().compress_set(&ids, universe).unwrap();
+    let huffman = HuffmanCompressor::new()
+        .compress_set(&ids, universe)
+        .unwrap();
+
+    assert!(
+        (huffman.len() as f64) <= 0.8 * roc.len() as f64,
+        "huffman {} bytes should be at least 20% smaller than varint {} bytes",
+        huffman.len(),
+        roc.len()
+    );
+}
+
+#[test]
+fn default_method_is_roc() {
+    assert_eq!(IdCompressionMethod::default(), IdCompressionMethod::Roc);
+    #[allow(deprecated)]
+    let none = IdCompressionMethod::None;
+    assert_eq!(none, IdCompressionMethod::Uncompressed);
+}
+
+#[test]
+fn auto_select_uses_density() {
+    assert_eq!(
+        IdCompressionMethod::auto_select(100, 1_000_000),
+        IdCompressionMethod::Roc
+    );
+    // Roaring only when it is compiled in, so the choice always has a codec
+    let dense = IdCompressionMethod::auto_select(100_000, 1_000_000);
+    if cfg!(feature = "roaring") {
+        assert_eq!(dense, IdCompressionMethod::RoaringBitmap);
+    } else {
+        assert_eq!(dense, IdCompressionMethod::Roc);
+    }
+    for num_ids in [0, 100, 100_000, 600_000] {
+        let method = IdCompressionMethod::auto_select(num_ids, 1_000_000);
+        assert!(Codec::try_from(method.clone()).is_ok(), "{:?}", method);
+    }
+    assert_eq!(
+        IdCompressionMethod::auto_select(600_000, 1_000_000),
+        IdCompressionMethod::Bitset
+    );
+    assert_eq!(
+        IdCompressionMethod::auto_select(0, 0),
+        IdCompressionMethod::Roc
+    );
 }
@@ -0,0 +1,94 @@
+//! Thread-safety guarantees of the public types.
+
+use cnk::{CompressionError, IdSetCompressor, RocCompressor};
+
+mod sync_assertions {
+    use cnk::*;
+    use static_assertions::assert_impl_all;
+
+    assert_impl_all!(RocCompressor: Send, Sync, Clone);
+    assert_impl_all!(HuffmanCompressor: Send, Sync, Clone);
+    assert_impl_all!(ContextualRocCompressor: Send, Sync, Clone);
+    assert_impl_all!(GapHistogram: Send, Sync, Clone);
+    assert_impl_all!(CompressorWriter<RocCompressor, Vec<u8>>: Send, Sync);
+    assert_impl_all!(DecompressorReader<RocCompressor, &[u8]>: Send, Sync);
+    assert_impl_all!(BlockDeltaCompressor: Send, Sync, Clone);
+    assert_impl_all!(PForDeltaCompressor: Send, Sync, Clone);
+    assert_impl_all!(ExceptionBasedDeltaCompressor: Send, Sync, Clone);
+    assert_impl_all!(FibonacciCompressor: Send, Sync, Clone);
+    assert_impl_all!(NibbleCompressor: Send, Sync, Clone);
+    assert_impl_all!(EliasGammaCompressor: Send, Sync, Clone);
+    assert_impl_all!(EliasDeltaCompressor: Send, Sync, Clone);
+    assert_impl_all!(BitmapSetCompressor: Send, Sync, Clone);
+    assert_impl_all!(Simple16Compressor: Send, Sync, Clone);
+    assert_impl_all!(XorDeltaCompressor: Send, Sync, Clone);
+    assert_impl_all!(ZigzagDeltaCompressor: Send, Sync, Clone);
+    assert_impl_all!(EliasFanoCompressor: Send, Sync, Clone);
+    assert_impl_all!(EliasFanoWithSelect: Send, Sync, Clone);
+    assert_impl_all!(RandomAccessCompressedSet: Send, Sync, Clone);
+    assert_impl_all!(StackCompressor<64>: Send, Sync, Clone);
+    assert_impl_all!(InterpolativeCompressor: Send, Sync, Clone);
+    assert_impl_all!(Codec: Send, Sync, Clone);
+    assert_impl_all!(AdaptiveCompressor: Send, Sync);
+    assert_impl_all!(SegmentedCompressor: Send, Sync, Clone);
+    assert_impl_all!(WindowedCompressor: Send, Sync, Clone);
+    assert_impl_all!(SplitEliasFanoCompressor: Send, Sync, Clone);
+    assert_impl_all!(TwoLevelEliasFanoCompressor: Send, Sync, Clone);
+    assert_impl_all!(BaseOffsetCompressor<RocCompressor>: Send, Sync, Clone);
+    assert_impl_all!(RemappingCompressor<RocCompressor>: Send, Sync, Clone);
+    assert_impl_all!(RocMultisetCompressor: Send, Sync, Clone);
+    assert_impl_all!(VerifyingCompressor<RocCompressor>: Send, Sync, Clone);
+    assert_impl_all!(MaxSizeCompressor<RocCompressor>: Send, Sync, Clone);
+    #[cfg(feature = "arithmetic")]
+    assert_impl_all!(ArithmeticCompressor: Send, Sync, Clone);
+    #[cfg(any(feature = "brotli", feature = "zstd"))]
+    assert_impl_all!(PostCompressor<RocCompressor>: Send, Sync, Clone);
+    #[cfg(feature = "roaring")]
+    assert_impl_all!(RoaringBitmapCompressor: Send, Sync, Clone);
+
+    // Interior mutability behind a Mutex: shareable, but not Clone
+    assert_impl_all!(RecordingCompressor<RocCompressor>: Send, Sync);
+    #[cfg(feature = "concurrent")]
+    assert_impl_all!(ConcurrentCompressedIndex<RocCompressor>: Send, Sync);
+
+    assert_impl_all!(CompressedSet: Send, Sync, Clone);
+    assert_impl_all!(CompressedSetWithHash: Send, Sync, Clone);
+    assert_impl_all!(CompressedIndex<RocCompressor>: Send, Sync, Clone);
+    assert_impl_all!(CompressedSetVec: Send, Sync, Clone);
+    assert_impl_all!(CompressedSetMap<u64>: Send, Sync, Clone);
+    assert_impl_all!(CompressedSet: std::hash::Hash, Eq, PartialEq);
+    assert_impl_all!(CheckpointedCompressor: Send, Sync, Clone);
+    assert_impl_all!(CompressionError: Send, Sync, std::error::Error);
+}
+
+#[test]
+fn test_shared_compressor_across_threads() {
+    let roc = RocCompressor::new();
+    let universe = 80_000u32;
+    let sets: Vec<Vec<u32>> = (0..8u32)
+        .map(|t| (0..10_000).map(|i| t * 10_000 + i).collect())
+        .collect();
+
+    let compressed: Vec<Vec<u8>> = std::thread::scope(|s| {
+        let handles: Vec<_> = sets
+            .iter()
+            .map(|ids| s.spawn(|| roc.compress_set(ids, universe).unwrap()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    for (ids, bytes) in sets.iter().zip(&compressed) {
+        assert_eq!(&roc.decompress_set(bytes, universe).unwrap(), ids);
+    }
+}
+
+#[test]
+fn test_error_crosses_thread_boundary() {
+    let roc = RocCompressor::new();
+    let err: CompressionError = std::thread::scope(|s| {
+        s.spawn(|| roc.compress_set(&[3u32, 1], 10).unwrap_err())
+            .join()
+            .unwrap()
+    });
+    assert!(matches!(err, CompressionError::InvalidInput(_)));
+}
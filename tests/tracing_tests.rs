@@ -0,0 +1,95 @@
+//! Trace events emitted with the `tracing` feature.
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use cnk::{IdSetCompressor, RocCompressor};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::Registry;
+
+/// One captured event: its level and fields rendered with `Debug`.
+type Captured = (Level, HashMap<String, String>);
+
+/// Layer recording every event it sees.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Captured>>>);
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Capture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.0
+            .lock()
+            .unwrap()
+            .push((*event.metadata().level(), fields));
+    }
+}
+
+fn capture(f: impl FnOnce()) -> Vec<Captured> {
+    let layer = Capture::default();
+    let subscriber = Registry::default().with(layer.clone());
+    tracing::subscriber::with_default(subscriber, f);
+    let events = layer.0.lock().unwrap().clone();
+    events
+}
+
+#[test]
+fn test_round_trip_emits_structured_fields() {
+    let roc = RocCompressor::new();
+    let ids: Vec<u32> = (0..100).map(|i| i * 3).collect();
+
+    let events = capture(|| {
+        let compressed = roc.compress_set(&ids, 1000).unwrap();
+        roc.decompress_set(&compressed, 1000u32).unwrap();
+    });
+
+    assert_eq!(events.len(), 2);
+    for (level, fields) in &events {
+        assert_eq!(*level, Level::TRACE);
+        for name in [
+            "codec",
+            "ids_count",
+            "universe_size",
+            "compressed_bytes",
+            "duration_ns",
+            "compression_ratio",
+        ] {
+            assert!(fields.contains_key(name), "missing {}", name);
+        }
+        assert_eq!(fields["ids_count"], "100");
+        assert_eq!(fields["universe_size"], "1000");
+        // level + count + 100 one-byte deltas
+        assert_eq!(fields["compressed_bytes"], "102");
+    }
+    assert_eq!(events[0].1["message"], "compressed set");
+    assert_eq!(events[1].1["message"], "decompressed set");
+}
+
+#[test]
+fn test_trailing_data_warns() {
+    let roc = RocCompressor::new();
+    let mut compressed = roc.compress_set(&[1u32, 2], 10).unwrap();
+    compressed.extend_from_slice(&[0, 0, 0]);
+
+    let events = capture(|| {
+        assert!(roc.decompress_set(&compressed, 10u32).is_err());
+    });
+
+    assert_eq!(events.len(), 1);
+    let (level, fields) = &events[0];
+    assert_eq!(*level, Level::WARN);
+    assert_eq!(fields["trailing_bytes"], "3");
+}
@@ -0,0 +1,6 @@
+use cnk::{IdSetCompressor, RocCompressor};
+
+fn main() {
+    let roc = RocCompressor::new();
+    let _ = roc.compress_set_in(&[1u32, 5, 10], 1000u32);
+}